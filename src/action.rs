@@ -0,0 +1,321 @@
+//! Action: everything the user can ask the view to do, and the names they go
+//! by in the command palette.
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::{
+    column::Column,
+    coredump::DumpWith,
+    group::Grouping,
+    platform::IoPriority,
+    view_state::{DeadRows, IoChange, Jump, Metric, Move, Step, ZoomTab},
+};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    SortBy(Metric),
+    FlipSort,
+    ToggleSmartSort,
+    ToggleColumn(Column),
+    SetColumn(Column, bool), // shown or not
+    CycleCellKind(Column),
+    ToggleGroupHeaders,
+    ToggleTallRows,
+    ToggleEventLog,
+    ToggleAlerts,
+    ScrollAlerts(Step), // down is older
+    ToggleCumulative,
+    ToggleBandwidth,
+    ToggleSummary,
+    ToggleCores,
+    ToggleRanking,
+    TogglePause,
+    Delay(Step),      // between samples
+    EwmaWeight(Step), // of each new sample
+    ToggleSpikeFilter,
+    SavedFilter(usize), // from the config, from 0
+    ToggleBaseline,
+    ToggleBraille,
+    GroupBy(Grouping), // or stop, if already
+    CycleDeadRows,
+    PlaceDeadRows(DeadRows),
+    ToggleSplit,
+    FocusNext,
+    ScrollLeft,
+    ScrollRight,
+    Select(Move),
+    JumpTo(Jump),
+    ToggleExpand,
+    ToggleBookmark,
+    Mute, // hide processes like the selected one
+    UnmuteAll,
+    Ionice(IoChange),   // of the selected process
+    ToggleFreeze,       // the selected process's cgroup, or its tree
+    CoreDump(DumpWith), // of the selected process, once confirmed
+    Export,             // every process, to a JSON file
+    Zoom,
+    ZoomTo(ZoomTab),
+    OpenPalette,
+    OpenSearch,
+    ClearAlert,
+    Undo,
+    ToggleHelp,
+    Quit,
+}
+
+// direct key bindings in the table view.
+pub const BINDINGS: &[(KeyCode, Action)] = &[
+    (KeyCode::Char('N'), Action::SortBy(Metric::Pid)),
+    (KeyCode::Char('M'), Action::SortBy(Metric::Mem)),
+    (KeyCode::Char('P'), Action::SortBy(Metric::Cpu)),
+    (KeyCode::Char('R'), Action::SortBy(Metric::DiskRead)),
+    (KeyCode::Char('W'), Action::SortBy(Metric::DiskWrite)),
+    (KeyCode::Char('D'), Action::SortBy(Metric::DiskTotal)),
+    (KeyCode::Char('I'), Action::FlipSort),
+    (KeyCode::Char('A'), Action::ToggleSmartSort),
+    (KeyCode::Char('g'), Action::CycleCellKind(Column::MemPct)),
+    (KeyCode::Char('G'), Action::ToggleGroupHeaders),
+    (KeyCode::Char('T'), Action::ToggleTallRows),
+    (KeyCode::Char('L'), Action::ToggleEventLog),
+    (KeyCode::Char('a'), Action::ToggleAlerts),
+    (KeyCode::Char('>'), Action::ScrollAlerts(Step::Down)),
+    (KeyCode::Char('<'), Action::ScrollAlerts(Step::Up)),
+    (KeyCode::Char('S'), Action::ToggleCumulative),
+    (KeyCode::Char('B'), Action::ToggleBandwidth),
+    (KeyCode::Char('O'), Action::ToggleSummary),
+    (KeyCode::F(2), Action::ToggleCores),
+    (KeyCode::Char('H'), Action::ToggleRanking),
+    (KeyCode::Char(' '), Action::TogglePause),
+    (KeyCode::Char('+'), Action::Delay(Step::Up)),
+    (KeyCode::Char('-'), Action::Delay(Step::Down)),
+    (KeyCode::Char(']'), Action::EwmaWeight(Step::Up)),
+    (KeyCode::Char('['), Action::EwmaWeight(Step::Down)),
+    (KeyCode::Char('Z'), Action::ToggleSpikeFilter),
+    (KeyCode::Char('1'), Action::SavedFilter(0)),
+    (KeyCode::Char('2'), Action::SavedFilter(1)),
+    (KeyCode::Char('3'), Action::SavedFilter(2)),
+    (KeyCode::Char('4'), Action::SavedFilter(3)),
+    (KeyCode::Char('5'), Action::SavedFilter(4)),
+    (KeyCode::Char('6'), Action::SavedFilter(5)),
+    (KeyCode::Char('7'), Action::SavedFilter(6)),
+    (KeyCode::Char('8'), Action::SavedFilter(7)),
+    (KeyCode::Char('9'), Action::SavedFilter(8)),
+    (KeyCode::Char('o'), Action::ToggleBaseline),
+    (KeyCode::Char('v'), Action::ToggleBraille),
+    (KeyCode::Char('K'), Action::GroupBy(Grouping::Pod)),
+    (KeyCode::Char('V'), Action::GroupBy(Grouping::Vm)),
+    (KeyCode::Char('X'), Action::CycleDeadRows),
+    (KeyCode::Char('|'), Action::ToggleSplit),
+    (KeyCode::Tab, Action::FocusNext),
+    (KeyCode::Left, Action::ScrollLeft),
+    (KeyCode::Right, Action::ScrollRight),
+    (KeyCode::Up, Action::Select(Move::Up)),
+    (KeyCode::Char('k'), Action::Select(Move::Up)),
+    (KeyCode::Down, Action::Select(Move::Down)),
+    (KeyCode::Char('j'), Action::Select(Move::Down)),
+    (KeyCode::PageUp, Action::Select(Move::PageUp)),
+    (KeyCode::PageDown, Action::Select(Move::PageDown)),
+    (KeyCode::Home, Action::Select(Move::Home)),
+    (KeyCode::End, Action::Select(Move::End)),
+    (KeyCode::Char('p'), Action::JumpTo(Jump::Parent)),
+    (KeyCode::Char('c'), Action::JumpTo(Jump::Child)),
+    (KeyCode::Char('n'), Action::JumpTo(Jump::Sibling)),
+    (KeyCode::Char('e'), Action::ToggleExpand),
+    (KeyCode::Char('b'), Action::ToggleBookmark),
+    (KeyCode::Char('x'), Action::Mute),
+    (KeyCode::Char('i'), Action::Ionice(IoChange::Lower)),
+    (KeyCode::Char('F'), Action::ToggleFreeze),
+    (KeyCode::Char('C'), Action::CoreDump(DumpWith::Gcore)),
+    (KeyCode::Char('E'), Action::Export),
+    (KeyCode::Enter, Action::Zoom),
+    (KeyCode::Char(':'), Action::OpenPalette),
+    (KeyCode::Char('/'), Action::OpenSearch),
+    (KeyCode::Char('u'), Action::Undo),
+    (KeyCode::Char('?'), Action::ToggleHelp),
+    (KeyCode::Char('q'), Action::Quit),
+    (KeyCode::Esc, Action::ClearAlert),
+];
+
+pub fn for_key(key: KeyEvent) -> Option<Action> {
+    BINDINGS
+        .iter()
+        .find(|&&(k, _)| k == key.code)
+        .map(|&(_, a)| a)
+}
+
+// the palette name for an action, for help text.
+pub fn name(action: Action) -> Option<String> {
+    registry()
+        .into_iter()
+        .find(|&(_, a)| a == action)
+        .map(|(n, _)| n)
+}
+
+/// Every action reachable from the command palette, by name.
+pub fn registry() -> Vec<(String, Action)> {
+    use Action::*;
+    let mut actions = vec![];
+    for &m in Metric::ALL.iter() {
+        actions.push((format!("sort {}", m.name()), SortBy(m)));
+    }
+    actions.push(("sort reverse".into(), FlipSort));
+    actions.push(("sort by pressure".into(), ToggleSmartSort));
+    for &c in Column::ALL.iter() {
+        actions.push((format!("toggle column {}", c.header()), ToggleColumn(c)));
+        if c.kinds().len() > 1 {
+            actions.push((format!("cycle renderer {}", c.header()), CycleCellKind(c)));
+        }
+    }
+    actions.push(("toggle group headers".into(), ToggleGroupHeaders));
+    actions.push(("toggle tall rows".into(), ToggleTallRows));
+    actions.push(("toggle event log".into(), ToggleEventLog));
+    actions.push(("toggle alert log".into(), ToggleAlerts));
+    actions.push(("toggle cumulative cpu".into(), ToggleCumulative));
+    actions.push(("toggle memory bandwidth".into(), ToggleBandwidth));
+    actions.push(("toggle system summary".into(), ToggleSummary));
+    actions.push(("toggle cpu cores page".into(), ToggleCores));
+    actions.push(("toggle history ranking".into(), ToggleRanking));
+    actions.push(("pause the display".into(), TogglePause));
+    for &s in Step::ALL.iter() {
+        actions.push((format!("delay {}", s.name()), Delay(s)));
+        actions.push((format!("ewma weight {}", s.name()), EwmaWeight(s)));
+        actions.push((format!("scroll alerts {}", s.name()), ScrollAlerts(s)));
+    }
+    actions.push(("filter recent spikes".into(), ToggleSpikeFilter));
+    for i in 0..9 {
+        actions.push((format!("saved filter {}", i + 1), SavedFilter(i)));
+    }
+    actions.push(("toggle history baseline".into(), ToggleBaseline));
+    actions.push(("toggle braille history".into(), ToggleBraille));
+    actions.push(("group by pod".into(), GroupBy(Grouping::Pod)));
+    actions.push(("group by vm".into(), GroupBy(Grouping::Vm)));
+    actions.push(("cycle dead rows".into(), CycleDeadRows));
+    for &d in DeadRows::ALL.iter() {
+        actions.push((format!("dead rows {}", d.name()), PlaceDeadRows(d)));
+    }
+    actions.push(("toggle split".into(), ToggleSplit));
+    actions.push(("focus next pane".into(), FocusNext));
+    actions.push(("scroll columns left".into(), ScrollLeft));
+    actions.push(("scroll columns right".into(), ScrollRight));
+    for &m in Move::ALL.iter() {
+        actions.push((format!("select {}", m.name()), Select(m)));
+    }
+    for &j in Jump::ALL.iter() {
+        actions.push((format!("jump to {}", j.name()), JumpTo(j)));
+    }
+    actions.push(("expand row".into(), ToggleExpand));
+    actions.push(("bookmark process".into(), ToggleBookmark));
+    actions.push(("mute process".into(), Mute));
+    actions.push(("unmute all".into(), UnmuteAll));
+    actions.push(("freeze or thaw process".into(), ToggleFreeze));
+    for &d in DumpWith::ALL.iter() {
+        actions.push((format!("core dump with {}", d.name()), CoreDump(d)));
+    }
+    actions.push(("export json".into(), Export));
+    actions.push(("ionice lower".into(), Ionice(IoChange::Lower)));
+    for &p in [
+        IoPriority::Default,
+        IoPriority::BestEffort(4),
+        IoPriority::Idle,
+        IoPriority::RealTime(4),
+    ]
+    .iter()
+    {
+        actions.push((format!("ionice {}", p.name()), Ionice(IoChange::Set(p))));
+    }
+    actions.push(("zoom".into(), Zoom));
+    for &t in ZoomTab::ALL.iter() {
+        actions.push((format!("zoom {}", t.name()), ZoomTo(t)));
+    }
+    actions.push(("undo".into(), Undo));
+    actions.push(("help".into(), ToggleHelp));
+    actions.push(("clear alert".into(), ClearAlert));
+    actions.push(("command palette".into(), OpenPalette));
+    actions.push(("search".into(), OpenSearch));
+    actions.push(("quit".into(), Quit));
+    actions
+}
+
+/// A command typed in the palette or given with --cmd: an action, or one that
+/// needs more than an Action can carry.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Run(Action),
+    // only show processes whose name or command line contains this.
+    Filter(Option<String>),
+}
+
+/// Parse `;`-separated commands, e.g. "sort mem; filter name=postgres;
+/// columns -pid +fds". Each is a palette name, "filter [name=]<text>" (or
+/// "filter off"), or "columns" followed by column names to show (+) or
+/// hide (-).
+pub fn parse_script(script: &str) -> Result<Vec<Command>, String> {
+    let mut cmds = vec![];
+    for s in script.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+        cmds.extend(parse_command(s)?);
+    }
+    Ok(cmds)
+}
+
+pub fn parse_command(s: &str) -> Result<Vec<Command>, String> {
+    let s = s.trim();
+    if let Some((_, a)) = registry().into_iter().find(|(n, _)| n == s) {
+        return Ok(vec![Command::Run(a)]);
+    }
+    let (verb, rest) = s.split_once(' ').unwrap_or((s, ""));
+    let rest = rest.trim();
+    match verb {
+        "filter" => Ok(vec![Command::Filter(match rest {
+            "" | "off" => None,
+            r => Some(r.strip_prefix("name=").unwrap_or(r).to_string()),
+        })]),
+        "columns" if !rest.is_empty() => rest
+            .split_whitespace()
+            .map(|c| {
+                let (show, name) = match c.strip_prefix('-') {
+                    Some(name) => (false, name),
+                    None => (true, c.strip_prefix('+').unwrap_or(c)),
+                };
+                let col =
+                    Column::by_name(name).ok_or_else(|| format!("no such column: {}", name))?;
+                Ok(Command::Run(Action::SetColumn(col, show)))
+            })
+            .collect(),
+        _ => Err(format!("no such command: {}", s)),
+    }
+}
+
+/// Registry entries fuzzily matching `query`, best first. Every query char
+/// must appear in order in the name; contiguous runs and word starts score
+/// higher.
+pub fn complete(query: &str) -> Vec<(String, Action)> {
+    let mut scored: Vec<(i32, String, Action)> = registry()
+        .into_iter()
+        .filter_map(|(name, a)| fuzzy_score(query, &name).map(|s| (s, name, a)))
+        .collect();
+    // stable, so equal scores keep registry order.
+    scored.sort_by_key(|(s, _, _)| -s);
+    scored.into_iter().map(|(_, n, a)| (n, a)).collect()
+}
+
+fn fuzzy_score(query: &str, name: &str) -> Option<i32> {
+    let name: Vec<char> = name.chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut prev_match: Option<usize> = None;
+    for q in query.to_lowercase().chars() {
+        if q == ' ' {
+            continue;
+        }
+        let found = (pos..name.len()).find(|&i| name[i].to_ascii_lowercase() == q)?;
+        if prev_match.is_some_and(|p| p + 1 == found) {
+            score += 2;
+        }
+        if found == 0 || name[found - 1] == ' ' {
+            score += 3;
+        }
+        score += 1;
+        prev_match = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
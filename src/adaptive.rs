@@ -0,0 +1,93 @@
+//! Adaptive: a sampling delay that stretches while the system is quiet and
+//! snaps back when it gets busy (see --adaptive).
+//
+// Samples then aren't evenly spaced; sproc records when each was taken, so
+// histories still line up with the clock.
+use serde::{Deserialize, Serialize};
+
+use crate::sprocs::SystemStats;
+
+// quiet samples in a row before slowing down another step.
+const QUIET_SAMPLES: usize = 3;
+
+/// When to slow down and when to speed back up.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdaptiveTick {
+    /// Whether to adapt at all, like --adaptive.
+    pub on: bool,
+    /// Total cpu % (of all cpus) under which the system counts as quiet.
+    pub idle_cpu: f64,
+    /// Total cpu % at or over which it's busy.
+    pub busy_cpu: f64,
+    /// Processes started or exited in a sample at or over which it's busy.
+    pub busy_churn: usize,
+    /// The longest delay to stretch to, in seconds.
+    pub max_delay: f64,
+}
+
+impl Default for AdaptiveTick {
+    fn default() -> Self {
+        Self {
+            on: false,
+            idle_cpu: 10.,
+            busy_cpu: 50.,
+            busy_churn: 5,
+            max_delay: 8.,
+        }
+    }
+}
+
+/// The delay to sample at: `base` while busy, doubling (up to `max_delay`)
+/// after every few quiet samples.
+pub struct Adaptive {
+    levels: AdaptiveTick,
+    base: f64,
+    delay: f64,
+    quiet: usize, // samples in a row
+}
+
+impl Adaptive {
+    pub fn new(levels: AdaptiveTick, base: f64) -> Self {
+        Self {
+            levels,
+            base,
+            delay: base,
+            quiet: 0,
+        }
+    }
+
+    /// A new delay to sample at from, e.g. after changing it by hand.
+    pub fn set_base(&mut self, base: f64) {
+        self.base = base;
+        self.delay = base;
+        self.quiet = 0;
+    }
+
+    pub fn delay(&self) -> f64 {
+        self.delay
+    }
+
+    /// Go by the latest sample; Some new delay if it's changed.
+    pub fn update(&mut self, system: &SystemStats) -> Option<f64> {
+        let l = &self.levels;
+        let churn = [&system.starts_hist, &system.exits_hist]
+            .iter()
+            .map(|h| h.front().copied().unwrap_or(0.))
+            .sum::<f64>() as usize;
+        let before = self.delay;
+        if system.cpu_pct >= l.busy_cpu || churn >= l.busy_churn {
+            self.quiet = 0;
+            self.delay = self.base;
+        } else if system.cpu_pct < l.idle_cpu && churn == 0 {
+            self.quiet += 1;
+            if self.quiet >= QUIET_SAMPLES {
+                self.quiet = 0;
+                self.delay = (self.delay * 2.).min(l.max_delay.max(self.base));
+            }
+        } else {
+            self.quiet = 0;
+        }
+        (self.delay != before).then_some(self.delay)
+    }
+}
@@ -0,0 +1,340 @@
+//! Alert: rules checked with every sample, like "cpu > 90 for 30s" or
+//! "total mem_pct > 95", and the log of everything the view has alerted.
+//
+// Syntax: `[total] <metric> <op> <value> [for <duration>]`. Without `total`
+// it's checked against each process: cpu (% of one core), mem (K/M/G
+// suffixes, binary), mem_pct, read and write (bytes per tick). With it,
+// against the whole system: cpu (% of all cpus), mem_pct, swap_pct and load
+// (the 1 minute average). op is one of > >= < <=, and the duration is like
+// --for's (30s, 2m). A rule fires once when its condition has held for the
+// duration, and again only after it's stopped holding.
+//
+// A rule can end in `-> <command>`, run with `sh -c` when it fires, e.g.
+// `cpu > 90 for 30s -> jstack $SPARKTOP_PID > /tmp/$SPARKTOP_NAME.stack`.
+// SPARKTOP_RULE, SPARKTOP_VALUE and (for process rules) SPARKTOP_PID,
+// SPARKTOP_NAME and SPARKTOP_CPU are set. Each rule's command runs at most
+// once every RUN_GAP_SECS, however often it fires, so a flapping rule or one
+// matching many processes doesn't spawn a crowd.
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    str::FromStr,
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, bail, Error, Result};
+
+use crate::{
+    headless::parse_duration, notify::spawn_hook, sproc::SProc, sprocs::SystemStats,
+    threshold::parse_bytes, units::render_bytes,
+};
+
+// alerts kept in the log, oldest dropped first.
+const LOG_LIMIT: usize = 1000;
+
+// the least time between runs of a rule's command.
+pub const RUN_GAP_SECS: f64 = 60.;
+
+#[derive(Clone, Debug)]
+pub struct AlertRule {
+    src: String, // the condition
+    metric: Metric,
+    op: Op,
+    value: f64,
+    for_secs: f64,
+    command: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Metric {
+    Cpu,
+    Mem,
+    MemPct,
+    Read,
+    Write,
+    TotalCpu,
+    TotalMemPct,
+    TotalSwapPct,
+    TotalLoad,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl FromStr for AlertRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (s, command) = match s.split_once("->") {
+            Some((cond, cmd)) if cmd.trim().is_empty() => {
+                bail!("no command after -> in {:?}", cond)
+            }
+            Some((cond, cmd)) => (cond, Some(cmd.trim().to_string())),
+            None => (s, None),
+        };
+        let ops = [(">=", Op::Ge), ("<=", Op::Le), (">", Op::Gt), ("<", Op::Lt)];
+        let (lhs, op, rhs) = ops
+            .iter()
+            .find_map(|&(tok, op)| s.split_once(tok).map(|(l, r)| (l.trim(), op, r.trim())))
+            .ok_or_else(|| anyhow!("no comparison in {:?}", s))?;
+        let (rhs, for_secs) = match rhs.split_once(" for ") {
+            Some((v, d)) => (v.trim(), parse_duration(d)?.as_secs_f64()),
+            None => (rhs, 0.),
+        };
+        let metric = match lhs.strip_prefix("total ").map(str::trim) {
+            Some("cpu") => Metric::TotalCpu,
+            Some("mem_pct") => Metric::TotalMemPct,
+            Some("swap_pct") => Metric::TotalSwapPct,
+            Some("load") => Metric::TotalLoad,
+            Some(m) => bail!("unknown total {:?} (want cpu, mem_pct, swap_pct, load)", m),
+            None => match lhs {
+                "cpu" => Metric::Cpu,
+                "mem" => Metric::Mem,
+                "mem_pct" => Metric::MemPct,
+                "read" => Metric::Read,
+                "write" => Metric::Write,
+                m => bail!(
+                    "unknown metric {:?} (want cpu, mem, mem_pct, read, write, or total ...)",
+                    m
+                ),
+            },
+        };
+        let value = match metric {
+            Metric::Mem | Metric::Read | Metric::Write => parse_bytes(rhs)?,
+            _ => rhs
+                .trim_end_matches('%')
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("{:?} isn't a number", rhs))?,
+        };
+        Ok(Self {
+            src: s.trim().into(),
+            metric,
+            op,
+            value,
+            for_secs,
+            command,
+        })
+    }
+}
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.command {
+            Some(cmd) => write!(f, "{} -> {}", self.src, cmd),
+            None => f.write_str(&self.src),
+        }
+    }
+}
+
+impl AlertRule {
+    fn total(&self) -> bool {
+        use Metric::*;
+        matches!(
+            self.metric,
+            TotalCpu | TotalMemPct | TotalSwapPct | TotalLoad
+        )
+    }
+
+    fn value(&self, sp: Option<&SProc>, system: &SystemStats) -> f64 {
+        match (self.metric, sp) {
+            (Metric::Cpu, Some(sp)) => sp.cpu_hist.front().copied().unwrap_or(0.),
+            (Metric::Mem, Some(sp)) => sp.mem_bytes as f64,
+            (Metric::MemPct, Some(sp)) => sp.mem_pct,
+            (Metric::Read, Some(sp)) => sp.disk_read_hist.front().copied().unwrap_or(0) as f64,
+            (Metric::Write, Some(sp)) => sp.disk_write_hist.front().copied().unwrap_or(0) as f64,
+            (Metric::TotalCpu, _) => system.cpu_pct,
+            (Metric::TotalMemPct, _) => system.mem_pct(),
+            (Metric::TotalSwapPct, _) => system.swap_pct(),
+            (Metric::TotalLoad, _) => system.load[0],
+            (_, None) => 0.,
+        }
+    }
+
+    fn holds(&self, v: f64) -> bool {
+        match self.op {
+            Op::Gt => v > self.value,
+            Op::Ge => v >= self.value,
+            Op::Lt => v < self.value,
+            Op::Le => v <= self.value,
+        }
+    }
+
+    // what a command is told about the firing.
+    fn env(&self, sp: Option<&SProc>, v: f64) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("SPARKTOP_RULE", self.src.clone()),
+            ("SPARKTOP_VALUE", v.to_string()),
+        ];
+        if let Some(sp) = sp {
+            let cpu = sp.cpu_hist.front().copied().unwrap_or(0.);
+            env.extend([
+                ("SPARKTOP_PID", sp.pid.to_string()),
+                ("SPARKTOP_NAME", sp.name.clone()),
+                ("SPARKTOP_CPU", format!("{:.1}", cpu)),
+            ]);
+        }
+        env
+    }
+
+    fn render(&self, v: f64) -> String {
+        match self.metric {
+            Metric::Mem | Metric::Read | Metric::Write => render_bytes(v),
+            Metric::TotalLoad => format!("{:.2}", v),
+            _ => format!("{:.1}%", v),
+        }
+    }
+}
+
+/// Alert rules, how long each has held for, by process (or None for the
+/// system), and when each last ran its command.
+#[derive(Default)]
+pub struct Alerts {
+    rules: Vec<AlertRule>,
+    held: Vec<HashMap<Option<i32>, Held>>,
+    ran: Vec<Option<f64>>,
+    clock: f64, // seconds of samples checked
+}
+
+#[derive(Copy, Clone)]
+struct Held {
+    secs: f64,
+    fired: bool,
+}
+
+impl Alerts {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        let held = vec![HashMap::new(); rules.len()];
+        let ran = vec![None; rules.len()];
+        Self {
+            rules,
+            held,
+            ran,
+            clock: 0.,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Check every rule against the latest sample, `secs` after the last,
+    /// returning messages for those that fired, and running their commands.
+    pub fn check(&mut self, procs: &[&SProc], system: &SystemStats, secs: f64) -> Vec<String> {
+        self.clock += secs;
+        let mut msgs = vec![];
+        let rules = self.rules.iter().zip(&mut self.held).zip(&mut self.ran);
+        for ((rule, held), ran) in rules {
+            let subjects: Vec<Option<&SProc>> = if rule.total() {
+                vec![None]
+            } else {
+                procs
+                    .iter()
+                    .copied()
+                    .filter(|sp| !sp.is_dead())
+                    .map(Some)
+                    .collect()
+            };
+            // only what holds now carries over; anything else starts again.
+            let mut now = HashMap::new();
+            for sp in subjects {
+                let v = rule.value(sp, system);
+                if !rule.holds(v) {
+                    continue;
+                }
+                let key = sp.map(|sp| sp.pid);
+                let mut h = match held.get(&key) {
+                    Some(h) => Held {
+                        secs: h.secs + secs,
+                        ..*h
+                    },
+                    None => Held {
+                        secs: 0.,
+                        fired: false,
+                    },
+                };
+                if !h.fired && h.secs >= rule.for_secs {
+                    h.fired = true;
+                    let mut msg = match sp {
+                        Some(sp) => format!(
+                            "alert: {} ({}) {}: {}",
+                            sp.name,
+                            sp.pid,
+                            rule.render(v),
+                            rule.src
+                        ),
+                        None => format!("alert: system {}: {}", rule.render(v), rule.src),
+                    };
+                    if let Some(cmd) = &rule.command {
+                        match *ran {
+                            Some(at) if self.clock - at < RUN_GAP_SECS => {
+                                msg += "; not running its command again yet"
+                            }
+                            _ => {
+                                *ran = Some(self.clock);
+                                spawn_hook(cmd, &rule.env(sp, v));
+                                msg += &format!("; ran {}", cmd);
+                            }
+                        }
+                    }
+                    msgs.push(msg);
+                }
+                now.insert(key, h);
+            }
+            *held = now;
+        }
+        msgs
+    }
+}
+
+/// Everything alerted, newest last: the latest is shown over the table
+/// until dismissed, and the rest stay for the alert pane.
+#[derive(Default)]
+pub struct AlertLog {
+    entries: VecDeque<(f64, String)>, // unix seconds, message
+    shown: bool,
+}
+
+impl AlertLog {
+    pub fn push(&mut self, msg: String) {
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0., |d| d.as_secs_f64());
+        self.entries.push_back((time, msg));
+        if self.entries.len() > LOG_LIMIT {
+            self.entries.pop_front();
+        }
+        self.shown = true;
+    }
+
+    /// Stop showing the latest, keeping it in the log.
+    pub fn dismiss(&mut self) {
+        self.shown = false;
+    }
+
+    /// The latest, unless dismissed.
+    pub fn current(&self) -> Option<&str> {
+        self.entries
+            .back()
+            .filter(|_| self.shown)
+            .map(|(_, msg)| msg.as_str())
+    }
+
+    /// Oldest first, with when each was raised.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &(f64, String)> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
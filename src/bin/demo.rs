@@ -0,0 +1,309 @@
+//! Demo: made-up processes in the real UI, for screenshots, UI work and
+//! timing draws without needing a busy machine.
+//
+// Each process follows a simple script (idle, steady, spiky, leaking, or
+// short-lived); scenarios pick the mix. Seeded, so the same flags give the
+// same run.
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use sparktop::ui::backend::TestBackend;
+use structopt::StructOpt;
+
+use sparktop::{
+    event::{Event, EventStream, Next},
+    lifetime::{LifeEventKind, LifetimeLog},
+    sproc::{DeadStatus, History, SProc},
+    sterm::STerm,
+    view::{self, View},
+};
+
+const EWMA_WEIGHT: f64 = 0.5;
+const MB: u64 = 1024 * 1024;
+const TOTAL_MEM_MB: f64 = 16384.; // the pretend machine's
+
+#[derive(StructOpt)]
+struct Opt {
+    /// What the processes do: mixed, spikes, leak or exits.
+    #[structopt(default_value = "mixed")]
+    scenario: Scenario,
+    /// Seconds between samples.
+    #[structopt(short, default_value = "0.5")]
+    delay: f64,
+    /// How many processes to start with.
+    #[structopt(short, default_value = "40")]
+    procs: usize,
+    #[structopt(long, default_value = "1")]
+    seed: u64,
+    /// Instead of the UI, draw this many ticks to an off-screen terminal
+    /// and print how long each draw took.
+    #[structopt(long)]
+    bench: Option<usize>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Scenario {
+    Mixed,  // a bit of everything
+    Spikes, // mostly idle, with bursts
+    Leak,   // a few growing without bound
+    Exits,  // waves of workers dying and being replaced
+}
+
+impl std::str::FromStr for Scenario {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "mixed" => Scenario::Mixed,
+            "spikes" => Scenario::Spikes,
+            "leak" => Scenario::Leak,
+            "exits" => Scenario::Exits,
+            _ => bail!("unknown scenario {:?} (mixed, spikes, leak or exits)", s),
+        })
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Script {
+    Idle,
+    Steady(f64),          // cpu %
+    Spiky { every: u64 }, // idle, but busy for a few ticks every so often
+    Leak { mb_per_tick: f64 },
+    ShortLived { left: u64 }, // ticks until it exits
+}
+
+struct Sim {
+    procs: Vec<(SProc, Script)>,
+    events: LifetimeLog,
+    history: History,
+    tick: u64,
+    next_pid: i32,
+    rng: Rng,
+    scenario: Scenario,
+}
+
+// xorshift64; no need for anything better here.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n.max(1)
+    }
+
+    // 0 to 1.
+    fn unit(&mut self) -> f64 {
+        self.below(10_000) as f64 / 10_000.
+    }
+}
+
+const NAMES: [&str; 8] = [
+    "postgres",
+    "nginx",
+    "python3",
+    "node",
+    "java",
+    "redis-server",
+    "cargo",
+    "chrome",
+];
+
+impl Sim {
+    fn new(scenario: Scenario, n: usize, seed: u64) -> Self {
+        let mut sim = Sim {
+            procs: vec![],
+            events: LifetimeLog::default(),
+            history: History::default(),
+            tick: 0,
+            next_pid: 1000,
+            rng: Rng(seed.max(1)),
+            scenario,
+        };
+        // a parent for the workers, so the tree keys have something to show.
+        let master = sim.spawn("supervisor", Script::Steady(2.), None);
+        for i in 0..n {
+            let script = sim.pick_script(i);
+            let name = NAMES[i % NAMES.len()];
+            sim.spawn(name, script, Some(master));
+        }
+        sim
+    }
+
+    fn pick_script(&mut self, i: usize) -> Script {
+        let r = self.rng.below(100);
+        match self.scenario {
+            Scenario::Mixed => match r {
+                0..=49 => Script::Idle,
+                50..=74 => Script::Steady(self.rng.unit() * 60.),
+                75..=89 => Script::Spiky {
+                    every: 10 + self.rng.below(30),
+                },
+                90..=94 => Script::Leak {
+                    mb_per_tick: 1. + self.rng.unit() * 5.,
+                },
+                _ => Script::ShortLived {
+                    left: 5 + self.rng.below(20),
+                },
+            },
+            Scenario::Spikes => match r {
+                0..=69 => Script::Spiky {
+                    every: 8 + self.rng.below(40),
+                },
+                _ => Script::Idle,
+            },
+            Scenario::Leak if i < 3 => Script::Leak {
+                mb_per_tick: 2. + 4. * i as f64,
+            },
+            Scenario::Leak => Script::Steady(self.rng.unit() * 10.),
+            Scenario::Exits => Script::Steady(5. + self.rng.unit() * 30.),
+        }
+    }
+
+    fn spawn(&mut self, name: &str, script: Script, ppid: Option<i32>) -> i32 {
+        let pid = self.next_pid;
+        self.next_pid += 1;
+        let mut sp = SProc::synthetic(pid, name);
+        sp.ppid = ppid;
+        sp.cmd = vec![name.into(), format!("--worker={}", pid)];
+        sp.mem_bytes = ((20. + self.rng.unit() * 200.) * MB as f64) as u64;
+        sp.set_unborn(self.tick as usize);
+        self.events.push(LifeEventKind::Start, &sp, self.tick);
+        self.procs.push((sp, script));
+        pid
+    }
+
+    fn step(&mut self) {
+        self.tick += 1;
+        let tick = self.tick;
+        // simulated time passes exactly on schedule.
+        let secs = self.history.interval();
+        self.history.next_sample(secs);
+        let mut exited = 0;
+        // waves of exits: a third of the workers at a time.
+        let wave = self.scenario == Scenario::Exits && tick.is_multiple_of(30);
+        let mut reaped = vec![];
+        for (sp, script) in &mut self.procs {
+            // the dead fade out, then go.
+            if sp.is_dead() {
+                if let DeadStatus::ShouldReap = sp.add_dead_sample(&self.history, EWMA_WEIGHT) {
+                    reaped.push(sp.pid);
+                }
+                continue;
+            }
+            let dies = match script {
+                Script::ShortLived { left } if *left == 0 => true,
+                _ => wave && sp.ppid.is_some() && self.rng.below(3) == 0,
+            };
+            if dies {
+                sp.add_dead_sample(&self.history, EWMA_WEIGHT);
+                self.events.push(LifeEventKind::Exit, sp, tick);
+                exited += 1;
+                continue;
+            }
+            let noise = self.rng.unit();
+            let (cpu, mem) = match script {
+                Script::Idle => (noise * 0.5, sp.mem_bytes),
+                Script::Steady(cpu) => (*cpu * (0.8 + 0.4 * noise), sp.mem_bytes),
+                Script::Spiky { every } => match tick % *every < 3 {
+                    true => (150. + noise * 200., sp.mem_bytes),
+                    false => (noise, sp.mem_bytes),
+                },
+                Script::Leak { mb_per_tick } => (
+                    5. + noise * 5.,
+                    sp.mem_bytes + (*mb_per_tick * MB as f64) as u64,
+                ),
+                Script::ShortLived { left } => {
+                    *left -= 1;
+                    (40. + noise * 40., sp.mem_bytes)
+                }
+            };
+            let read = self.rng.below(4) * MB * (cpu > 50.) as u64;
+            let write = self.rng.below(2) * MB * (cpu > 100.) as u64;
+            sp.add_synthetic_sample(&self.history, cpu, mem, read, write, EWMA_WEIGHT);
+            sp.mem_pct = 100. * mem as f64 / (TOTAL_MEM_MB * MB as f64);
+        }
+        self.procs.retain(|(sp, _)| !reaped.contains(&sp.pid));
+        // replace the exited, so the population holds steady.
+        let master = self.procs.first().map(|(sp, _)| sp.pid);
+        for i in 0..exited {
+            let script = self.pick_script(i);
+            let name = NAMES[self.rng.below(NAMES.len() as u64) as usize];
+            self.spawn(name, script, master);
+        }
+    }
+
+    fn procs(&self) -> Vec<&SProc> {
+        self.procs.iter().map(|(sp, _)| sp).collect()
+    }
+}
+
+fn main() -> Result<()> {
+    let opt = Opt::from_args();
+    let mut sim = Sim::new(opt.scenario, opt.procs, opt.seed);
+    if let Some(ticks) = opt.bench {
+        return bench(&mut sim, ticks);
+    }
+    let mut view = View::default();
+    view.set_sample_interval(opt.delay);
+    view.set_monochrome(view::no_color_env());
+    sim.history.set_interval(opt.delay);
+    let events = EventStream::new(Duration::from_secs_f64(opt.delay));
+    let ticker = events.ticker();
+    for event in events {
+        let next = match event {
+            Event::Resize => Next::Continue,
+            Event::Key(k) => view.handle_key(k),
+            Event::Mouse(m) => view.handle_mouse(m),
+            Event::Tick => {
+                sim.step();
+                Next::Continue
+            }
+        };
+        match next {
+            Next::Continue => {
+                // simulated processes cost nothing to read.
+                if let Some(col) = view.take_cost_probe() {
+                    view.ask_cadence(col, Duration::ZERO, col.every().unwrap_or(1));
+                }
+                view.set_history(sim.history.clone());
+                view.draw(&sim.procs(), &sim.events)?;
+                // the simulation has no ewma of its own to adjust.
+                if let Some((secs, _)) = view.take_tuning() {
+                    sim.history.set_interval(secs);
+                    ticker.set_interval(Duration::from_secs_f64(secs));
+                }
+            }
+            Next::Quit => break,
+        }
+    }
+    Ok(())
+}
+
+// draw times on a large off-screen terminal.
+fn bench(sim: &mut Sim, ticks: usize) -> Result<()> {
+    let mut view = View::with_terminal(STerm::with_backend(TestBackend::new(200, 60))?);
+    let mut times = Vec::with_capacity(ticks);
+    for _ in 0..ticks {
+        sim.step();
+        let start = Instant::now();
+        view.draw(&sim.procs(), &sim.events)?;
+        times.push(start.elapsed());
+    }
+    times.sort();
+    let total: Duration = times.iter().sum();
+    println!(
+        "{} draws of {} processes: mean {:?}, median {:?}, max {:?}",
+        ticks,
+        sim.procs.len(),
+        total / ticks.max(1) as u32,
+        times.get(ticks / 2).copied().unwrap_or_default(),
+        times.last().copied().unwrap_or_default(),
+    );
+    Ok(())
+}
@@ -1,19 +1,215 @@
+use std::{
+    io::IsTerminal,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
 use anyhow::Result;
-use structopt::StructOpt;
+use structopt::{clap::Shell, StructOpt};
 
 use sparktop::{
+    action,
+    adaptive::Adaptive,
+    alert::AlertRule,
+    cgroup_io::CgroupIo,
+    column::Column,
+    config::{self, Config},
+    coredump::Dumps,
     event::{Event, EventStream, Next},
+    export::Snapshot,
+    filter::ProcFilter,
+    group::Grouping,
+    headless,
+    helper::{self, Helper},
+    kstack::KernelStacks,
+    lifetime::LifetimeLog,
+    notify::Notifier,
+    platform::{self, IoPriority},
+    record::Recorder,
+    rename::Renames,
+    render::Bars,
+    session::Session,
+    sproc::SProc,
     sprocs::SProcs,
-    view::View,
+    threshold::{self, Threshold, Thresholds},
+    view::{self, View},
+    view_state::{Dir, Metric, ZoomTab},
+    watch::Watch,
 };
 
+/// top, but like with sparkles
 #[derive(StructOpt)]
 struct Opt {
-    #[structopt(short, default_value = "1.")]
-    delay: f64,
-    // weight given to new samples.
-    #[structopt(short, default_value = "0.5")]
-    ewma_weight: f64,
+    /// Seconds between samples [default: 1, or the config file's delay].
+    #[structopt(short)]
+    delay: Option<f64>,
+    /// Weight given to new samples [default: 0.5, or the config file's
+    /// ewma_weight].
+    #[structopt(short)]
+    ewma_weight: Option<f64>,
+    /// Sample less often while the system is quiet, up to every 8s, and
+    /// back at -d as soon as cpu or process churn picks up (thresholds are
+    /// in the config file's adaptive).
+    #[structopt(long)]
+    adaptive: bool,
+    /// Seconds of history kept per process [default: 60 samples, or the
+    /// config file's history_secs or history]. Past 10 minutes, older
+    /// samples are kept as averages of 4.
+    #[structopt(long)]
+    history_secs: Option<f64>,
+    /// Append samples to this file as JSON lines, resuming if it exists.
+    #[structopt(long, parse(from_os_str))]
+    record: Option<PathBuf>,
+    /// Command to run `sparktop helper` with for privileged reads, e.g.
+    /// "sudo sparktop" or the path to a copy with CAP_SYS_PTRACE.
+    #[structopt(long)]
+    privileged_helper: Option<String>,
+    /// Let the privileged helper change io priorities too ('i'), not just
+    /// read. Realtime only for root or your own processes.
+    #[structopt(long)]
+    helper_ionice: bool,
+    /// Account for processes too short-lived to sample, in a "(short-lived)"
+    /// row. Uses the netlink proc connector; needs CAP_NET_ADMIN.
+    #[cfg(all(feature = "proc-connector", target_os = "linux"))]
+    #[structopt(long)]
+    short_lived: bool,
+    /// Show memory bandwidth per resctrl monitoring group (press B).
+    #[cfg(all(feature = "resctrl", target_os = "linux"))]
+    #[structopt(long)]
+    resctrl: bool,
+    /// Export the latest sample on the session bus as org.sparktop.Monitor
+    /// (methods Top and Processes), for desktop widgets and scripts.
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    #[structopt(long)]
+    dbus: bool,
+    /// Exit with status 99 if this is ever true while running, e.g.
+    /// "mem(firefox) > 8G" or "cpu > 200". Metrics: cpu, mem, read, write
+    /// (per tick); (name) sums processes whose name contains it, otherwise
+    /// all processes or the watched command's tree. Repeatable.
+    #[structopt(long = "fail-if", number_of_values = 1)]
+    fail_if: Vec<Threshold>,
+    /// Alert when this holds, e.g. "cpu > 90 for 30s" (any process) or
+    /// "total mem_pct > 95"; shown over the table and kept in the alert log
+    /// ('a'). End it with "-> <command>" to also run that (at most once a
+    /// minute), with $SPARKTOP_PID, $SPARKTOP_NAME and $SPARKTOP_CPU set.
+    /// Added to the config file's alerts. Repeatable.
+    #[structopt(long = "alert", number_of_values = 1)]
+    alerts: Vec<AlertRule>,
+    /// Run this rhai script's computed columns, row highlights and alerts
+    /// against every sample, e.g. `column("gb", |p| p.mem_mb / 1024)`.
+    /// Overrides the config file's script.
+    #[cfg(feature = "scripting")]
+    #[structopt(long, parse(from_os_str))]
+    script: Option<PathBuf>,
+    /// On exit, write the log of process starts and exits seen here, as JSON
+    /// lines.
+    #[structopt(long, parse(from_os_str))]
+    event_log: Option<PathBuf>,
+    /// Run this shell command when a bookmarked process exits or restarts,
+    /// with SPARKTOP_EVENT (exit or restart), SPARKTOP_PID, SPARKTOP_NAME and
+    /// SPARKTOP_OLD_PID (restarts) set.
+    #[structopt(long)]
+    bookmark_hook: Option<String>,
+    /// Commands to run at startup, as if typed in the command palette (:),
+    /// separated by ";". e.g. "sort mem; filter name=postgres; columns -pid".
+    #[structopt(long = "cmd")]
+    startup: Option<String>,
+    /// Read settings from this file instead of
+    /// $XDG_CONFIG_HOME/sparktop/config.toml.
+    #[structopt(long, parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Print a config file with every setting at its default, and exit.
+    #[structopt(long)]
+    write_default_config: bool,
+    /// Print a plain-text table of every process after each sample instead
+    /// of running the TUI, like `top -b`; see -n.
+    #[structopt(long)]
+    batch: bool,
+    /// With --batch, how many samples to print before exiting [default: until
+    /// interrupted].
+    #[structopt(short = "n", requires = "batch")]
+    iterations: Option<usize>,
+    /// With --batch, print each sample as a table, or as one line of JSON
+    /// with every process's current values and histories [default: table].
+    #[structopt(long, requires = "batch")]
+    output: Option<headless::Output>,
+    /// Mirror the screen, read-only, to `sparktop attach <socket>` over a
+    /// unix socket here. Only you can attach until you grant access to it.
+    #[cfg(unix)]
+    #[structopt(long, parse(from_os_str))]
+    share: Option<PathBuf>,
+    /// Only watch this process; repeatable. Others aren't sampled at all.
+    #[structopt(long = "pid", number_of_values = 1)]
+    pids: Vec<i32>,
+    /// Only watch processes whose name or command line matches this regex.
+    #[structopt(long)]
+    name: Option<String>,
+    /// Only watch processes run by this user (a name or uid).
+    #[structopt(long)]
+    user: Option<String>,
+    /// For screen readers: describe trends in words ("85%, rising") instead
+    /// of sparklines, and keep rows in place until the sort is changed.
+    #[structopt(long)]
+    accessible: bool,
+    /// Draw with plain ASCII only, for serial consoles and terminals without
+    /// block or braille glyphs: bars as `_.:-=#@`, and box drawing as `-|+`.
+    #[structopt(long)]
+    ascii: bool,
+    /// Draw without color, using bold and dim instead; also when NO_COLOR
+    /// is set.
+    #[structopt(long)]
+    no_color: bool,
+    /// Leave sparktop's own usage (always shown in its own row) out of the
+    /// system summary, group and filter totals.
+    #[structopt(long)]
+    exclude_self: bool,
+    /// Write a JSON summary of the watched command's resource usage here.
+    #[structopt(long, parse(from_os_str))]
+    summary_json: Option<PathBuf>,
+    /// Print a recap of the session on quitting: how long it was, peak
+    /// load, the processes that used the most cpu and memory, and alerts.
+    #[structopt(long)]
+    session_stats: bool,
+    #[structopt(subcommand)]
+    cmd: Option<Cmd>,
+    /// Run this command and watch it, exiting (with its exit code) when it
+    /// does.
+    #[structopt(last = true)]
+    command: Vec<String>,
+}
+
+#[derive(StructOpt)]
+enum Cmd {
+    /// Sample for a while without the TUI, then print the top processes by
+    /// average cpu.
+    Top {
+        /// How many processes to show.
+        #[structopt(short, default_value = "10")]
+        n: usize,
+        /// How long to sample for, e.g. 30s, 2m.
+        #[structopt(long = "for", default_value = "10s", parse(try_from_str = headless::parse_duration))]
+        period: Duration,
+    },
+    /// Watch a session shared with --share, read-only (q to leave).
+    #[cfg(unix)]
+    Attach {
+        #[structopt(parse(from_os_str))]
+        socket: PathBuf,
+    },
+    /// Print a shell completion script to stdout.
+    GenCompletions {
+        #[structopt(possible_values = &Shell::variants())]
+        shell: Shell,
+    },
+    /// Print a man page to stdout.
+    GenMan,
+    /// Serve privileged reads on stdin/stdout (see --privileged-helper).
+    #[structopt(setting = structopt::clap::AppSettings::Hidden)]
+    Helper {
+        /// Also set io priorities.
+        #[structopt(long)]
+        allow_ionice: bool,
+    },
 }
 
 fn main() -> Result<()> {
@@ -21,26 +217,601 @@ fn main() -> Result<()> {
     std::env::set_var("RUST_LOG", "info");
     // std::env::set_var("RUST_LOG", "debug");
     pretty_env_logger::init();
-    let opt = Opt::from_args();
+    let mut opt = Opt::from_args();
+    if opt.write_default_config {
+        print!("{}", Config::template()?);
+        return Ok(());
+    }
+
+    match opt.cmd {
+        Some(Cmd::GenCompletions { shell }) => {
+            Opt::clap().gen_completions_to("sparktop", shell, &mut std::io::stdout());
+            return Ok(());
+        }
+        Some(Cmd::GenMan) => {
+            print!("{}", man_page()?);
+            return Ok(());
+        }
+        Some(Cmd::Helper { allow_ionice }) => return helper::serve(allow_ionice),
+        #[cfg(unix)]
+        Some(Cmd::Attach { socket }) => return sparktop::share::attach(&socket),
+        _ => (),
+    }
+
+    // flags win over the config file.
+    let mut config = Config::load(opt.config.as_deref())?;
+    opt.delay = opt.delay.or(config.delay);
+    opt.ewma_weight = opt.ewma_weight.or(config.ewma_weight);
+    opt.history_secs = opt.history_secs.or(config.history_secs);
+
+    if let Some(Cmd::Top { n, period }) = opt.cmd {
+        let mut sprocs = opt.sprocs(&config)?;
+        let mut thresholds = Thresholds::new(std::mem::take(&mut opt.fail_if));
+        let delay = Duration::from_secs_f64(opt.delay());
+        let tallies = headless::sample(
+            &mut sprocs,
+            opt.ewma_weight(),
+            delay,
+            period,
+            &mut thresholds,
+        );
+        print!("{}", headless::top_report(&tallies, n));
+        if let Some(path) = &opt.event_log {
+            sprocs.events().write_json(path)?;
+        }
+        if thresholds.any_violated() {
+            std::process::exit(threshold::EXIT_CODE);
+        }
+        return Ok(());
+    }
+
+    if opt.batch {
+        let mut sprocs = opt.sprocs(&config)?;
+        let mut thresholds = Thresholds::new(std::mem::take(&mut opt.fail_if));
+        let batch = headless::Batch {
+            delay: Duration::from_secs_f64(opt.delay()),
+            iterations: opt.iterations,
+            output: opt.output.unwrap_or(headless::Output::Table),
+            sort: (
+                config.sort.unwrap_or(Metric::Cpu),
+                config.sort_dir.unwrap_or(Dir::Desc),
+            ),
+        };
+        headless::batch(
+            &mut sprocs,
+            opt.ewma_weight(),
+            &batch,
+            &mut thresholds,
+            &mut std::io::stdout(),
+        )?;
+        if thresholds.any_violated() {
+            std::process::exit(threshold::EXIT_CODE);
+        }
+        return Ok(());
+    }
+
+    let interactive = std::io::stdout().is_terminal();
+    let mut watch = if opt.command.is_empty() {
+        None
+    } else {
+        Some(Watch::spawn(&opt.command, interactive)?)
+    };
+    let mut thresholds = Thresholds::new(std::mem::take(&mut opt.fail_if));
+    match (&mut watch, interactive) {
+        (Some(w), false) => watch_headless(&opt, &config, w, &mut thresholds)?,
+        (w, _) => run_tui(&opt, &mut config, w.as_mut(), &mut thresholds)?,
+    }
+    if thresholds.any_violated() {
+        drop(watch);
+        std::process::exit(threshold::EXIT_CODE);
+    }
+
+    if let Some(mut w) = watch {
+        if let Some(path) = &opt.summary_json {
+            w.exited()?;
+            w.write_summary(&opt.command, path)?;
+        }
+        if let Some(code) = w.exit_code()? {
+            std::process::exit(code);
+        }
+    }
+    Ok(())
+}
+
+impl Opt {
+    fn delay(&self) -> f64 {
+        self.delay.unwrap_or(config::DEFAULT_DELAY)
+    }
+
+    fn ewma_weight(&self) -> f64 {
+        self.ewma_weight.unwrap_or(config::DEFAULT_EWMA_WEIGHT)
+    }
+
+    // sampling only the processes asked for with --pid, --name and --user,
+    // keeping as much history as asked for.
+    fn sprocs(&self, config: &Config) -> Result<SProcs> {
+        let mut sprocs = SProcs::default();
+        sprocs.set_sample_interval(self.delay());
+        match (self.history_secs, config.history) {
+            (Some(secs), _) => sprocs.set_history_secs(secs),
+            (None, Some(n)) => sprocs.set_history_len(n),
+            (None, None) => (),
+        }
+        if let Some(by) = config.compact_by {
+            sprocs.set_compact_by(by);
+        }
+        let filter = ProcFilter::new(
+            self.pids.clone(),
+            self.name.as_deref(),
+            self.user.as_deref(),
+        )?;
+        if let Some(f) = filter {
+            sprocs.set_filter(f);
+        }
+        Ok(sprocs)
+    }
+}
 
-    let mut sprocs = SProcs::default();
+fn run_tui(
+    opt: &Opt,
+    config: &mut Config,
+    mut watch: Option<&mut Watch>,
+    thresholds: &mut Thresholds,
+) -> Result<()> {
+    let mut recorder = opt.record.as_deref().map(Recorder::open).transpose()?;
+    let mut startup = vec![];
+    for script in config.startup.iter().chain(&opt.startup) {
+        startup.extend(action::parse_script(script).map_err(anyhow::Error::msg)?);
+    }
+    let renames = Renames::new(&config.renames)?;
+    let quirks = platform::quirks();
+    let min_delay = quirks.min_delay.unwrap_or(0.);
+    let delay = opt.delay().max(min_delay);
+    let mut ewma_weight = opt.ewma_weight();
+    let mut adaptive = match config.adaptive.clone().unwrap_or_default() {
+        a if a.on || opt.adaptive => Some(Adaptive::new(a, delay)),
+        _ => None,
+    };
+    let mut sprocs = opt.sprocs(config)?;
+    let own_pid = std::process::id() as i32;
+    sprocs.keep(own_pid);
+    // tolerate ticks arriving a bit early, but not bunched up.
+    sprocs.set_min_interval(Duration::from_secs_f64(delay * 0.5));
+    sprocs.set_counters(opt.record.is_some() || opt.summary_json.is_some());
+    for (&col, &n) in &config.refresh {
+        sprocs.set_every(col, n);
+    }
+    let mut alert_rules = config.alert_rules()?;
+    alert_rules.extend(opt.alerts.iter().cloned());
+    sprocs.set_alert_rules(alert_rules);
+    let bell = config.alert_bell == Some(true);
+    if let Some(cmd) = &opt.privileged_helper {
+        sprocs.set_helper(Helper::spawn(cmd, opt.helper_ionice)?);
+    }
+    #[cfg(all(feature = "proc-connector", target_os = "linux"))]
+    if opt.short_lived {
+        use anyhow::Context;
+        let connector = sparktop::proc_connector::ProcConnector::spawn()
+            .context("listening for process events (needs CAP_NET_ADMIN)")?;
+        sprocs.set_proc_connector(connector);
+    }
+    #[cfg(all(feature = "resctrl", target_os = "linux"))]
+    let mut resctrl = if opt.resctrl {
+        use anyhow::Context;
+        let r = sparktop::resctrl::Resctrl::open()
+            .context("resctrl bandwidth monitoring (is /sys/fs/resctrl mounted?)")?;
+        Some((r, std::time::Instant::now()))
+    } else {
+        None
+    };
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    let dbus = if opt.dbus {
+        use anyhow::Context;
+        let export = sparktop::dbus::DbusExport::spawn().with_context(|| {
+            format!("exporting {} on the session bus", sparktop::dbus::BUS_NAME)
+        })?;
+        Some(export)
+    } else {
+        None
+    };
+    // the zoomed process's cgroup io: (pid, tracker or why not, last update).
+    let mut cgroup_io: Option<(i32, Result<CgroupIo, String>, Instant)> = None;
+    // sampling the zoomed process's kernel stacks, on that tab.
+    let mut kernel_stacks: Option<KernelStacks> = None;
+    let mut notifier = Notifier::new(opt.bookmark_hook.clone());
+    let mut dumps = Dumps::default();
+    let mut session = opt.session_stats.then(Session::default);
+    #[cfg(unix)]
+    let mut share = opt
+        .share
+        .as_deref()
+        .map(sparktop::share::Share::bind)
+        .transpose()?;
     let mut view = View::default();
-    let events = EventStream::new(std::time::Duration::from_secs_f64(opt.delay));
+    #[cfg(unix)]
+    if share.is_some() {
+        view.terminal_mut().keep_frames();
+    }
+    view.set_sample_interval(delay);
+    sprocs.set_sample_interval(delay);
+    view.set_ewma_weight(ewma_weight);
+    if let Some(columns) = quirks.columns {
+        view.set_columns(columns);
+    }
+    if let Some(columns) = config.columns.clone() {
+        view.set_columns(columns);
+    }
+    match (config.sort, config.sort_dir) {
+        (None, None) => (),
+        (by, dir) => view.set_sort(by.unwrap_or(Metric::Cpu), dir.unwrap_or(Dir::Desc)),
+    }
+    view.set_breakpoints(config.breakpoints.clone());
+    view.set_bookmarks(config.bookmarks.clone());
+    view.set_muted(config.muted.clone());
+    view.set_accessible(opt.accessible);
+    view.set_monochrome(opt.no_color || view::no_color_env());
+    let glyphs = config.bars.clone().unwrap_or_default();
+    view.set_braille(glyphs.braille == Some(true));
+    let bars = match opt.ascii {
+        true => Bars::ascii(),
+        false => glyphs.bars()?,
+    };
+    view.set_bars(bars.clone(), glyphs.column_bars(&bars));
+    view.set_ascii(opt.ascii);
+    if let Some(theme) = &config.theme {
+        view.set_theme(theme.theme()?);
+    }
+    if let Some(levels) = config.colors.clone() {
+        view.set_color_levels(levels);
+    }
+    view.show_own_row(own_pid, !opt.exclude_self);
+    view.set_recording(opt.record.as_ref().map(|p| p.display().to_string()));
+    if watch.is_some() {
+        view.set_watching(Some(opt.command.join(" ")));
+    }
+    view.set_renames(renames);
+    view.set_row_rules(config.row_rules()?);
+    #[cfg(feature = "scripting")]
+    if let Some(path) = opt.script.as_ref().or(config.script.as_ref()) {
+        let script = sparktop::script::Script::load(path)?;
+        view.set_script(script.columns(), script.highlights());
+        sprocs.set_script(script);
+    }
+    #[cfg(not(feature = "scripting"))]
+    if config.script.is_some() {
+        view.set_alert("no script: built without scripting".into());
+    }
+    view.set_saved_filters(config.saved_filters()?);
+    let mut unavailable = platform::probe();
+    unavailable.extend(quirks.unavailable);
+    view.set_unavailable(unavailable);
+    if let Some(note) = quirks.note {
+        view.add_note(note);
+    }
+    // alerts as desktop notifications too, if we can.
+    #[cfg(all(feature = "desktop-notify", target_os = "linux"))]
+    let desktop = match config.alert_desktop {
+        Some(true) => match sparktop::notify::DesktopNotifier::spawn() {
+            Ok(n) => Some(n),
+            Err(e) => {
+                view.set_alert(format!("no desktop notifications: {}", e));
+                None
+            }
+        },
+        _ => None,
+    };
+    #[cfg(not(all(feature = "desktop-notify", target_os = "linux")))]
+    if config.alert_desktop == Some(true) {
+        view.set_alert("no desktop notifications: built without desktop-notify".into());
+    }
+    if view.run(startup) == Next::Quit {
+        return Ok(());
+    }
+    let events = EventStream::new(Duration::from_secs_f64(delay));
+    let ticker = events.ticker();
+    let mut frozen: Option<(Vec<SProc>, LifetimeLog)> = None;
     // hmm, maybe can restructure so that quitting gets injected as an event,
     // which halts the EventStream iterator?
     for event in events {
         let next = match event {
             Event::Resize => Next::Continue,
             Event::Key(k) => view.handle_key(k),
+            Event::Mouse(m) => view.handle_mouse(m),
             Event::Tick => {
-                sprocs.update(opt.ewma_weight);
+                sprocs.set_child_cpu(view.cumulative());
+                sprocs.set_latency(view.showing(Column::Latency));
+                sprocs.set_wakeups(view.showing(Column::Wakeups));
+                sprocs.set_state(view.showing(Column::State) || view.showing(Column::Threads));
+                sprocs.set_io_priority(view.showing(Column::IoPrio));
+                sprocs.set_fds(view.showing(Column::Fds));
+                sprocs.set_ports(view.showing(Column::Ports));
+                sprocs.set_mem_limits(view.forecasting());
+                sprocs.set_cpu_quotas(view.cpu_gauges());
+                sprocs.set_cpu_split(view.splitting_cpu());
+                sprocs.set_zoomed(view.zoomed());
+                sprocs.set_pods(view.showing(Column::Pod) || view.grouping(Grouping::Pod));
+                sprocs.update(ewma_weight);
+                // paused, the view keeps what it showed; sampling, and
+                // everything acting on samples, carries on.
+                let live = !view.paused();
+                if live {
+                    view.set_system(sprocs.system().clone());
+                    view.set_history(sprocs.history().clone());
+                    view.set_stale(sprocs.stale());
+                }
+                if let Some(a) = &mut adaptive {
+                    if let Some(secs) = a.update(sprocs.system()) {
+                        ticker.set_interval(Duration::from_secs_f64(secs));
+                        view.set_adaptive_delay(Some(secs));
+                    }
+                }
+                if live && view.smart_sort() {
+                    match platform::pressure() {
+                        Ok(p) => view.set_pressure(p),
+                        Err(e) => {
+                            view.disable_smart_sort(format!("can't sort by pressure: psi: {}", e))
+                        }
+                    }
+                }
+                #[cfg(all(feature = "resctrl", target_os = "linux"))]
+                if let Some((r, last)) = &mut resctrl {
+                    r.update(last.elapsed().as_secs_f64(), sprocs.history().samples());
+                    *last = std::time::Instant::now();
+                    let groups = r
+                        .groups()
+                        .map(|(name, g)| (name.clone(), g.mb_per_sec.iter().copied().collect()));
+                    if live {
+                        view.set_bandwidth(groups.collect());
+                    }
+                }
+                match view.zoomed() {
+                    Some(pid) => {
+                        if cgroup_io.as_ref().map(|(p, _, _)| *p) != Some(pid) {
+                            let io = CgroupIo::for_pid(pid).map_err(|e| e.to_string());
+                            cgroup_io = Some((pid, io, Instant::now()));
+                        }
+                        if let Some((_, io, last)) = &mut cgroup_io {
+                            if let Ok(tracker) = io {
+                                let keep = sprocs.history().samples();
+                                if let Err(e) = tracker.update(last.elapsed().as_secs_f64(), keep) {
+                                    *io = Err(e.to_string());
+                                }
+                            }
+                            *last = Instant::now();
+                            if live {
+                                view.set_cgroup_io(Some(
+                                    io.as_ref().map(|t| t.summary()).map_err(String::clone),
+                                ));
+                            }
+                        }
+                    }
+                    None => {
+                        cgroup_io = None;
+                        view.set_cgroup_io(None);
+                    }
+                }
+                match (view.zoomed(), view.zoom_tab()) {
+                    (Some(pid), Some(ZoomTab::KernelStack)) => {
+                        if kernel_stacks.as_ref().map(|k| k.pid) != Some(pid) {
+                            kernel_stacks = Some(KernelStacks::spawn(pid));
+                        }
+                        if let Some(k) = &mut kernel_stacks {
+                            k.update();
+                            if live {
+                                view.set_kernel_stacks(Some(k.summary(5)));
+                            }
+                        }
+                    }
+                    _ => {
+                        kernel_stacks = None;
+                        view.set_kernel_stacks(None);
+                    }
+                }
+                if let Some(r) = &mut recorder {
+                    r.record(sprocs.get())?;
+                }
+                #[cfg(all(feature = "dbus", target_os = "linux"))]
+                if let Some(d) = &dbus {
+                    d.publish(&sprocs.get().collect::<Vec<_>>(), view.cumulative());
+                }
+                let raised = sprocs.take_alerts();
+                if bell && !raised.is_empty() {
+                    view.terminal_mut().bell();
+                }
+                #[cfg(all(feature = "desktop-notify", target_os = "linux"))]
+                if let Some(d) = &desktop {
+                    for msg in &raised {
+                        d.notify("sparktop", msg.trim_start_matches("alert: "));
+                    }
+                }
+                let mut msgs = notifier.check(&sprocs, view.bookmarks());
+                msgs.extend(raised);
+                msgs.extend(dumps.finished());
+                msgs.extend(match &mut watch {
+                    Some(w) => {
+                        if !w.is_running() {
+                            break;
+                        }
+                        w.sample(&sprocs, thresholds)
+                    }
+                    None => thresholds.check(&sprocs.get().collect::<Vec<_>>()),
+                });
+                if let Some(s) = &mut session {
+                    s.sample(&sprocs);
+                    for msg in &msgs {
+                        s.alert(msg);
+                    }
+                }
+                for msg in msgs {
+                    view.set_alert(msg);
+                }
                 Next::Continue
             }
         };
         match next {
-            Next::Continue => view.draw(&mut sprocs.get().collect())?,
+            Next::Continue => {
+                if let Some(col) = view.take_cost_probe() {
+                    let cost = sprocs.measure(col);
+                    view.ask_cadence(col, cost, sprocs.read_every(col));
+                }
+                // paused: what was on screen then, until unpaused.
+                match (view.paused(), &frozen) {
+                    (false, _) => frozen = None,
+                    (true, None) => {
+                        frozen = Some((sprocs.get().cloned().collect(), sprocs.events().clone()))
+                    }
+                    (true, Some(_)) => (),
+                }
+                match &frozen {
+                    Some((procs, events)) => {
+                        view.draw(&procs.iter().collect::<Vec<_>>(), events)?
+                    }
+                    None => view.draw(&sprocs.get().collect::<Vec<_>>(), sprocs.events())?,
+                }
+                #[cfg(unix)]
+                if let (Some(s), Some(frame)) = (&mut share, view.terminal().last_frame()) {
+                    if s.publish(frame) > 0 {
+                        view.set_alert("someone attached to this shared session".into());
+                    }
+                }
+                if let Some((pid, name, with)) = view.take_dump() {
+                    match dumps.start(pid, &name, with) {
+                        Ok(done) | Err(done) => view.set_alert(done),
+                    }
+                }
+                if let Some((secs, weight)) = view.take_tuning() {
+                    let secs = secs.max(min_delay);
+                    view.set_sample_interval(secs);
+                    sprocs.set_sample_interval(secs);
+                    sprocs.set_min_interval(Duration::from_secs_f64(secs * 0.5));
+                    ticker.set_interval(Duration::from_secs_f64(secs));
+                    if let Some(a) = &mut adaptive {
+                        a.set_base(secs);
+                        view.set_adaptive_delay(Some(secs));
+                    }
+                    ewma_weight = weight;
+                }
+                if view.take_stop_recording() {
+                    // dropping it writes out what's buffered.
+                    recorder = None;
+                    view.set_recording(None);
+                    if let Some(path) = &opt.record {
+                        view.set_alert(format!("stopped recording to {}", path.display()));
+                    }
+                }
+                if view.take_export() {
+                    let snapshot = Snapshot::now(sprocs.get());
+                    let path = PathBuf::from(format!("sparktop-{:.0}.json", snapshot.time));
+                    match snapshot.write(&path) {
+                        Ok(()) => view.set_alert(format!("exported to {}", path.display())),
+                        Err(e) => view.set_alert(format!("couldn't export: {:#}", e)),
+                    }
+                }
+                if let Some((col, n)) = view.take_cadence() {
+                    sprocs.set_every(col, n);
+                }
+                if let Some(pid) = view.take_freeze() {
+                    match sprocs.toggle_freeze(pid) {
+                        Ok(done) | Err(done) => view.set_alert(done),
+                    }
+                }
+                if let Some((pid, change)) = view.take_ionice() {
+                    let current = platform::io_priority(pid).unwrap_or(IoPriority::Default);
+                    let prio = change.apply(current);
+                    if prio == current {
+                        view.set_alert(format!(
+                            "io priority of {} is already {}",
+                            pid,
+                            prio.name()
+                        ));
+                    } else {
+                        match sprocs.ionice(pid, prio) {
+                            Ok(()) => view.set_alert(format!(
+                                "io priority of {} set to {}",
+                                pid,
+                                prio.name()
+                            )),
+                            Err(e) => view.set_alert(e),
+                        }
+                    }
+                }
+                if let Some(bookmarks) = view.take_changed_bookmarks() {
+                    config.bookmarks = bookmarks;
+                    if let Err(e) = config.save() {
+                        view.set_alert(format!("couldn't save bookmarks: {:#}", e));
+                    }
+                }
+                if let Some(muted) = view.take_changed_muted() {
+                    config.muted = muted;
+                    if let Err(e) = config.save() {
+                        view.set_alert(format!("couldn't save mutes: {:#}", e));
+                    }
+                }
+            }
             Next::Quit => break,
         }
     }
+    if let Some(path) = &opt.event_log {
+        sprocs.events().write_json(path)?;
+    }
+    if let Some(s) = session {
+        // after the terminal's restored, so it stays on screen.
+        drop(view);
+        print!("{}", s.report(opt.ascii));
+    }
     Ok(())
 }
+
+// Not on a terminal (e.g. in CI): just sample the watched command until it
+// exits, letting it use stdout/stderr.
+fn watch_headless(
+    opt: &Opt,
+    config: &Config,
+    watch: &mut Watch,
+    thresholds: &mut Thresholds,
+) -> Result<()> {
+    let mut recorder = opt.record.as_deref().map(Recorder::open).transpose()?;
+    let mut sprocs = opt.sprocs(config)?;
+    sprocs.set_counters(opt.record.is_some() || opt.summary_json.is_some());
+    let delay = Duration::from_secs_f64(opt.delay());
+    sprocs.update(opt.ewma_weight());
+    while watch.is_running() {
+        std::thread::sleep(delay);
+        sprocs.update(opt.ewma_weight());
+        if let Some(r) = &mut recorder {
+            r.record(sprocs.get())?;
+        }
+        for msg in watch.sample(&sprocs, thresholds) {
+            eprintln!("{}", msg);
+        }
+    }
+    if let Some(path) = &opt.event_log {
+        sprocs.events().write_json(path)?;
+    }
+    Ok(())
+}
+
+// A minimal roff man page built from clap's own help output, so it can't
+// drift from the real flags.
+fn man_page() -> Result<String> {
+    let mut help = vec![];
+    Opt::clap().write_long_help(&mut help)?;
+    let help = String::from_utf8(help)?;
+    let escaped: Vec<String> = help
+        .lines()
+        .skip_while(|l| !l.starts_with("USAGE"))
+        .map(|l| {
+            let l = l.replace('\\', "\\\\");
+            if l.starts_with('.') || l.starts_with('\'') {
+                format!("\\&{}", l)
+            } else {
+                l
+            }
+        })
+        .collect();
+    Ok(format!(
+        ".TH SPARKTOP 1 \"\" \"sparktop {}\"\n.SH NAME\nsparktop \\- top, but like with sparkles\n.SH DESCRIPTION\n.nf\n{}\n.fi\n",
+        env!("CARGO_PKG_VERSION"),
+        escaped.join("\n")
+    ))
+}
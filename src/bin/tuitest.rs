@@ -1,4 +1,4 @@
-/// scratch space for figuring out how to build things in tui reasonably
+/// scratch space for figuring out how to build things in ratatui reasonably
 //
 // ok, structure that seems reasonable:
 // - State struct
@@ -10,8 +10,7 @@
 use std::fmt::Display;
 
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
-use tui::{
-    backend::Backend,
+use sparktop::ui::{
     layout::{Constraint, Direction, Layout, Rect},
     widgets::{Block, Borders, Paragraph},
     Frame,
@@ -110,6 +109,7 @@ impl DrawState {
             Event::Key(KeyEvent {
                 code: KeyCode::Char('c'),
                 modifiers: KeyModifiers::CONTROL,
+                ..
             }) => self.should_quit = true,
             Event::Key(KeyEvent {
                 code: KeyCode::Char(c),
@@ -153,7 +153,7 @@ impl DrawState {
     }
 }
 
-fn draw_main<B: Backend>(f: &mut Frame<B>, area: Rect, state: &DrawState) {
+fn draw_main(f: &mut Frame, area: Rect, state: &DrawState) {
     let main = Block::default()
         .title(&state.title[..])
         .borders(Borders::ALL);
@@ -161,7 +161,7 @@ fn draw_main<B: Backend>(f: &mut Frame<B>, area: Rect, state: &DrawState) {
     f.render_widget(main, area);
 }
 
-fn draw_help<B: Backend>(f: &mut Frame<B>, area: Rect, activity: &ActivityMode) {
+fn draw_help(f: &mut Frame, area: Rect, activity: &ActivityMode) {
     let text = match activity {
         ActivityMode::Top => "s:sort c:columns",
         ActivityMode::SortSelect => "sort: pid proc cpu",
@@ -171,13 +171,13 @@ fn draw_help<B: Backend>(f: &mut Frame<B>, area: Rect, activity: &ActivityMode)
     f.render_widget(para, area);
 }
 
-fn draw_error<B: Backend>(f: &mut Frame<B>, area: Rect, err: &str) {
+fn draw_error(f: &mut Frame, area: Rect, err: &str) {
     let msg = Paragraph::new(err);
     f.render_widget(msg, area);
 }
 
-fn draw<B: Backend>(f: &mut Frame<B>, draw_input: &DrawState) {
-    let full = f.size();
+fn draw(f: &mut Frame, draw_input: &DrawState) {
+    let full = f.area();
     let has_error = draw_input.error.is_some();
     let constraints = if has_error {
         vec![
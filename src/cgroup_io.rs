@@ -0,0 +1,169 @@
+//! CgroupIo: block io throughput and latency for a process's cgroup.
+//
+// The kernel doesn't keep per-process io latency, but cgroup v2 has per-cgroup
+// io counters (io.stat) and io stall time (io.pressure). When the io.latency
+// controller is on, io.stat also has the average completion latency
+// ("avg_lat"); otherwise we estimate the wait per io as stall time over ios
+// completed. Either way it's one number per sample; the percentiles are over
+// the retained samples.
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::stats;
+
+#[derive(Copy, Clone, Default)]
+struct Totals {
+    rbytes: u64,
+    wbytes: u64,
+    ios: u64,
+    avg_lat_us: Option<f64>,
+    stall_us: Option<u64>,
+}
+
+/// What the zoom view shows of a CgroupIo.
+pub struct IoSummary {
+    pub cgroup: String,
+    pub read_bps: Vec<f64>, // newest first
+    pub write_bps: Vec<f64>,
+    pub latency_us: Option<(f64, f64, f64)>, // p50, p95, p99
+    pub measured: bool,
+}
+
+pub struct CgroupIo {
+    dir: PathBuf,
+    pub name: String, // the cgroup path
+    prev: Option<Totals>,
+    pub read_bps: VecDeque<f64>, // newest first
+    pub write_bps: VecDeque<f64>,
+    pub latency_us: VecDeque<f64>,
+    // whether latency_us is measured (io.latency) or estimated from stalls.
+    pub measured: bool,
+}
+
+impl CgroupIo {
+    /// The cgroup (v2) `pid` is in.
+    pub fn for_pid(pid: i32) -> io::Result<Self> {
+        let cgroups = fs::read_to_string(format!("/proc/{}/cgroup", pid))?;
+        let path = cgroups
+            .lines()
+            .find_map(|l| l.strip_prefix("0::"))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not in a cgroup v2"))?;
+        // v2 alone, or alongside v1 ("hybrid").
+        for root in ["/sys/fs/cgroup", "/sys/fs/cgroup/unified"] {
+            let dir = Path::new(root).join(path.trim_start_matches('/'));
+            if dir.join("io.stat").exists() {
+                return Ok(Self::at(dir, path));
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no io.stat (io controller not enabled?)",
+        ))
+    }
+
+    pub fn at(dir: PathBuf, name: &str) -> Self {
+        Self {
+            dir,
+            name: name.into(),
+            prev: None,
+            read_bps: VecDeque::new(),
+            write_bps: VecDeque::new(),
+            latency_us: VecDeque::new(),
+            measured: false,
+        }
+    }
+
+    /// Read the counters; `elapsed` is the seconds since the last update,
+    /// and `keep` how many samples of each history to keep.
+    pub fn update(&mut self, elapsed: f64, keep: usize) -> io::Result<()> {
+        let cur = self.read()?;
+        if let (Some(prev), true) = (self.prev, elapsed > 0.) {
+            let per_sec = |now: u64, then: u64| now.saturating_sub(then) as f64 / elapsed;
+            push(&mut self.read_bps, per_sec(cur.rbytes, prev.rbytes), keep);
+            push(&mut self.write_bps, per_sec(cur.wbytes, prev.wbytes), keep);
+            let ios = cur.ios.saturating_sub(prev.ios);
+            self.measured = cur.avg_lat_us.is_some();
+            let latency = match (cur.avg_lat_us, cur.stall_us, prev.stall_us) {
+                (Some(lat), _, _) => Some(lat),
+                (None, Some(now), Some(then)) if ios > 0 => {
+                    Some(now.saturating_sub(then) as f64 / ios as f64)
+                }
+                _ => None,
+            };
+            if let Some(l) = latency {
+                push(&mut self.latency_us, l, keep);
+            }
+        }
+        self.prev = Some(cur);
+        Ok(())
+    }
+
+    /// (p50, p95, p99) of the latency samples, in µs.
+    pub fn latency_percentiles(&self) -> Option<(f64, f64, f64)> {
+        let xs: Vec<f64> = self.latency_us.iter().copied().collect();
+        Some((
+            stats::percentile(&xs, 50.)?,
+            stats::percentile(&xs, 95.)?,
+            stats::percentile(&xs, 99.)?,
+        ))
+    }
+
+    pub fn summary(&self) -> IoSummary {
+        IoSummary {
+            cgroup: self.name.clone(),
+            read_bps: self.read_bps.iter().copied().collect(),
+            write_bps: self.write_bps.iter().copied().collect(),
+            latency_us: self.latency_percentiles(),
+            measured: self.measured,
+        }
+    }
+
+    fn read(&self) -> io::Result<Totals> {
+        // "8:0 rbytes=1 wbytes=2 rios=3 wios=4 dbytes=0 dios=0 [... avg_lat=5 ...]"
+        let stat = fs::read_to_string(self.dir.join("io.stat"))?;
+        let mut t = Totals::default();
+        let mut lats = vec![];
+        for line in stat.lines() {
+            for field in line.split_whitespace().skip(1) {
+                let (k, v) = match field.split_once('=') {
+                    Some(kv) => kv,
+                    None => continue,
+                };
+                let v: u64 = match v.parse() {
+                    Ok(v) => v,
+                    Err(_) => continue,
+                };
+                match k {
+                    "rbytes" => t.rbytes += v,
+                    "wbytes" => t.wbytes += v,
+                    "rios" | "wios" => t.ios += v,
+                    "avg_lat" => lats.push(v as f64),
+                    _ => (),
+                }
+            }
+        }
+        if !lats.is_empty() {
+            t.avg_lat_us = Some(lats.iter().sum::<f64>() / lats.len() as f64);
+        }
+        // "some avg10=0.00 avg60=0.00 avg300=0.00 total=1234"
+        t.stall_us = fs::read_to_string(self.dir.join("io.pressure"))
+            .ok()
+            .and_then(|p| {
+                p.lines()
+                    .find_map(|l| l.strip_prefix("some "))?
+                    .split_whitespace()
+                    .find_map(|f| f.strip_prefix("total="))?
+                    .parse()
+                    .ok()
+            });
+        Ok(t)
+    }
+}
+
+fn push(deq: &mut VecDeque<f64>, x: f64, keep: usize) {
+    deq.push_front(x);
+    deq.truncate(keep);
+}
@@ -0,0 +1,228 @@
+//! Column: the process table's columns and how each can be drawn.
+use serde::{Deserialize, Serialize};
+
+use crate::ui::layout::Constraint;
+use crate::view_state::Metric;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Pid,
+    Name,
+    Pod,
+    DiskRead,
+    DiskWrite,
+    Mem,
+    MemPct,
+    MemEta,
+    Fds,
+    Threads,
+    State,
+    Latency,
+    Wakeups,
+    IoPrio,
+    Ports,
+    Script, // the --script's columns, side by side
+    TreeCpu,
+    Cpu,
+    CpuHist,
+}
+
+// how a cell's value is drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CellKind {
+    Text,
+    Number,
+    Gauge, // only for bounded metrics (0-100%), e.g. cpu of its quota
+    Sparkline,
+    Trend, // a history in words, e.g. "85%, rising"
+    Split, // user/kernel, e.g. "12/40"
+}
+
+impl CellKind {
+    /// The text-only stand-in for this kind, for screen readers.
+    pub fn plain(self) -> CellKind {
+        match self {
+            CellKind::Gauge => CellKind::Number,
+            CellKind::Sparkline => CellKind::Trend,
+            k => k,
+        }
+    }
+}
+
+pub const GAUGE_WIDTH: usize = 6;
+
+impl Column {
+    pub const ALL: [Column; 19] = [
+        Column::Pid,
+        Column::Name,
+        Column::Pod,
+        Column::DiskRead,
+        Column::DiskWrite,
+        Column::Mem,
+        Column::MemPct,
+        Column::MemEta,
+        Column::Fds,
+        Column::Threads,
+        Column::State,
+        Column::Latency,
+        Column::Wakeups,
+        Column::IoPrio,
+        Column::Ports,
+        Column::Script,
+        Column::TreeCpu,
+        Column::Cpu,
+        Column::CpuHist,
+    ];
+
+    /// By header (e.g. "mem%") or config name (e.g. "mem_pct").
+    pub fn by_name(name: &str) -> Option<Column> {
+        let by_key = serde_json::from_value(serde_json::Value::String(name.into())).ok();
+        by_key.or_else(|| Column::ALL.iter().copied().find(|c| c.header() == name))
+    }
+
+    pub fn header(self) -> &'static str {
+        use Column::*;
+        match self {
+            Pid => "pid",
+            Name => "process",
+            Pod => "pod",
+            DiskRead => "dr",
+            DiskWrite => "dw",
+            Mem => "mem",
+            MemPct => "mem%",
+            MemEta => "oom in",
+            Fds => "fds",
+            Threads => "threads",
+            State => "state",
+            Cpu => "cpu",
+            CpuHist => "cpu history",
+            Latency => "wait%",
+            Wakeups => "wake/s",
+            IoPrio => "ionice",
+            Ports => "ports",
+            Script => "script",
+            TreeCpu => "tree",
+        }
+    }
+
+    // what the column's numbers count, spelled out after one (e.g. in the
+    // accessible trends): "12% waiting", not "12 wait%".
+    pub fn unit(self) -> &'static str {
+        use Column::*;
+        match self {
+            Cpu | CpuHist | TreeCpu => "% cpu",
+            MemPct => "% of memory",
+            Latency => "% waiting",
+            Wakeups => " wakeups/s",
+            Fds => " fds",
+            Threads => " threads",
+            Pid | Name | Pod | DiskRead | DiskWrite | Mem | MemEta | State | IoPrio | Ports
+            | Script => "",
+        }
+    }
+
+    // label shared by related columns in the group header row.
+    pub fn group(self) -> Option<&'static str> {
+        use Column::*;
+        match self {
+            DiskRead | DiskWrite => Some("disk"),
+            Mem | MemPct | MemEta => Some("memory"),
+            TreeCpu | Cpu | CpuHist => Some("cpu"),
+            Pid | Name | Pod | Fds | Threads | State | Latency | Wakeups | IoPrio | Ports
+            | Script => None,
+        }
+    }
+
+    // the metric this column sorts by, if any.
+    pub fn metric(self) -> Option<Metric> {
+        use Column::*;
+        match self {
+            Pid => Some(Metric::Pid),
+            DiskRead => Some(Metric::DiskRead),
+            DiskWrite => Some(Metric::DiskWrite),
+            Mem | MemPct => Some(Metric::Mem),
+            Cpu => Some(Metric::Cpu),
+            Latency => Some(Metric::Latency),
+            Wakeups => Some(Metric::Wakeups),
+            Ports => Some(Metric::Ports),
+            TreeCpu => Some(Metric::TreeCpu),
+            Name | Pod | Fds | Threads | State | CpuHist | MemEta | IoPrio | Script => None,
+        }
+    }
+
+    // supported renderers; the first is the default.
+    pub fn kinds(self) -> &'static [CellKind] {
+        use CellKind::*;
+        use Column::*;
+        match self {
+            Pid | Name | Pod | MemEta | IoPrio | State | Ports | Script => &[Text],
+            DiskRead | DiskWrite | Mem | Fds | Threads | TreeCpu => &[Number],
+            Cpu => &[Number, Split, Gauge],
+            MemPct => &[Number, Gauge],
+            CpuHist => &[Sparkline],
+            Latency | Wakeups => &[Number, Sparkline],
+        }
+    }
+
+    // whether it's costly to read: turning it on says what it costs first,
+    // and it can be refreshed less often than every sample (see
+    // SProcs::set_every).
+    pub fn heavy(self) -> bool {
+        matches!(self, Column::Fds | Column::Ports)
+    }
+
+    // how many samples apart it's read by default, for columns read in a
+    // pass of their own (see SProcs::set_every); None if it comes with every
+    // sample, like cpu and memory.
+    pub fn every(self) -> Option<u64> {
+        use Column::*;
+        match self {
+            Fds | Ports => Some(10),
+            IoPrio => Some(5),
+            State | Threads => Some(2),
+            Pid | Name | Pod | DiskRead | DiskWrite | Mem | MemPct | MemEta | Cpu | CpuHist
+            | TreeCpu | Latency | Wakeups | Script => None,
+        }
+    }
+
+    // how important the column is to keep on a narrow terminal. Columns are
+    // dropped lowest priority first; None means never drop.
+    pub fn priority(self) -> Option<u8> {
+        use Column::*;
+        match self {
+            Name | Cpu | CpuHist => None,
+            Mem => Some(3),
+            MemPct => Some(2),
+            Pid => Some(1),
+            Pod | DiskRead | DiskWrite | Fds | Threads | State | Latency | Wakeups | MemEta
+            | IoPrio | Ports | Script | TreeCpu => Some(0),
+        }
+    }
+
+    // the narrowest this column can usefully be.
+    pub fn min_width(self, kind: CellKind) -> u16 {
+        match self.width(kind) {
+            Constraint::Length(n) | Constraint::Min(n) => n,
+            _ => 10,
+        }
+    }
+
+    pub fn width(self, kind: CellKind) -> Constraint {
+        use Column::*;
+        match (self, kind) {
+            (_, CellKind::Gauge) => Constraint::Length(GAUGE_WIDTH as u16 + 7),
+            (Pid, _) | (MemEta, _) => Constraint::Length(6),
+            (IoPrio, _) | (Threads, _) => Constraint::Length(7), // "default", "threads"
+            (Wakeups, CellKind::Number) => Constraint::Length(7), // "*wake/s"
+            (Name, _) | (Pod, _) => Constraint::Length(24),
+            (Cpu, CellKind::Split) => Constraint::Length(9),
+            (Ports, _) => Constraint::Length(15), // "2 ns 4026532198"
+            (Cpu, _) => Constraint::Length(4),
+            (CpuHist, _) => Constraint::Percentage(100),
+            (_, CellKind::Sparkline) => Constraint::Length(12),
+            (_, CellKind::Trend) => Constraint::Length(23), // "9.9k wakeups/s, falling"
+            _ => Constraint::Length(5),
+        }
+    }
+}
@@ -0,0 +1,447 @@
+//! Config: settings read from a TOML file at startup.
+//
+// Looked for at $XDG_CONFIG_HOME/sparktop/config.toml (or
+// ~/.config/sparktop/config.toml); everything in it is optional, and flags
+// given on the command line win. Bookmarks and mutes are saved back to it.
+// `sparktop --write-default-config` prints one with every setting. e.g.
+//   delay = 2
+//   sort = "mem"
+//   history = 120
+//   bookmarks = ["postgres", "nginx: master"]
+//   muted = ["tracker-miner"]
+//   highlight = ["cpu_ewma > 200 -> bg:red", 'name =~ "chrome" -> fg:yellow']
+//   alerts = ["cpu > 90 for 30s -> jstack $SPARKTOP_PID > /tmp/stack", "total mem_pct > 95"]
+//   alert_bell = true
+//   alert_desktop = true
+//   script = "/home/me/.config/sparktop/columns.rhai"
+//   startup = "sort mem; columns -pid"
+//   colors = { cpu = [100, 200, 400], latency = [10, 50] }
+//   refresh = { fds = 30, state = 1 }
+//   bars = { glyphs = "dots", levels = { wakeups = 2 } }
+//   theme = { preset = "light", colors = { dead = "#aa0000" } }
+//   filters = [
+//     { name = "db", filter = "name in {postgres, redis}" },
+//     { name = "mine", filter = "user == $USER" },
+//   ]
+//   renames = [{ pattern = '^python3\S* /opt/app/(\w+)\.py', name = "app-$1" }]
+//   breakpoints = [
+//     { min_width = 0, columns = ["name", "cpu", "cpu_hist"] },
+//     { min_width = 160, columns = ["pid", "name", "disk_read", "disk_write",
+//                                   "mem", "mem_pct", "fds", "cpu", "cpu_hist"] },
+//   ]
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use toml::Table;
+use toml_edit::{DocumentMut, Item};
+
+use crate::{
+    adaptive::AdaptiveTick,
+    alert::AlertRule,
+    column::Column,
+    render::Bars,
+    sproc::CompactBy,
+    theme::{self, Slot, Theme},
+    view_state::{Dir, Metric, RowCond, RowRule, TableState},
+};
+
+pub const DEFAULT_DELAY: f64 = 1.;
+pub const DEFAULT_EWMA_WEIGHT: f64 = 0.5;
+const DEFAULT_HISTORY: usize = 60;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Seconds between samples, like -d.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delay: Option<f64>,
+    /// Sampling less often while the system is quiet; see adaptive.rs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adaptive: Option<AdaptiveTick>,
+    /// Weight given to new samples, like -e.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ewma_weight: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<Metric>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_dir: Option<Dir>,
+    /// The columns shown (unless breakpoints pick them by width).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub columns: Option<Vec<Column>>,
+    /// How many samples apart to read the columns that needn't be read with
+    /// every sample, e.g. { fds = 30 }; see Column::every for the defaults.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub refresh: HashMap<Column, u64>,
+    /// Samples of history kept per process, for sparklines and zoom.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<usize>,
+    /// Seconds of history kept per process, like --history-secs; wins over
+    /// history.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_secs: Option<f64>,
+    /// How history older than 10 minutes is compacted: "mean", "max" or
+    /// "mean_and_peak" (the mean, with spikes it hides marked).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compact_by: Option<CompactBy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colors: Option<ColorLevels>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bars: Option<BarGlyphs>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<ThemeConfig>,
+    /// Patterns for processes to pin to the top, matched against the name
+    /// and command line.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<String>,
+    /// Names of processes to hide; only an exact name matches.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub muted: Vec<String>,
+    /// Column sets by terminal width; see ViewState::fit_width.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub breakpoints: Vec<Breakpoint>,
+    /// Friendlier display names; see rename.rs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub renames: Vec<Rename>,
+    /// Row highlighting rules, e.g. "cpu_ewma > 200 -> bg:red"; see
+    /// view_state::RowRule.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub highlight: Vec<String>,
+    /// Alert rules, e.g. "cpu > 90 for 30s" or "total mem_pct > 95", with
+    /// an optional "-> <command>" to run when they fire; see alert.rs. Like
+    /// --alert.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alerts: Vec<String>,
+    /// Named filters, e.g. "name in {postgres, redis}", switched to with
+    /// keys 1-9 in order; see view_state::RowCond.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<SavedFilter>,
+    /// Ring the terminal bell when an alert rule fires.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert_bell: Option<bool>,
+    /// Show a desktop notification when an alert rule fires (needs the
+    /// desktop-notify feature).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert_desktop: Option<bool>,
+    /// A rhai script of computed columns, highlights and alerts (needs the
+    /// scripting feature); see script.rs. Like --script.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script: Option<PathBuf>,
+    /// Commands to run at startup, like --cmd.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub startup: Option<String>,
+    #[serde(skip)]
+    path: Option<PathBuf>, // where it was (or would be) read from
+}
+
+/// Where values turn yellow, red and (for cpu) magenta: cpu above the first
+/// (% of one core), at or over the rest.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorLevels {
+    pub cpu: [f64; 3],
+    pub latency: [f64; 2], // run queue wait %
+}
+
+impl Default for ColorLevels {
+    fn default() -> Self {
+        Self {
+            cpu: [100., 200., 400.],
+            latency: [10., 50.],
+        }
+    }
+}
+
+/// What sparklines are drawn with: "blocks", "quarters", "dots", "ascii", or a string
+/// of glyphs shortest first (see Bars::parse), and for any history column
+/// that reads better with fewer, how many levels of them to use.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BarGlyphs {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glyphs: Option<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub levels: HashMap<Column, usize>,
+    /// Two samples to a cell in braille instead, for twice the history in
+    /// the same width ('v' toggles).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub braille: Option<bool>,
+}
+
+impl BarGlyphs {
+    pub fn bars(&self) -> Result<Bars> {
+        match &self.glyphs {
+            Some(g) => Bars::parse(g),
+            None => Ok(Bars::default()),
+        }
+    }
+
+    /// The glyphs for each column given levels, picked from `bars`.
+    pub fn column_bars(&self, bars: &Bars) -> HashMap<Column, Bars> {
+        self.levels
+            .iter()
+            .map(|(&col, &n)| (col, bars.with_levels(n)))
+            .collect()
+    }
+}
+
+/// The colors to draw in: a builtin preset ("dark", "light" or
+/// "colorblind"), with any of its slots overridden, e.g.
+/// { preset = "light", colors = { dead = "#aa0000", selected_row = "i237" } }.
+/// Colors are named as in theme.rs.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub colors: HashMap<Slot, String>,
+}
+
+impl ThemeConfig {
+    pub fn theme(&self) -> Result<Theme> {
+        let mut t = Theme::preset(self.preset.as_deref().unwrap_or("dark"))?;
+        for (&slot, name) in &self.colors {
+            match theme::parse_color(name) {
+                Some(c) => t.set(slot, c),
+                None => anyhow::bail!("no color {:?} (for {:?})", name, slot),
+            }
+        }
+        Ok(t)
+    }
+}
+
+/// The columns to show when the terminal is at least `min_width` wide.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Breakpoint {
+    pub min_width: u16,
+    pub columns: Vec<Column>,
+}
+
+/// Show processes whose command line matches `pattern` as `name`, which can
+/// refer to capture groups ($1, ${name}).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rename {
+    pub pattern: String,
+    pub name: String,
+}
+
+/// A filter to switch to by number.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SavedFilter {
+    pub name: String,
+    pub filter: String,
+}
+
+impl Config {
+    /// Read `path`, or the default location if None. A missing file at the
+    /// default location is an empty config.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, required) = match path {
+            Some(p) => (p.to_path_buf(), true),
+            None => match default_path() {
+                Some(p) => (p, false),
+                None => return Ok(Self::default()),
+            },
+        };
+        let s = match fs::read_to_string(&path) {
+            Ok(s) => s,
+            Err(e) if !required && e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Self {
+                    path: Some(path),
+                    ..Self::default()
+                })
+            }
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+        let mut config: Self =
+            toml::from_str(&s).with_context(|| format!("parsing {}", path.display()))?;
+        config
+            .check()
+            .with_context(|| format!("in {}", path.display()))?;
+        config.path = Some(path);
+        Ok(config)
+    }
+
+    fn check(&self) -> Result<()> {
+        if matches!(self.delay, Some(d) if d <= 0.) {
+            anyhow::bail!("delay must be more than 0");
+        }
+        if let Some(a) = &self.adaptive {
+            if a.idle_cpu > a.busy_cpu {
+                anyhow::bail!("adaptive.idle_cpu must be at most adaptive.busy_cpu");
+            }
+            if a.max_delay <= 0. {
+                anyhow::bail!("adaptive.max_delay must be more than 0");
+            }
+        }
+        if matches!(self.ewma_weight, Some(w) if w <= 0. || w > 1.) {
+            anyhow::bail!("ewma_weight must be more than 0, and at most 1");
+        }
+        if self.history == Some(0) {
+            anyhow::bail!("history must be at least 1");
+        }
+        if matches!(self.history_secs, Some(s) if s <= 0.) {
+            anyhow::bail!("history_secs must be more than 0");
+        }
+        for (col, &n) in &self.refresh {
+            if col.every().is_none() {
+                anyhow::bail!("refresh: {} is read with every sample", col.header());
+            }
+            if n == 0 {
+                anyhow::bail!("refresh.{} must be at least 1", col.header());
+            }
+        }
+        if let Some(theme) = &self.theme {
+            theme.theme().context("in theme")?;
+        }
+        self.row_rules()?;
+        self.alert_rules()?;
+        self.saved_filters()?;
+        if self.filters.len() > 9 {
+            anyhow::bail!("filters: only 9 fit on keys 1-9");
+        }
+        if let Some(bars) = &self.bars {
+            bars.bars().context("in bars.glyphs")?;
+            if bars.levels.values().any(|&n| n == 0) {
+                anyhow::bail!("bars.levels must be at least 1");
+            }
+        }
+        Ok(())
+    }
+
+    /// The highlight rules, parsed.
+    pub fn row_rules(&self) -> Result<Vec<RowRule>> {
+        self.highlight
+            .iter()
+            .map(|r| r.parse().with_context(|| format!("in highlight {:?}", r)))
+            .collect()
+    }
+
+    /// The saved filters, parsed, with their names.
+    pub fn saved_filters(&self) -> Result<Vec<(String, RowCond)>> {
+        self.filters
+            .iter()
+            .map(|f| {
+                let cond = f
+                    .filter
+                    .parse()
+                    .with_context(|| format!("in filters {:?}", f.name))?;
+                Ok((f.name.clone(), cond))
+            })
+            .collect()
+    }
+
+    /// The alert rules, parsed.
+    pub fn alert_rules(&self) -> Result<Vec<AlertRule>> {
+        self.alerts
+            .iter()
+            .map(|r| r.parse().with_context(|| format!("in alerts {:?}", r)))
+            .collect()
+    }
+
+    /// Every setting at its default, as TOML, to start a config file from.
+    pub fn template() -> Result<String> {
+        let table = TableState::default();
+        let config = Self {
+            delay: Some(DEFAULT_DELAY),
+            adaptive: Some(AdaptiveTick::default()),
+            ewma_weight: Some(DEFAULT_EWMA_WEIGHT),
+            sort: Some(table.sort_by),
+            sort_dir: Some(table.sort_dir),
+            columns: Some(table.columns),
+            refresh: Column::ALL
+                .iter()
+                .filter_map(|&c| Some((c, c.every()?)))
+                .collect(),
+            history: Some(DEFAULT_HISTORY),
+            compact_by: Some(CompactBy::Mean),
+            colors: Some(ColorLevels::default()),
+            bars: Some(BarGlyphs {
+                glyphs: Some("blocks".into()),
+                levels: HashMap::new(),
+                braille: Some(false),
+            }),
+            theme: Some(ThemeConfig {
+                preset: Some("dark".into()),
+                colors: HashMap::new(),
+            }),
+            alert_bell: Some(false),
+            alert_desktop: Some(false),
+            ..Self::default()
+        };
+        Ok(toml::to_string_pretty(&config)?)
+    }
+
+    /// Write back to where it was loaded from. Only settings that changed
+    /// are rewritten; the rest of the file, comments and all, stays as it
+    /// was.
+    pub fn save(&self) -> Result<()> {
+        let path = self
+            .path
+            .as_deref()
+            .context("nowhere to save config ($HOME unset)")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let old = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+        let mut doc: DocumentMut = old
+            .parse()
+            .with_context(|| format!("parsing {}", path.display()))?;
+        // what the file said, and what we'd say, in the same words.
+        let was = toml::to_string(&toml::from_str::<Self>(&old).unwrap_or_default())?;
+        let was: Table = toml::from_str(&was)?;
+        let now = toml::to_string(self)?;
+        let (now, now_doc): (Table, DocumentMut) = (toml::from_str(&now)?, now.parse()?);
+        let added = now.keys().filter(|k| !was.contains_key(*k));
+        for key in was.keys().chain(added) {
+            if was.get(key) == now.get(key) {
+                continue;
+            }
+            match (doc.get_mut(key), now_doc.get(key)) {
+                (Some(Item::Value(old)), Some(Item::Value(new))) => {
+                    let decor = old.decor().clone();
+                    *old = new.clone();
+                    *old.decor_mut() = decor;
+                }
+                (_, Some(new)) => doc[key.as_str()] = new.clone(),
+                (_, None) => {
+                    doc.remove(key);
+                }
+            }
+        }
+        // a whole new file or none, whatever happens partway.
+        let tmp = path.with_extension(format!("toml.{}.tmp", std::process::id()));
+        let write = || -> io::Result<()> {
+            let mut f = fs::File::create(&tmp)?;
+            f.write_all(doc.to_string().as_bytes())?;
+            f.sync_all()?;
+            fs::rename(&tmp, path)
+        };
+        write()
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&tmp);
+            })
+            .with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(d) if !d.is_empty() => PathBuf::from(d),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("sparktop").join("config.toml"))
+}
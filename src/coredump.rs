@@ -0,0 +1,141 @@
+//! Dumps: core dumps of a running process, asked for from the table.
+//
+// gcore (from gdb) leaves the process running and writes core.<pid> in our
+// working directory, but can take a while on a big process, so it runs in the
+// background. SIGQUIT is immediate but kills the process, and where the core
+// goes is up to the kernel's core_pattern, which we can only try to predict.
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::mpsc,
+    thread,
+};
+
+use crate::platform;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DumpWith {
+    Gcore,
+    SigQuit,
+}
+
+impl DumpWith {
+    pub const ALL: [DumpWith; 2] = [DumpWith::Gcore, DumpWith::SigQuit];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DumpWith::Gcore => "gcore",
+            DumpWith::SigQuit => "sigquit",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Dumps {
+    running: Vec<mpsc::Receiver<Result<String, String>>>,
+}
+
+impl Dumps {
+    /// Start dumping `pid` (called `name`). Ok and Err are both messages for
+    /// the user; for gcore, Ok only says it's started (see finished).
+    pub fn start(&mut self, pid: i32, name: &str, with: DumpWith) -> Result<String, String> {
+        match with {
+            DumpWith::Gcore => {
+                let dir = std::env::current_dir().map_err(|e| e.to_string())?;
+                let (tx, rx) = mpsc::channel();
+                thread::spawn(move || tx.send(gcore(pid, &dir)));
+                self.running.push(rx);
+                Ok(format!("dumping {} with gcore...", pid))
+            }
+            DumpWith::SigQuit => {
+                if let Ok(Some(0)) = platform::core_size_limit(pid) {
+                    return Err(format!(
+                        "core dumps are off for {} (its core size limit is 0); try gcore",
+                        pid
+                    ));
+                }
+                platform::quit_process(pid)
+                    .map_err(|e| format!("couldn't send SIGQUIT to {}: {}", pid, e))?;
+                Ok(format!(
+                    "sent SIGQUIT to {}; {}",
+                    pid,
+                    core_destination(pid, name)
+                ))
+            }
+        }
+    }
+
+    /// How the gcore runs that have ended since the last call went.
+    pub fn finished(&mut self) -> Vec<String> {
+        let mut done = vec![];
+        self.running.retain(|rx| match rx.try_recv() {
+            Ok(r) => {
+                done.push(r.unwrap_or_else(|e| e));
+                false
+            }
+            Err(mpsc::TryRecvError::Empty) => true,
+            Err(mpsc::TryRecvError::Disconnected) => false,
+        });
+        done
+    }
+}
+
+fn gcore(pid: i32, dir: &Path) -> Result<String, String> {
+    let out = Command::new("gcore")
+        .arg(pid.to_string())
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("couldn't run gcore (it comes with gdb): {}", e))?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        let why = stderr.lines().rev().find(|l| !l.trim().is_empty());
+        return Err(format!(
+            "gcore of {} failed: {}",
+            pid,
+            why.unwrap_or("no output")
+        ));
+    }
+    let path = dir.join(format!("core.{}", pid));
+    Ok(format!("core of {} written to {}", pid, path.display()))
+}
+
+// where the kernel will put the core, going by core_pattern.
+fn core_destination(pid: i32, name: &str) -> String {
+    let pattern = match platform::core_pattern() {
+        Ok(p) => p,
+        Err(_) => return "the core goes wherever the system puts them".into(),
+    };
+    if let Some(handler) = pattern.strip_prefix('|') {
+        let prog = handler.split_whitespace().next().unwrap_or(handler);
+        return match prog.ends_with("systemd-coredump") {
+            true => "see coredumpctl for the core".into(),
+            false => format!("the core is handed to {}", prog),
+        };
+    }
+    let mut path = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            path.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('p') | Some('P') => path.push_str(&pid.to_string()),
+            Some('e') => path.push_str(name),
+            Some('%') => path.push('%'),
+            // left as is: the time, hostname, etc.
+            Some(other) => path.extend(['%', other].iter()),
+            None => path.push('%'),
+        }
+    }
+    let uses_pid = std::fs::read_to_string("/proc/sys/kernel/core_uses_pid");
+    if !pattern.contains("%p") && uses_pid.is_ok_and(|u| u.trim() == "1") {
+        path.push_str(&format!(".{}", pid));
+    }
+    // relative to the process's working directory.
+    let path = match std::fs::read_link(format!("/proc/{}/cwd", pid)) {
+        Ok(cwd) => cwd.join(path),
+        Err(_) => PathBuf::from(path),
+    };
+    format!("the core should be at {}", path.display())
+}
@@ -0,0 +1,115 @@
+//! DbusExport: the latest snapshot, on the session bus as org.sparktop.Monitor.
+//
+// So desktop widgets and scripts can ask "what's busy right now" without
+// scraping the TUI:
+//
+//   gdbus call --session --dest org.sparktop.Monitor \
+//       --object-path /org/sparktop/Monitor --method org.sparktop.Monitor.Top
+//
+// Methods (cpu in %, mem in MB):
+//   Top() -> (pid i, name s, cpu d, mem d)      busiest process by cpu
+//   Processes(count u) -> a(isdd)               the `count` busiest
+//
+// zbus answers the calls (and Introspect/Ping) from its own thread.
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use zbus::{
+    blocking::connection,
+    fdo::{self, RequestNameFlags},
+    interface,
+};
+
+use crate::{sproc::SProc, units};
+
+pub const BUS_NAME: &str = "org.sparktop.Monitor";
+pub const PATH: &str = "/org/sparktop/Monitor";
+
+/// One process as exported: (pid, name, cpu %, mem MB).
+pub type Entry = (i32, String, f64, f64);
+
+pub struct DbusExport {
+    // busiest first.
+    snapshot: Arc<Mutex<Vec<Entry>>>,
+    // served for as long as this is open.
+    _conn: zbus::blocking::Connection,
+}
+
+impl DbusExport {
+    /// Connect to the session bus, claim org.sparktop.Monitor and start
+    /// answering calls. Fails if another sparktop has the name.
+    pub fn spawn() -> io::Result<Self> {
+        let snapshot = Arc::new(Mutex::new(vec![]));
+        let monitor = Monitor {
+            snapshot: Arc::clone(&snapshot),
+        };
+        let conn = connection::Builder::session()
+            .and_then(|b| b.serve_at(PATH, monitor))
+            .and_then(|b| b.build())
+            .map_err(io::Error::other)?;
+        // fail rather than queue behind another sparktop.
+        match conn.request_name_with_flags(BUS_NAME, RequestNameFlags::DoNotQueue.into()) {
+            Ok(_) => (),
+            Err(zbus::Error::NameTaken) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} is already taken", BUS_NAME),
+                ))
+            }
+            Err(e) => return Err(io::Error::other(e)),
+        }
+        Ok(Self {
+            snapshot,
+            _conn: conn,
+        })
+    }
+
+    /// Replace what callers see with this tick's processes.
+    pub fn publish(&self, sprocs: &[&SProc], cumulative: bool) {
+        let mut entries: Vec<Entry> = sprocs
+            .iter()
+            .filter(|sp| !sp.is_dead())
+            .map(|sp| {
+                let mem_mb = units::mib(sp.mem_bytes as f64);
+                (sp.pid, sp.name.clone(), sp.cpu(cumulative), mem_mb)
+            })
+            .collect();
+        entries.sort_by(|a, b| b.2.total_cmp(&a.2).then(a.0.cmp(&b.0)));
+        if let Ok(mut s) = self.snapshot.lock() {
+            *s = entries;
+        }
+    }
+}
+
+struct Monitor {
+    snapshot: Arc<Mutex<Vec<Entry>>>,
+}
+
+impl Monitor {
+    fn entries(&self) -> fdo::Result<Vec<Entry>> {
+        match self.snapshot.lock() {
+            Ok(s) => Ok(s.clone()),
+            Err(_) => Err(fdo::Error::Failed("snapshot unavailable".into())),
+        }
+    }
+}
+
+#[interface(name = "org.sparktop.Monitor")]
+impl Monitor {
+    /// The busiest process by cpu.
+    fn top(&self) -> fdo::Result<Entry> {
+        match self.entries()?.into_iter().next() {
+            Some(e) => Ok(e),
+            None => Err(fdo::Error::Failed("no samples yet".into())),
+        }
+    }
+
+    /// The `count` busiest processes, busiest first.
+    fn processes(&self, count: u32) -> fdo::Result<Vec<Entry>> {
+        let mut entries = self.entries()?;
+        entries.truncate(count as usize);
+        Ok(entries)
+    }
+}
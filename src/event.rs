@@ -1,13 +1,17 @@
 /// Event: event stream (keys, ticks, etc).
 use std::{sync::mpsc, thread, time};
 
+use mpsc::RecvTimeoutError;
+
 use crossterm::event::Event as CTEvent;
 use crossterm::event::KeyEvent as CTKeyEvent;
+use crossterm::event::{KeyEventKind, MouseEvent as CTMouseEvent, MouseEventKind};
 
 pub enum Event {
     Tick,   // time to update internal state and redraw
     Resize, // terminal resized
     Key(CTKeyEvent),
+    Mouse(CTMouseEvent), // clicks and the wheel, not plain moves or drags
 }
 
 // an instruction to the main loop
@@ -19,6 +23,19 @@ pub enum Next {
 
 pub struct EventStream {
     stream: mpsc::Receiver<Event>,
+    intervals: mpsc::Sender<time::Duration>, // to the tick thread
+}
+
+/// Changes how often an `EventStream` ticks, while it's being iterated.
+#[derive(Clone)]
+pub struct Ticker(mpsc::Sender<time::Duration>);
+
+impl Ticker {
+    /// Tick now, and then every `every`.
+    pub fn set_interval(&self, every: time::Duration) {
+        // the tick thread only stops when the stream is dropped.
+        let _ = self.0.send(every);
+    }
 }
 
 // LEARN: why is move needed for the thread closure?
@@ -27,9 +44,18 @@ impl EventStream {
         let (tx, rx) = mpsc::channel();
 
         let tick_tx = tx.clone();
-        thread::spawn(move || loop {
-            tick_tx.send(Event::Tick).expect("failed to tick");
-            thread::sleep(tick_every);
+        let (intervals, interval_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut every = tick_every;
+            loop {
+                tick_tx.send(Event::Tick).expect("failed to tick");
+                // sleep, unless told a new interval first.
+                match interval_rx.recv_timeout(every) {
+                    Ok(new) => every = new,
+                    Err(RecvTimeoutError::Timeout) => (),
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
         });
 
         let event_tx = tx;
@@ -37,15 +63,28 @@ impl EventStream {
             // TODO: limit Resize frequency.
             loop {
                 match crossterm::event::read().expect("read term event") {
+                    // presses only; some terminals report releases too.
+                    CTEvent::Key(ke) if ke.kind == KeyEventKind::Release => Ok(()),
                     CTEvent::Key(ke) => event_tx.send(Event::Key(ke)),
                     CTEvent::Resize(_, _) => event_tx.send(Event::Resize),
-                    CTEvent::Mouse(_) => Ok(()),
+                    CTEvent::Mouse(me) => match me.kind {
+                        MouseEventKind::Moved | MouseEventKind::Drag(_) => Ok(()),
+                        _ => event_tx.send(Event::Mouse(me)),
+                    },
+                    CTEvent::FocusGained | CTEvent::FocusLost | CTEvent::Paste(_) => Ok(()),
                 }
                 .expect("send term event")
             }
         });
 
-        Self { stream: rx }
+        Self {
+            stream: rx,
+            intervals,
+        }
+    }
+
+    pub fn ticker(&self) -> Ticker {
+        Ticker(self.intervals.clone())
     }
 }
 
@@ -0,0 +1,91 @@
+//! Export: the current state of every process as JSON, histories included,
+//! for scripts. Unlike record.rs, one self-contained snapshot at a time.
+use std::{fs, path::Path, time::SystemTime};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{sproc::SProc, units};
+
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub time: f64, // unix seconds
+    pub procs: Vec<ProcState>,
+}
+
+// histories are newest first; past the full resolution part each entry
+// stands for several samples (see sproc::History), so they aren't evenly
+// spaced.
+#[derive(Serialize, Deserialize)]
+pub struct ProcState {
+    pub pid: i32,
+    pub ppid: Option<i32>,
+    pub name: String,
+    pub cmd: Vec<String>,
+    pub dead: bool,
+    pub cpu_ewma: f64,
+    pub mem_mb: f64, // MiB, as is mem_hist
+    pub mem_pct: f64,
+    pub disk_read_ewma: f64, // bytes per tick
+    pub disk_write_ewma: f64,
+    // totals over the process's life, as in record.rs; cpu_secs only where
+    // counters are read (see SProcs::set_counters).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_secs: Option<f64>,
+    #[serde(default)]
+    pub read_bytes: u64,
+    #[serde(default)]
+    pub write_bytes: u64,
+    pub cpu_hist: Vec<f64>,
+    pub mem_hist: Vec<f64>,
+    pub disk_read_hist: Vec<u64>,
+    pub disk_write_hist: Vec<u64>,
+}
+
+impl From<&SProc> for ProcState {
+    fn from(sp: &SProc) -> Self {
+        Self {
+            pid: sp.pid,
+            ppid: sp.ppid,
+            name: sp.name.clone(),
+            cmd: sp.cmd.clone(),
+            dead: sp.is_dead(),
+            cpu_ewma: sp.cpu_ewma,
+            mem_mb: units::mib(sp.mem_bytes as f64),
+            mem_pct: sp.mem_pct,
+            disk_read_ewma: sp.disk_read_ewma,
+            disk_write_ewma: sp.disk_write_ewma,
+            cpu_secs: sp.cpu_secs,
+            read_bytes: sp.read_bytes,
+            write_bytes: sp.write_bytes,
+            cpu_hist: sp.cpu_hist.iter().copied().collect(),
+            mem_hist: sp.mem_hist.iter().map(|&b| units::mib(b)).collect(),
+            disk_read_hist: sp.disk_read_hist.iter().copied().collect(),
+            disk_write_hist: sp.disk_write_hist.iter().copied().collect(),
+        }
+    }
+}
+
+impl Snapshot {
+    pub fn now<'a, I>(sprocs: I) -> Self
+    where
+        I: IntoIterator<Item = &'a SProc>,
+    {
+        Self {
+            time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0., |d| d.as_secs_f64()),
+            procs: sprocs.into_iter().map(ProcState::from).collect(),
+        }
+    }
+
+    /// On one line, so a run of snapshots is JSON lines.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("writing {}", path.display()))
+    }
+}
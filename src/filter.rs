@@ -0,0 +1,77 @@
+//! ProcFilter: which processes to watch at all (--pid, --name, --user).
+//! Others are never sampled, unlike the table's filters which only hide.
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+use sysinfo::{Process, ProcessExt};
+
+use crate::platform;
+
+// whether sysinfo reads processes' uids here, for --user.
+const HAS_UIDS: bool = cfg!(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd"
+));
+
+#[derive(Default)]
+pub struct ProcFilter {
+    pids: Vec<i32>,      // any of these, if any
+    name: Option<Regex>, // against the name or command line
+    uid: Option<u32>,
+}
+
+impl ProcFilter {
+    /// None if there's nothing to filter on. `user` is a name or uid.
+    pub fn new(pids: Vec<i32>, name: Option<&str>, user: Option<&str>) -> Result<Option<Self>> {
+        if pids.is_empty() && name.is_none() && user.is_none() {
+            return Ok(None);
+        }
+        let uid = match user {
+            Some(_) if !HAS_UIDS => bail!("--user isn't supported on this platform"),
+            Some(u) => Some(
+                u.parse()
+                    .ok()
+                    .or_else(|| platform::user_uid(u))
+                    .ok_or_else(|| anyhow!("no such user: {}", u))?,
+            ),
+            None => None,
+        };
+        Ok(Some(Self {
+            pids,
+            name: name.map(Regex::new).transpose()?,
+            uid,
+        }))
+    }
+
+    /// Whether to sample `p` (as `pid`); all the criteria given must hold.
+    pub fn matches(&self, pid: i32, p: &Process) -> bool {
+        (self.pids.is_empty() || self.pids.contains(&pid))
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|re| re.is_match(p.name()) || re.is_match(&p.cmd().join(" ")))
+            && self.uid.is_none_or(|u| uid(p) == Some(u))
+    }
+}
+
+// the uid sysinfo read along with the rest of the process.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd"
+))]
+fn uid(p: &Process) -> Option<u32> {
+    Some(p.uid)
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd"
+)))]
+fn uid(_p: &Process) -> Option<u32> {
+    None
+}
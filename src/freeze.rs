@@ -0,0 +1,164 @@
+//! Freezer: pausing a process, and whatever it's part of, while looking into
+//! it (e.g. a runaway build).
+//
+// The whole cgroup (v2) is frozen when that's allowed and doesn't take us
+// with it; otherwise the process and its descendants get SIGSTOP. Either way
+// it's undone with the same toggle, or when the Freezer is dropped, so
+// quitting never leaves anything paused.
+use std::{collections::HashSet, fs, io, path::Path, path::PathBuf};
+
+use crate::platform;
+
+enum Frozen {
+    Cgroup { name: String, dir: PathBuf },
+    Stopped(Vec<i32>), // a process tree, root first
+}
+
+#[derive(Default)]
+pub struct Freezer {
+    frozen: Vec<Frozen>,
+    members: HashSet<i32>, // pids paused by any of frozen, as of refresh
+}
+
+impl Freezer {
+    /// Freeze `tree` (a process and its descendants), or if its root is
+    /// already paused, thaw whatever paused it. Ok is what was done, Err
+    /// why not; both are messages for the user.
+    pub fn toggle(&mut self, tree: &[i32]) -> Result<String, String> {
+        let pid = *tree.first().ok_or("no process")?;
+        if pid == std::process::id() as i32 {
+            return Err("can't freeze sparktop itself".into());
+        }
+        if let Some(i) = self.frozen.iter().position(|f| f.contains(pid)) {
+            let f = self.frozen.remove(i);
+            let done = f.thaw().map(|()| format!("thawed {}", f.name()));
+            self.refresh();
+            return done.map_err(|e| format!("couldn't thaw {}: {}", f.name(), e));
+        }
+        let f = match self.freeze_cgroup(pid) {
+            Some(f) => f,
+            None => stop_tree(tree)?,
+        };
+        let done = format!("froze {}", f.name());
+        self.frozen.push(f);
+        self.refresh();
+        Ok(done)
+    }
+
+    // pid's cgroup, unless it's the root or has us in it, or can't be frozen.
+    fn freeze_cgroup(&self, pid: i32) -> Option<Frozen> {
+        let (name, dir) = platform::cgroup_v2(pid).ok()?;
+        let (ours, _) = platform::cgroup_v2(std::process::id() as i32).ok()?;
+        if name == "/" || Path::new(&ours).starts_with(&name) {
+            return None;
+        }
+        match platform::freeze_cgroup(&dir, true) {
+            Ok(()) => Some(Frozen::Cgroup { name, dir }),
+            Err(e) => {
+                log::debug!("freezing cgroup {}: {}", name, e);
+                None
+            }
+        }
+    }
+
+    /// Re-read which processes are paused, for is_frozen.
+    pub fn refresh(&mut self) {
+        self.members = self.frozen.iter().flat_map(Frozen::pids).collect();
+    }
+
+    pub fn is_frozen(&self, pid: i32) -> bool {
+        self.members.contains(&pid)
+    }
+
+    pub fn thaw_all(&mut self) {
+        for f in self.frozen.drain(..) {
+            if let Err(e) = f.thaw() {
+                log::warn!("couldn't thaw {}: {}", f.name(), e);
+            }
+        }
+        self.members.clear();
+    }
+}
+
+impl Drop for Freezer {
+    fn drop(&mut self) {
+        self.thaw_all();
+    }
+}
+
+impl Frozen {
+    fn name(&self) -> String {
+        match self {
+            Frozen::Cgroup { name, .. } => format!("cgroup {}", name),
+            Frozen::Stopped(pids) if pids.len() == 1 => format!("process {}", pids[0]),
+            Frozen::Stopped(pids) => format!("{} and {} below it", pids[0], pids.len() - 1),
+        }
+    }
+
+    fn contains(&self, pid: i32) -> bool {
+        match self {
+            Frozen::Cgroup { dir, .. } => {
+                let cgroup_dir = platform::cgroup_v2(pid).map(|(_, d)| d);
+                cgroup_dir.is_ok_and(|d| d.starts_with(dir))
+            }
+            Frozen::Stopped(pids) => pids.contains(&pid),
+        }
+    }
+
+    fn pids(&self) -> Vec<i32> {
+        match self {
+            Frozen::Cgroup { dir, .. } => {
+                let mut pids = vec![];
+                cgroup_pids(dir, &mut pids);
+                pids
+            }
+            Frozen::Stopped(pids) => pids.clone(),
+        }
+    }
+
+    fn thaw(&self) -> io::Result<()> {
+        match self {
+            Frozen::Cgroup { dir, .. } => platform::freeze_cgroup(dir, false),
+            Frozen::Stopped(pids) => {
+                // carry on past failures; ones that have since exited don't
+                // count.
+                let mut done = Ok(());
+                for &pid in pids {
+                    match platform::stop_process(pid, false) {
+                        Err(e) if platform::pid_exists(pid) => done = Err(e),
+                        _ => (),
+                    }
+                }
+                done
+            }
+        }
+    }
+}
+
+// every process in the cgroup at `dir` and those below it.
+fn cgroup_pids(dir: &Path, pids: &mut Vec<i32>) {
+    if let Ok(procs) = fs::read_to_string(dir.join("cgroup.procs")) {
+        pids.extend(procs.lines().filter_map(|l| l.parse::<i32>().ok()));
+    }
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            cgroup_pids(&entry.path(), pids);
+        }
+    }
+}
+
+// stops the root first, so it can't start more children meanwhile; all or
+// nothing. Leaves us out, if we're below it.
+fn stop_tree(tree: &[i32]) -> Result<Frozen, String> {
+    let ours = std::process::id() as i32;
+    let pids: Vec<i32> = tree.iter().copied().filter(|&p| p != ours).collect();
+    for (i, &pid) in pids.iter().enumerate() {
+        if let Err(e) = platform::stop_process(pid, true) {
+            for &stopped in &pids[..i] {
+                let _ = platform::stop_process(stopped, false);
+            }
+            return Err(format!("couldn't stop {}: {}", pid, e));
+        }
+    }
+    Ok(Frozen::Stopped(pids))
+}
@@ -0,0 +1,54 @@
+//! Grouping: folding the process table into one row per pod or VM.
+use std::collections::BTreeMap;
+
+use crate::sproc::SProc;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Grouping {
+    Pod,
+    Vm,
+}
+
+impl Grouping {
+    pub fn name(self) -> &'static str {
+        match self {
+            Grouping::Pod => "pod",
+            Grouping::Vm => "vm",
+        }
+    }
+
+    // which group a process is in, if any.
+    pub fn key(self, sp: &SProc) -> Option<&str> {
+        match self {
+            Grouping::Pod => sp.pod.as_deref(),
+            Grouping::Vm => sp.vm.as_deref(),
+        }
+    }
+}
+
+/// One row per group, totalling its members, named e.g. "default/web (3)".
+/// Each takes its lowest member pid, so selecting or zooming a group lands
+/// on a real process. Processes in no group are left out.
+pub fn fold(sprocs: &[&SProc], by: Grouping) -> Vec<SProc> {
+    let mut groups: BTreeMap<&str, Vec<&SProc>> = BTreeMap::new();
+    for &sp in sprocs {
+        if let Some(key) = by.key(sp) {
+            groups.entry(key).or_default().push(sp);
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(key, members)| {
+            let pid = members.iter().map(|sp| sp.pid).min().unwrap_or_default();
+            let mut row = SProc::synthetic(pid, &format!("{} ({})", key, members.len()));
+            for sp in members {
+                row.absorb(sp);
+            }
+            match by {
+                Grouping::Pod => row.pod = Some(key.into()),
+                Grouping::Vm => row.vm = Some(key.into()),
+            }
+            row
+        })
+        .collect()
+}
@@ -0,0 +1,231 @@
+//! Headless: non-interactive modes that sample for a while and print a report.
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use ordered_float::OrderedFloat as OrdFloat;
+
+use crate::{
+    export::Snapshot,
+    render::{self, Bars},
+    sproc::SProc,
+    sprocs::SProcs,
+    threshold::Thresholds,
+    units, view,
+    view_state::{Dir, Metric},
+};
+
+// sparklines in reports are ASCII (see Bars::ascii): they end up in logs,
+// mail and CI output as often as on a terminal.
+const SPARK_WIDTH: usize = 20;
+
+/// How --batch prints each sample.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Output {
+    Table,
+    Json, // a Snapshot per line
+}
+
+impl std::str::FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Output::Table),
+            "json" => Ok(Output::Json),
+            _ => Err(format!("unknown output {:?} (table or json)", s)),
+        }
+    }
+}
+
+/// Per-process stats accumulated over a headless run.
+#[derive(Default)]
+pub struct Tally {
+    pub name: String,
+    pub cpu_sum: f64,
+    pub cpu_peak: f64,
+    pub samples: Vec<f64>, // cpu, oldest first
+    // total cpu seconds when first and last seen, where readable.
+    pub cpu_secs: Option<(f64, f64)>,
+}
+
+impl Tally {
+    pub fn cpu_avg(&self) -> f64 {
+        self.cpu_sum / self.samples.len().max(1) as f64
+    }
+
+    /// Cpu seconds used while sampled.
+    pub fn cpu_secs_used(&self) -> Option<f64> {
+        self.cpu_secs.map(|(first, last)| last - first)
+    }
+
+    fn add(&mut self, sp: &SProc) {
+        let cpu = sp.cpu_hist.front().copied().unwrap_or(0.);
+        self.cpu_sum += cpu;
+        self.cpu_peak = self.cpu_peak.max(cpu);
+        self.samples.push(cpu);
+        if let Some(secs) = sp.cpu_secs {
+            let first = self.cpu_secs.map_or(secs, |(first, _)| first);
+            self.cpu_secs = Some((first, secs));
+        }
+    }
+}
+
+/// Sample every `delay` for `period`, tallying each process seen.
+pub fn sample(
+    sprocs: &mut SProcs,
+    ewma_weight: f64,
+    delay: Duration,
+    period: Duration,
+    thresholds: &mut Thresholds,
+) -> HashMap<i32, Tally> {
+    let mut tallies: HashMap<i32, Tally> = HashMap::new();
+    sprocs.set_counters(true);
+    // the first update only establishes a baseline for cpu usage.
+    sprocs.update(ewma_weight);
+    let ticks = ((period.as_secs_f64() / delay.as_secs_f64()).round() as usize).max(1);
+    for _ in 0..ticks {
+        std::thread::sleep(delay);
+        sprocs.update(ewma_weight);
+        for msg in thresholds.check(&sprocs.get().collect::<Vec<_>>()) {
+            eprintln!("{}", msg);
+        }
+        for sp in sprocs.get().filter(|sp| !sp.is_dead()) {
+            let t = tallies.entry(sp.pid).or_default();
+            t.name.clone_from(&sp.name);
+            t.add(sp);
+        }
+    }
+    tallies
+}
+
+/// The `n` processes with the highest average cpu, as a plain-text table.
+pub fn top_report(tallies: &HashMap<i32, Tally>, n: usize) -> String {
+    let mut top: Vec<(&i32, &Tally)> = tallies.iter().collect();
+    top.sort_by_key(|(&pid, t)| (OrdFloat(-t.cpu_avg()), pid));
+    let mut out = format!(
+        "{:>7} {:<24} {:>6} {:>6} {:>7}  {}\n",
+        "pid", "process", "avg%", "peak%", "cpu s", "cpu history"
+    );
+    for (pid, t) in top.into_iter().take(n) {
+        let hist = squash(&t.samples, SPARK_WIDTH);
+        out += &format!(
+            "{:>7} {:<24} {:>6.1} {:>6.1} {:>7}  {}\n",
+            pid,
+            truncate(&t.name, 24),
+            t.cpu_avg(),
+            t.cpu_peak,
+            t.cpu_secs_used()
+                .map_or(String::from("_"), |s| format!("{:.2}", s)),
+            render::render_vec(hist.iter().rev(), 100., &Bars::ascii())
+        );
+    }
+    out
+}
+
+/// What --batch prints, and how often.
+pub struct Batch {
+    pub delay: Duration,
+    pub iterations: Option<usize>, // None for until interrupted
+    pub output: Output,
+    pub sort: (Metric, Dir),
+}
+
+/// Like `top -b`: sample every `delay`, `iterations` times, writing every
+/// living process to `out` each time. Stops quietly if `out` is closed,
+/// e.g. piped to head.
+pub fn batch(
+    sprocs: &mut SProcs,
+    ewma_weight: f64,
+    b: &Batch,
+    thresholds: &mut Thresholds,
+    out: &mut dyn Write,
+) -> Result<()> {
+    sprocs.set_counters(true);
+    // the first update only establishes a baseline for cpu usage.
+    sprocs.update(ewma_weight);
+    let mut i = 0;
+    while b.iterations.is_none_or(|n| i < n) {
+        std::thread::sleep(b.delay);
+        sprocs.update(ewma_weight);
+        i += 1;
+        let procs: Vec<&SProc> = sprocs.get().collect();
+        for msg in thresholds.check(&procs) {
+            eprintln!("{}", msg);
+        }
+        let mut rows: Vec<&SProc> = procs.into_iter().filter(|sp| !sp.is_dead()).collect();
+        view::sort_rows(&mut rows, b.sort.0, b.sort.1, false);
+        let written = match (b.output, i) {
+            (Output::Json, _) => writeln!(out, "{}", Snapshot::now(rows).to_json()?),
+            (Output::Table, 1) => write!(out, "{}", snapshot_report(&rows)),
+            (Output::Table, _) => write!(out, "\n{}", snapshot_report(&rows)),
+        };
+        match written.and_then(|()| out.flush()) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            r => r?,
+        }
+    }
+    Ok(())
+}
+
+/// One sample of `rows` as a plain-text table, newest cpu history first.
+pub fn snapshot_report(rows: &[&SProc]) -> String {
+    let mut out = format!(
+        "{:>7} {:<24} {:>6} {:>9} {:>9} {:>9}  {}\n",
+        "pid", "process", "cpu%", "mem MB", "read B/s", "write B/s", "cpu history"
+    );
+    for sp in rows {
+        out += &format!(
+            "{:>7} {:<24} {:>6.1} {:>9.1} {:>9.0} {:>9.0}  {}\n",
+            sp.pid,
+            truncate(&sp.name, 24),
+            sp.cpu_ewma,
+            units::mib(sp.mem_bytes as f64),
+            sp.disk_read_ewma,
+            sp.disk_write_ewma,
+            render::render_vec(sp.cpu_hist.iter().take(SPARK_WIDTH), 100., &Bars::ascii())
+        );
+    }
+    out
+}
+
+// Average `xs` down to at most `width` buckets, keeping order.
+fn squash(xs: &[f64], width: usize) -> Vec<f64> {
+    if xs.len() <= width {
+        return xs.to_vec();
+    }
+    (0..width)
+        .map(|i| {
+            let bucket = &xs[i * xs.len() / width..(i + 1) * xs.len() / width];
+            bucket.iter().sum::<f64>() / bucket.len().max(1) as f64
+        })
+        .collect()
+}
+
+fn truncate(s: &str, n: usize) -> String {
+    s.chars().take(n).collect()
+}
+
+/// Parse durations like "30s", "2m", "500ms", "1h"; a bare number is seconds.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let num: f64 = num.parse()?;
+    let secs = match unit {
+        "" | "s" => num,
+        "ms" => num / 1000.,
+        "m" => num * 60.,
+        "h" => num * 3600.,
+        _ => bail!("unknown duration unit: {:?}", unit),
+    };
+    if !secs.is_finite() || secs < 0. {
+        bail!("bad duration: {}", s);
+    }
+    Ok(Duration::from_secs_f64(secs))
+}
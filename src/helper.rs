@@ -0,0 +1,163 @@
+//! Helper: privileged reads done in a separate process.
+//
+// The TUI runs unprivileged. Reads that need privileges (other users' io
+// stats, fd counts) can be delegated to `sparktop helper`, run as root or with
+// CAP_SYS_PTRACE/CAP_DAC_READ_SEARCH, which answers line-based requests on
+// stdin/stdout and does nothing else:
+//
+//   fds <pid>  ->  ok <count>
+//   io <pid>   ->  ok <read_bytes> <write_bytes>
+//   (errors)   ->  err <message>
+//
+// Started as `helper --allow-ionice` (which sudo rules etc. must allow as
+// such), it will also change io priorities, needing CAP_SYS_NICE:
+//
+//   ionice <pid> <priority, e.g. be/7>  ->  ok
+//
+// The realtime class can starve everything else's io, so it's only set for
+// root, or on the caller's own processes; the caller is whoever ran sudo or
+// pkexec to start it, else whoever it runs as.
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::platform::{self, IoPriority};
+
+pub struct Helper {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Helper {
+    /// Start the helper. `cmd` is the command to run it with, split on
+    /// whitespace (e.g. "sudo /usr/bin/sparktop"); "helper" is appended, and
+    /// "--allow-ionice" if `ionice`.
+    pub fn spawn(cmd: &str, ionice: bool) -> Result<Self> {
+        let mut words = cmd.split_whitespace();
+        let prog = words.next().context("empty helper command")?;
+        let mut child = Command::new(prog)
+            .args(words)
+            .arg("helper")
+            .args(ionice.then_some("--allow-ionice"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("starting privileged helper: {}", cmd))?;
+        let stdin = child.stdin.take().context("helper stdin")?;
+        let stdout = BufReader::new(child.stdout.take().context("helper stdout")?);
+        Ok(Self {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+
+    pub fn fd_count(&mut self, pid: i32) -> Result<usize> {
+        let resp = self.request(&format!("fds {}", pid))?;
+        Ok(resp.trim().parse()?)
+    }
+
+    pub fn io_totals(&mut self, pid: i32) -> Result<(u64, u64)> {
+        let resp = self.request(&format!("io {}", pid))?;
+        match resp.split_once(' ') {
+            Some((r, w)) => Ok((r.parse()?, w.parse()?)),
+            None => bail!("bad helper response: {}", resp),
+        }
+    }
+
+    pub fn set_io_priority(&mut self, pid: i32, prio: IoPriority) -> Result<()> {
+        self.request(&format!("ionice {} {}", pid, prio.name()))?;
+        Ok(())
+    }
+
+    pub fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    fn request(&mut self, req: &str) -> Result<String> {
+        writeln!(self.stdin, "{}", req)?;
+        self.stdin.flush()?;
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            bail!("helper exited");
+        }
+        let line = line.trim_end();
+        match line.split_once(' ') {
+            Some(("ok", rest)) => Ok(rest.into()),
+            Some(("err", msg)) => Err(anyhow!("helper: {}", msg)),
+            None if line == "ok" => Ok(String::new()),
+            _ => bail!("bad helper response: {}", line),
+        }
+    }
+}
+
+impl Drop for Helper {
+    fn drop(&mut self) {
+        // it holds no state, so there's nothing to shut down gracefully.
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The helper side: answer requests until stdin closes. Io priority changes
+/// are refused unless `allow_ionice`.
+pub fn serve(allow_ionice: bool) -> Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, line.clone());
+        let pid = words.get(1).and_then(|p| p.parse::<i32>().ok());
+        let resp = match (&words[..], pid) {
+            (["fds", _], Some(pid)) => platform::fd_count(pid).map(|n| n.to_string()),
+            (["io", _], Some(pid)) => platform::io_totals(pid).map(|(r, w)| format!("{} {}", r, w)),
+            (["ionice", _, _], Some(_)) if !allow_ionice => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "not started with --allow-ionice",
+            )),
+            (["ionice", _, prio], Some(pid)) => match IoPriority::parse(prio) {
+                Some(IoPriority::RealTime(_)) if !may_realtime(pid) => Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "realtime io is only for root or the caller's own processes",
+                )),
+                Some(prio) => platform::set_io_priority(pid, prio).map(|()| String::new()),
+                None => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        };
+        match resp {
+            Ok(r) => writeln!(stdout, "ok {}", r)?,
+            Err(e) => writeln!(stdout, "err {}", e)?,
+        }
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+// whether the caller may put `pid`'s io in the realtime class.
+fn may_realtime(pid: i32) -> bool {
+    match caller_uid() {
+        Some(0) => true,
+        Some(uid) => platform::uid(pid) == Some(uid),
+        None => false,
+    }
+}
+
+// the user who asked for the helper. sudo and pkexec say who ran them, but
+// that's only to be believed when they did start it as root: anyone can set
+// the variables.
+fn caller_uid() -> Option<u32> {
+    let own = platform::own_uid()?;
+    if own != 0 {
+        return Some(own);
+    }
+    ["SUDO_UID", "PKEXEC_UID"]
+        .iter()
+        .find_map(|v| std::env::var(v).ok()?.parse().ok())
+        .or(Some(own))
+}
@@ -0,0 +1,64 @@
+//! Input: a single-line text input (palette, search, etc).
+use crossterm::event::{KeyCode, KeyEvent};
+
+#[derive(Clone, Debug, Default)]
+pub struct LineInput {
+    pub text: String,
+    cursor: usize, // in chars
+}
+
+// what a key press did to the input.
+#[derive(Debug, PartialEq)]
+pub enum InputEvent {
+    Edited,
+    Submit,
+    Cancel,
+    Ignored, // not an editing key; the owner may want it
+}
+
+impl LineInput {
+    pub fn handle_key(&mut self, key: KeyEvent) -> InputEvent {
+        match key.code {
+            KeyCode::Enter => return InputEvent::Submit,
+            KeyCode::Esc => return InputEvent::Cancel,
+            KeyCode::Char(c) => {
+                let at = self.byte_index();
+                self.text.insert(at, c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let at = self.byte_index();
+                self.text.remove(at);
+            }
+            KeyCode::Delete if self.cursor < self.text.chars().count() => {
+                let at = self.byte_index();
+                self.text.remove(at);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.text.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.chars().count(),
+            KeyCode::Backspace | KeyCode::Delete => (),
+            _ => return InputEvent::Ignored,
+        }
+        InputEvent::Edited
+    }
+
+    pub fn set(&mut self, text: &str) {
+        self.text = text.into();
+        self.cursor = self.text.chars().count();
+    }
+
+    // cursor column, for placing the terminal cursor.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn byte_index(&self) -> usize {
+        self.text
+            .char_indices()
+            .nth(self.cursor)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+}
@@ -0,0 +1,161 @@
+//! Pods: which Kubernetes pod a process on this node belongs to.
+//
+// Each container's cgroup path has its pod's uid, e.g.
+//   systemd driver:  .../kubepods-burstable-pod<uid, _ for ->.slice/cri-containerd-<id>.scope
+//   cgroupfs driver: /kubepods/burstable/pod<uid>/<container id>
+// and the kubelet says which pod has which uid. Its podresources API (gRPC
+// on a unix socket) lists pods without their uids or containers' ids, so it
+// can't be matched up with processes; its /pods endpoint can, from the
+// read-only port where that's enabled. Otherwise, the kubelet leaves the same
+// mapping on disk: /var/log/pods/<namespace>_<name>_<uid>/ exists for each
+// pod it runs (namespaces and pod names can't contain '_', so the split is
+// unambiguous).
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+const POD_LOGS: &str = "/var/log/pods";
+const KUBELET_READ_ONLY: ([u8; 4], u16) = ([127, 0, 0, 1], 10255);
+const KUBELET_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Default)]
+pub struct Pods {
+    // "namespace/name" by pod uid, from the kubelet or POD_LOGS.
+    names: HashMap<String, String>,
+    // processes don't change pods, so each is only looked up once; by uid,
+    // so one whose name wasn't known yet gets it once it is.
+    by_pid: HashMap<i32, Option<String>>,
+    refreshed: bool, // names, since the last prune
+}
+
+impl Pods {
+    /// "namespace/name" of the pod `pid` runs in, if any. Pods whose name
+    /// can't be found show as "pod <uid>".
+    pub fn lookup(&mut self, pid: i32) -> Option<String> {
+        let uid = match self.by_pid.get(&pid) {
+            Some(uid) => uid.clone(),
+            None => {
+                let uid = read_uid(pid);
+                self.by_pid.insert(pid, uid.clone());
+                uid
+            }
+        }?;
+        // names are read again at most once a sample, for pods new since.
+        if !self.names.contains_key(&uid) && !self.refreshed {
+            self.refresh();
+        }
+        Some(match self.names.get(&uid) {
+            Some(name) => name.clone(),
+            None => format!("pod {}", uid),
+        })
+    }
+
+    /// Drop what's known about processes `live` says have exited, in case
+    /// their pids are reused; once a sample.
+    pub fn prune(&mut self, live: impl Fn(i32) -> bool) {
+        self.by_pid.retain(|&pid, _| live(pid));
+        self.refreshed = false;
+    }
+
+    fn refresh(&mut self) {
+        self.refreshed = true;
+        let kubelet = SocketAddr::from(KUBELET_READ_ONLY);
+        match kubelet_pods(kubelet) {
+            Ok(names) => self.names.extend(names),
+            Err(e) => log::debug!("kubelet at {}: {:#}", kubelet, e),
+        }
+        let dirs = match fs::read_dir(POD_LOGS) {
+            Ok(d) => d,
+            Err(e) => {
+                log::debug!("{}: {}", POD_LOGS, e);
+                return;
+            }
+        };
+        for dir in dirs.flatten() {
+            let name = dir.file_name().to_string_lossy().into_owned();
+            let mut parts = name.splitn(3, '_');
+            if let (Some(ns), Some(pod), Some(uid)) = (parts.next(), parts.next(), parts.next()) {
+                self.names
+                    .insert(uid.to_string(), format!("{}/{}", ns, pod));
+            }
+        }
+    }
+}
+
+/// "namespace/name" by pod uid, from the kubelet's /pods (a PodList).
+pub fn pod_names(json: &str) -> serde_json::Result<HashMap<String, String>> {
+    #[derive(Deserialize)]
+    struct PodList {
+        items: Vec<Pod>,
+    }
+    #[derive(Deserialize)]
+    struct Pod {
+        metadata: Meta,
+    }
+    #[derive(Deserialize)]
+    struct Meta {
+        name: String,
+        namespace: String,
+        uid: String,
+    }
+    let pods: PodList = serde_json::from_str(json)?;
+    Ok(pods
+        .items
+        .into_iter()
+        .map(|p| {
+            (
+                p.metadata.uid,
+                format!("{}/{}", p.metadata.namespace, p.metadata.name),
+            )
+        })
+        .collect())
+}
+
+// GET /pods from the kubelet's read-only port, over plain HTTP.
+fn kubelet_pods(addr: SocketAddr) -> Result<HashMap<String, String>> {
+    let mut stream = TcpStream::connect_timeout(&addr, KUBELET_TIMEOUT)?;
+    stream.set_read_timeout(Some(KUBELET_TIMEOUT))?;
+    stream.write_all(b"GET /pods HTTP/1.0\r\nHost: localhost\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .context("no end to the headers")?;
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        bail!("{}", status);
+    }
+    Ok(pod_names(body)?)
+}
+
+// The pod uid of the process's cgroup, if it's under kubepods.
+fn read_uid(pid: i32) -> Option<String> {
+    let cgroups = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    cgroups
+        .lines()
+        .find_map(|l| pod_uid(l.splitn(3, ':').nth(2)?))
+}
+
+// The pod uid in a cgroup path, if it's under kubepods.
+fn pod_uid(path: &str) -> Option<String> {
+    if !path.contains("kubepods") {
+        return None;
+    }
+    path.split('/').find_map(|seg| {
+        let seg = seg.trim_end_matches(".slice");
+        let uid = match seg.rsplit_once("-pod") {
+            Some((_, uid)) => uid,
+            None => seg.strip_prefix("pod")?,
+        };
+        let uid = uid.replace('_', "-");
+        let is_uid = uid.len() == 36 && uid.chars().all(|c| c.is_ascii_hexdigit() || c == '-');
+        is_uid.then_some(uid)
+    })
+}
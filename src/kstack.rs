@@ -0,0 +1,101 @@
+//! KernelStacks: where a process is in the kernel, from sampling
+//! /proc/<pid>/stack, for the zoom view's kernel stack tab.
+//
+// One read is one moment; a process stuck in D state shows the same stack
+// read after read, so a thread reads it often and we count the stacks seen.
+// Reading another process's kernel stack needs root (CAP_SYS_ADMIN).
+use std::{collections::HashMap, fs, io, sync::mpsc, thread, time::Duration};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(20);
+
+/// What the zoom view shows of a KernelStacks.
+pub struct StackSummary {
+    pub samples: usize,
+    // the most frequent stacks, innermost frame first, with their counts.
+    pub top: Vec<(usize, Vec<String>)>,
+    pub error: Option<String>, // why sampling stopped, if it did
+}
+
+pub struct KernelStacks {
+    pub pid: i32,
+    rx: mpsc::Receiver<io::Result<String>>,
+    counts: HashMap<Vec<String>, usize>,
+    samples: usize,
+    error: Option<String>,
+}
+
+impl KernelStacks {
+    /// Start sampling `pid`; stops when dropped, or on the first failed read.
+    pub fn spawn(pid: i32) -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || loop {
+            let stack = fs::read_to_string(format!("/proc/{}/stack", pid));
+            let failed = stack.is_err();
+            if tx.send(stack).is_err() || failed {
+                return;
+            }
+            thread::sleep(SAMPLE_INTERVAL);
+        });
+        Self {
+            pid,
+            rx,
+            counts: HashMap::new(),
+            samples: 0,
+            error: None,
+        }
+    }
+
+    /// Count the samples taken since the last update.
+    pub fn update(&mut self) {
+        for stack in self.rx.try_iter() {
+            match stack {
+                Ok(s) => {
+                    *self.counts.entry(parse_stack(&s)).or_default() += 1;
+                    self.samples += 1;
+                }
+                Err(e) => self.error = Some(describe(self.pid, &e)),
+            }
+        }
+    }
+
+    /// The `n` most frequent stacks so far.
+    pub fn summary(&self, n: usize) -> StackSummary {
+        let mut top: Vec<(usize, Vec<String>)> =
+            self.counts.iter().map(|(s, &c)| (c, s.clone())).collect();
+        // ties broken by the stack, so the order doesn't flicker.
+        top.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        top.truncate(n);
+        StackSummary {
+            samples: self.samples,
+            top,
+            error: self.error.clone(),
+        }
+    }
+}
+
+/// The function names in a /proc/<pid>/stack, innermost first: from
+/// "[<0>] do_wait+0x1ab/0x2e0", "do_wait". Empty while it's in user space.
+pub fn parse_stack(stack: &str) -> Vec<String> {
+    stack
+        .lines()
+        .filter_map(|l| {
+            let frame = match l.split_once("] ") {
+                Some((_, f)) => f,
+                None => l,
+            };
+            let func = frame.split('+').next().unwrap_or(frame).trim();
+            Some(func.to_string()).filter(|f| !f.is_empty())
+        })
+        .collect()
+}
+
+fn describe(pid: i32, e: &io::Error) -> String {
+    match e.kind() {
+        io::ErrorKind::PermissionDenied => "reading kernel stacks needs root".into(),
+        io::ErrorKind::NotFound if crate::platform::pid_exists(pid) => {
+            "no /proc/<pid>/stack here (kernel without CONFIG_STACKTRACE?)".into()
+        }
+        io::ErrorKind::NotFound => "process is gone".into(),
+        _ => format!("reading /proc/{}/stack: {}", pid, e),
+    }
+}
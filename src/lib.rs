@@ -1,7 +1,46 @@
+pub mod action;
+pub mod adaptive;
+pub mod alert;
+pub mod cgroup_io;
+pub mod column;
+pub mod config;
+pub mod coredump;
+#[cfg(all(feature = "dbus", target_os = "linux"))]
+pub mod dbus;
 pub mod event;
+pub mod export;
+pub mod filter;
+pub mod freeze;
+pub mod group;
+pub mod headless;
+pub mod helper;
+pub mod input;
+pub mod k8s;
+pub mod kstack;
+pub mod lifetime;
+pub mod notify;
+pub mod platform;
+#[cfg(all(feature = "proc-connector", target_os = "linux"))]
+pub mod proc_connector;
+pub mod record;
+pub mod rename;
 pub mod render;
+#[cfg(all(feature = "resctrl", target_os = "linux"))]
+pub mod resctrl;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod session;
+#[cfg(unix)]
+pub mod share;
 pub mod sproc;
 pub mod sprocs;
+pub mod stats;
 pub mod sterm;
+pub mod theme;
+pub mod threshold;
+pub mod ui;
+pub mod units;
 pub mod view;
 pub mod view_state;
+pub mod vm;
+pub mod watch;
@@ -0,0 +1,83 @@
+//! Lifetime: a log of process starts and exits.
+//
+// Processes that come and go between glances at the table (compilers run by a
+// build, cron jobs...) are easy to miss; the log keeps a record of them. It's
+// bounded, dropping the oldest events first.
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::sproc::SProc;
+
+const EVENT_LIMIT: usize = 10_000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LifeEventKind {
+    Start,
+    Exit,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LifeEvent {
+    pub kind: LifeEventKind,
+    pub pid: i32,
+    pub name: String,
+    pub time: f64, // unix seconds
+    pub tick: u64, // the update it was observed in
+}
+
+#[derive(Clone, Default)]
+pub struct LifetimeLog {
+    events: VecDeque<LifeEvent>,
+}
+
+impl LifetimeLog {
+    pub fn push(&mut self, kind: LifeEventKind, sp: &SProc, tick: u64) {
+        self.events.push_back(LifeEvent {
+            kind,
+            pid: sp.pid,
+            name: sp.name.clone(),
+            time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0., |d| d.as_secs_f64()),
+            tick,
+        });
+        if self.events.len() > EVENT_LIMIT {
+            self.events.pop_front();
+        }
+    }
+
+    /// Oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &LifeEvent> {
+        self.events.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Write the log to `path` as JSON lines.
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let f =
+            File::create(path).with_context(|| format!("writing event log {}", path.display()))?;
+        let mut w = BufWriter::new(f);
+        for e in &self.events {
+            serde_json::to_writer(&mut w, e)?;
+            w.write_all(b"\n")?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
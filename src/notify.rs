@@ -0,0 +1,172 @@
+//! Notify: alerts when a bookmarked process exits or restarts.
+//
+// Works off the lifetime log: an exit of a bookmarked process is reported
+// with its last-known stats, and a start of one that exited recently under
+// the same name is reported as a restart. Optionally runs a hook command for
+// each, with the details in SPARKTOP_* environment variables.
+//
+// With the desktop-notify feature, DesktopNotifier shows alerts as desktop
+// notifications.
+use std::{
+    collections::{HashMap, VecDeque},
+    process::{Command, Stdio},
+};
+
+use crate::{
+    lifetime::{LifeEvent, LifeEventKind},
+    sprocs::SProcs,
+    units::render_bytes,
+};
+
+// a start this many ticks after an exit still counts as a restart.
+const RESTART_TICKS: u64 = 60;
+
+#[derive(Default)]
+pub struct Notifier {
+    hook: Option<String>,
+    seen_tick: u64,                      // events up to here are handled
+    exited: HashMap<String, (i32, u64)>, // name -> (pid, tick)
+}
+
+impl Notifier {
+    /// `hook` is run with `sh -c` on each exit or restart.
+    pub fn new(hook: Option<String>) -> Self {
+        Self {
+            hook,
+            ..Self::default()
+        }
+    }
+
+    /// Messages about bookmarked processes that exited or restarted since the
+    /// last call.
+    pub fn check(&mut self, sprocs: &SProcs, bookmarks: &[String]) -> Vec<String> {
+        let seen = self.seen_tick;
+        let mut new: Vec<&LifeEvent> = sprocs.events().iter().filter(|e| e.tick > seen).collect();
+        // a restart within a tick shows up as a start and an exit; take the
+        // exit first.
+        new.sort_by_key(|e| (e.tick, e.kind == LifeEventKind::Start));
+        let mut msgs = vec![];
+        for e in new {
+            self.seen_tick = self.seen_tick.max(e.tick);
+            let sp = match sprocs.by_pid(e.pid) {
+                Some(sp) if bookmarks.iter().any(|b| sp.matches(b)) => sp,
+                _ => continue,
+            };
+            let msg = match e.kind {
+                LifeEventKind::Exit => {
+                    self.exited.insert(e.name.clone(), (e.pid, e.tick));
+                    self.run_hook("exit", e, None);
+                    format!(
+                        "{} {} ({}) exited; last seen at cpu {:.1}%, mem {}",
+                        clock(e.time),
+                        e.name,
+                        e.pid,
+                        last_live(&sp.cpu_hist),
+                        render_bytes(last_live(&sp.mem_hist)),
+                    )
+                }
+                LifeEventKind::Start => match self.exited.remove(&e.name) {
+                    Some((old_pid, tick)) if e.tick - tick <= RESTART_TICKS => {
+                        self.run_hook("restart", e, Some(old_pid));
+                        format!(
+                            "{} {} restarted: pid {} -> {}",
+                            clock(e.time),
+                            e.name,
+                            old_pid,
+                            e.pid
+                        )
+                    }
+                    _ => continue,
+                },
+            };
+            msgs.push(msg);
+        }
+        msgs
+    }
+
+    fn run_hook(&self, event: &str, e: &LifeEvent, old_pid: Option<i32>) {
+        let hook = match &self.hook {
+            Some(h) => h,
+            None => return,
+        };
+        let mut env = vec![
+            ("SPARKTOP_EVENT", event.to_string()),
+            ("SPARKTOP_PID", e.pid.to_string()),
+            ("SPARKTOP_NAME", e.name.clone()),
+        ];
+        if let Some(pid) = old_pid {
+            env.push(("SPARKTOP_OLD_PID", pid.to_string()));
+        }
+        spawn_hook(hook, &env);
+    }
+}
+
+/// Run `hook` with `sh -c` and these environment variables, in the
+/// background and with no stdio.
+pub(crate) fn spawn_hook(hook: &str, env: &[(&str, String)]) {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(hook)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    match cmd.spawn() {
+        // reap it in the background.
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => log::warn!("running hook {:?}: {}", hook, e),
+    }
+}
+
+// the newest sample from before the process died (dead samples are zeros).
+fn last_live(hist: &VecDeque<f64>) -> f64 {
+    hist.get(1).copied().unwrap_or(0.)
+}
+
+// "HH:MM:SS UTC" of a unix time.
+fn clock(time: f64) -> String {
+    let secs = time as u64 % 86400;
+    format!(
+        "{:02}:{:02}:{:02} UTC",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Desktop notifications, sent from a background thread so a slow
+/// notification daemon doesn't hold up sampling.
+#[cfg(all(feature = "desktop-notify", target_os = "linux"))]
+pub struct DesktopNotifier {
+    tx: std::sync::mpsc::Sender<(String, String)>,
+}
+
+#[cfg(all(feature = "desktop-notify", target_os = "linux"))]
+impl DesktopNotifier {
+    /// Check there's a notification daemon to show them.
+    pub fn spawn() -> std::io::Result<Self> {
+        notify_rust::get_server_information().map_err(std::io::Error::other)?;
+        let (tx, rx) = std::sync::mpsc::channel::<(String, String)>();
+        std::thread::Builder::new()
+            .name("notify".into())
+            .spawn(move || {
+                for (summary, body) in rx {
+                    let shown = notify_rust::Notification::new()
+                        .appname("sparktop")
+                        .summary(&summary)
+                        .body(&body)
+                        .show();
+                    if let Err(e) = shown {
+                        log::warn!("desktop notification: {}", e);
+                    }
+                }
+            })?;
+        Ok(Self { tx })
+    }
+
+    pub fn notify(&self, summary: &str, body: &str) {
+        let _ = self.tx.send((summary.into(), body.into()));
+    }
+}
@@ -0,0 +1,826 @@
+//! Platform: per-process metrics sysinfo doesn't provide, read straight from
+//! the OS.
+use std::{collections::HashMap, io};
+
+use crate::column::Column;
+
+/// A metric source we can't read, and the columns that depend on it.
+pub struct Unavailable {
+    pub columns: Vec<Column>,
+    pub reason: String,
+}
+
+/// Platform oddities that change how we sample.
+#[derive(Default)]
+pub struct Quirks {
+    pub min_delay: Option<f64>, // seconds
+    pub note: Option<String>,
+    pub unavailable: Vec<Unavailable>,
+    pub columns: Option<Vec<Column>>, // a default column set that suits
+}
+
+pub fn quirks() -> Quirks {
+    if is_termux() {
+        termux_quirks()
+    } else {
+        wsl_quirks()
+    }
+}
+
+fn is_termux() -> bool {
+    cfg!(target_os = "android")
+        || std::env::var_os("TERMUX_VERSION").is_some()
+        || std::env::var("PREFIX").is_ok_and(|p| p.contains("com.termux"))
+}
+
+/// Android apps can only see their own processes' /proc details, and phones
+/// have small screens and batteries: use a compact column set and sample less
+/// often.
+fn termux_quirks() -> Quirks {
+    use Column::*;
+    Quirks {
+        min_delay: Some(3.),
+        note: Some("Termux: compact view, own processes' details only".into()),
+        unavailable: vec![Unavailable {
+            columns: vec![DiskRead, DiskWrite, Fds],
+            reason: "Android only exposes io and fds for the app's own processes".into(),
+        }],
+        columns: Some(vec![Name, Mem, Cpu, CpuHist]),
+    }
+}
+
+/// Under WSL, cpu accounting has coarse clock granularity (short intervals
+/// read as 0% or wild spikes), and WSL1 has no per-process io counters.
+fn wsl_quirks() -> Quirks {
+    let release = std::fs::read_to_string("/proc/sys/kernel/osrelease").unwrap_or_default();
+    let release = release.to_lowercase();
+    if !release.contains("microsoft") && std::env::var_os("WSL_DISTRO_NAME").is_none() {
+        return Quirks::default();
+    }
+    let wsl2 = release.contains("wsl2") || release.contains("microsoft-standard");
+    let mut quirks = Quirks {
+        min_delay: Some(2.),
+        ..Quirks::default()
+    };
+    if wsl2 {
+        quirks.note = Some("WSL2: sampling every 2s or slower".into());
+    } else {
+        quirks.note = Some("WSL1: sampling every 2s or slower, no disk io".into());
+        quirks.unavailable.push(Unavailable {
+            columns: vec![Column::DiskRead, Column::DiskWrite],
+            reason: "WSL1 has no per-process io counters".into(),
+        });
+    }
+    quirks
+}
+
+/// Check which metric sources are readable under the current confinement
+/// (SELinux, AppArmor, hidepid, containers...). Probes our own process, so
+/// anything denied here would show as zeros for every process.
+pub fn probe() -> Vec<Unavailable> {
+    let pid = std::process::id() as i32;
+    let mut unavailable = vec![];
+    if let Err(e) = io_totals(pid) {
+        unavailable.push(Unavailable {
+            columns: vec![Column::DiskRead, Column::DiskWrite],
+            reason: format!("disk io: {}", e),
+        });
+    }
+    if let Err(e) = run_delay_ns(pid) {
+        unavailable.push(Unavailable {
+            columns: vec![Column::Latency],
+            reason: format!("scheduler stats: {}", e),
+        });
+    }
+    if let Err(e) = wakeups(pid) {
+        unavailable.push(Unavailable {
+            columns: vec![Column::Wakeups],
+            reason: format!("context switches: {}", e),
+        });
+    }
+    if let Err(e) = state_threads(pid) {
+        unavailable.push(Unavailable {
+            columns: vec![Column::State, Column::Threads],
+            reason: format!("process state: {}", e),
+        });
+    }
+    if let Err(e) = fd_count(pid) {
+        unavailable.push(Unavailable {
+            columns: vec![Column::Fds],
+            reason: format!("open files: {}", e),
+        });
+    }
+    if let Err(e) = listening_sockets(pid) {
+        unavailable.push(Unavailable {
+            columns: vec![Column::Ports],
+            reason: format!("sockets: {}", e),
+        });
+    }
+    unavailable
+}
+
+/// Whether `pid` is still running (or at least not yet reaped).
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn pid_exists(pid: i32) -> bool {
+    std::path::Path::new(&format!("/proc/{}", pid)).exists()
+}
+
+// elsewhere, trust sysinfo's process list.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn pid_exists(_pid: i32) -> bool {
+    true
+}
+
+/// The user a process runs as (the owner of its /proc directory).
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn uid(pid: i32) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(format!("/proc/{}", pid))
+        .ok()
+        .map(|m| m.uid())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn uid(_pid: i32) -> Option<u32> {
+    None
+}
+
+/// The user this process was started by (its real uid).
+#[cfg(unix)]
+pub fn own_uid() -> Option<u32> {
+    Some(unsafe { libc::getuid() })
+}
+
+#[cfg(not(unix))]
+pub fn own_uid() -> Option<u32> {
+    None
+}
+
+/// The name of user `uid`, from /etc/passwd.
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|l| {
+        let mut fields = l.split(':');
+        let name = fields.next()?;
+        (fields.nth(1)?.parse() == Ok(uid)).then(|| name.to_string())
+    })
+}
+
+#[cfg(not(unix))]
+pub fn user_name(_uid: u32) -> Option<String> {
+    None
+}
+
+/// The uid of user `name`, from /etc/passwd.
+#[cfg(unix)]
+pub fn user_uid(name: &str) -> Option<u32> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|l| {
+        let mut fields = l.split(':');
+        (fields.next()? == name).then(|| fields.nth(1)?.parse().ok())?
+    })
+}
+
+#[cfg(not(unix))]
+pub fn user_uid(_name: &str) -> Option<u32> {
+    None
+}
+
+/// Number of open file descriptors.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn fd_count(pid: i32) -> io::Result<usize> {
+    Ok(std::fs::read_dir(format!("/proc/{}/fd", pid))?.count())
+}
+
+/// The network namespace a process is in, as the inode number lsns shows.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn net_ns(pid: i32) -> io::Result<u64> {
+    let link = std::fs::read_link(format!("/proc/{}/ns/net", pid))?;
+    bracketed_inode(&link.to_string_lossy(), "net:")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bad /proc/<pid>/ns/net"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn net_ns(_pid: i32) -> io::Result<u64> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// A port a process is listening on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Port {
+    pub number: u16,
+    pub udp: bool, // else TCP
+}
+
+impl std::fmt::Display for Port {
+    /// e.g. "22", or "53/udp".
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.udp {
+            true => write!(f, "{}/udp", self.number),
+            false => write!(f, "{}", self.number),
+        }
+    }
+}
+
+/// The listening TCP sockets, and bound but unconnected UDP ones, in a
+/// process's network namespace (not just its own), by inode, with the port
+/// each is on.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn listening_sockets(pid: i32) -> io::Result<HashMap<u64, Port>> {
+    // TCP_LISTEN, and TCP_CLOSE: what a UDP socket is until connected.
+    const LISTEN: &str = "0A";
+    const UNCONNECTED: &str = "07";
+    let mut sockets = HashMap::new();
+    for (table, udp) in [
+        ("tcp", false),
+        ("tcp6", false),
+        ("udp", true),
+        ("udp6", true),
+    ] {
+        let text = match std::fs::read_to_string(format!("/proc/{}/net/{}", pid, table)) {
+            Ok(t) => t,
+            // no ipv6
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let listening = if udp { UNCONNECTED } else { LISTEN };
+        // sl local_address rem_address st ... inode, after a header line.
+        for line in text.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(3) != Some(&listening) {
+                continue;
+            }
+            let number = fields
+                .get(1)
+                .and_then(|a| a.rsplit_once(':'))
+                .and_then(|(_, p)| u16::from_str_radix(p, 16).ok());
+            let inode = fields.get(9).and_then(|i| i.parse().ok());
+            if let (Some(number), Some(inode)) = (number, inode) {
+                sockets.insert(inode, Port { number, udp });
+            }
+        }
+    }
+    Ok(sockets)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn listening_sockets(_pid: i32) -> io::Result<HashMap<u64, Port>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// The inodes of a process's open sockets.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn socket_inodes(pid: i32) -> io::Result<Vec<u64>> {
+    let mut inodes = vec![];
+    for fd in std::fs::read_dir(format!("/proc/{}/fd", pid))? {
+        // fds can close under us.
+        if let Ok(link) = std::fs::read_link(fd?.path()) {
+            inodes.extend(bracketed_inode(&link.to_string_lossy(), "socket:"));
+        }
+    }
+    Ok(inodes)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn socket_inodes(_pid: i32) -> io::Result<Vec<u64>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+// the N in a "<kind>[N]" link, e.g. "socket:[1234]".
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn bracketed_inode(link: &str, kind: &str) -> Option<u64> {
+    link.strip_prefix(kind)?
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+/// Cumulative (read, written) storage bytes.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn io_totals(pid: i32) -> io::Result<(u64, u64)> {
+    let io = std::fs::read_to_string(format!("/proc/{}/io", pid))?;
+    let field = |name: &str| {
+        io.lines()
+            .find_map(|l| l.strip_prefix(name))
+            .and_then(|v| v.trim().parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, name.to_string()))
+    };
+    Ok((field("read_bytes:")?, field("write_bytes:")?))
+}
+
+/// System-wide pressure stall information: the % of the last 10s in which
+/// some task was stalled waiting on each resource.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Pressure {
+    pub cpu: f64,
+    pub memory: f64,
+    pub io: f64,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn pressure() -> io::Result<Pressure> {
+    let some_avg10 = |resource: &str| -> io::Result<f64> {
+        let psi = std::fs::read_to_string(format!("/proc/pressure/{}", resource))?;
+        // "some avg10=1.33 avg60=8.25 avg300=7.22 total=74429266"
+        psi.lines()
+            .find_map(|l| l.strip_prefix("some "))
+            .and_then(|l| l.split_whitespace().find_map(|f| f.strip_prefix("avg10=")))
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bad psi"))
+    };
+    Ok(Pressure {
+        cpu: some_avg10("cpu")?,
+        memory: some_avg10("memory")?,
+        io: some_avg10("io")?,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn pressure() -> io::Result<Pressure> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// The memory limit of the process's cgroup, if it has one.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn cgroup_mem_limit_bytes(pid: i32) -> Option<u64> {
+    let cgroups = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    // "0::/path" for cgroup v2, "N:memory:/path" for v1's memory controller.
+    let limit = cgroups.lines().find_map(|l| {
+        let mut parts = l.splitn(3, ':');
+        let (_, controllers, path) = (parts.next()?, parts.next()?, parts.next()?);
+        let file = match controllers {
+            "" => format!("/sys/fs/cgroup{}/memory.max", path),
+            c if c.split(',').any(|c| c == "memory") => {
+                format!("/sys/fs/cgroup/memory{}/memory.limit_in_bytes", path)
+            }
+            _ => return None,
+        };
+        std::fs::read_to_string(file).ok()
+    })?;
+    // "max" (v2) or a huge number (v1) when unlimited.
+    let bytes: u64 = limit.trim().parse().ok()?;
+    if bytes >= i64::MAX as u64 / 2 {
+        return None;
+    }
+    Some(bytes)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn cgroup_mem_limit_bytes(_pid: i32) -> Option<u64> {
+    None
+}
+
+/// How many cpus' worth of time the process's cgroup may use, if it's
+/// limited.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn cgroup_cpu_quota(pid: i32) -> Option<f64> {
+    let cgroups = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    // v2's cpu.max is "<quota> <period>", quota "max" when unlimited; v1's
+    // cpu controller has them in files of their own, quota -1 when unlimited.
+    let (quota, period) = cgroups.lines().find_map(|l| {
+        let mut parts = l.splitn(3, ':');
+        let (_, controllers, path) = (parts.next()?, parts.next()?, parts.next()?);
+        match controllers {
+            "" => {
+                let max = std::fs::read_to_string(format!("/sys/fs/cgroup{}/cpu.max", path));
+                let max = max.ok()?;
+                let mut fields = max.split_whitespace();
+                Some((fields.next()?.to_string(), fields.next()?.to_string()))
+            }
+            c if c.split(',').any(|c| c == "cpu") => {
+                let dir = format!("/sys/fs/cgroup/{}{}", c, path);
+                let read = |f| std::fs::read_to_string(format!("{}/{}", dir, f)).ok();
+                Some((read("cpu.cfs_quota_us")?, read("cpu.cfs_period_us")?))
+            }
+            _ => None,
+        }
+    })?;
+    let (quota, period): (f64, f64) = (quota.trim().parse().ok()?, period.trim().parse().ok()?);
+    if quota <= 0. || period <= 0. {
+        return None;
+    }
+    Some(quota / period)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn cgroup_cpu_quota(_pid: i32) -> Option<f64> {
+    None
+}
+
+/// Total time spent runnable but waiting for a cpu, from schedstat.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn run_delay_ns(pid: i32) -> io::Result<u64> {
+    // "<time on cpu> <time waiting> <timeslices>", in ns.
+    let stat = std::fs::read_to_string(format!("/proc/{}/schedstat", pid))?;
+    stat.split_whitespace()
+        .nth(1)
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bad schedstat"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn run_delay_ns(_pid: i32) -> io::Result<u64> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// How many times any of the process's threads has gone to sleep and been
+/// woken since it started: the voluntary context switches, summed over
+/// threads. Timers, polling and futex waits all count, which is what keeps a
+/// laptop's cpus out of deep idle.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn wakeups(pid: i32) -> io::Result<u64> {
+    let mut total = 0;
+    for task in std::fs::read_dir(format!("/proc/{}/task", pid))? {
+        // threads can exit between listing and reading.
+        let status = match std::fs::read_to_string(task?.path().join("status")) {
+            Ok(s) => s,
+            Err(_) => continue,
+        };
+        total += status
+            .lines()
+            .find_map(|l| l.strip_prefix("voluntary_ctxt_switches:"))
+            .and_then(|n| n.trim().parse::<u64>().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bad status"))?;
+    }
+    Ok(total)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn wakeups(_pid: i32) -> io::Result<u64> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Interrupts serviced since boot, on every cpu: timer ticks, devices and
+/// IPIs, each of which wakes a cpu that was idle. System-wide, since the
+/// kernel doesn't say which process a timer or interrupt was for (timer_list
+/// stopped naming them along with timer_stats).
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn interrupts() -> io::Result<u64> {
+    let stat = std::fs::read_to_string("/proc/stat")?;
+    stat.lines()
+        .find_map(|l| l.strip_prefix("intr "))
+        .and_then(|l| l.split_whitespace().next())
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bad /proc/stat"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn interrupts() -> io::Result<u64> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// A process's major page faults so far (pages it had to wait on being read
+/// in, from swap or from files), and how much of it is swapped out, in bytes.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn paging(pid: i32) -> io::Result<(u64, u64)> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid))?;
+    let bad = || io::Error::new(io::ErrorKind::InvalidData, "bad /proc/<pid>/stat");
+    let (_, rest) = stat.rsplit_once(')').ok_or_else(bad)?;
+    // majflt is field 12.
+    let faults = rest
+        .split_whitespace()
+        .nth(9)
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(bad)?;
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid))?;
+    // kernel threads have no VmSwap.
+    let swap_kb: u64 = status
+        .lines()
+        .find_map(|l| l.strip_prefix("VmSwap:"))
+        .and_then(|v| v.trim().trim_end_matches("kB").trim().parse().ok())
+        .unwrap_or(0);
+    Ok((faults, swap_kb * 1024))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn paging(_pid: i32) -> io::Result<(u64, u64)> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// A process's state, as ps shows it (R running, S sleeping, D waiting on io
+/// uninterruptibly, Z zombie, T stopped...), and how many threads it has.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn state_threads(pid: i32) -> io::Result<(char, usize)> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid))?;
+    let bad = || io::Error::new(io::ErrorKind::InvalidData, "bad /proc/<pid>/stat");
+    let (_, rest) = stat.rsplit_once(')').ok_or_else(bad)?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let state = fields
+        .first()
+        .and_then(|f| f.chars().next())
+        .ok_or_else(bad)?;
+    // num_threads is field 20.
+    let threads = fields
+        .get(17)
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(bad)?;
+    Ok((state, threads))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn state_threads(_pid: i32) -> io::Result<(char, usize)> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// An io scheduling class and level (0 first, to 7), as `ionice` sets them.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IoPriority {
+    Default, // follows the cpu nice value
+    RealTime(u8),
+    BestEffort(u8),
+    Idle,
+}
+
+impl IoPriority {
+    /// e.g. "be/4", or "idle".
+    pub fn name(self) -> String {
+        match self {
+            IoPriority::Default => "default".into(),
+            IoPriority::RealTime(l) => format!("rt/{}", l),
+            IoPriority::BestEffort(l) => format!("be/{}", l),
+            IoPriority::Idle => "idle".into(),
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<IoPriority> {
+        let level = |l: &str| l.parse().ok().filter(|&l| l <= 7);
+        match s.split_once('/') {
+            Some(("rt", l)) => level(l).map(IoPriority::RealTime),
+            Some(("be", l)) => level(l).map(IoPriority::BestEffort),
+            None if s == "default" => Some(IoPriority::Default),
+            None if s == "idle" => Some(IoPriority::Idle),
+            _ => None,
+        }
+    }
+
+    /// One step further out of the way: default, then best effort at the
+    /// lowest level, then idle, where it stays.
+    pub fn lower(self) -> IoPriority {
+        match self {
+            IoPriority::Default | IoPriority::RealTime(_) => IoPriority::BestEffort(7),
+            IoPriority::BestEffort(_) | IoPriority::Idle => IoPriority::Idle,
+        }
+    }
+}
+
+// ioprio values are the class in the top bits and the level in the rest.
+#[cfg(target_os = "linux")]
+const IOPRIO_CLASS_SHIFT: i32 = 13;
+#[cfg(target_os = "linux")]
+const IOPRIO_WHO_PROCESS: i32 = 1;
+
+#[cfg(target_os = "linux")]
+pub fn io_priority(pid: i32) -> io::Result<IoPriority> {
+    let raw = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, pid) };
+    if raw < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let level = (raw & ((1 << IOPRIO_CLASS_SHIFT) - 1)) as u8;
+    Ok(match raw >> IOPRIO_CLASS_SHIFT {
+        1 => IoPriority::RealTime(level),
+        2 => IoPriority::BestEffort(level),
+        3 => IoPriority::Idle,
+        _ => IoPriority::Default,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn io_priority(_pid: i32) -> io::Result<IoPriority> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Set a process's io priority. Raising it, or changing another user's
+/// process, needs CAP_SYS_NICE (PermissionDenied otherwise).
+#[cfg(target_os = "linux")]
+pub fn set_io_priority(pid: i32, prio: IoPriority) -> io::Result<()> {
+    let (class, level) = match prio {
+        IoPriority::Default => (0, 0),
+        IoPriority::RealTime(l) => (1, l),
+        IoPriority::BestEffort(l) => (2, l),
+        IoPriority::Idle => (3, 0),
+    };
+    let raw = (class << IOPRIO_CLASS_SHIFT) | level as i32;
+    let r = unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, pid, raw) };
+    if r < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_io_priority(_pid: i32, _prio: IoPriority) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// The cgroup (v2) a process is in: its path in the hierarchy, e.g.
+/// "/system.slice/sshd.service", and its directory.
+#[cfg(target_os = "linux")]
+pub fn cgroup_v2(pid: i32) -> io::Result<(String, std::path::PathBuf)> {
+    let cgroups = std::fs::read_to_string(format!("/proc/{}/cgroup", pid))?;
+    let path = cgroups
+        .lines()
+        .find_map(|l| l.strip_prefix("0::"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not in a cgroup v2"))?;
+    // v2 alone, or alongside v1 ("hybrid").
+    ["/sys/fs/cgroup", "/sys/fs/cgroup/unified"]
+        .iter()
+        .map(|root| std::path::Path::new(root).join(path.trim_start_matches('/')))
+        .find(|dir| dir.join("cgroup.procs").exists())
+        .map(|dir| (path.to_string(), dir))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "cgroup v2 not mounted"))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn cgroup_v2(_pid: i32) -> io::Result<(String, std::path::PathBuf)> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Freeze or thaw a cgroup (v2) and everything under it. Needs linux 5.2+
+/// (NotFound before that).
+pub fn freeze_cgroup(dir: &std::path::Path, frozen: bool) -> io::Result<()> {
+    std::fs::write(dir.join("cgroup.freeze"), if frozen { "1" } else { "0" })
+}
+
+/// Stop (SIGSTOP) or continue (SIGCONT) a process.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub fn stop_process(pid: i32, stopped: bool) -> io::Result<()> {
+    let sig = if stopped {
+        libc::SIGSTOP
+    } else {
+        libc::SIGCONT
+    };
+    if unsafe { libc::kill(pid, sig) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+pub fn stop_process(_pid: i32, _stopped: bool) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Ask a process to quit with a core dump (SIGQUIT). Those that handle the
+/// signal themselves (e.g. the JVM's thread dump) carry on instead.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub fn quit_process(pid: i32) -> io::Result<()> {
+    if unsafe { libc::kill(pid, libc::SIGQUIT) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+pub fn quit_process(_pid: i32) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Where the kernel writes core dumps, e.g. "core.%p", or "|/path/to/handler
+/// ..." if it hands them to a program.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn core_pattern() -> io::Result<String> {
+    Ok(std::fs::read_to_string("/proc/sys/kernel/core_pattern")?
+        .trim()
+        .to_string())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn core_pattern() -> io::Result<String> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// A process's (soft) limit on core dump size in bytes; None if unlimited.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn core_size_limit(pid: i32) -> io::Result<Option<u64>> {
+    let limits = std::fs::read_to_string(format!("/proc/{}/limits", pid))?;
+    let soft = limits
+        .lines()
+        .find_map(|l| l.strip_prefix("Max core file size"))
+        .and_then(|l| l.split_whitespace().next())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no core size limit"))?;
+    match soft {
+        "unlimited" => Ok(None),
+        n => n
+            .parse()
+            .map(Some)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, n.to_string())),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn core_size_limit(_pid: i32) -> io::Result<Option<u64>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Cpu seconds used by the process's exited, waited-for children (cutime +
+/// cstime).
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn child_cpu_secs(pid: i32) -> io::Result<f64> {
+    // cutime and cstime are fields 16, 17.
+    let (user, sys) = stat_secs(pid, 13, 14)?;
+    Ok(user + sys)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn child_cpu_secs(_pid: i32) -> io::Result<f64> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Cpu seconds the process has spent in user mode and in the kernel (utime,
+/// stime).
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn cpu_split_secs(pid: i32) -> io::Result<(f64, f64)> {
+    // utime and stime are fields 14, 15.
+    stat_secs(pid, 11, 12)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn cpu_split_secs(_pid: i32) -> io::Result<(f64, f64)> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+// two clock tick counts from /proc/<pid>/stat, in seconds; `a` and `b` count
+// from "state" (field 3), the first after comm.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn stat_secs(pid: i32, a: usize, b: usize) -> io::Result<(f64, f64)> {
+    // /proc reports these in USER_HZ, which is always 100 to userspace.
+    const USER_HZ: f64 = 100.;
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid))?;
+    let bad = || io::Error::new(io::ErrorKind::InvalidData, "bad /proc/<pid>/stat");
+    let (_, rest) = stat.rsplit_once(')').ok_or_else(bad)?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let field = |i: usize| -> io::Result<f64> {
+        let ticks: u64 = fields.get(i).and_then(|f| f.parse().ok()).ok_or_else(bad)?;
+        Ok(ticks as f64 / USER_HZ)
+    };
+    Ok((field(a)?, field(b)?))
+}
+
+// On FreeBSD, sysinfo lists the processes and fills in the summary header;
+// only io and open files are read here, and every other column is
+// Unsupported, which probe() hides.
+//
+// Per-process io comes from the kernel's rusage accounting via
+// sysctl(KERN_PROC). It counts block operations, not bytes (sysinfo passes
+// the counts on as they are), so the "bytes" here are an estimate at
+// BSD_BLOCK_SIZE per operation.
+#[cfg(target_os = "freebsd")]
+const BSD_BLOCK_SIZE: u64 = 512;
+
+#[cfg(target_os = "freebsd")]
+pub fn fd_count(pid: i32) -> io::Result<usize> {
+    let mib = [libc::CTL_KERN, libc::KERN_PROC, libc::KERN_PROC_NFDS, pid];
+    let n: libc::c_int = bsd_sysctl(&mib)?;
+    Ok(n as usize)
+}
+
+#[cfg(target_os = "freebsd")]
+pub fn io_totals(pid: i32) -> io::Result<(u64, u64)> {
+    let mib = [libc::CTL_KERN, libc::KERN_PROC, libc::KERN_PROC_PID, pid];
+    let kp: libc::kinfo_proc = bsd_sysctl(&mib)?;
+    let ru = kp.ki_rusage;
+    Ok((
+        ru.ru_inblock as u64 * BSD_BLOCK_SIZE,
+        ru.ru_oublock as u64 * BSD_BLOCK_SIZE,
+    ))
+}
+
+// Read a single fixed-size value with sysctl(3).
+#[cfg(target_os = "freebsd")]
+fn bsd_sysctl<T>(mib: &[libc::c_int]) -> io::Result<T> {
+    let mut val = std::mem::MaybeUninit::<T>::zeroed();
+    let mut len = std::mem::size_of::<T>();
+    // safe: the kernel writes at most `len` bytes into `val`.
+    let ret = unsafe {
+        libc::sysctl(
+            mib.as_ptr(),
+            mib.len() as libc::c_uint,
+            val.as_mut_ptr() as *mut libc::c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if len != std::mem::size_of::<T>() {
+        // KERN_PROC_PID returns nothing for a pid that's gone.
+        return Err(io::ErrorKind::NotFound.into());
+    }
+    // safe: fully written, and these are plain C structs where all-zero
+    // (from zeroed()) is valid too.
+    Ok(unsafe { val.assume_init() })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+pub fn fd_count(_pid: i32) -> io::Result<usize> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+pub fn io_totals(_pid: i32) -> io::Result<(u64, u64)> {
+    Err(io::ErrorKind::Unsupported.into())
+}
@@ -0,0 +1,196 @@
+//! ProcConnector: process fork/exec/exit events from the kernel.
+//
+// Sampling once a tick misses processes that live for less than a tick, which
+// for shell scripts and builds can be most of the work. The netlink proc
+// connector (needs CAP_NET_ADMIN) reports every exit as it happens; a
+// background thread catches them and reads the cpu time of the still-unreaped
+// process from /proc. That's best-effort: if the parent reaps it first, its
+// cpu time is lost.
+//
+// Wire format (linux/connector.h, linux/cn_proc.h), all native-endian:
+//   nlmsghdr (16 bytes) | cn_msg (20 bytes) | proc_event
+//   proc_event: what u32, cpu u32, timestamp_ns u64, then per-event data.
+use std::{
+    io,
+    mem::size_of,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+const NLMSG_HDR_LEN: usize = 16;
+const CN_MSG_LEN: usize = 20;
+// offset of the per-event data in a message.
+const EVENT_DATA: usize = NLMSG_HDR_LEN + CN_MSG_LEN + 16;
+
+/// A process that exited, with the cpu time it used.
+#[derive(Debug)]
+pub struct Exited {
+    pub pid: i32,
+    pub ppid: i32,
+    pub name: String,
+    pub cpu_secs: f64,
+}
+
+pub struct ProcConnector {
+    exited: Arc<Mutex<Vec<Exited>>>,
+}
+
+impl ProcConnector {
+    /// Subscribe to process events and start collecting exits.
+    pub fn spawn() -> io::Result<Self> {
+        let sock = Socket::open()?;
+        let exited = Arc::new(Mutex::new(vec![]));
+        let sink = Arc::clone(&exited);
+        thread::Builder::new()
+            .name("proc-connector".into())
+            .spawn(move || listen(sock, sink))?;
+        Ok(Self { exited })
+    }
+
+    /// Exits since the last call.
+    pub fn drain(&self) -> Vec<Exited> {
+        match self.exited.lock() {
+            Ok(mut v) => std::mem::take(&mut *v),
+            Err(_) => vec![],
+        }
+    }
+}
+
+fn listen(sock: Socket, sink: Arc<Mutex<Vec<Exited>>>) {
+    let clk_tck = match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        n if n > 0 => n as f64,
+        _ => 100.,
+    };
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = match sock.recv(&mut buf) {
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                log::debug!("proc connector: {}", e);
+                return;
+            }
+        };
+        let msg = &buf[..n];
+        if n < EVENT_DATA + 24 || u32_at(msg, NLMSG_HDR_LEN + CN_MSG_LEN) != PROC_EVENT_EXIT {
+            continue;
+        }
+        let pid = u32_at(msg, EVENT_DATA) as i32;
+        let tgid = u32_at(msg, EVENT_DATA + 4) as i32;
+        // only whole processes, not threads.
+        if pid != tgid {
+            continue;
+        }
+        // parent_tgid is only filled in by newer kernels (0 otherwise).
+        let ppid = u32_at(msg, EVENT_DATA + 20) as i32;
+        let (name, ticks) = read_stat(pid).unwrap_or_default();
+        let exited = Exited {
+            pid,
+            ppid,
+            name,
+            cpu_secs: ticks as f64 / clk_tck,
+        };
+        match sink.lock() {
+            Ok(mut v) => v.push(exited),
+            Err(_) => return,
+        }
+    }
+}
+
+fn u32_at(buf: &[u8], off: usize) -> u32 {
+    let mut b = [0u8; 4];
+    b.copy_from_slice(&buf[off..off + 4]);
+    u32::from_ne_bytes(b)
+}
+
+// (comm, utime + stime in clock ticks) from /proc/<pid>/stat.
+fn read_stat(pid: i32) -> Option<(String, u64)> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let (head, rest) = stat.rsplit_once(')')?;
+    let name = head.split_once('(')?.1.to_string();
+    // fields after comm start at "state", field 3; utime and stime are 14, 15.
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some((name, utime + stime))
+}
+
+struct Socket(libc::c_int);
+
+impl Socket {
+    fn open() -> io::Result<Self> {
+        // safe: plain syscalls on a socket we own; addr is fully initialized.
+        unsafe {
+            let fd = libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_CONNECTOR,
+            );
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let sock = Self(fd);
+            let mut addr: libc::sockaddr_nl = std::mem::zeroed();
+            addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+            addr.nl_pid = std::process::id();
+            addr.nl_groups = CN_IDX_PROC;
+            let ret = libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            );
+            if ret != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            sock.subscribe()?;
+            Ok(sock)
+        }
+    }
+
+    fn subscribe(&self) -> io::Result<()> {
+        let len = NLMSG_HDR_LEN + CN_MSG_LEN + 4;
+        let mut msg = Vec::with_capacity(len);
+        // nlmsghdr: len, type, flags, seq, pid
+        msg.extend_from_slice(&(len as u32).to_ne_bytes());
+        msg.extend_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+        msg.extend_from_slice(&0u16.to_ne_bytes());
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        msg.extend_from_slice(&std::process::id().to_ne_bytes());
+        // cn_msg: id.idx, id.val, seq, ack, len, flags
+        msg.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+        msg.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        msg.extend_from_slice(&4u16.to_ne_bytes());
+        msg.extend_from_slice(&0u16.to_ne_bytes());
+        msg.extend_from_slice(&PROC_CN_MCAST_LISTEN.to_ne_bytes());
+        // safe: msg is a valid buffer of msg.len() bytes.
+        let ret = unsafe { libc::send(self.0, msg.as_ptr() as *const libc::c_void, msg.len(), 0) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        // safe: the kernel writes at most buf.len() bytes into buf.
+        let ret =
+            unsafe { libc::recv(self.0, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(ret as usize)
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        // safe: we own the fd.
+        unsafe { libc::close(self.0) };
+    }
+}
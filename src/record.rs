@@ -0,0 +1,221 @@
+//! Record: saving samples to disk as they're taken.
+//
+// The data file is JSON lines, one line per tick. Ticks are buffered and
+// written in segments; after each segment the data file is fsynced and then
+// its end offset is appended to `<path>.idx` (also fsynced). When recording
+// resumes, the data file is trusted up to the last indexed offset; after it
+// (all of it, if the index is missing or empty) whole ticks are kept and
+// indexed again, and only a torn tick left by a crash is dropped.
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{sproc::SProc, units};
+
+// ticks per segment, i.e. the most that can be lost in a crash.
+const SEGMENT_TICKS: usize = 10;
+
+#[derive(Serialize, Deserialize)]
+pub struct Tick {
+    pub time: f64, // unix seconds
+    pub procs: Vec<ProcSample>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProcSample {
+    pub pid: i32,
+    pub name: String,
+    pub cpu: f64,
+    pub mem_mb: f64,
+    pub disk_read: u64,
+    pub disk_write: u64,
+    // totals over the process's life, so readers can take rates over any
+    // window; absent in older recordings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_secs: Option<f64>,
+    #[serde(default)]
+    pub read_bytes: u64,
+    #[serde(default)]
+    pub write_bytes: u64,
+}
+
+impl From<&SProc> for ProcSample {
+    fn from(sp: &SProc) -> Self {
+        Self {
+            pid: sp.pid,
+            name: sp.name.clone(),
+            cpu: sp.cpu_hist.front().copied().unwrap_or(0.),
+            mem_mb: units::mib(sp.mem_bytes as f64),
+            disk_read: sp.disk_read_hist.front().copied().unwrap_or(0),
+            disk_write: sp.disk_write_hist.front().copied().unwrap_or(0),
+            cpu_secs: sp.cpu_secs,
+            read_bytes: sp.read_bytes,
+            write_bytes: sp.write_bytes,
+        }
+    }
+}
+
+pub struct Recorder {
+    path: PathBuf,
+    data: File,
+    index: File,
+    committed: u64, // data file length as of the last index entry
+    pending: Vec<u8>,
+    pending_ticks: usize,
+}
+
+impl Recorder {
+    /// Open `path` for recording, resuming after the last complete segment
+    /// if it already exists.
+    pub fn open(path: &Path) -> Result<Self> {
+        let idx_path = index_path(path);
+        let mut data = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("opening recording {}", path.display()))?;
+        let index = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&idx_path)
+            .with_context(|| format!("opening recording index {}", idx_path.display()))?;
+        let len = data.metadata()?.len();
+        // (the data is synced before the index, so it shouldn't be shorter.)
+        let indexed = last_committed(&index)?.min(len);
+        let (committed, ticks) = whole_ticks(&mut data, indexed)?;
+        if len != committed {
+            log::info!(
+                "recording {}: dropping {} bytes of a torn tick",
+                path.display(),
+                len - committed
+            );
+            data.set_len(committed)?;
+        }
+        data.seek(SeekFrom::Start(committed))?;
+        let mut recorder = Self {
+            path: path.into(),
+            data,
+            index,
+            committed,
+            pending: vec![],
+            pending_ticks: 0,
+        };
+        if ticks > 0 {
+            log::info!(
+                "recording {}: indexing {} ticks after last segment",
+                path.display(),
+                ticks
+            );
+            recorder.commit(ticks)?;
+        }
+        Ok(recorder)
+    }
+
+    pub fn record<'a, I>(&mut self, sprocs: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a SProc>,
+    {
+        let tick = Tick {
+            time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0., |d| d.as_secs_f64()),
+            procs: sprocs.into_iter().map(ProcSample::from).collect(),
+        };
+        serde_json::to_writer(&mut self.pending, &tick)?;
+        self.pending.push(b'\n');
+        self.pending_ticks += 1;
+        if self.pending_ticks >= SEGMENT_TICKS {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write out buffered ticks as a segment and commit it to the index.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        self.data.write_all(&self.pending)?;
+        self.data.sync_data()?;
+        self.committed += self.pending.len() as u64;
+        self.commit(self.pending_ticks)?;
+        self.pending.clear();
+        self.pending_ticks = 0;
+        Ok(())
+    }
+
+    // index the data up to `committed`, `ticks` more than the last entry.
+    fn commit(&mut self, ticks: usize) -> Result<()> {
+        writeln!(self.index, "{} {}", self.committed, ticks)?;
+        self.index.sync_data()?;
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            log::error!("flushing recording {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+fn index_path(path: &Path) -> PathBuf {
+    let mut p = path.as_os_str().to_owned();
+    p.push(".idx");
+    p.into()
+}
+
+// The data file offset from the last well-formed index entry. A torn entry
+// (and anything after it) is truncated away so new entries follow good ones.
+fn last_committed(index: &File) -> Result<u64> {
+    let mut committed = 0;
+    let mut good_len = 0;
+    let mut reader = BufReader::new(index);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        let entry = line
+            .strip_suffix('\n')
+            .and_then(|l| l.split_once(' '))
+            .and_then(|(offset, ticks)| Some((offset.parse().ok()?, ticks.parse::<usize>().ok()?)));
+        match entry {
+            Some((offset, _)) => committed = offset,
+            None => break,
+        }
+        good_len += line.len() as u64;
+        line.clear();
+    }
+    if good_len != index.metadata()?.len() {
+        index.set_len(good_len)?;
+    }
+    Ok(committed)
+}
+
+// Where the whole ticks in the data file from `from` on end, and how many
+// there are. A tick is whole if its line is complete and parses.
+fn whole_ticks(data: &mut File, from: u64) -> Result<(u64, usize)> {
+    data.seek(SeekFrom::Start(from))?;
+    let (mut end, mut ticks) = (from, 0);
+    let mut reader = BufReader::new(data);
+    // (bytes: a torn tick can end partway through a character.)
+    let mut line = vec![];
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        let whole = line.ends_with(b"\n") && serde_json::from_slice::<Tick>(&line).is_ok();
+        if !whole {
+            break;
+        }
+        end += line.len() as u64;
+        ticks += 1;
+        line.clear();
+    }
+    Ok((end, ticks))
+}
@@ -0,0 +1,49 @@
+//! Renames: friendlier display names for processes, from config rules.
+//
+// Each rule is a regex matched against the command line (or the name, for
+// processes without one), and a replacement that can use its capture groups:
+//   {"pattern": "^python3[.0-9]* /opt/app/(\\w+)\\.py --id (\\d+)",
+//    "name": "app-$1 #$2"}
+// turns `python3.11 /opt/app/worker.py --id 7` into `app-worker #7`. The
+// first matching rule wins. Only the display changes: filters and bookmarks
+// still see the real name, and the zoom view shows both.
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::{config::Rename, sproc::SProc};
+
+#[derive(Clone, Default)]
+pub struct Renames {
+    rules: Vec<(Regex, String)>,
+}
+
+impl Renames {
+    pub fn new(rules: &[Rename]) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|r| {
+                let re = Regex::new(&r.pattern)
+                    .with_context(|| format!("bad rename pattern {:?}", r.pattern))?;
+                Ok((re, r.name.clone()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    /// The display name for `sp`, if a rule matches.
+    pub fn apply(&self, sp: &SProc) -> Option<String> {
+        if self.rules.is_empty() {
+            return None;
+        }
+        let cmdline = match sp.cmd.is_empty() {
+            true => sp.name.clone(),
+            false => sp.cmd.join(" "),
+        };
+        self.rules.iter().find_map(|(re, name)| {
+            let caps = re.captures(&cmdline)?;
+            let mut out = String::new();
+            caps.expand(name, &mut out);
+            Some(out)
+        })
+    }
+}
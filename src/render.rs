@@ -1,30 +1,456 @@
-/// Rendering logic.
+//! Rendering logic: sparklines and gauges for a history of samples.
+//
+// Usable outside sparktop. Each renderer comes in two forms: plain cells
+// (`String`, or `Vec<Cell>` for colored ones) for any terminal library, and
+// ratatui `Line`s built from those. Histories are any iterator of samples, drawn
+// one cell per sample in the order given, and scaled against `max`; samples
+// over `max` draw as a full bar.
+//
+// Bars are drawn with the glyphs given (see `Bars`; 8 block heights by
+// default), since some fonts draw partial blocks badly. `Series::with_ascii`
+// (with `Bars::ascii()`) keeps to plain ASCII, for serial consoles and the
+// like, and `to_ascii` stands in for whatever else was drawn.
+use anyhow::{bail, Result};
 
-pub fn render_vec<'a, II>(xs: II, max: f64) -> String
+use crate::ui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+
+/// One colored sparkline cell: a bar glyph, or ' ' when too small to show,
+/// its color and its background (`Color::Reset` but for overlaps, see
+/// `render_multi`).
+pub type Cell = (char, Color, Color);
+
+/// A sparkline of `xs` in `bars`, uncolored, e.g. "▁▃█ ▅".
+pub fn render_vec<'a, II>(xs: II, max: f64, bars: &Bars) -> String
 where
     II: IntoIterator<Item = &'a f64>,
 {
     let mut r = String::new();
     for x in xs.into_iter() {
         let p = *x / max;
-        r.push(float_bar(p));
+        r.push(bars.bar(p));
     }
     r
 }
 
-const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// One series of samples for `render_multi`, each with a color so a series
+/// can shade itself by value (e.g. hot cpu).
+pub struct Series {
+    pub samples: Vec<f64>,
+    pub max: f64,
+    pub colors: Vec<Color>, // one per sample
+    // samples to highlight (e.g. spikes), and the color to use for them;
+    // later ones win.
+    pub marks: Vec<(Vec<bool>, Color)>,
+    // cells after the samples for when there was nothing to sample yet, e.g.
+    // before a process started; drawn as UNBORN rather than blank like idle.
+    pub unborn: usize,
+    // glyphs to draw with.
+    pub bars: Bars,
+    // two samples to a cell, as braille dots, for twice the history.
+    pub braille: bool,
+    // nothing but ASCII: no braille, and UNBORN_ASCII.
+    pub ascii: bool,
+}
+
+/// A cell from before a series began.
+pub const UNBORN: Cell = ('·', Color::DarkGray, Color::Reset);
+/// `UNBORN` in ASCII.
+pub const UNBORN_ASCII: Cell = ('.', Color::DarkGray, Color::Reset);
+
+impl Series {
+    /// `color` is called with each raw sample.
+    pub fn new<'a, II>(xs: II, max: f64, color: impl Fn(f64) -> Color) -> Self
+    where
+        II: IntoIterator<Item = &'a f64>,
+    {
+        let samples: Vec<f64> = xs.into_iter().copied().collect();
+        Self {
+            colors: samples.iter().map(|&x| color(x)).collect(),
+            samples,
+            max,
+            marks: vec![],
+            unborn: 0,
+            bars: Bars::default(),
+            braille: false,
+            ascii: false,
+        }
+    }
+
+    /// Follow the samples with `n` UNBORN cells.
+    pub fn with_unborn(mut self, n: usize) -> Self {
+        self.unborn = n;
+        self
+    }
+
+    /// Draw the samples flagged in `marks` in `color` instead, over any
+    /// marks given before.
+    pub fn with_marks(mut self, marks: Vec<bool>, color: Color) -> Self {
+        self.marks.push((marks, color));
+        self
+    }
+
+    /// Draw with `bars` instead of the default glyphs.
+    pub fn with_bars(mut self, bars: &Bars) -> Self {
+        self.bars = bars.clone();
+        self
+    }
+
+    /// Draw two samples to a cell, side by side in braille, four levels
+    /// each.
+    pub fn with_braille(mut self, on: bool) -> Self {
+        self.braille = on;
+        self
+    }
+
+    /// Keep to ASCII: plain bars even if braille, and UNBORN_ASCII. The
+    /// bars themselves are whatever `with_bars` gave.
+    pub fn with_ascii(mut self, on: bool) -> Self {
+        self.ascii = on;
+        self
+    }
+}
+
+/// `render_vec_colored` as plain cells.
+pub fn cells_colored<'a, II>(
+    xs: II,
+    max: f64,
+    bars: &Bars,
+    color: impl Fn(f64) -> Color,
+) -> Vec<Cell>
+where
+    II: IntoIterator<Item = &'a f64>,
+{
+    cells_multi(&[Series::new(xs, max, color).with_bars(bars)])
+}
+
+/// Like `render_vec`, but colors each bar with `color`.
+pub fn render_vec_colored<'a, II>(
+    xs: II,
+    max: f64,
+    bars: &Bars,
+    color: impl Fn(f64) -> Color,
+) -> Line<'static>
+where
+    II: IntoIterator<Item = &'a f64>,
+{
+    render_multi(&[Series::new(xs, max, color).with_bars(bars)])
+}
+
+/// Overlay up to two series in a single run of cells. Each cell's bar is as
+/// tall as the larger of the two samples and takes that series' color, so the
+/// dominant metric at each point in time is visible at a glance (e.g. disk
+/// read vs write). Ties go to the first series. Extra series are ignored, and
+/// all are drawn as the first one says (glyphs, braille, ASCII).
+///
+/// Where both are high enough to matter (the taller at least 3/4 of full
+/// scale, the shorter at least 1/4) the cell is split instead: a half block
+/// '▄' with the shorter series' color below and the taller's above, so
+/// neither hides the other (e.g. cpu user vs system). Not in braille or
+/// ASCII.
+pub fn render_multi(series: &[Series]) -> Line<'static> {
+    let mut spans: Vec<Span> = vec![];
+    let mut run = String::new();
+    let mut run_style = Style::default();
+    for (ch, color, bg) in cells_multi(series) {
+        let style = match ch {
+            ' ' => Style::default(),
+            _ if bg == Color::Reset => Style::default().fg(color),
+            _ => Style::default().fg(color).bg(bg),
+        };
+        if style != run_style && !run.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut run), run_style));
+        }
+        run_style = style;
+        run.push(ch);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(run, run_style));
+    }
+    Line::from(spans)
+}
+
+/// `render_multi` as plain cells. Blank cells are `Color::Reset`.
+pub fn cells_multi(series: &[Series]) -> Vec<Cell> {
+    let series = &series[..series.len().min(2)];
+    let first = match series.first() {
+        Some(s) => s,
+        None => return vec![],
+    };
+    let unborn = series.iter().map(|s| s.unborn).max().unwrap_or(0);
+    let unborn_cell = if first.ascii { UNBORN_ASCII } else { UNBORN };
+    // there's no braille in ASCII, so those get plain bars.
+    if first.braille && !first.ascii {
+        let dots = Bars(BRAILLE_LEVELS.to_vec());
+        let mut cells: Vec<Cell> = levels_multi(series, &dots)
+            .chunks(2)
+            .map(|pair| {
+                let right = pair.get(1).map(|s| s.0).unwrap_or((0, 0., Color::Reset));
+                let left = pair[0].0;
+                let color = match right.0 > left.0 {
+                    true => right.2,
+                    false => left.2,
+                };
+                match (left.0, right.0) {
+                    (0, 0) => (' ', Color::Reset, Color::Reset),
+                    (l, r) => (braille(l, r), color, Color::Reset),
+                }
+            })
+            .collect();
+        cells.extend(std::iter::repeat_n(unborn_cell, unborn.div_ceil(2)));
+        return cells;
+    }
+    let bars = &first.bars;
+    let mut cells: Vec<Cell> = levels_multi(series, bars)
+        .into_iter()
+        .map(|(tall, short)| match (tall, short) {
+            ((_, t, top), Some((_, s, bottom)))
+                if !first.ascii && halves(t) == 2 && halves(s) >= 1 =>
+            {
+                (HALF, bottom, top)
+            }
+            ((0, _, _), _) => (' ', Color::Reset, Color::Reset),
+            ((_, frac, color), _) => (bars.bar(frac), color, Color::Reset),
+        })
+        .collect();
+    cells.extend(std::iter::repeat_n(unborn_cell, unborn));
+    cells
+}
+
+type Level = (usize, f64, Color);
+
+// each sample's (level in `bars`, fraction of full scale, color), from
+// whichever series is tallest there, and the other series' if it has one
+// there too.
+fn levels_multi(series: &[Series], bars: &Bars) -> Vec<(Level, Option<Level>)> {
+    let len = series.iter().map(|s| s.samples.len()).max().unwrap_or(0);
+    (0..len)
+        .map(|i| {
+            let mut best = (0, 0., Color::Reset);
+            let mut other = None;
+            for (n, s) in series.iter().enumerate() {
+                let raw = match s.samples.get(i) {
+                    Some(&x) => x,
+                    None => continue,
+                };
+                let frac = raw / s.max;
+                let level = bars.level(frac);
+                let marked = s.marks.iter().rev().find(|(m, _)| m.get(i) == Some(&true));
+                let color = match marked {
+                    Some(&(_, c)) => c,
+                    None => s.colors[i],
+                };
+                if n == 0 || level > best.0 {
+                    other = if n == 0 { None } else { Some(best) };
+                    best = (level, frac, color);
+                } else {
+                    other = Some((level, frac, color));
+                }
+            }
+            (best, other)
+        })
+        .collect()
+}
+
+// the lower half block, for cells two series overlap in.
+const HALF: char = '▄';
 
-// f must be between 0 and 1.
-fn float_bar(mut f: f64) -> char {
-    f = f.min(1.); // cpu usage can be > 1. do something special?
-    if f < 0.03 {
-        return ' ';
+// how many halves of a cell a fraction of full scale fills, rounded.
+fn halves(f: f64) -> usize {
+    (f.clamp(0., 1.) * 2.).round() as usize
+}
+
+// braille columns filled from the bottom, one to four dots.
+const BRAILLE_LEVELS: [char; 4] = ['⣀', '⣤', '⣶', '⣿'];
+const BRAILLE_LEFT: [u32; 5] = [0, 0x40, 0x44, 0x46, 0x47];
+const BRAILLE_RIGHT: [u32; 5] = [0, 0x80, 0xa0, 0xb0, 0xb8];
+
+// a cell with `left` dots up its left column and `right` up its right.
+fn braille(left: usize, right: usize) -> char {
+    char::from_u32(0x2800 + BRAILLE_LEFT[left] + BRAILLE_RIGHT[right]).unwrap_or(' ')
+}
+
+/// Like `render_vec_colored`, but `height` rows tall (top row first), for
+/// when there's room to show more detail.
+pub fn render_vec_tall<'a, II>(
+    xs: II,
+    max: f64,
+    height: usize,
+    bars: &Bars,
+    color: impl Fn(f64) -> Color,
+) -> Vec<Line<'static>>
+where
+    II: IntoIterator<Item = &'a f64>,
+{
+    cells_tall(xs, max, height, bars, color)
+        .into_iter()
+        .map(|row| {
+            let spans: Vec<Span> = row
+                .into_iter()
+                .map(|(ch, c, _)| Span::styled(ch.to_string(), Style::default().fg(c)))
+                .collect();
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// `render_vec_tall` as plain cells, a row of them per line, top row first.
+/// Blank cells keep their sample's color.
+pub fn cells_tall<'a, II>(
+    xs: II,
+    max: f64,
+    height: usize,
+    bars: &Bars,
+    color: impl Fn(f64) -> Color,
+) -> Vec<Vec<Cell>>
+where
+    II: IntoIterator<Item = &'a f64>,
+{
+    let xs: Vec<f64> = xs.into_iter().copied().collect();
+    (0..height)
+        .rev()
+        .map(|row| {
+            xs.iter()
+                .map(|&x| {
+                    // how much of this row the bar fills.
+                    let fill = ((x / max).min(1.) * height as f64 - row as f64).clamp(0., 1.);
+                    let ch = if fill >= 1. {
+                        bars.full()
+                    } else {
+                        bars.bar(fill)
+                    };
+                    (ch, color(x), Color::Reset)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A fixed-width horizontal gauge for a bounded (0-100%) metric, like
+/// `[###---]  45%`. `width` is the number of fill characters.
+pub fn render_gauge(pct: f64, width: usize) -> String {
+    let pct = pct.clamp(0., 100.);
+    let filled = ((pct / 100.) * width as f64).round() as usize;
+    format!(
+        "[{}{}]{:>4.0}%",
+        "#".repeat(filled),
+        "-".repeat(width - filled),
+        pct
+    )
+}
+
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const ASCII: [char; 7] = ['_', '.', ':', '-', '=', '#', '@'];
+
+/// The glyphs bars are drawn with, shortest first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bars(Vec<char>);
+
+impl Default for Bars {
+    fn default() -> Self {
+        Self(BLOCKS.to_vec())
+    }
+}
+
+impl Bars {
+    /// The "ascii" ramp, `_.:-=#@`.
+    pub fn ascii() -> Self {
+        Self(ASCII.to_vec())
+    }
+
+    /// "blocks" (8 heights), "quarters" (4), "dots" (4, braille), "ascii"
+    /// (7, `_.:-=#@`), or any other string as the glyphs themselves, e.g.
+    /// ".:|".
+    pub fn parse(s: &str) -> Result<Self> {
+        let glyphs: Vec<char> = match s {
+            "blocks" => BLOCKS.to_vec(),
+            "ascii" => ASCII.to_vec(),
+            "quarters" => vec!['▂', '▄', '▆', '█'],
+            "dots" => vec!['⣀', '⣤', '⣶', '⣿'],
+            custom => custom.chars().collect(),
+        };
+        if glyphs.is_empty() || glyphs.contains(&' ') {
+            bail!("bar glyphs must be at least one character, and no spaces");
+        }
+        Ok(Self(glyphs))
+    }
+
+    /// At most `n` levels, picked evenly and keeping the tallest, for columns
+    /// that read better with fewer.
+    pub fn with_levels(&self, n: usize) -> Self {
+        let len = self.0.len();
+        if n == 0 || n >= len {
+            return self.clone();
+        }
+        Self((1..=n).map(|i| self.0[i * len / n - 1]).collect())
+    }
+
+    pub fn levels(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The glyph for a fraction of full scale: ' ' below about 3%, the
+    /// tallest at 1 or over.
+    pub fn bar(&self, mut f: f64) -> char {
+        f = f.min(1.); // cpu usage can be > 1. do something special?
+        if f < 0.03 {
+            return ' ';
+        }
+        let sub_seg = 1. / self.0.len() as f64;
+        let mut i = 0;
+        while f > sub_seg && i + 1 < self.0.len() {
+            f -= sub_seg;
+            i += 1;
+        }
+        self.0[i]
+    }
+
+    fn full(&self) -> char {
+        self.0[self.0.len() - 1]
+    }
+
+    // 0 for blank, otherwise 1 + index of the glyph.
+    fn level(&self, f: f64) -> usize {
+        match self.bar(f) {
+            ' ' => 0,
+            c => 1 + self.0.iter().position(|&b| b == c).unwrap_or(0),
+        }
+    }
+}
+
+/// An ASCII stand-in for a glyph drawn by sparktop or the widgets it uses:
+/// bars and braille by how full they are, box drawing as `-|+`, and
+/// anything else unknown as '?'. ASCII passes through.
+pub fn to_ascii(c: char) -> char {
+    if c.is_ascii() {
+        return c;
+    }
+    if let Some(i) = BLOCKS.iter().position(|&b| b == c) {
+        return ASCII[i * ASCII.len() / BLOCKS.len()];
     }
-    let sub_seg = 1. / BARS.len() as f64;
-    let mut i = 0;
-    while f > sub_seg {
-        f -= sub_seg;
-        i += 1;
+    match c {
+        // braille, by how many of its 8 dots are raised.
+        '\u{2800}'..='\u{28ff}' => match (c as u32 - 0x2800).count_ones() {
+            0 => ' ',
+            1..=2 => '.',
+            3..=5 => ':',
+            _ => '#',
+        },
+        '─' | '━' | '═' | '╌' | '┄' => '-',
+        '│' | '┃' | '║' | '╎' | '┆' => '|',
+        // corners and junctions
+        '\u{250c}'..='\u{254b}' | '\u{2552}'..='\u{256c}' | '╭' | '╮' | '╯' | '╰' => '+',
+        '·' => '.',
+        '•' => '*',
+        '←' => '<',
+        '→' => '>',
+        '↑' | '▲' => '^',
+        '↓' | '▼' => 'v',
+        '…' => '~',
+        '❄' => '*',
+        'µ' => 'u',
+        _ => '?',
     }
-    BARS[i]
 }
@@ -0,0 +1,120 @@
+//! Resctrl: memory bandwidth per resctrl monitoring group (Intel RDT MBM,
+//! AMD PQoS).
+//
+// Bandwidth can't be attributed per process, only per group of tasks that the
+// admin has set up under /sys/fs/resctrl: the default group (the root),
+// control groups (its subdirectories) and their mon_groups. Each group's
+// mon_data has a cumulative byte counter per L3 cache domain, which we sum.
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const ROOT: &str = "/sys/fs/resctrl";
+
+pub struct Resctrl {
+    root: PathBuf,
+    groups: BTreeMap<String, Group>,
+}
+
+#[derive(Default)]
+pub struct Group {
+    prev_bytes: Option<u64>,
+    pub mb_per_sec: VecDeque<f64>, // newest first
+}
+
+impl Resctrl {
+    /// Fails if resctrl isn't mounted or has no bandwidth monitoring.
+    pub fn open() -> io::Result<Self> {
+        Self::open_at(Path::new(ROOT))
+    }
+
+    pub fn open_at(root: &Path) -> io::Result<Self> {
+        fs::metadata(root.join("info/L3_MON"))?;
+        Ok(Self {
+            root: root.into(),
+            groups: BTreeMap::new(),
+        })
+    }
+
+    /// Read the counters; `elapsed` is the seconds since the last update,
+    /// and `keep` how many samples of each group's history to keep.
+    pub fn update(&mut self, elapsed: f64, keep: usize) {
+        let mut seen = vec![];
+        for (name, dir) in self.group_dirs() {
+            let bytes = match total_bytes(&dir) {
+                Ok(b) => b,
+                Err(e) => {
+                    log::debug!("resctrl group {}: {}", name, e);
+                    continue;
+                }
+            };
+            let group = self.groups.entry(name.clone()).or_default();
+            if let Some(prev) = group.prev_bytes {
+                let rate = if elapsed > 0. {
+                    bytes.saturating_sub(prev) as f64 / elapsed / (1024. * 1024.)
+                } else {
+                    0.
+                };
+                group.mb_per_sec.push_front(rate);
+                group.mb_per_sec.truncate(keep);
+            }
+            group.prev_bytes = Some(bytes);
+            seen.push(name);
+        }
+        // groups can be removed at any time.
+        self.groups.retain(|name, _| seen.contains(name));
+    }
+
+    pub fn groups(&self) -> impl Iterator<Item = (&String, &Group)> {
+        self.groups.iter()
+    }
+
+    // (name, directory) of every group with monitoring data.
+    fn group_dirs(&self) -> Vec<(String, PathBuf)> {
+        let mut dirs = vec![("default".to_string(), self.root.clone())];
+        let mut ctrl = vec![(String::new(), self.root.clone())];
+        for entry in read_dirs(&self.root) {
+            let name = entry.file_name().map(|n| n.to_string_lossy().into_owned());
+            match name.as_deref() {
+                Some("info") | Some("mon_groups") | Some("mon_data") | None => (),
+                Some(n) => {
+                    dirs.push((n.to_string(), entry.clone()));
+                    ctrl.push((format!("{}/", n), entry));
+                }
+            }
+        }
+        for (prefix, dir) in ctrl {
+            for entry in read_dirs(&dir.join("mon_groups")) {
+                if let Some(n) = entry.file_name() {
+                    dirs.push((format!("{}{}", prefix, n.to_string_lossy()), entry));
+                }
+            }
+        }
+        dirs.retain(|(_, d)| d.join("mon_data").is_dir());
+        dirs
+    }
+}
+
+fn read_dirs(dir: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+// mbm_total_bytes summed over the group's cache domains.
+fn total_bytes(group: &Path) -> io::Result<u64> {
+    let mut total = 0;
+    for domain in fs::read_dir(group.join("mon_data"))? {
+        let s = fs::read_to_string(domain?.path().join("mbm_total_bytes"))?;
+        // "Unavailable" when the hardware counter isn't ready.
+        total += s.trim().parse::<u64>().unwrap_or(0);
+    }
+    Ok(total)
+}
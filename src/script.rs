@@ -0,0 +1,218 @@
+//! Script: computed columns, highlights and alerts from a rhai script
+//! (--script), run against every sample.
+//
+// The script's top level registers callbacks:
+//
+//   column("mb/thr", |p| p.mem_mb / (p.threads ?? 1));
+//   highlight(|p| p.name == "java" && p.cpu > 100., "fg:red, bold");
+//   alert("swapping", |s| s.swap_pct > 50.);
+//   alert("zombies", |s| {
+//       let n = s.procs.filter(|p| p.state == "Z").len();
+//       if n > 5 { `${n} of them` } else { false }
+//   });
+//
+// Columns and highlights are given each process as a map of the fields row
+// rules know (see view_state::RowCond): pid, fds and threads as integers,
+// cpu, cpu_ewma, mem_mb, mem_pct, disk_read_ewma, disk_write_ewma,
+// latency_ewma and wakeups_ewma as floats, and name, cmd, state, pod and user
+// (a uid) as strings; fields it hasn't got are missing, so () when read. A
+// column's values are shown under its name, floats to one decimal place. A
+// highlight's style is a row rule's, patched over the row's when it returns
+// true. Alerts are given the whole sample: procs (every live process, as
+// above), cpu (% of all cpus), mem_pct, swap_pct and load (the 1, 5 and 15
+// minute averages). One fires when it returns true or some text (said after
+// its name), and again only after it's stopped.
+//
+// Each call gets MAX_OPERATIONS to finish in. A callback that fails is
+// reported once, as an alert, and left blank (or unmatched) after.
+use std::{
+    collections::HashSet,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Context, Result};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FnPtr, Map, AST};
+
+use crate::{
+    sproc::SProc,
+    sprocs::SystemStats,
+    ui::style::Style,
+    view_state::{parse_style, row_fields, FieldValue},
+};
+
+// how long a callback may run, in rhai's operations.
+const MAX_OPERATIONS: u64 = 100_000;
+
+#[derive(Default)]
+struct Callbacks {
+    columns: Vec<(String, FnPtr)>,
+    highlights: Vec<(FnPtr, Style)>,
+    alerts: Vec<(String, FnPtr)>,
+}
+
+pub struct Script {
+    run: Runner,
+    callbacks: Callbacks,
+    firing: Vec<bool>, // by alert
+}
+
+struct Runner {
+    engine: Engine,
+    ast: AST,
+    failed: HashSet<String>, // callbacks already reported failing
+    msgs: Vec<String>,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Self> {
+        let src = fs::read_to_string(path).with_context(|| format!("reading {:?}", path))?;
+        Self::compile(&src).with_context(|| format!("in {:?}", path))
+    }
+
+    /// Run a script's top level, to register its callbacks.
+    pub fn compile(src: &str) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let registered = Arc::new(Mutex::new(Callbacks::default()));
+        let r = Arc::clone(&registered);
+        engine.register_fn("column", move |name: &str, f: FnPtr| {
+            r.lock().unwrap().columns.push((name.into(), f));
+        });
+        let r = Arc::clone(&registered);
+        engine.register_fn(
+            "highlight",
+            move |f: FnPtr, style: &str| -> Result<(), Box<EvalAltResult>> {
+                let style = parse_style(style).map_err(|e| e.to_string())?;
+                r.lock().unwrap().highlights.push((f, style));
+                Ok(())
+            },
+        );
+        let r = Arc::clone(&registered);
+        engine.register_fn("alert", move |name: &str, f: FnPtr| {
+            r.lock().unwrap().alerts.push((name.into(), f));
+        });
+        let ast = engine.compile(src).map_err(|e| anyhow!("{}", e))?;
+        engine.run_ast(&ast).map_err(|e| anyhow!("{}", e))?;
+        let callbacks = std::mem::take(&mut *registered.lock().unwrap());
+        Ok(Self {
+            run: Runner {
+                engine,
+                ast,
+                failed: HashSet::new(),
+                msgs: vec![],
+            },
+            firing: vec![false; callbacks.alerts.len()],
+            callbacks,
+        })
+    }
+
+    /// The names of its columns, in order.
+    pub fn columns(&self) -> Vec<String> {
+        self.callbacks
+            .columns
+            .iter()
+            .map(|(n, _)| n.clone())
+            .collect()
+    }
+
+    /// The styles of its highlights, in order.
+    pub fn highlights(&self) -> Vec<Style> {
+        self.callbacks.highlights.iter().map(|&(_, s)| s).collect()
+    }
+
+    /// Fill in the live processes' script_values and script_highlights, and
+    /// check the alerts. Returns messages for those that fired, and for
+    /// callbacks that failed for the first time.
+    pub fn tick<'a>(
+        &mut self,
+        procs: impl IntoIterator<Item = &'a mut SProc>,
+        system: &SystemStats,
+    ) -> Vec<String> {
+        let mut maps = Array::new();
+        for sp in procs.into_iter().filter(|sp| !sp.is_dead()) {
+            let p = Dynamic::from_map(proc_map(sp));
+            sp.script_values.clear();
+            for (name, f) in &self.callbacks.columns {
+                let v = self.run.call(f, &p, || format!("column {}", name));
+                sp.script_values.push(v.map_or_else(String::new, render));
+            }
+            sp.script_highlights.clear();
+            for (i, (f, _)) in self.callbacks.highlights.iter().enumerate() {
+                let v = self.run.call(f, &p, || format!("highlight {}", i + 1));
+                if v.and_then(|v| v.as_bool().ok()) == Some(true) {
+                    sp.script_highlights.push(i);
+                }
+            }
+            maps.push(p);
+        }
+        if !self.callbacks.alerts.is_empty() {
+            let s = Dynamic::from_map(system_map(maps, system));
+            for (i, (name, f)) in self.callbacks.alerts.iter().enumerate() {
+                let detail = match self.run.call(f, &s, || format!("alert {}", name)) {
+                    Some(v) if v.is_string() => Some(v.to_string()),
+                    Some(v) if v.as_bool() == Ok(true) => Some(String::new()),
+                    _ => None,
+                };
+                match &detail {
+                    _ if self.firing[i] => (),
+                    Some(d) if d.is_empty() => self.run.msgs.push(format!("alert: {}", name)),
+                    Some(d) => self.run.msgs.push(format!("alert: {}: {}", name, d)),
+                    None => (),
+                }
+                self.firing[i] = detail.is_some();
+            }
+        }
+        std::mem::take(&mut self.run.msgs)
+    }
+}
+
+impl Runner {
+    // None if it failed, which is said the first time.
+    fn call(&mut self, f: &FnPtr, arg: &Dynamic, label: impl Fn() -> String) -> Option<Dynamic> {
+        match f.call::<Dynamic>(&self.engine, &self.ast, (arg.clone(),)) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                let label = label();
+                if self.failed.insert(label.clone()) {
+                    self.msgs.push(format!("script: {} failed: {}", label, e));
+                }
+                None
+            }
+        }
+    }
+}
+
+fn system_map(procs: Array, system: &SystemStats) -> Map {
+    let load: Array = system.load.iter().map(|&l| l.into()).collect();
+    let mut s = Map::new();
+    s.insert("procs".into(), procs.into());
+    s.insert("cpu".into(), system.cpu_pct.into());
+    s.insert("mem_pct".into(), system.mem_pct().into());
+    s.insert("swap_pct".into(), system.swap_pct().into());
+    s.insert("load".into(), load.into());
+    s
+}
+
+fn proc_map(sp: &SProc) -> Map {
+    row_fields(sp)
+        .into_iter()
+        .map(|(name, v)| {
+            let v = match v {
+                FieldValue::Count(n) => n.into(),
+                FieldValue::Number(n) => n.into(),
+                FieldValue::Text(s) => s.into(),
+            };
+            (name.into(), v)
+        })
+        .collect()
+}
+
+fn render(v: Dynamic) -> String {
+    match v.as_float() {
+        Ok(f) => format!("{:.1}", f),
+        Err(_) if v.is_unit() => String::new(),
+        Err(_) => v.to_string(),
+    }
+}
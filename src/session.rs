@@ -0,0 +1,111 @@
+//! Session: a recap of what was watched, for printing on quitting (see
+//! --session-stats).
+use std::{collections::HashMap, fmt::Write, time::Instant};
+
+use crate::{render, sprocs::SProcs, units::render_bytes, view::render_duration};
+
+// processes listed under each heading of the recap.
+const TOP: usize = 5;
+
+pub struct Session {
+    start: Instant,
+    last: Option<Instant>,
+    peak_load: f64, // 1 minute average
+    peak_cpu: f64,  // of all cpus together
+    usage: HashMap<i32, Usage>,
+    alerts: Vec<String>,
+}
+
+// a process's usage over the session, summed over each interval.
+struct Usage {
+    name: String,
+    cpu_secs: f64,
+    byte_secs: f64, // memory over time, for its average
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            last: None,
+            peak_load: 0.,
+            peak_cpu: 0.,
+            usage: HashMap::new(),
+            alerts: vec![],
+        }
+    }
+}
+
+impl Session {
+    /// Fold in the latest sample.
+    pub fn sample(&mut self, sprocs: &SProcs) {
+        let now = Instant::now();
+        // the first sample's cpu has no interval to go on.
+        let secs = match self.last.replace(now) {
+            Some(last) => (now - last).as_secs_f64(),
+            None => return,
+        };
+        let system = sprocs.system();
+        self.peak_load = self.peak_load.max(system.load[0]);
+        self.peak_cpu = self.peak_cpu.max(system.cpu_pct);
+        for sp in sprocs.get().filter(|sp| !sp.is_dead()) {
+            let u = self.usage.entry(sp.pid).or_insert_with(|| Usage {
+                name: sp.name.clone(),
+                cpu_secs: 0.,
+                byte_secs: 0.,
+            });
+            u.cpu_secs += sp.cpu_hist.front().unwrap_or(&0.) / 100. * secs;
+            u.byte_secs += sp.mem_bytes as f64 * secs;
+        }
+    }
+
+    pub fn alert(&mut self, msg: &str) {
+        self.alerts.push(msg.into());
+    }
+
+    /// How long it lasted, its peaks, the processes that used the most cpu
+    /// and memory over it, and the alerts raised; in plain ASCII if `ascii`.
+    pub fn report(&self, ascii: bool) -> String {
+        let secs = self.start.elapsed().as_secs_f64();
+        let mut out = String::new();
+        let _ = writeln!(out, "session: {}", render_duration(secs));
+        let _ = writeln!(
+            out,
+            "peak load {:.2}, peak cpu {:.0}%",
+            self.peak_load, self.peak_cpu
+        );
+        let top = |by: fn(&Usage) -> f64| {
+            let mut ranked: Vec<(&i32, &Usage)> =
+                self.usage.iter().filter(|(_, u)| by(u) > 0.).collect();
+            ranked.sort_by(|a, b| by(b.1).total_cmp(&by(a.1)).then(a.0.cmp(b.0)));
+            ranked.truncate(TOP);
+            ranked
+        };
+        let _ = writeln!(out, "most cpu:");
+        for (pid, u) in top(|u| u.cpu_secs) {
+            let _ = writeln!(
+                out,
+                "  {:>7} {:<24.24} {:>8.1} cpu·s",
+                pid, u.name, u.cpu_secs
+            );
+        }
+        let _ = writeln!(out, "most memory, on average:");
+        for (pid, u) in top(|u| u.byte_secs) {
+            let avg = render_bytes(u.byte_secs / secs.max(1.));
+            let _ = writeln!(out, "  {:>7} {:<24.24} {:>8}", pid, u.name, avg);
+        }
+        match self.alerts.len() {
+            0 => out.push_str("no alerts\n"),
+            n => {
+                let _ = writeln!(out, "{} alerts:", n);
+                for a in &self.alerts {
+                    let _ = writeln!(out, "  {}", a);
+                }
+            }
+        }
+        if ascii {
+            out = out.chars().map(render::to_ascii).collect();
+        }
+        out
+    }
+}
@@ -0,0 +1,237 @@
+//! Share: mirroring a running session's screen, read-only, to `sparktop
+//! attach` over a unix socket.
+//
+// Each draw is sent to every attached client as one line of JSON: the screen's
+// rows as runs of same-styled text. Clients only read; nothing they send is
+// looked at. The socket is made owner-only, so sharing with another user
+// means granting access to it explicitly (chmod, or forwarding it over
+// `ssh -L local.sock:/path/to/share.sock`).
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+
+use crate::ui::{
+    buffer::Buffer,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+use crate::{
+    event::{Event, EventStream},
+    sterm::STerm,
+    theme::{color_name, parse_color},
+};
+
+// a client that can't take a frame this quickly is dropped, rather than
+// holding up the session.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+const ATTACH_POLL: Duration = Duration::from_millis(50);
+
+/// One screenful, as sent to clients.
+#[derive(Serialize, Deserialize)]
+pub struct Frame {
+    pub lines: Vec<Vec<Run>>,
+}
+
+// text in one style.
+#[derive(Serialize, Deserialize)]
+pub struct Run {
+    pub text: String,
+    pub fg: String, // see theme::color_name
+    pub bg: String,
+    pub modifiers: u16, // ratatui Modifier bits
+}
+
+impl Frame {
+    pub fn from_buffer(buf: &Buffer) -> Self {
+        let area = buf.area;
+        let lines = (area.top()..area.bottom())
+            .map(|y| {
+                let mut runs: Vec<Run> = vec![];
+                // cells under the right half of a wide character.
+                let mut hidden = 0;
+                for x in area.left()..area.right() {
+                    if hidden > 0 {
+                        hidden -= 1;
+                        continue;
+                    }
+                    let cell = &buf[(x, y)];
+                    hidden = Span::raw(cell.symbol()).width().saturating_sub(1);
+                    let (fg, bg) = (color_name(cell.fg), color_name(cell.bg));
+                    let modifiers = cell.modifier.bits();
+                    match runs.last_mut() {
+                        Some(r) if r.fg == fg && r.bg == bg && r.modifiers == modifiers => {
+                            r.text.push_str(cell.symbol())
+                        }
+                        _ => runs.push(Run {
+                            text: cell.symbol().to_string(),
+                            fg,
+                            bg,
+                            modifiers,
+                        }),
+                    }
+                }
+                runs
+            })
+            .collect();
+        Self { lines }
+    }
+
+    pub fn to_spans(&self) -> Vec<Line<'static>> {
+        self.lines
+            .iter()
+            .map(|runs| {
+                let spans: Vec<Span> = runs
+                    .iter()
+                    .map(|r| {
+                        let style = Style::default()
+                            .fg(parse_color(&r.fg).unwrap_or(Color::Reset))
+                            .bg(parse_color(&r.bg).unwrap_or(Color::Reset))
+                            .add_modifier(Modifier::from_bits_truncate(r.modifiers));
+                        Span::styled(r.text.clone(), style)
+                    })
+                    .collect();
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
+
+/// The serving end: a socket that clients attach to.
+pub struct Share {
+    path: PathBuf,
+    listener: UnixListener,
+    clients: Vec<UnixStream>,
+}
+
+impl Share {
+    /// Listen at `path`, replacing a socket left behind by a session that's
+    /// gone, but not one still in use, nor anything that isn't a socket.
+    pub fn bind(path: &Path) -> Result<Self> {
+        match fs::symlink_metadata(path) {
+            Ok(meta) if !meta.file_type().is_socket() => {
+                bail!("{} exists and isn't a socket", path.display())
+            }
+            Ok(_) => {
+                if UnixStream::connect(path).is_ok() {
+                    bail!("{} is already being shared to", path.display());
+                }
+                fs::remove_file(path).with_context(|| format!("removing {}", path.display()))?;
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => (),
+            Err(e) => return Err(e).with_context(|| format!("checking {}", path.display())),
+        }
+        // owner-only from the start, not just once chmodded: anyone could
+        // connect in between.
+        let umask = unsafe { libc::umask(0o077) };
+        let listener = UnixListener::bind(path);
+        unsafe { libc::umask(umask) };
+        let listener = listener.with_context(|| format!("sharing at {}", path.display()))?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            path: path.into(),
+            listener,
+            clients: vec![],
+        })
+    }
+
+    /// Send `buf` to every client, taking on any newly attached first.
+    /// Returns how many newly attached.
+    pub fn publish(&mut self, buf: &Buffer) -> usize {
+        let mut attached = 0;
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = stream.set_write_timeout(Some(WRITE_TIMEOUT)) {
+                        log::warn!("share client: {}", e);
+                        continue;
+                    }
+                    self.clients.push(stream);
+                    attached += 1;
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log::warn!("share: {}", e);
+                    break;
+                }
+            }
+        }
+        if self.clients.is_empty() {
+            return attached;
+        }
+        let mut line = match serde_json::to_string(&Frame::from_buffer(buf)) {
+            Ok(l) => l,
+            Err(e) => {
+                log::warn!("share: {}", e);
+                return attached;
+            }
+        };
+        line.push('\n');
+        self.clients
+            .retain_mut(|c| c.write_all(line.as_bytes()).is_ok());
+        attached
+    }
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Mirror the session shared at `path` until it ends, or q or Esc.
+pub fn attach(path: &Path) -> Result<()> {
+    let stream =
+        UnixStream::connect(path).with_context(|| format!("attaching to {}", path.display()))?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let frame = line.and_then(|l| {
+                serde_json::from_str::<Frame>(&l)
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+            });
+            if tx.send(frame).is_err() {
+                return;
+            }
+        }
+    });
+    let mut term = STerm::default();
+    let mut frame = None;
+    for event in EventStream::new(ATTACH_POLL) {
+        match event {
+            Event::Key(k) if matches!(k.code, KeyCode::Char('q') | KeyCode::Esc) => return Ok(()),
+            Event::Key(_) | Event::Mouse(_) => continue, // read-only
+            Event::Tick | Event::Resize => (),
+        }
+        loop {
+            match rx.try_recv() {
+                Ok(f) => frame = Some(f?),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    drop(term);
+                    println!("the shared session ended");
+                    return Ok(());
+                }
+            }
+        }
+        if let Some(f) = &frame {
+            let lines = f.to_spans();
+            term.draw(|fr| fr.render_widget(Paragraph::new(lines), fr.area()))?;
+        }
+    }
+    Ok(())
+}
@@ -1,22 +1,118 @@
 /// SProc: a single process.
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use sysinfo::{Process, ProcessExt};
 
-const SAMPLE_LIMIT: usize = 60;
+use crate::{
+    platform::{IoPriority, Port},
+    units,
+};
 
-#[derive(Debug)]
+// how far back samples are kept as they are; older ones are kept as
+// averages of COMPACTION each, so long histories don't take much more memory
+// than this. In samples, see History::set_interval.
+const FULL_RES_SECS: f64 = 600.;
+const COMPACTION: usize = 4;
+// kernel cpu % below which a process isn't worth calling kernel-heavy.
+const KERNEL_HEAVY_MIN: f64 = 5.;
+
+#[derive(Clone, Debug)]
 pub struct SProc {
-    // TODO: ppid, cmd, memory?
     pub pid: i32,
+    pub ppid: Option<i32>,
     pub name: String,
+    pub cmd: Vec<String>,
+    pub uid: Option<u32>,
+    pub start_time: u64, // seconds since the epoch
+    // "namespace/name" of its kubernetes pod; only looked up while needed
+    // (see SProcs::set_pods).
+    pub pod: Option<String>,
+    // the VM it runs, or works for (see vm.rs).
+    pub vm: Option<String>,
     pub cpu_ewma: f64,
     pub cpu_hist: VecDeque<f64>,
-    pub mem_mb: f64,
+    // the highest sample behind each cpu_hist entry, for spikes that
+    // compaction averages away; only kept with CompactBy::MeanAndPeak.
+    pub cpu_peaks: VecDeque<f64>,
+    // samples taken before this process appeared, so its histories can show
+    // where it didn't exist yet apart from where it was idle.
+    unborn: usize,
+    // cpu of exited children, only sampled in cumulative mode (see
+    // SProcs::set_child_cpu).
+    pub child_cpu_ewma: f64,
+    pub child_cpu_hist: VecDeque<f64>,
+    child_cpu_secs: Option<f64>, // cumulative
+    // cpu of it and everything below it in the process tree, and of their
+    // exited children; summed after each sample (see sprocs::sum_subtrees).
+    pub tree_cpu_ewma: f64,
+    pub tree_child_cpu_ewma: f64,
+    // cpu in user mode and in the kernel, only sampled while shown (see
+    // SProcs::set_cpu_split and set_zoomed).
+    pub user_cpu_ewma: f64,
+    pub user_cpu_hist: VecDeque<f64>,
+    pub sys_cpu_ewma: f64,
+    pub sys_cpu_hist: VecDeque<f64>,
+    cpu_split_secs: Option<(f64, f64)>, // cumulative
+    pub mem_bytes: u64,                 // resident
+    pub mem_hist: VecDeque<f64>,        // bytes
+    pub mem_pct: f64,                   // of total system memory
+    // what it'd get OOM-killed at, in bytes: its cgroup's limit, or all of
+    // memory. Only read while forecasting (see SProcs::set_mem_limits).
+    pub mem_limit_bytes: Option<u64>,
+    // the cpus' worth it may use, for the cpu gauge: its cgroup's quota, or
+    // every cpu. Only read while the gauge is shown (see
+    // SProcs::set_cpu_quotas).
+    pub cpu_quota: Option<f64>,
     // maybe want total bytes over history, and combined read/write?
     pub disk_read_ewma: f64,
     pub disk_read_hist: VecDeque<u64>,
     pub disk_write_ewma: f64,
     pub disk_write_hist: VecDeque<u64>,
+    // monotonic totals, for exports: bytes read and written over the
+    // process's life, and cpu seconds (only read while exporting, see
+    // SProcs::set_counters).
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub cpu_secs: Option<f64>,
+    // None if unreadable; only read while shown (see SProcs::set_fds and
+    // set_zoomed).
+    pub fds: Option<usize>,
+    // ps's state letter and thread count; only read while shown (see
+    // SProcs::set_state).
+    pub state: Option<char>,
+    pub threads: Option<usize>,
+    // only read while shown (see SProcs::set_io_priority).
+    pub io_priority: Option<IoPriority>,
+    // the TCP and UDP ports it's listening on, sorted, and its network
+    // namespace if not the host's; only read while shown (see
+    // SProcs::set_ports).
+    pub ports: Option<Vec<Port>>,
+    pub net_ns: Option<u64>,
+    pub frozen: bool, // paused by us, see freeze.rs
+    // % of the time runnable but waiting for a cpu; only sampled while
+    // shown (see SProcs::set_latency).
+    pub latency_ewma: f64,
+    pub latency_hist: VecDeque<f64>,
+    run_delay_ns: Option<u64>, // cumulative
+    // wakeups per second (see platform::wakeups); only sampled while shown
+    // (see SProcs::set_wakeups).
+    pub wakeups_ewma: f64,
+    pub wakeups_hist: VecDeque<f64>,
+    wakeups_total: Option<u64>, // cumulative
+    // major page faults per second (pages read back in, from swap or from
+    // files), and bytes per second swapped in and out, going by how much of
+    // it is in swap; only sampled while zoomed in (see SProcs::set_zoomed).
+    pub faults_hist: VecDeque<f64>,
+    pub swap_in_hist: VecDeque<f64>,
+    pub swap_out_hist: VecDeque<f64>,
+    pub swap_bytes: Option<u64>,
+    faults_total: Option<u64>, // cumulative
+    // cumulative io counters, when sysinfo can't read them (see helper.rs).
+    io_totals: Option<(u64, u64)>,
+    // the --script's column values and the highlights it matches, by
+    // index, as of the latest sample (see script.rs).
+    pub script_values: Vec<String>,
+    pub script_highlights: Vec<usize>,
     tombstone: Option<Tombstone>,
 }
 
@@ -25,7 +121,7 @@ pub enum DeadStatus {
     ShouldReap,       // died a while ago, should remove from list
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Tombstone {
     dead_for_ticks: usize, // how ticks has this process been dead for
 }
@@ -35,26 +131,417 @@ impl SProc {
         self.tombstone.is_some()
     }
 
-    pub fn add_sample(&mut self, p: &Process, ewma_weight: f64) {
+    /// Note that `samples` were taken before this process first appeared.
+    pub fn set_unborn(&mut self, samples: usize) {
+        self.unborn = samples;
+    }
+
+    /// How much of the history window, after its samples, is from before
+    /// this process existed.
+    pub fn unborn_samples(&self, history: &History) -> usize {
+        self.unborn
+            .min(history.entries().saturating_sub(self.cpu_hist.len()))
+    }
+
+    /// Whether the name or command line contains `pattern`.
+    pub fn matches(&self, pattern: &str) -> bool {
+        self.name.contains(pattern) || self.cmd.join(" ").contains(pattern)
+    }
+
+    /// Like `matches`, ignoring case.
+    pub fn matches_any_case(&self, pattern: &str) -> bool {
+        let pattern = pattern.to_lowercase();
+        self.name.to_lowercase().contains(&pattern)
+            || self.cmd.join(" ").to_lowercase().contains(&pattern)
+    }
+
+    // `io_totals` overrides sysinfo's disk usage with cumulative counters read
+    // some other way; the sample is the delta since the previous totals.
+    pub fn add_sample(
+        &mut self,
+        p: &Process,
+        history: &History,
+        ewma_weight: f64,
+        io_totals: Option<(u64, u64)>,
+    ) {
         let du = p.disk_usage();
+        let (read, written) = match (io_totals, self.io_totals) {
+            (Some((r, w)), Some((prev_r, prev_w))) => {
+                (r.saturating_sub(prev_r), w.saturating_sub(prev_w))
+            }
+            (Some(_), None) => (0, 0),
+            (None, _) => (du.read_bytes, du.written_bytes),
+        };
+        self.io_totals = io_totals;
+        (self.read_bytes, self.write_bytes) =
+            io_totals.unwrap_or((du.total_read_bytes, du.total_written_bytes));
+        let mem = units::process_mem_bytes(p.memory());
+        self.add_sample_helper(history, cpu_usage(p), mem, read, written, ewma_weight);
+    }
+
+    /// A row that isn't a single real process, e.g. the total for processes
+    /// too short-lived to sample.
+    pub fn synthetic(pid: i32, name: &str) -> Self {
+        Self {
+            pid,
+            ppid: None,
+            name: name.into(),
+            cmd: vec![],
+            uid: None,
+            start_time: 0,
+            pod: None,
+            vm: None,
+            cpu_ewma: 0.,
+            cpu_hist: VecDeque::new(),
+            cpu_peaks: VecDeque::new(),
+            unborn: 0,
+            child_cpu_ewma: 0.,
+            child_cpu_hist: VecDeque::new(),
+            child_cpu_secs: None,
+            tree_cpu_ewma: 0.,
+            tree_child_cpu_ewma: 0.,
+            user_cpu_ewma: 0.,
+            user_cpu_hist: VecDeque::new(),
+            sys_cpu_ewma: 0.,
+            sys_cpu_hist: VecDeque::new(),
+            cpu_split_secs: None,
+            mem_bytes: 0,
+            mem_hist: VecDeque::new(),
+            mem_pct: 0.,
+            mem_limit_bytes: None,
+            cpu_quota: None,
+            disk_read_ewma: 0.,
+            disk_read_hist: VecDeque::new(),
+            disk_write_ewma: 0.,
+            disk_write_hist: VecDeque::new(),
+            read_bytes: 0,
+            write_bytes: 0,
+            cpu_secs: None,
+            fds: None,
+            state: None,
+            threads: None,
+            io_priority: None,
+            ports: None,
+            net_ns: None,
+            frozen: false,
+            latency_ewma: 0.,
+            latency_hist: VecDeque::new(),
+            wakeups_ewma: 0.,
+            wakeups_hist: VecDeque::new(),
+            wakeups_total: None,
+            faults_hist: VecDeque::new(),
+            swap_in_hist: VecDeque::new(),
+            swap_out_hist: VecDeque::new(),
+            swap_bytes: None,
+            faults_total: None,
+            run_delay_ns: None,
+            io_totals: None,
+            script_values: vec![],
+            script_highlights: vec![],
+            tombstone: None,
+        }
+    }
+
+    pub fn add_cpu_sample(&mut self, history: &History, cpu: f64, ewma_weight: f64) {
+        self.add_sample_helper(history, cpu, 0, 0, 0, ewma_weight);
+    }
+
+    // `total` is the children's cumulative cpu seconds, `elapsed` the
+    // seconds since the previous sample.
+    pub fn add_child_cpu_sample(
+        &mut self,
+        history: &History,
+        total: f64,
+        elapsed: f64,
+        ewma_weight: f64,
+    ) {
+        let cpu = match self.child_cpu_secs {
+            Some(prev) if elapsed > 0. => 100. * (total - prev).max(0.) / elapsed,
+            _ => 0.,
+        };
+        self.child_cpu_secs = Some(total);
+        self.child_cpu_ewma = ewma(cpu, self.child_cpu_ewma, ewma_weight);
+        history.push(&mut self.child_cpu_hist, cpu);
+    }
+
+    /// Drop the children's cpu total and history, so sampling them again
+    /// starts over rather than counting everything since as one sample, and
+    /// their history lines up with cpu_hist again.
+    pub fn forget_child_cpu(&mut self) {
+        self.child_cpu_secs = None;
+        self.child_cpu_ewma = 0.;
+        self.child_cpu_hist.clear();
+    }
+
+    // `user` and `sys` are the cumulative cpu seconds in each mode, `elapsed`
+    // the seconds since the previous sample.
+    pub fn add_cpu_split_sample(
+        &mut self,
+        history: &History,
+        user: f64,
+        sys: f64,
+        elapsed: f64,
+        ewma_weight: f64,
+    ) {
+        let (user_pct, sys_pct) = match self.cpu_split_secs {
+            Some((prev_user, prev_sys)) if elapsed > 0. => (
+                100. * (user - prev_user).max(0.) / elapsed,
+                100. * (sys - prev_sys).max(0.) / elapsed,
+            ),
+            _ => (0., 0.),
+        };
+        self.cpu_split_secs = Some((user, sys));
+        self.user_cpu_ewma = ewma(user_pct, self.user_cpu_ewma, ewma_weight);
+        history.push(&mut self.user_cpu_hist, user_pct);
+        self.sys_cpu_ewma = ewma(sys_pct, self.sys_cpu_ewma, ewma_weight);
+        history.push(&mut self.sys_cpu_hist, sys_pct);
+    }
+
+    /// Drop the user and kernel cpu totals and histories (see
+    /// forget_child_cpu).
+    pub fn forget_cpu_split(&mut self) {
+        self.cpu_split_secs = None;
+        self.user_cpu_ewma = 0.;
+        self.user_cpu_hist.clear();
+        self.sys_cpu_ewma = 0.;
+        self.sys_cpu_hist.clear();
+    }
+
+    /// Whether most of its cpu goes to the kernel, e.g. a syscall-heavy
+    /// process.
+    pub fn kernel_heavy(&self) -> bool {
+        self.sys_cpu_ewma >= KERNEL_HEAVY_MIN && self.sys_cpu_ewma > self.user_cpu_ewma
+    }
+
+    // `total` is the cumulative run queue wait, `elapsed` the seconds since
+    // the previous sample.
+    pub fn add_latency_sample(
+        &mut self,
+        history: &History,
+        total_ns: u64,
+        elapsed: f64,
+        ewma_weight: f64,
+    ) {
+        let pct = match self.run_delay_ns {
+            Some(prev) if elapsed > 0. => {
+                100. * total_ns.saturating_sub(prev) as f64 / 1e9 / elapsed
+            }
+            _ => 0.,
+        };
+        self.run_delay_ns = Some(total_ns);
+        self.latency_ewma = ewma(pct, self.latency_ewma, ewma_weight);
+        history.push(&mut self.latency_hist, pct);
+    }
+
+    /// Drop the run queue wait total and history (see forget_child_cpu).
+    pub fn forget_latency(&mut self) {
+        self.run_delay_ns = None;
+        self.latency_ewma = 0.;
+        self.latency_hist.clear();
+    }
+
+    // `total` is the cumulative wakeups, `elapsed` the seconds since the
+    // previous sample.
+    pub fn add_wakeups_sample(
+        &mut self,
+        history: &History,
+        total: u64,
+        elapsed: f64,
+        ewma_weight: f64,
+    ) {
+        let rate = match self.wakeups_total {
+            Some(prev) if elapsed > 0. => total.saturating_sub(prev) as f64 / elapsed,
+            _ => 0.,
+        };
+        self.wakeups_total = Some(total);
+        self.wakeups_ewma = ewma(rate, self.wakeups_ewma, ewma_weight);
+        history.push(&mut self.wakeups_hist, rate);
+    }
+
+    /// Drop the wakeups total and history (see forget_child_cpu).
+    pub fn forget_wakeups(&mut self) {
+        self.wakeups_total = None;
+        self.wakeups_ewma = 0.;
+        self.wakeups_hist.clear();
+    }
+
+    // `faults` is the cumulative major faults, `swap` the bytes swapped out
+    // now, and `elapsed` the seconds since the previous sample.
+    pub fn add_paging_sample(&mut self, history: &History, faults: u64, swap: u64, elapsed: f64) {
+        let per_sec = |d: u64| if elapsed > 0. { d as f64 / elapsed } else { 0. };
+        let rate = match self.faults_total {
+            Some(prev) => per_sec(faults.saturating_sub(prev)),
+            None => 0.,
+        };
+        let (swap_in, swap_out) = match self.swap_bytes {
+            Some(prev) => (
+                per_sec(prev.saturating_sub(swap)),
+                per_sec(swap.saturating_sub(prev)),
+            ),
+            None => (0., 0.),
+        };
+        self.faults_total = Some(faults);
+        self.swap_bytes = Some(swap);
+        history.push(&mut self.faults_hist, rate);
+        history.push(&mut self.swap_in_hist, swap_in);
+        history.push(&mut self.swap_out_hist, swap_out);
+    }
+
+    /// Drop the fault and swap totals and histories (see forget_child_cpu).
+    pub fn forget_paging(&mut self) {
+        self.faults_total = None;
+        self.swap_bytes = None;
+        self.faults_hist.clear();
+        self.swap_in_hist.clear();
+        self.swap_out_hist.clear();
+    }
+
+    /// Seconds until memory reaches its limit at the current steady rate of
+    /// growth, if it's steadily growing.
+    pub fn mem_forecast(&self, history: &History) -> Option<f64> {
+        let limit = self.mem_limit_bytes? as f64;
+        let points = history.ages().zip(self.mem_hist.iter().copied());
+        crate::stats::secs_until(points, limit)
+    }
+
+    /// Own cpu, plus exited children's if `cumulative`.
+    pub fn cpu(&self, cumulative: bool) -> f64 {
+        if cumulative {
+            self.cpu_ewma + self.child_cpu_ewma
+        } else {
+            self.cpu_ewma
+        }
+    }
+
+    /// Cpu as a % of its quota (see cpu_quota), if read.
+    pub fn cpu_quota_pct(&self, cumulative: bool) -> Option<f64> {
+        Some(self.cpu(cumulative) / self.cpu_quota?)
+    }
+
+    /// Cpu of the whole subtree under it, itself included; exited
+    /// children's too if `cumulative`.
+    pub fn tree_cpu(&self, cumulative: bool) -> f64 {
+        if cumulative {
+            self.tree_cpu_ewma + self.tree_child_cpu_ewma
+        } else {
+            self.tree_cpu_ewma
+        }
+    }
+
+    /// Cpu history, newest first, plus exited children's if `cumulative`.
+    pub fn cpu_history(&self, cumulative: bool) -> Vec<f64> {
+        let mut hist: Vec<f64> = self.cpu_hist.iter().copied().collect();
+        if cumulative {
+            for (h, c) in hist.iter_mut().zip(self.child_cpu_hist.iter()) {
+                *h += c;
+            }
+        }
+        hist
+    }
+
+    /// Add `other`'s usage to this one's, for a row totalling a group.
+    /// Latency is a share of time rather than an amount, so takes the max.
+    pub fn absorb(&mut self, other: &SProc) {
+        self.cpu_ewma += other.cpu_ewma;
+        add_hist(&mut self.cpu_hist, &other.cpu_hist, |a, b| a + b);
+        add_hist(&mut self.cpu_peaks, &other.cpu_peaks, |a, b| a + b);
+        self.child_cpu_ewma += other.child_cpu_ewma;
+        self.tree_cpu_ewma += other.tree_cpu_ewma;
+        self.tree_child_cpu_ewma += other.tree_child_cpu_ewma;
+        add_hist(&mut self.child_cpu_hist, &other.child_cpu_hist, |a, b| {
+            a + b
+        });
+        self.user_cpu_ewma += other.user_cpu_ewma;
+        add_hist(&mut self.user_cpu_hist, &other.user_cpu_hist, |a, b| a + b);
+        self.sys_cpu_ewma += other.sys_cpu_ewma;
+        add_hist(&mut self.sys_cpu_hist, &other.sys_cpu_hist, |a, b| a + b);
+        self.read_bytes += other.read_bytes;
+        self.write_bytes += other.write_bytes;
+        self.cpu_secs = match (self.cpu_secs, other.cpu_secs) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        self.mem_bytes += other.mem_bytes;
+        add_hist(&mut self.mem_hist, &other.mem_hist, |a, b| a + b);
+        self.mem_pct += other.mem_pct;
+        self.disk_read_ewma += other.disk_read_ewma;
+        add_hist(&mut self.disk_read_hist, &other.disk_read_hist, |a, b| {
+            a + b
+        });
+        self.disk_write_ewma += other.disk_write_ewma;
+        add_hist(&mut self.disk_write_hist, &other.disk_write_hist, |a, b| {
+            a + b
+        });
+        self.fds = match (self.fds, other.fds) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        self.threads = match (self.threads, other.threads) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        // a group is as stuck as its most stuck member.
+        self.state = match (self.state, other.state) {
+            (Some(a), Some(b)) => Some(if state_rank(b) > state_rank(a) { b } else { a }),
+            (a, b) => a.or(b),
+        };
+        self.latency_ewma = self.latency_ewma.max(other.latency_ewma);
+        add_hist(&mut self.latency_hist, &other.latency_hist, f64::max);
+        self.wakeups_ewma += other.wakeups_ewma;
+        add_hist(&mut self.wakeups_hist, &other.wakeups_hist, |a, b| a + b);
+        add_hist(&mut self.faults_hist, &other.faults_hist, |a, b| a + b);
+        add_hist(&mut self.swap_in_hist, &other.swap_in_hist, |a, b| a + b);
+        add_hist(&mut self.swap_out_hist, &other.swap_out_hist, |a, b| a + b);
+        self.swap_bytes = match (self.swap_bytes, other.swap_bytes) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        self.ports = match (self.ports.take(), &other.ports) {
+            (Some(mut a), Some(b)) => {
+                a.extend(b);
+                a.sort_unstable();
+                a.dedup();
+                Some(a)
+            }
+            (a, b) => a.or_else(|| b.clone()),
+        };
+        self.net_ns = self.net_ns.or(other.net_ns);
+        if self.start_time == 0 || other.start_time < self.start_time {
+            self.start_time = other.start_time;
+        }
+    }
+
+    /// A sample from made-up numbers rather than a real process, e.g. for
+    /// demos; disk io in bytes since the last sample.
+    pub fn add_synthetic_sample(
+        &mut self,
+        history: &History,
+        cpu: f64,
+        mem_bytes: u64,
+        disk_read_bytes: u64,
+        disk_write_bytes: u64,
+        ewma_weight: f64,
+    ) {
         self.add_sample_helper(
-            p.cpu_usage().into(),
-            p.memory(),
-            du.read_bytes,
-            du.written_bytes,
+            history,
+            cpu,
+            mem_bytes,
+            disk_read_bytes,
+            disk_write_bytes,
             ewma_weight,
         );
     }
 
-    pub fn add_dead_sample(&mut self, ewma_weight: f64) -> DeadStatus {
-        self.add_sample_helper(0., 0, 0, 0, ewma_weight);
+    pub fn add_dead_sample(&mut self, history: &History, ewma_weight: f64) -> DeadStatus {
+        self.add_sample_helper(history, 0., 0, 0, 0, ewma_weight);
+        self.mem_pct = 0.;
         // probably an off-by-one or two in here but whatevs
         match &mut self.tombstone {
             None => self.tombstone = Some(Tombstone { dead_for_ticks: 1 }),
             Some(ref mut t) => t.dead_for_ticks += 1,
         }
         // LEARN: as_ref, how does it work?
-        if self.tombstone.as_ref().unwrap().dead_for_ticks > SAMPLE_LIMIT {
+        if self.tombstone.as_ref().unwrap().dead_for_ticks > history.samples() {
             DeadStatus::ShouldReap
         } else {
             DeadStatus::StillFreshlyDead
@@ -63,19 +550,24 @@ impl SProc {
 
     fn add_sample_helper(
         &mut self,
+        history: &History,
         cpu: f64,
-        mem_kb: u64,
+        mem_bytes: u64,
         disk_read_bytes: u64,
         disk_write_bytes: u64,
         ewma_weight: f64,
     ) {
         self.cpu_ewma = ewma(cpu, self.cpu_ewma, ewma_weight);
-        self.mem_mb = (mem_kb as f64) / 1024.;
+        self.mem_bytes = mem_bytes;
+        history.push(&mut self.mem_hist, mem_bytes as f64);
         self.disk_read_ewma = ewma(disk_read_bytes as f64, self.disk_read_ewma, ewma_weight);
         self.disk_write_ewma = ewma(disk_write_bytes as f64, self.disk_write_ewma, ewma_weight);
-        push_sample(&mut self.cpu_hist, cpu, SAMPLE_LIMIT);
-        push_sample(&mut self.disk_read_hist, disk_read_bytes, SAMPLE_LIMIT);
-        push_sample(&mut self.disk_write_hist, disk_write_bytes, SAMPLE_LIMIT);
+        history.push(&mut self.cpu_hist, cpu);
+        if history.compact_by() == CompactBy::MeanAndPeak {
+            history.push_by(&mut self.cpu_peaks, cpu, CompactBy::Max);
+        }
+        history.push(&mut self.disk_read_hist, disk_read_bytes);
+        history.push(&mut self.disk_write_hist, disk_write_bytes);
     }
 }
 
@@ -84,25 +576,404 @@ impl From<&Process> for SProc {
         let du = p.disk_usage();
         Self {
             pid: p.pid(),
+            ppid: p.parent(),
             name: p.name().into(),
-            cpu_ewma: p.cpu_usage().into(),
-            // TODO: how does the final into() work?
-            cpu_hist: vec![p.cpu_usage().into()].into(),
-            mem_mb: (p.memory() as f64) / 1024.,
+            cmd: p.cmd().to_vec(),
+            uid: crate::platform::uid(p.pid()),
+            start_time: p.start_time(),
+            pod: None,
+            vm: None,
+            cpu_ewma: cpu_usage(p),
+            cpu_hist: vec![cpu_usage(p)].into(),
+            // from the next sample, if the History keeps them; peaks line up
+            // with cpu_hist newest first, so the first sample just goes
+            // unmarked.
+            cpu_peaks: VecDeque::new(),
+            unborn: 0,
+            child_cpu_ewma: 0.,
+            child_cpu_hist: VecDeque::new(),
+            child_cpu_secs: None,
+            tree_cpu_ewma: 0.,
+            tree_child_cpu_ewma: 0.,
+            user_cpu_ewma: 0.,
+            user_cpu_hist: VecDeque::new(),
+            sys_cpu_ewma: 0.,
+            sys_cpu_hist: VecDeque::new(),
+            cpu_split_secs: None,
+            mem_bytes: units::process_mem_bytes(p.memory()),
+            mem_hist: vec![units::process_mem_bytes(p.memory()) as f64].into(),
+            mem_pct: 0.,
+            mem_limit_bytes: None,
+            cpu_quota: None,
             disk_read_ewma: du.read_bytes as f64, // TODO: how come no into()?
             disk_read_hist: vec![du.read_bytes].into(),
             disk_write_ewma: du.written_bytes as f64,
             disk_write_hist: vec![du.written_bytes].into(),
+            read_bytes: du.total_read_bytes,
+            write_bytes: du.total_written_bytes,
+            cpu_secs: None,
+            fds: None,
+            state: None,
+            threads: None,
+            io_priority: None,
+            ports: None,
+            net_ns: None,
+            frozen: false,
+            latency_ewma: 0.,
+            latency_hist: VecDeque::new(),
+            wakeups_ewma: 0.,
+            wakeups_hist: VecDeque::new(),
+            wakeups_total: None,
+            faults_hist: VecDeque::new(),
+            swap_in_hist: VecDeque::new(),
+            swap_out_hist: VecDeque::new(),
+            swap_bytes: None,
+            faults_total: None,
+            run_delay_ns: None,
+            io_totals: None,
+            script_values: vec![],
+            script_highlights: vec![],
             tombstone: None,
         }
     }
 }
 
+// how much a state is worth noticing, for groups.
+fn state_rank(state: char) -> u8 {
+    match state {
+        'D' => 3,
+        'Z' => 2,
+        'R' => 1,
+        _ => 0,
+    }
+}
+
+// sysinfo occasionally reports NaN (e.g. a zero-length interval), which would
+// stick in the ewma forever.
+fn cpu_usage(p: &Process) -> f64 {
+    let cpu = f64::from(p.cpu_usage());
+    if cpu.is_finite() {
+        cpu
+    } else {
+        0.
+    }
+}
+
 fn ewma(new_val: f64, prev_ewma: f64, ewma_weight: f64) -> f64 {
     new_val * ewma_weight + prev_ewma * (1. - ewma_weight)
 }
 
-fn push_sample<T>(deq: &mut VecDeque<T>, x: T, limit: usize) {
-    deq.push_front(x);
-    deq.truncate(limit);
+// combine histories sample by sample, newest first; the longer one's extra
+// samples are kept as is.
+fn add_hist<T: Copy>(into: &mut VecDeque<T>, from: &VecDeque<T>, f: impl Fn(T, T) -> T) {
+    for (i, &x) in from.iter().enumerate() {
+        match into.get_mut(i) {
+            Some(y) => *y = f(*y, x),
+            None => into.push_back(x),
+        }
+    }
+}
+
+/// How histories are kept: how many samples back, how much of that at full
+/// resolution, and what each entry stands for. One per SProcs, and passed to
+/// everything that pushes to or draws its histories.
+#[derive(Clone, Debug)]
+pub struct History {
+    compact_by: CompactBy,
+    len: usize,            // samples kept
+    len_secs: Option<f64>, // or as many as take this long, whatever the delay
+    interval: f64,         // the delay samples are taken at
+    full_res: usize,       // how many of the newest samples are kept as they are
+    // what each entry stands for, newest first; every history's entries
+    // line up with these.
+    entries: VecDeque<Entry>,
+    // where the latest sample compacted entries, for the histories pushed
+    // after it to do the same; and how many entries a history can hold.
+    merged_at: Option<usize>,
+    cap: usize,
+}
+
+// An entry of every history: how many samples it stands for, and the
+// seconds they covered (samples needn't be evenly spaced, see adaptive.rs).
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    samples: usize,
+    secs: f64,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            compact_by: CompactBy::Mean,
+            len: 60,
+            len_secs: None,
+            interval: 1.,
+            full_res: 600,
+            entries: VecDeque::new(),
+            merged_at: None,
+            cap: 60,
+        }
+    }
+}
+
+impl History {
+    /// How older entries stand for the samples they replace; set before
+    /// sampling starts.
+    pub fn set_compact_by(&mut self, by: CompactBy) {
+        self.compact_by = by;
+    }
+
+    pub fn compact_by(&self) -> CompactBy {
+        self.compact_by
+    }
+
+    /// How many samples of each history to keep, and how long dead
+    /// processes stay.
+    pub fn set_len(&mut self, n: usize) {
+        self.len = n.max(1);
+        self.len_secs = None;
+        self.update_cap();
+    }
+
+    /// Like set_len, but as many samples as cover `secs` at the delay,
+    /// which changes with it.
+    pub fn set_secs(&mut self, secs: f64) {
+        self.len_secs = Some(secs);
+        self.set_interval(self.interval);
+    }
+
+    /// How many samples of each history are kept.
+    pub fn samples(&self) -> usize {
+        self.len
+    }
+
+    /// Keep the last FULL_RES_SECS of each history at full resolution with
+    /// `secs` between samples, whatever the delay. Entries already kept stay
+    /// as they are; only samples from now on go by it.
+    pub fn set_interval(&mut self, secs: f64) {
+        self.interval = secs;
+        self.full_res = ((FULL_RES_SECS / secs).ceil() as usize).max(1);
+        if let Some(len_secs) = self.len_secs {
+            self.len = ((len_secs / secs).ceil() as usize).max(1);
+        }
+        self.update_cap();
+    }
+
+    /// The delay samples are taken at (see set_interval).
+    pub fn interval(&self) -> f64 {
+        self.interval
+    }
+
+    // room for the entries so far, and the rest of what's kept as single
+    // samples.
+    fn update_cap(&mut self) {
+        let rest = match self.len_secs {
+            Some(len_secs) => {
+                let kept: f64 = self.entries.iter().map(|e| e.secs).sum();
+                ((len_secs - kept) / self.interval).ceil().max(0.) as usize
+            }
+            None => {
+                let kept: usize = self.entries.iter().map(|e| e.samples).sum();
+                self.len.saturating_sub(kept)
+            }
+        };
+        self.cap = self.entries.len() + rest;
+    }
+
+    // whether the entries but the oldest still cover what's kept.
+    fn oldest_spare(&self) -> bool {
+        let oldest = match self.entries.back() {
+            Some(e) => e,
+            None => return false,
+        };
+        match self.len_secs {
+            Some(len_secs) => {
+                let kept: f64 = self.entries.iter().map(|e| e.secs).sum();
+                kept - oldest.secs >= len_secs
+            }
+            None => {
+                let kept: usize = self.entries.iter().map(|e| e.samples).sum();
+                kept - oldest.samples >= self.len
+            }
+        }
+    }
+
+    /// How many entries each history can hold.
+    pub fn entries(&self) -> usize {
+        self.cap
+    }
+
+    /// How many samples the `i`th newest entry of a history stands for.
+    pub fn sample_weight(&self, i: usize) -> usize {
+        match self.entries.get(i) {
+            Some(e) => e.samples,
+            None if i < self.full_res => 1,
+            None => COMPACTION,
+        }
+    }
+
+    /// How many seconds before the newest the `i`th newest entry of a
+    /// history was taken, going by when samples actually were, and the
+    /// delay for any not taken yet.
+    pub fn secs_ago(&self, i: usize) -> f64 {
+        (0..i).map(|j| self.entry_secs(j)).sum()
+    }
+
+    /// secs_ago for each entry in turn, newest first, without adding up the
+    /// ones before it again for each.
+    pub fn ages(&self) -> impl Iterator<Item = f64> + '_ {
+        (0..).scan(0., move |age, i| {
+            let this = *age;
+            *age += self.entry_secs(i);
+            Some(this)
+        })
+    }
+
+    /// How many seconds the `i`th newest entry of a history covers.
+    pub fn entry_secs(&self, i: usize) -> f64 {
+        match self.entries.get(i) {
+            Some(e) => e.secs,
+            None => self.sample_weight(i) as f64 * self.interval,
+        }
+    }
+
+    /// The stretches of each history kept at one resolution, newest first.
+    pub fn tiers(&self) -> Vec<Tier> {
+        let mut tiers: Vec<Tier> = vec![];
+        for e in &self.entries {
+            match tiers.last_mut() {
+                Some(t) if t.samples_each == e.samples => t.secs += e.secs,
+                _ => tiers.push(Tier {
+                    samples_each: e.samples,
+                    secs: e.secs,
+                }),
+            }
+        }
+        tiers
+    }
+
+    /// The sum of every sample in `hist`, counting compacted ones for each
+    /// sample they average.
+    pub fn total<'a>(&self, hist: impl IntoIterator<Item = &'a f64>) -> f64 {
+        hist.into_iter()
+            .enumerate()
+            .map(|(i, x)| x * self.sample_weight(i) as f64)
+            .sum()
+    }
+
+    /// A history of rates (e.g. cpu %) summed over the time each entry
+    /// covers, e.g. into %·seconds.
+    pub fn integral<'a>(&self, hist: impl IntoIterator<Item = &'a f64>) -> f64 {
+        hist.into_iter()
+            .enumerate()
+            .map(|(i, x)| x * self.entry_secs(i))
+            .sum()
+    }
+
+    // push `x` onto the front of `deq`, compacting it as compact_by says.
+    pub(crate) fn push<T: Sample>(&self, deq: &mut VecDeque<T>, x: T) {
+        let by = match self.compact_by {
+            CompactBy::Max => CompactBy::Max,
+            CompactBy::Mean | CompactBy::MeanAndPeak => CompactBy::Mean,
+        };
+        self.push_by(deq, x, by);
+    }
+
+    // Compacts `deq` where the latest sample compacted the entries, so they
+    // line up across histories. A history that started partway into the
+    // merged entry averages its samples over the whole entry, the ones from
+    // before it counting as 0 like unborn ones, so it still stands for as
+    // many samples as the entry says.
+    pub(crate) fn push_by<T: Sample>(&self, deq: &mut VecDeque<T>, x: T, by: CompactBy) {
+        deq.push_front(x);
+        if let Some(at) = self.merged_at.filter(|&at| deq.len() > at) {
+            let n = (deq.len() - at).min(COMPACTION);
+            let xs: Vec<f64> = deq.drain(at..at + n).map(T::to_f64).collect();
+            let merged = match by {
+                CompactBy::Max => xs.iter().copied().fold(f64::MIN, f64::max),
+                _ => xs.iter().sum::<f64>() / self.sample_weight(at) as f64,
+            };
+            deq.insert(at, T::from_f64(merged));
+        }
+        deq.truncate(self.cap);
+    }
+
+    /// Note a sample being taken, `secs` after the one before; call before
+    /// pushing any of its values.
+    ///
+    /// Once COMPACTION single samples have aged past the full resolution
+    /// ones, they're combined into one entry. Entries already combined are
+    /// never combined again, so a change of delay (and so of full_res) leaves
+    /// what's kept alone. The oldest entries go once the rest cover the
+    /// samples (or seconds) kept.
+    pub fn next_sample(&mut self, secs: f64) {
+        self.entries.push_front(Entry { samples: 1, secs });
+        let at = self.full_res;
+        self.merged_at = None;
+        if self.entries.len() >= at + COMPACTION
+            && self
+                .entries
+                .range(at..at + COMPACTION)
+                .all(|e| e.samples == 1)
+        {
+            let merged = Entry {
+                samples: COMPACTION,
+                secs: self
+                    .entries
+                    .drain(at..at + COMPACTION)
+                    .map(|e| e.secs)
+                    .sum(),
+            };
+            self.entries.insert(at, merged);
+            self.merged_at = Some(at);
+        }
+        while self.oldest_spare() {
+            self.entries.pop_back();
+        }
+        self.update_cap();
+    }
+}
+
+/// A stretch of history kept at one resolution.
+#[derive(Debug, PartialEq)]
+pub struct Tier {
+    pub samples_each: usize, // averaged into each entry
+    pub secs: f64,           // of wall-clock time it covers
+}
+
+// history entries get averaged when compacted.
+pub(crate) trait Sample: Copy {
+    fn to_f64(self) -> f64;
+    fn from_f64(x: f64) -> Self;
+}
+
+impl Sample for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(x: f64) -> Self {
+        x
+    }
+}
+
+impl Sample for u64 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(x: f64) -> Self {
+        x.round() as u64
+    }
+}
+
+/// How compacted history entries stand for the samples they replace.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactBy {
+    Mean,
+    Max, // so short spikes stay, at the cost of overstating usage
+    // the mean, but keeping each entry's highest cpu sample too (see
+    // SProc::cpu_peaks), to mark the spikes averaged away.
+    MeanAndPeak,
 }
@@ -1,63 +1,918 @@
 /// SProcs: a collection of all processes on the system.
-use std::collections::{hash_map::Values, HashMap};
+use std::{
+    collections::{
+        hash_map::{Entry, Values},
+        HashMap, HashSet, VecDeque,
+    },
+    io,
+    time::{Duration, Instant},
+};
 
-use sysinfo::{ProcessExt, System, SystemExt};
+use sysinfo::{Process, ProcessExt, ProcessorExt, System, SystemExt};
 
-use crate::sproc::{DeadStatus, SProc};
+use crate::{
+    alert::{AlertRule, Alerts},
+    column::Column,
+    filter::ProcFilter,
+    freeze::Freezer,
+    helper::Helper,
+    k8s::Pods,
+    lifetime::{LifeEventKind, LifetimeLog},
+    platform::{self, IoPriority, Port},
+    sproc::{CompactBy, DeadStatus, History, SProc},
+    units, vm,
+};
+
+#[cfg(all(feature = "proc-connector", target_os = "linux"))]
+use crate::proc_connector::ProcConnector;
+#[cfg(feature = "scripting")]
+use crate::script::Script;
+
+/// Pid of the synthetic row totalling processes that came and went between
+/// samples.
+pub const SHORT_LIVED_PID: i32 = -1;
+
+// Columns read in a pass over the processes of their own, each only every so
+// many samples (see SProcs::set_every). Threads come with state.
+const PASSES: [Column; 4] = [Column::Fds, Column::Ports, Column::State, Column::IoPrio];
+
+fn pass_for(col: Column) -> Column {
+    match col {
+        Column::Threads => Column::State,
+        c => c,
+    }
+}
+
+fn pct(used: u64, total: u64) -> f64 {
+    match total {
+        0 => 0.,
+        t => 100. * used as f64 / t as f64,
+    }
+}
+
+/// System-wide totals, for the summary header.
+#[derive(Clone, Debug, Default)]
+pub struct SystemStats {
+    pub cpu_pct: f64,                   // of all cpus together
+    pub cpu_hist: VecDeque<f64>,        // newest first
+    pub core_hists: Vec<VecDeque<f64>>, // each core's cpu %, newest first
+    pub mem_used_bytes: u64,
+    pub mem_total_bytes: u64,
+    pub swap_used_bytes: u64,
+    pub swap_total_bytes: u64,
+    pub load: [f64; 3], // 1, 5 and 15 minute averages
+    pub uptime_secs: u64,
+    pub procs: usize,               // processes on the system, filtered or not
+    pub procs_hist: VecDeque<f64>,  // newest first
+    pub starts_hist: VecDeque<f64>, // sampled processes started each tick, newest first
+    pub exits_hist: VecDeque<f64>,  // sampled processes exited each tick, newest first
+    // over the last sample; only while sampling wakeups (see
+    // platform::interrupts).
+    pub interrupts_per_sec: Option<f64>,
+}
+
+impl SystemStats {
+    /// These totals less `sp`'s share, e.g. to leave out sparktop's own.
+    pub fn without(&self, sp: &SProc) -> Self {
+        // the process's cpu is % of one core.
+        let cores = self.core_hists.len().max(1) as f64;
+        let mut s = self.clone();
+        s.cpu_pct = (s.cpu_pct - sp.cpu_ewma / cores).max(0.);
+        for (total, own) in s.cpu_hist.iter_mut().zip(&sp.cpu_hist) {
+            *total = (*total - own / cores).max(0.);
+        }
+        s.mem_used_bytes = s.mem_used_bytes.saturating_sub(sp.mem_bytes);
+        s
+    }
+
+    pub fn mem_pct(&self) -> f64 {
+        pct(self.mem_used_bytes, self.mem_total_bytes)
+    }
+
+    pub fn swap_pct(&self) -> f64 {
+        pct(self.swap_used_bytes, self.swap_total_bytes)
+    }
+
+    fn push_churn(&mut self, history: &History, procs: usize, starts: usize, exits: usize) {
+        self.procs = procs;
+        history.push(&mut self.procs_hist, procs as f64);
+        history.push(&mut self.starts_hist, starts as f64);
+        history.push(&mut self.exits_hist, exits as f64);
+    }
+}
 
 pub struct SProcs {
     sys: System,
+    system: SystemStats,
     sprocs: HashMap<i32, SProc>,
+    history: History,
+    helper: Option<Helper>,
+    min_interval: Duration,
+    last_update: Option<Instant>,
+    child_cpu: bool,
+    cpu_split: bool,
+    // also split whatever cpu_split says, and its fds and paging read.
+    zoomed: Option<i32>,
+    counters: bool,
+    latency: bool,
+    wakeups: bool,
+    interrupts: Option<u64>, // cumulative, while sampling wakeups
+    state: bool,
+    mem_limits: bool,
+    cpu_quotas: bool,
+    io_priority: bool,
+    fds: bool,
+    ports: bool,
+    root_net_ns: Option<u64>, // the host's, read once
+    // columns read in passes of their own (see PASSES): how many samples
+    // apart each is read if not its default, the sample each was last read
+    // at, and how long that took.
+    every: HashMap<Column, u64>,
+    last_read: HashMap<Column, u64>,
+    costs: HashMap<Column, Duration>,
+    pods: Option<Pods>, // Some while looking up pods
+    only: Option<ProcFilter>,
+    keep: Option<i32>, // sampled whatever the filter
+    freezer: Freezer,
+    ticks: u64, // updates done
+    events: LifetimeLog,
+    alerts: Alerts,
+    raised: Vec<String>, // alerts fired since the last take_alerts
+    #[cfg(all(feature = "proc-connector", target_os = "linux"))]
+    connector: Option<ProcConnector>,
+    #[cfg(feature = "scripting")]
+    script: Option<Script>,
 }
 
 impl Default for SProcs {
     fn default() -> Self {
         Self {
             sys: System::new_all(),
+            system: SystemStats::default(),
             sprocs: HashMap::default(),
+            history: History::default(),
+            helper: None,
+            min_interval: Duration::default(),
+            last_update: None,
+            child_cpu: false,
+            cpu_split: false,
+            zoomed: None,
+            counters: false,
+            latency: false,
+            wakeups: false,
+            interrupts: None,
+            state: false,
+            mem_limits: false,
+            cpu_quotas: false,
+            io_priority: false,
+            fds: false,
+            ports: false,
+            root_net_ns: None,
+            every: HashMap::new(),
+            last_read: HashMap::new(),
+            costs: HashMap::new(),
+            pods: None,
+            only: None,
+            keep: None,
+            freezer: Freezer::default(),
+            ticks: 0,
+            events: LifetimeLog::default(),
+            alerts: Alerts::default(),
+            raised: vec![],
+            #[cfg(all(feature = "proc-connector", target_os = "linux"))]
+            connector: None,
+            #[cfg(feature = "scripting")]
+            script: None,
         }
     }
 }
 
 impl SProcs {
+    /// How many samples of each process's history to keep (see
+    /// History::set_len); set before sampling starts.
+    pub fn set_history_len(&mut self, n: usize) {
+        self.history.set_len(n);
+    }
+
+    /// How each process's history compacts (see History::set_compact_by).
+    pub fn set_compact_by(&mut self, by: CompactBy) {
+        self.history.set_compact_by(by);
+    }
+
+    /// Keep `secs` worth of each process's history, however many samples
+    /// that is at the delay (see History::set_secs).
+    pub fn set_history_secs(&mut self, secs: f64) {
+        self.history.set_secs(secs);
+    }
+
+    /// The delay samples are taken at, to say how long the first one
+    /// covers and where the full resolution part of each history ends.
+    pub fn set_sample_interval(&mut self, secs: f64) {
+        self.history.set_interval(secs);
+    }
+
+    /// How the histories are kept, for drawing them.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Check these with every update (see take_alerts).
+    pub fn set_alert_rules(&mut self, rules: Vec<AlertRule>) {
+        self.alerts = Alerts::new(rules);
+    }
+
+    /// Run `script`'s columns, highlights and alerts with every update; its
+    /// alerts come with take_alerts.
+    #[cfg(feature = "scripting")]
+    pub fn set_script(&mut self, script: Script) {
+        self.script = Some(script);
+    }
+
+    /// Only sample processes matching `filter`; the rest aren't read at all.
+    pub fn set_filter(&mut self, filter: ProcFilter) {
+        self.only = Some(filter);
+    }
+
+    /// Sample `pid` even if the filter leaves it out.
+    pub fn keep(&mut self, pid: i32) {
+        self.keep = Some(pid);
+    }
+
+    /// Fall back to `helper` for reads we don't have permission for.
+    pub fn set_helper(&mut self, helper: Helper) {
+        self.helper = Some(helper);
+    }
+
+    /// Account for processes too short-lived to sample, in a synthetic
+    /// "short-lived" row.
+    #[cfg(all(feature = "proc-connector", target_os = "linux"))]
+    pub fn set_proc_connector(&mut self, connector: ProcConnector) {
+        self.connector = Some(connector);
+    }
+
+    /// Also sample the cpu used by processes' exited children, for
+    /// cumulative mode.
+    pub fn set_child_cpu(&mut self, enabled: bool) {
+        if !enabled && self.child_cpu {
+            self.sprocs.values_mut().for_each(SProc::forget_child_cpu);
+        }
+        self.child_cpu = enabled;
+    }
+
+    /// Also sample user and kernel cpu separately, for every process.
+    pub fn set_cpu_split(&mut self, enabled: bool) {
+        if !enabled && self.cpu_split {
+            let zoomed = self.zoomed;
+            self.sprocs
+                .values_mut()
+                .filter(|sp| Some(sp.pid) != zoomed)
+                .for_each(SProc::forget_cpu_split);
+        }
+        self.cpu_split = enabled;
+    }
+
+    /// The process zoomed in on, which gets what only zoom shows (its cpu
+    /// split, open files, and major faults and swapping, see
+    /// SProc::faults_hist) sampled for it alone.
+    pub fn set_zoomed(&mut self, pid: Option<i32>) {
+        if pid == self.zoomed {
+            return;
+        }
+        let (sprocs, cpu_split) = (&mut self.sprocs, self.cpu_split);
+        if let Some(sp) = self.zoomed.and_then(|old| sprocs.get_mut(&old)) {
+            sp.forget_paging();
+            if !cpu_split {
+                sp.forget_cpu_split();
+            }
+        }
+        self.zoomed = pid;
+    }
+
+    /// Also read each process's total cpu seconds (see SProc::cpu_secs).
+    pub fn set_counters(&mut self, enabled: bool) {
+        self.counters = enabled;
+    }
+
+    /// Also sample run queue latency (see SProc::latency_ewma).
+    pub fn set_latency(&mut self, enabled: bool) {
+        if !enabled && self.latency {
+            self.sprocs.values_mut().for_each(SProc::forget_latency);
+        }
+        self.latency = enabled;
+    }
+
+    /// Also sample wakeups (see SProc::wakeups_ewma), and the system's
+    /// interrupts.
+    pub fn set_wakeups(&mut self, enabled: bool) {
+        if !enabled && self.wakeups {
+            self.sprocs.values_mut().for_each(SProc::forget_wakeups);
+            self.interrupts = None;
+            self.system.interrupts_per_sec = None;
+        }
+        self.wakeups = enabled;
+    }
+
+    /// Also read each process's state and thread count.
+    pub fn set_state(&mut self, enabled: bool) {
+        self.state = enabled;
+        if !enabled {
+            self.last_read.remove(&Column::State);
+        }
+    }
+
+    /// Also read each process's memory limit, for forecasting.
+    pub fn set_mem_limits(&mut self, enabled: bool) {
+        self.mem_limits = enabled;
+    }
+
+    /// Also read each process's cpu quota, for the cpu gauge.
+    pub fn set_cpu_quotas(&mut self, enabled: bool) {
+        self.cpu_quotas = enabled;
+    }
+
+    /// Also read each process's io priority.
+    pub fn set_io_priority(&mut self, enabled: bool) {
+        self.io_priority = enabled;
+        if !enabled {
+            self.last_read.remove(&Column::IoPrio);
+        }
+    }
+
+    /// Also read how many files each process has open.
+    pub fn set_fds(&mut self, enabled: bool) {
+        self.fds = enabled;
+        if !enabled {
+            self.last_read.remove(&Column::Fds);
+        }
+    }
+
+    /// Also read which ports each process is listening on, and its network
+    /// namespace.
+    pub fn set_ports(&mut self, enabled: bool) {
+        self.ports = enabled;
+        if !enabled {
+            self.last_read.remove(&Column::Ports);
+        }
+    }
+
+    /// Read column `col` only every `n` samples, keeping the last reading
+    /// in between; for columns that can be (see Column::every).
+    pub fn set_every(&mut self, col: Column, n: u64) {
+        self.every.insert(col, n.max(1));
+    }
+
+    /// How many samples apart column `col` is read.
+    pub fn read_every(&self, col: Column) -> u64 {
+        self.every(pass_for(col))
+    }
+
+    // how many samples apart `pass` is read: state and threads come
+    // together, so as often as either wants.
+    fn every(&self, pass: Column) -> u64 {
+        let every = |c: Column| self.every.get(&c).copied().or(c.every()).unwrap_or(1);
+        match pass {
+            Column::State => every(Column::State).min(every(Column::Threads)),
+            c => every(c),
+        }
+    }
+
+    /// How long reading column `col` for every process took last time, if
+    /// it's been read in a pass of its own.
+    pub fn cost(&self, col: Column) -> Option<Duration> {
+        self.costs.get(&pass_for(col)).copied()
+    }
+
+    /// Read column `col` for every process now, e.g. to see what showing it
+    /// would cost; how long that took.
+    pub fn measure(&mut self, col: Column) -> Duration {
+        let pids: Vec<i32> = self
+            .sprocs
+            .values()
+            .filter(|sp| sp.pid != SHORT_LIVED_PID && !sp.is_dead())
+            .map(|sp| sp.pid)
+            .collect();
+        self.read_pass(pass_for(col), &pids);
+        self.cost(col).unwrap_or_default()
+    }
+
+    /// Columns being read that weren't on the latest sample, so show older
+    /// values.
+    pub fn stale(&self) -> Vec<Column> {
+        let stale = |&pass: &Column| {
+            self.reading(pass) && self.last_read.get(&pass).is_some_and(|&t| t < self.ticks)
+        };
+        let passes: Vec<Column> = PASSES.iter().copied().filter(stale).collect();
+        Column::ALL
+            .iter()
+            .copied()
+            .filter(|&c| c.every().is_some() && passes.contains(&pass_for(c)))
+            .collect()
+    }
+
+    fn reading(&self, pass: Column) -> bool {
+        match pass {
+            Column::Fds => self.fds,
+            Column::Ports => self.ports,
+            Column::State => self.state,
+            Column::IoPrio => self.io_priority,
+            _ => false,
+        }
+    }
+
+    // whether `pass` is due a read this sample.
+    fn due(&self, pass: Column) -> bool {
+        self.reading(pass)
+            && self
+                .last_read
+                .get(&pass)
+                .is_none_or(|&last| self.ticks >= last + self.every(pass))
+    }
+
+    fn read_pass(&mut self, pass: Column, pids: &[i32]) {
+        let start = Instant::now();
+        match pass {
+            Column::Fds => {
+                for &pid in pids {
+                    let fds = read_fds(&mut self.helper, pid);
+                    if let Some(sp) = self.sprocs.get_mut(&pid) {
+                        sp.fds = fds;
+                    }
+                }
+            }
+            Column::Ports => self.read_ports(pids),
+            Column::State => {
+                for &pid in pids {
+                    let st = platform::state_threads(pid).ok();
+                    if let Some(sp) = self.sprocs.get_mut(&pid) {
+                        sp.state = st.map(|(s, _)| s);
+                        sp.threads = st.map(|(_, t)| t);
+                    }
+                }
+            }
+            Column::IoPrio => {
+                for &pid in pids {
+                    let prio = platform::io_priority(pid).ok();
+                    if let Some(sp) = self.sprocs.get_mut(&pid) {
+                        sp.io_priority = prio;
+                    }
+                }
+            }
+            _ => return,
+        }
+        self.costs.insert(pass, start.elapsed());
+        self.last_read.insert(pass, self.ticks);
+    }
+
+    fn read_ports(&mut self, pids: &[i32]) {
+        if self.root_net_ns.is_none() {
+            // init's, or failing that (e.g. hidepid) our own.
+            self.root_net_ns = platform::net_ns(1)
+                .or_else(|_| platform::net_ns(std::process::id() as i32))
+                .ok();
+        }
+        let root_net_ns = self.root_net_ns;
+        // each network namespace's listening sockets, read once they can be.
+        let mut listening: HashMap<Option<u64>, HashMap<u64, Port>> = HashMap::new();
+        for &pid in pids {
+            let sp = match self.sprocs.get_mut(&pid) {
+                Some(sp) => sp,
+                None => continue,
+            };
+            let ns = platform::net_ns(pid).ok();
+            sp.net_ns = ns.filter(|&ns| Some(ns) != root_net_ns);
+            // unreadable through this process, maybe not through the next in
+            // its namespace.
+            let sockets = match listening.entry(ns) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => match platform::listening_sockets(pid) {
+                    Ok(sockets) => e.insert(sockets),
+                    Err(_) => {
+                        sp.ports = None;
+                        continue;
+                    }
+                },
+            };
+            sp.ports = platform::socket_inodes(pid).ok().map(|inodes| {
+                let mut ports: Vec<Port> = inodes
+                    .iter()
+                    .filter_map(|i| sockets.get(i))
+                    .copied()
+                    .collect();
+                ports.sort_unstable();
+                ports.dedup();
+                ports
+            });
+        }
+    }
+
+    /// Change `pid`'s io priority, through the helper if we aren't allowed
+    /// to. Err is a message for the user.
+    pub fn ionice(&mut self, pid: i32, prio: IoPriority) -> Result<(), String> {
+        let done = match platform::set_io_priority(pid, prio) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => match &mut self.helper {
+                Some(h) => h.set_io_priority(pid, prio).map_err(|e| format!("{:#}", e)),
+                None => Err(format!("{} (try --privileged-helper)", e)),
+            },
+            r => r.map_err(|e| e.to_string()),
+        };
+        done.map_err(|e| format!("couldn't set io priority of {}: {}", pid, e))?;
+        if let Some(sp) = self.sprocs.get_mut(&pid) {
+            sp.io_priority = Some(prio);
+        }
+        Ok(())
+    }
+
+    /// Freeze `pid` and everything below it, or thaw it if it's frozen (see
+    /// freeze.rs). Ok and Err are both messages for the user.
+    pub fn toggle_freeze(&mut self, pid: i32) -> Result<String, String> {
+        let tree: Vec<i32> = self.descendants(pid).iter().map(|sp| sp.pid).collect();
+        let done = self.freezer.toggle(&tree);
+        self.mark_frozen();
+        done
+    }
+
+    fn mark_frozen(&mut self) {
+        for sp in self.sprocs.values_mut() {
+            sp.frozen = self.freezer.is_frozen(sp.pid);
+        }
+    }
+
+    /// Also look up each process's kubernetes pod (see k8s.rs).
+    pub fn set_pods(&mut self, enabled: bool) {
+        match (enabled, &self.pods) {
+            (true, None) => self.pods = Some(Pods::default()),
+            (false, Some(_)) => self.pods = None,
+            _ => (),
+        }
+    }
+
+    /// Skip updates that come sooner than `interval` after the previous one.
+    /// Ticks can bunch up (e.g. after a stall), and on platforms with coarse
+    /// cpu accounting a too-short interval gives garbage readings. Uses the
+    /// monotonic clock, so wall-clock jumps don't matter.
+    pub fn set_min_interval(&mut self, interval: Duration) {
+        self.min_interval = interval;
+    }
+
     pub fn update(&mut self, ewma_weight: f64) {
+        let now = Instant::now();
+        if let Some(last) = self.last_update {
+            if now.duration_since(last) < self.min_interval {
+                log::debug!("skipping update, only {:?} since last", now - last);
+                return;
+            }
+        }
+        let elapsed = self.last_update.map(|last| now - last);
+        self.last_update = Some(now);
+        self.ticks += 1;
+        let tick = self.ticks;
+        let secs = elapsed.map_or(self.history.interval(), |e| e.as_secs_f64());
+        self.history.next_sample(secs);
         // Not completely sure why, but we need to refresh cpu immediately
         // before processes for refresh_processes to include cpu usage. This
         // isn't totally crazy, modern cpu power save features can scale things
         // and bust readings.
         self.sys.refresh_cpu();
         self.sys.refresh_processes();
+        self.sys.refresh_memory();
+        self.update_system();
+        if self.wakeups {
+            let total = platform::interrupts().ok();
+            self.system.interrupts_per_sec = match (total, self.interrupts, elapsed) {
+                (Some(total), Some(prev), Some(e)) if e.as_secs_f64() > 0. => {
+                    Some(total.saturating_sub(prev) as f64 / e.as_secs_f64())
+                }
+                _ => None,
+            };
+            self.interrupts = total;
+        }
         let latest_procs = self.sys.processes();
-        for (&pid, proc) in latest_procs {
+        let total_mem = units::system_mem_bytes(self.sys.total_memory()).max(1);
+        let cpus = self.sys.processors().len().max(1) as f64;
+        // sysinfo never drops exited processes that hadn't used any cpu time
+        // (it only clears its "updated" flag after computing cpu usage), so
+        // double-check the idle ones.
+        let gone: HashSet<i32> = latest_procs
+            .iter()
+            .filter(|(&pid, p)| p.cpu_usage() == 0. && !platform::pid_exists(pid))
+            .map(|(&pid, _)| pid)
+            .collect();
+        let procs = latest_procs.len() - gone.len();
+        let (mut starts, mut exits) = (0, 0);
+        let mut sampled = vec![];
+        let (only, keep) = (&self.only, self.keep);
+        let wanted = |(&pid, p): &(&i32, &Process)| {
+            !gone.contains(&pid)
+                && (keep == Some(pid) || only.as_ref().is_none_or(|f| f.matches(pid, p)))
+        };
+        for (&pid, proc) in latest_procs.iter().filter(wanted) {
             log::debug!("handling {} {} {}", pid, proc.name(), proc.cpu_usage());
-            self.sprocs
+            sampled.push(pid);
+            let io_totals = read_io_via_helper(&mut self.helper, proc);
+            let (events, history) = (&mut self.events, &self.history);
+            let sp = self
+                .sprocs
                 .entry(pid)
-                .and_modify(|sp| sp.add_sample(proc, ewma_weight))
-                .or_insert_with(|| proc.into());
+                .and_modify(|sp| sp.add_sample(proc, history, ewma_weight, io_totals))
+                .or_insert_with(|| {
+                    let mut sp: SProc = proc.into();
+                    sp.set_unborn(tick as usize - 1);
+                    // everything is new on the first update; that's not news.
+                    if tick > 1 {
+                        starts += 1;
+                        events.push(LifeEventKind::Start, &sp, tick);
+                    }
+                    sp
+                });
+            sp.mem_pct = 100. * sp.mem_bytes as f64 / total_mem as f64;
+            if self.mem_limits {
+                sp.mem_limit_bytes = Some(
+                    platform::cgroup_mem_limit_bytes(pid)
+                        .unwrap_or(total_mem)
+                        .min(total_mem),
+                );
+            }
+            if self.cpu_quotas {
+                sp.cpu_quota = Some(platform::cgroup_cpu_quota(pid).unwrap_or(cpus).min(cpus));
+            }
+            if let Some(pods) = &mut self.pods {
+                sp.pod = pods.lookup(pid);
+            }
+            if self.latency {
+                if let (Ok(total), Some(elapsed)) = (platform::run_delay_ns(pid), elapsed) {
+                    sp.add_latency_sample(history, total, elapsed.as_secs_f64(), ewma_weight);
+                }
+            }
+            if self.wakeups {
+                if let (Ok(total), Some(elapsed)) = (platform::wakeups(pid), elapsed) {
+                    sp.add_wakeups_sample(history, total, elapsed.as_secs_f64(), ewma_weight);
+                }
+            }
+            if self.zoomed == Some(pid) {
+                if let (Ok((faults, swap)), Some(elapsed)) = (platform::paging(pid), elapsed) {
+                    sp.add_paging_sample(history, faults, swap, elapsed.as_secs_f64());
+                }
+            }
+            let split = self.cpu_split || self.zoomed == Some(pid);
+            if split || self.counters {
+                if let Ok((user, sys)) = platform::cpu_split_secs(pid) {
+                    if self.counters {
+                        sp.cpu_secs = Some(user + sys);
+                    }
+                    if let (true, Some(elapsed)) = (split, elapsed) {
+                        sp.add_cpu_split_sample(
+                            history,
+                            user,
+                            sys,
+                            elapsed.as_secs_f64(),
+                            ewma_weight,
+                        );
+                    }
+                }
+            }
+            if self.child_cpu {
+                if let (Ok(total), Some(elapsed)) = (platform::child_cpu_secs(pid), elapsed) {
+                    sp.add_child_cpu_sample(history, total, elapsed.as_secs_f64(), ewma_weight);
+                }
+            }
         }
-
         // TODO: do this more concisely.
-        // get dead procs
+        // get dead procs: gone, or (as far as we're watching) no longer let
+        // through by the filter.
+        let sampled_now: HashSet<i32> = sampled.iter().copied().collect();
+        let (events, history) = (&mut self.events, &self.history);
         let mut dead_procs: Vec<(&i32, &mut SProc)> = self
             .sprocs
             .iter_mut()
-            .filter(|(p, _)| !latest_procs.contains_key(p))
+            .filter(|(&p, _)| p != SHORT_LIVED_PID)
+            .filter(|(p, _)| !sampled_now.contains(p))
             .collect();
         // add a pseudo-sample for them and filter for procs that should be removed
         let procs_to_reap: Vec<i32> = dead_procs
             .iter_mut()
-            .filter_map(|(&pid, proc)| match proc.add_dead_sample(ewma_weight) {
-                DeadStatus::ShouldReap => Some(pid),
-                DeadStatus::StillFreshlyDead => None,
+            .filter_map(|(&pid, proc)| {
+                if !proc.is_dead() {
+                    exits += 1;
+                    events.push(LifeEventKind::Exit, proc, tick);
+                }
+                match proc.add_dead_sample(history, ewma_weight) {
+                    DeadStatus::ShouldReap => Some(pid),
+                    DeadStatus::StillFreshlyDead => None,
+                }
             })
             .collect();
         for pid in procs_to_reap {
             log::debug!("removing dead pid: {}", pid);
             self.sprocs.remove(&pid);
         }
+        if let Some(pods) = &mut self.pods {
+            pods.prune(|pid| latest_procs.contains_key(&pid) && !gone.contains(&pid));
+        }
+        self.system.push_churn(&self.history, procs, starts, exits);
+
+        for pass in PASSES {
+            if self.due(pass) {
+                self.read_pass(pass, &sampled);
+            }
+        }
+        // zoom shows its process's open files, column or not.
+        if let (false, Some(pid)) = (self.fds, self.zoomed) {
+            let fds = read_fds(&mut self.helper, pid);
+            if let Some(sp) = self.sprocs.get_mut(&pid) {
+                sp.fds = fds;
+            }
+        }
+
+        self.freezer.refresh();
+        self.mark_frozen();
+        vm::annotate(&mut self.sprocs);
+        sum_subtrees(&mut self.sprocs);
+
+        #[cfg(all(feature = "proc-connector", target_os = "linux"))]
+        self.update_short_lived(elapsed, ewma_weight);
+
+        if !self.alerts.is_empty() {
+            let procs: Vec<&SProc> = self.sprocs.values().collect();
+            let secs = elapsed.map_or(0., |e| e.as_secs_f64());
+            let raised = self.alerts.check(&procs, &self.system, secs);
+            self.raised.extend(raised);
+        }
+        #[cfg(feature = "scripting")]
+        if let Some(script) = &mut self.script {
+            let raised = script.tick(self.sprocs.values_mut(), &self.system);
+            self.raised.extend(raised);
+        }
     }
 
-    pub fn get(&self) -> Values<i32, SProc> {
+    fn update_system(&mut self) {
+        let s = &mut self.system;
+        s.cpu_pct = self.sys.global_processor_info().cpu_usage() as f64;
+        self.history.push(&mut s.cpu_hist, s.cpu_pct);
+        let cores = self.sys.processors();
+        s.core_hists.resize_with(cores.len(), VecDeque::new);
+        for (hist, core) in s.core_hists.iter_mut().zip(cores) {
+            self.history.push(hist, core.cpu_usage() as f64);
+        }
+        s.mem_used_bytes = units::system_mem_bytes(self.sys.used_memory());
+        s.mem_total_bytes = units::system_mem_bytes(self.sys.total_memory());
+        s.swap_used_bytes = units::system_mem_bytes(self.sys.used_swap());
+        s.swap_total_bytes = units::system_mem_bytes(self.sys.total_swap());
+        let load = self.sys.load_average();
+        s.load = [load.one, load.five, load.fifteen];
+        s.uptime_secs = self.sys.uptime();
+    }
+
+    // Exits of processes we never sampled, as cpu% over the last interval.
+    #[cfg(all(feature = "proc-connector", target_os = "linux"))]
+    fn update_short_lived(&mut self, elapsed: Option<Duration>, ewma_weight: f64) {
+        let connector = match &self.connector {
+            Some(c) => c,
+            None => return,
+        };
+        let cpu_secs: f64 = connector
+            .drain()
+            .into_iter()
+            .filter(|e| !self.sprocs.contains_key(&e.pid))
+            .map(|e| e.cpu_secs)
+            .sum();
+        let secs = elapsed.map_or(0., |d| d.as_secs_f64());
+        if cpu_secs == 0. && !self.sprocs.contains_key(&SHORT_LIVED_PID) {
+            return;
+        }
+        let cpu = if secs > 0. {
+            100. * cpu_secs / secs
+        } else {
+            0.
+        };
+        self.sprocs
+            .entry(SHORT_LIVED_PID)
+            .or_insert_with(|| SProc::synthetic(SHORT_LIVED_PID, "(short-lived)"))
+            .add_cpu_sample(&self.history, cpu, ewma_weight);
+    }
+
+    pub fn get(&self) -> Values<'_, i32, SProc> {
         self.sprocs.values()
     }
+
+    pub fn by_pid(&self, pid: i32) -> Option<&SProc> {
+        self.sprocs.get(&pid)
+    }
+
+    /// Cpu, memory, load and uptime of the whole system, as of the last
+    /// update.
+    pub fn system(&self) -> &SystemStats {
+        &self.system
+    }
+
+    /// Messages for the alert rules that fired since the last call.
+    pub fn take_alerts(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.raised)
+    }
+
+    /// Processes seen starting and exiting.
+    pub fn events(&self) -> &LifetimeLog {
+        &self.events
+    }
+
+    /// `root` and everything below it in the process tree.
+    pub fn descendants(&self, root: i32) -> Vec<&SProc> {
+        let mut children: HashMap<i32, Vec<&SProc>> = HashMap::new();
+        for sp in self.sprocs.values() {
+            if let Some(ppid) = sp.ppid {
+                children.entry(ppid).or_default().push(sp);
+            }
+        }
+        let mut tree: Vec<&SProc> = self.sprocs.get(&root).into_iter().collect();
+        let mut i = 0;
+        while i < tree.len() {
+            if let Some(kids) = children.get(&tree[i].pid) {
+                tree.extend(kids.iter().filter(|k| k.pid != root));
+            }
+            i += 1;
+        }
+        tree
+    }
+}
+
+/// Set each process's tree cpu: its own plus its descendants'. Each adds
+/// itself to every ancestor we have, so a parent we don't (filtered out, or
+/// gone) ends the chain.
+pub fn sum_subtrees(sprocs: &mut HashMap<i32, SProc>) {
+    let own: Vec<(i32, f64, f64)> = sprocs
+        .values()
+        .map(|sp| (sp.pid, sp.cpu_ewma, sp.child_cpu_ewma))
+        .collect();
+    let parents: HashMap<i32, i32> = sprocs
+        .values()
+        .filter_map(|sp| Some((sp.pid, sp.ppid?)))
+        .collect();
+    for sp in sprocs.values_mut() {
+        sp.tree_cpu_ewma = 0.;
+        sp.tree_child_cpu_ewma = 0.;
+    }
+    for (pid, cpu, child_cpu) in own {
+        let mut at = Some(pid);
+        // bounded, in case of a cycle (pid reuse mid-sample).
+        for _ in 0..=sprocs.len() {
+            let sp = match at.and_then(|p| sprocs.get_mut(&p)) {
+                Some(sp) => sp,
+                None => break,
+            };
+            sp.tree_cpu_ewma += cpu;
+            sp.tree_child_cpu_ewma += child_cpu;
+            at = parents.get(&sp.pid).copied().filter(|&p| p != pid);
+        }
+    }
+}
+
+fn read_fds(helper: &mut Option<Helper>, pid: i32) -> Option<usize> {
+    match platform::fd_count(pid) {
+        Ok(n) => Some(n),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            ask_helper(helper, |h| h.fd_count(pid))
+        }
+        Err(_) => None,
+    }
+}
+
+// sysinfo reports zero io for processes it can't read; only then, and only
+// if we really can't read them, ask the helper for cumulative counters.
+#[cfg(not(target_os = "freebsd"))]
+fn read_io_via_helper(helper: &mut Option<Helper>, proc: &Process) -> Option<(u64, u64)> {
+    helper.as_ref()?;
+    let du = proc.disk_usage();
+    if du.total_read_bytes != 0 || du.total_written_bytes != 0 {
+        return None;
+    }
+    match platform::io_totals(proc.pid()) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            ask_helper(helper, |h| h.io_totals(proc.pid()))
+        }
+        _ => None,
+    }
+}
+
+// On FreeBSD sysinfo's io is counts of blocks; platform::io_totals has them
+// as bytes, and needs no privileges.
+#[cfg(target_os = "freebsd")]
+fn read_io_via_helper(_helper: &mut Option<Helper>, proc: &Process) -> Option<(u64, u64)> {
+    platform::io_totals(proc.pid()).ok()
+}
+
+// A dead helper is dropped so we don't keep retrying it every process.
+fn ask_helper<T>(
+    helper: &mut Option<Helper>,
+    f: impl FnOnce(&mut Helper) -> anyhow::Result<T>,
+) -> Option<T> {
+    match f(helper.as_mut()?) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            log::debug!("privileged helper: {}", e);
+            if !helper.as_mut()?.is_alive() {
+                log::warn!("privileged helper exited, no longer using it");
+                *helper = None;
+            }
+            None
+        }
+    }
 }
@@ -0,0 +1,154 @@
+//! Stats: small statistics over sample histories.
+
+// samples each one is compared against, and how unusual it has to be.
+const SPIKE_WINDOW: usize = 20;
+const SPIKE_Z: f64 = 3.;
+// fewer samples than this is too little to judge by.
+const MIN_BASELINE: usize = 5;
+
+/// Flags samples that are spikes: more than SPIKE_Z standard deviations above
+/// the mean of the SPIKE_WINDOW samples before them. `xs` is newest first, as
+/// in the history deques. `min_sd` floors the deviation, so a flat-lined
+/// history doesn't make every bit of jitter a spike.
+pub fn spikes<'a, II>(xs: II, min_sd: f64) -> Vec<bool>
+where
+    II: IntoIterator<Item = &'a f64>,
+{
+    let xs: Vec<f64> = xs.into_iter().copied().collect();
+    (0..xs.len())
+        .map(|i| {
+            let before = &xs[(i + 1).min(xs.len())..(i + 1 + SPIKE_WINDOW).min(xs.len())];
+            if before.len() < MIN_BASELINE {
+                return false;
+            }
+            let (mean, sd) = mean_sd(before);
+            xs[i] > mean + SPIKE_Z * sd.max(min_sd)
+        })
+        .collect()
+}
+
+/// Whether any of the newest `recent` samples is a spike.
+pub fn recent_spike<'a, II>(xs: II, min_sd: f64, recent: usize) -> bool
+where
+    II: IntoIterator<Item = &'a f64>,
+{
+    spikes(xs, min_sd).into_iter().take(recent).any(|s| s)
+}
+
+// samples averaged at each end of a history to judge its direction, and the
+// change (relative to the larger of the two, or at least TREND_MIN_CHANGE
+// absolute) that counts as moving.
+const DIRECTION_SAMPLES: usize = 3;
+const DIRECTION_CHANGE: f64 = 0.2;
+const TREND_MIN_CHANGE: f64 = 2.;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Direction {
+    Rising,
+    Falling,
+    Steady,
+}
+
+impl Direction {
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::Rising => "rising",
+            Direction::Falling => "falling",
+            Direction::Steady => "steady",
+        }
+    }
+}
+
+/// Which way a history (newest first) is heading: its newest few samples
+/// against the few before them.
+pub fn direction<'a, II>(xs: II) -> Direction
+where
+    II: IntoIterator<Item = &'a f64>,
+{
+    let xs: Vec<f64> = xs
+        .into_iter()
+        .copied()
+        .take(2 * DIRECTION_SAMPLES)
+        .collect();
+    if xs.len() < 2 * DIRECTION_SAMPLES {
+        return Direction::Steady;
+    }
+    let (recent, _) = mean_sd(&xs[..DIRECTION_SAMPLES]);
+    let (before, _) = mean_sd(&xs[DIRECTION_SAMPLES..]);
+    let threshold = (DIRECTION_CHANGE * recent.max(before)).max(TREND_MIN_CHANGE);
+    if recent - before > threshold {
+        Direction::Rising
+    } else if before - recent > threshold {
+        Direction::Falling
+    } else {
+        Direction::Steady
+    }
+}
+
+// a trend needs this many samples, and to fit them this well (r²), to be
+// worth extrapolating.
+const TREND_MIN_SAMPLES: usize = 10;
+const TREND_MIN_R2: f64 = 0.8;
+
+/// Seconds until a steadily rising history reaches `target`, by a
+/// least-squares line through its (seconds ago, value) points, newest first.
+/// None if it isn't steadily rising.
+pub fn secs_until<I>(points: I, target: f64) -> Option<f64>
+where
+    I: IntoIterator<Item = (f64, f64)>,
+{
+    // x is time, the newest at 0.
+    let (xs, ys): (Vec<f64>, Vec<f64>) = points.into_iter().map(|(ago, y)| (-ago, y)).unzip();
+    if ys.len() < TREND_MIN_SAMPLES {
+        return None;
+    }
+    let (mean_x, _) = mean_sd(&xs);
+    let (mean_y, _) = mean_sd(&ys);
+    let sxy: f64 = xs
+        .iter()
+        .zip(&ys)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let sxx: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let syy: f64 = ys.iter().map(|y| (y - mean_y).powi(2)).sum();
+    if sxx == 0. || syy == 0. {
+        return None;
+    }
+    let slope = sxy / sxx;
+    let r2 = sxy * sxy / (sxx * syy);
+    if slope <= 0. || r2 < TREND_MIN_R2 {
+        return None;
+    }
+    let now = mean_y - slope * mean_x;
+    Some(((target - now) / slope).max(0.))
+}
+
+/// The `p`th percentile (0-100) of `xs`, by nearest rank. None if empty.
+pub fn percentile(xs: &[f64], p: f64) -> Option<f64> {
+    if xs.is_empty() {
+        return None;
+    }
+    let mut sorted = xs.to_vec();
+    sorted.sort_by_key(|&x| ordered_float::OrderedFloat(x));
+    let rank = (p / 100. * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// The `lo` and `hi` percentiles of each sample in a history (newest first)
+/// and the `window - 1` before it: how widely it's been varying.
+pub fn rolling_band(xs: &[f64], window: usize, lo: f64, hi: f64) -> Vec<(f64, f64)> {
+    (0..xs.len())
+        .map(|i| {
+            let w = &xs[i..(i + window.max(1)).min(xs.len())];
+            let p = |q| percentile(w, q).unwrap_or(xs[i]);
+            (p(lo), p(hi))
+        })
+        .collect()
+}
+
+fn mean_sd(xs: &[f64]) -> (f64, f64) {
+    let n = xs.len().max(1) as f64;
+    let mean = xs.iter().sum::<f64>() / n;
+    let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+    (mean, var.sqrt())
+}
@@ -1,41 +1,95 @@
 /// STerm: a wrapper around nitty-gritty terminal details.
 use crossterm::execute;
-use tui::backend::CrosstermBackend;
-use tui::Terminal;
 
-type CTBackend = CrosstermBackend<std::io::Stdout>;
+use crate::ui::backend::{Backend, CrosstermBackend};
+use crate::ui::buffer::Buffer;
+use crate::ui::Terminal;
 
-// wrapper around tui and crossterm stuff
-pub struct STerm {
-    terminal: Terminal<CTBackend>,
+pub type CTBackend = CrosstermBackend<std::io::Stdout>;
+
+// wrapper around ratatui and crossterm stuff
+pub struct STerm<B: Backend = CTBackend> {
+    terminal: Terminal<B>,
+    raw: bool, // whether we put the real terminal in raw mode
+    // a copy of the last frame drawn, if asked for (see keep_frames).
+    last_frame: Option<Buffer>,
+    keep_frames: bool,
 }
 
 impl Default for STerm {
     fn default() -> Self {
         let stdout = std::io::stdout();
         let backend = CrosstermBackend::new(stdout);
-        let terminal = tui::Terminal::new(backend).expect("couldn't make tui::Terminal");
+        let terminal = Terminal::new(backend).expect("couldn't make ratatui::Terminal");
         init_terminal();
-        STerm { terminal }
+        STerm {
+            terminal,
+            raw: true,
+            last_frame: None,
+            keep_frames: false,
+        }
     }
 }
 
-impl STerm {
-    // proxying the only tui::Terminal method needed.
+impl<B: Backend> STerm<B> {
+    /// Draw to `backend` (e.g. ratatui's TestBackend), leaving the real terminal
+    /// alone.
+    pub fn with_backend(backend: B) -> std::io::Result<Self> {
+        Ok(STerm {
+            terminal: Terminal::new(backend)?,
+            raw: false,
+            last_frame: None,
+            keep_frames: false,
+        })
+    }
+
+    // proxying the only ui::Terminal method needed.
     // LEARN: why doesn't this work? (error about sized types, etc)
-    // pub fn draw(&mut self, f: FnOnce(&mut tui::Frame<CTBackend>)) -> std::io::Result<()> {
+    // pub fn draw(&mut self, f: FnOnce(&mut crate::ui::Frame)) -> std::io::Result<()> {
     pub fn draw<F>(&mut self, f: F) -> std::io::Result<()>
     where
-        F: FnOnce(&mut tui::Frame<CTBackend>),
+        F: FnOnce(&mut crate::ui::Frame),
     {
-        // LEARN: nicer way to throw away the Ok value?
-        self.terminal.draw(f).map(|_| ())
+        let frame = self.terminal.draw(f)?;
+        if self.keep_frames {
+            self.last_frame = Some(frame.buffer.clone());
+        }
+        Ok(())
+    }
+
+    /// Keep a copy of each frame drawn, e.g. for sharing the screen.
+    pub fn keep_frames(&mut self) {
+        self.keep_frames = true;
+    }
+
+    pub fn last_frame(&self) -> Option<&Buffer> {
+        self.last_frame.as_ref()
+    }
+
+    pub fn size(&self) -> std::io::Result<crate::ui::layout::Rect> {
+        let size = self.terminal.size()?;
+        Ok(crate::ui::layout::Rect::new(0, 0, size.width, size.height))
+    }
+
+    /// Ring the terminal's bell; only the real one.
+    pub fn bell(&mut self) {
+        use std::io::Write;
+        if self.raw {
+            let mut sout = std::io::stdout();
+            let _ = sout.write_all(b"\x07").and_then(|()| sout.flush());
+        }
+    }
+
+    pub fn backend(&self) -> &B {
+        self.terminal.backend()
     }
 }
 
-impl Drop for STerm {
+impl<B: Backend> Drop for STerm<B> {
     fn drop(&mut self) {
-        restore_terminal();
+        if self.raw {
+            restore_terminal();
+        }
     }
 }
 
@@ -51,11 +105,14 @@ fn init_terminal() {
         .expect("Unable to enter alternate screen");
     // needed to process key events as they come
     crossterm::terminal::enable_raw_mode().expect("Unable to enter raw mode.");
+    // clicks and the wheel; shift still selects text in most terminals.
+    execute!(sout, crossterm::event::EnableMouseCapture).expect("Unable to capture mouse");
 }
 
 fn restore_terminal() {
     log::debug!("restoring STerm");
     let mut sout = std::io::stdout();
+    execute!(sout, crossterm::event::DisableMouseCapture).expect("Unable to release mouse");
     execute!(sout, crossterm::terminal::LeaveAlternateScreen)
         .expect("Unable to leave alternate screen.");
     // fixes terminal offset weirdness
@@ -0,0 +1,241 @@
+//! Theme: the colors the UI is drawn in, by what they mean.
+//
+// Builtin presets, which the config file can pick from and override slot by
+// slot (see config::ThemeConfig). Colors are named as in share.rs's wire
+// format: "red", "lightblue", "#ff8000", or "i208" for the 256-color palette.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::ui::style::Color;
+
+/// What a color is for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Slot {
+    CpuWarm,
+    CpuHot,
+    CpuVeryHot,
+    Warn,  // e.g. some run queue wait, zombies
+    Alarm, // e.g. a lot of it, processes stuck on io
+    KernelHeavy,
+    Dead,
+    Started,
+    Frozen,
+    OwnRow,
+    SelectedRow, // "reset" to reverse video instead
+    Header,
+    SpikeMarks,
+    PeakMarks,
+    CpuChart,
+    MemChart,
+    CpuBand,
+    MemBand,
+    Grid,
+    Read,
+    Write,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub cpu_warm: Color,
+    pub cpu_hot: Color,
+    pub cpu_very_hot: Color,
+    pub warn: Color,
+    pub alarm: Color,
+    pub kernel_heavy: Color,
+    pub dead: Color,
+    pub started: Color,
+    pub frozen: Color,
+    pub own_row: Color,
+    pub selected_row: Color,
+    pub header: Color,
+    pub spike_marks: Color,
+    pub peak_marks: Color,
+    pub cpu_chart: Color,
+    pub mem_chart: Color,
+    pub cpu_band: Color,
+    pub mem_band: Color,
+    pub grid: Color,
+    pub read: Color,
+    pub write: Color,
+}
+
+pub const PRESETS: [&str; 3] = ["dark", "light", "colorblind"];
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// One of PRESETS.
+    pub fn preset(name: &str) -> Result<Self> {
+        Ok(match name {
+            "dark" => Self::dark(),
+            "light" => Self::light(),
+            "colorblind" => Self::colorblind(),
+            _ => bail!("no theme {:?} (try {})", name, PRESETS.join(", ")),
+        })
+    }
+
+    // for dark backgrounds, the colors sparktop has always had.
+    fn dark() -> Self {
+        Self {
+            cpu_warm: Color::Yellow,
+            cpu_hot: Color::Red,
+            cpu_very_hot: Color::Magenta,
+            warn: Color::Yellow,
+            alarm: Color::Red,
+            kernel_heavy: Color::LightRed,
+            dead: Color::Red,
+            started: Color::Green,
+            frozen: Color::LightBlue,
+            own_row: Color::LightBlue,
+            selected_row: Color::Reset,
+            header: Color::Reset,
+            spike_marks: Color::Cyan,
+            peak_marks: Color::Magenta,
+            cpu_chart: Color::Green,
+            mem_chart: Color::Blue,
+            cpu_band: Color::Indexed(22), // dark green
+            mem_band: Color::Indexed(17), // dark blue
+            grid: Color::DarkGray,
+            read: Color::Green,
+            write: Color::Yellow,
+        }
+    }
+
+    // for light backgrounds: no pale yellows or blues, and pale bands.
+    fn light() -> Self {
+        Self {
+            cpu_warm: Color::Indexed(136), // dark goldenrod
+            warn: Color::Indexed(136),
+            kernel_heavy: Color::Red,
+            frozen: Color::Blue,
+            own_row: Color::Blue,
+            spike_marks: Color::Indexed(30), // dark cyan
+            cpu_band: Color::Indexed(194),   // pale green
+            mem_band: Color::Indexed(189),   // pale blue
+            grid: Color::Gray,
+            write: Color::Indexed(136),
+            ..Self::dark()
+        }
+    }
+
+    // nothing told apart by red against green alone: blue and orange, from
+    // the Okabe-Ito palette, with yellow and magenta for the extremes.
+    fn colorblind() -> Self {
+        Self {
+            cpu_warm: Color::Yellow,
+            cpu_hot: Color::Indexed(208), // orange
+            cpu_very_hot: Color::Magenta,
+            warn: Color::Yellow,
+            alarm: Color::Indexed(208),
+            kernel_heavy: Color::Indexed(208),
+            dead: Color::Indexed(208),
+            started: Color::Indexed(33), // blue
+            spike_marks: Color::Cyan,
+            cpu_chart: Color::Indexed(33),
+            mem_chart: Color::Indexed(208),
+            cpu_band: Color::Indexed(17),
+            mem_band: Color::Indexed(94), // brown
+            read: Color::Indexed(33),
+            write: Color::Indexed(208),
+            ..Self::dark()
+        }
+    }
+
+    pub fn set(&mut self, slot: Slot, color: Color) {
+        let c = match slot {
+            Slot::CpuWarm => &mut self.cpu_warm,
+            Slot::CpuHot => &mut self.cpu_hot,
+            Slot::CpuVeryHot => &mut self.cpu_very_hot,
+            Slot::Warn => &mut self.warn,
+            Slot::Alarm => &mut self.alarm,
+            Slot::KernelHeavy => &mut self.kernel_heavy,
+            Slot::Dead => &mut self.dead,
+            Slot::Started => &mut self.started,
+            Slot::Frozen => &mut self.frozen,
+            Slot::OwnRow => &mut self.own_row,
+            Slot::SelectedRow => &mut self.selected_row,
+            Slot::Header => &mut self.header,
+            Slot::SpikeMarks => &mut self.spike_marks,
+            Slot::PeakMarks => &mut self.peak_marks,
+            Slot::CpuChart => &mut self.cpu_chart,
+            Slot::MemChart => &mut self.mem_chart,
+            Slot::CpuBand => &mut self.cpu_band,
+            Slot::MemBand => &mut self.mem_band,
+            Slot::Grid => &mut self.grid,
+            Slot::Read => &mut self.read,
+            Slot::Write => &mut self.write,
+        };
+        *c = color;
+    }
+
+    /// Whether `c` flags something wrong, for drawing it bold without color.
+    pub fn alarming(&self, c: Color) -> bool {
+        c != Color::Reset
+            && [
+                self.cpu_hot,
+                self.cpu_very_hot,
+                self.alarm,
+                self.kernel_heavy,
+                self.dead,
+                self.peak_marks,
+            ]
+            .contains(&c)
+    }
+
+    /// Whether `c` is for the background of things, for drawing it dim
+    /// without color.
+    pub fn muted(&self, c: Color) -> bool {
+        c == Color::DarkGray || [self.grid, self.cpu_band, self.mem_band].contains(&c)
+    }
+}
+
+/// e.g. "reset", "lightblue", "#ff8000", "i208".
+pub fn color_name(c: Color) -> String {
+    match c {
+        Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        Color::Indexed(i) => format!("i{}", i),
+        named => format!("{:?}", named).to_lowercase(),
+    }
+}
+
+/// The color named `s`, as `color_name` names it.
+pub fn parse_color(s: &str) -> Option<Color> {
+    if let Some(hex) = s.strip_prefix('#') {
+        let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return match hex.len() {
+            6 => Some(Color::Rgb(byte(0)?, byte(2)?, byte(4)?)),
+            _ => None,
+        };
+    }
+    if let Some(i) = s.strip_prefix('i') {
+        if let Ok(i) = i.parse() {
+            return Some(Color::Indexed(i));
+        }
+    }
+    NAMED.iter().copied().find(|&c| color_name(c) == s)
+}
+
+const NAMED: [Color; 17] = [
+    Color::Reset,
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
@@ -0,0 +1,162 @@
+//! Threshold: `--fail-if` assertions like "mem(firefox) > 8G".
+//
+// Syntax: `<metric>[(<name>)] <op> <value>`, where metric is cpu, mem,
+// read or write; name (a substring of the process name) picks which
+// processes to sum over, otherwise all of them (or the whole watched tree);
+// op is one of > >= < <=; and value takes K/M/G suffixes (binary) for
+// mem/read/write, or an optional % for cpu. read/write are per tick.
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, bail, Error, Result};
+
+use crate::sproc::SProc;
+
+/// Exit status when any --fail-if assertion was violated.
+pub const EXIT_CODE: i32 = 99;
+
+#[derive(Debug)]
+pub struct Threshold {
+    src: String,
+    metric: Metric,
+    name: Option<String>,
+    op: Op,
+    value: f64, // cpu %, or bytes
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Metric {
+    Cpu,
+    Mem,
+    Read,
+    Write,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl FromStr for Threshold {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let ops = [(">=", Op::Ge), ("<=", Op::Le), (">", Op::Gt), ("<", Op::Lt)];
+        let (lhs, op, rhs) = ops
+            .iter()
+            .find_map(|&(tok, op)| s.split_once(tok).map(|(l, r)| (l.trim(), op, r.trim())))
+            .ok_or_else(|| anyhow!("no comparison in {:?}", s))?;
+        let (metric, name) = match lhs.split_once('(') {
+            Some((m, rest)) => {
+                let name = rest
+                    .strip_suffix(')')
+                    .ok_or_else(|| anyhow!("unclosed ( in {:?}", s))?;
+                (m.trim(), Some(name.trim().to_string()))
+            }
+            None => (lhs, None),
+        };
+        let metric = match metric {
+            "cpu" => Metric::Cpu,
+            "mem" => Metric::Mem,
+            "read" => Metric::Read,
+            "write" => Metric::Write,
+            m => bail!("unknown metric {:?} (want cpu, mem, read, write)", m),
+        };
+        let value = match metric {
+            Metric::Cpu => rhs.trim_end_matches('%').parse()?,
+            Metric::Mem | Metric::Read | Metric::Write => parse_bytes(rhs)?,
+        };
+        Ok(Self {
+            src: s.into(),
+            metric,
+            name,
+            op,
+            value,
+        })
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.src)
+    }
+}
+
+impl Threshold {
+    /// The summed metric over matching processes, if it violates the limit.
+    pub fn check(&self, procs: &[&SProc]) -> Option<f64> {
+        let total: f64 = procs
+            .iter()
+            .filter(|sp| !sp.is_dead())
+            .filter(|sp| {
+                self.name
+                    .as_ref()
+                    .is_none_or(|n| sp.name.contains(n.as_str()))
+            })
+            .map(|sp| match self.metric {
+                Metric::Cpu => sp.cpu_hist.front().copied().unwrap_or(0.),
+                Metric::Mem => sp.mem_bytes as f64,
+                Metric::Read => sp.disk_read_hist.front().copied().unwrap_or(0) as f64,
+                Metric::Write => sp.disk_write_hist.front().copied().unwrap_or(0) as f64,
+            })
+            .sum();
+        let violated = match self.op {
+            Op::Gt => total > self.value,
+            Op::Ge => total >= self.value,
+            Op::Lt => total < self.value,
+            Op::Le => total <= self.value,
+        };
+        if violated {
+            Some(total)
+        } else {
+            None
+        }
+    }
+}
+
+/// A set of thresholds, remembering which have been violated during a run.
+#[derive(Default)]
+pub struct Thresholds {
+    list: Vec<Threshold>,
+    violated: Vec<bool>,
+}
+
+impl Thresholds {
+    pub fn new(list: Vec<Threshold>) -> Self {
+        let violated = vec![false; list.len()];
+        Self { list, violated }
+    }
+
+    /// Check all thresholds, returning messages for newly violated ones.
+    pub fn check(&mut self, procs: &[&SProc]) -> Vec<String> {
+        let mut msgs = vec![];
+        for (t, violated) in self.list.iter().zip(self.violated.iter_mut()) {
+            if *violated {
+                continue;
+            }
+            if let Some(v) = t.check(procs) {
+                msgs.push(format!("--fail-if violated: {} (was {:.1})", t, v));
+                *violated = true;
+            }
+        }
+        msgs
+    }
+
+    pub fn any_violated(&self) -> bool {
+        self.violated.iter().any(|&v| v)
+    }
+}
+
+// "8G" -> 8 * 2^30, etc.
+pub(crate) fn parse_bytes(s: &str) -> Result<f64> {
+    let s = s.trim().trim_end_matches(['b', 'B']);
+    let (num, mult) = match s.chars().last() {
+        Some('K') | Some('k') => (&s[..s.len() - 1], 1024.),
+        Some('M') | Some('m') => (&s[..s.len() - 1], 1024. * 1024.),
+        Some('G') | Some('g') => (&s[..s.len() - 1], 1024. * 1024. * 1024.),
+        _ => (s, 1.),
+    };
+    Ok(num.trim().parse::<f64>()? * mult)
+}
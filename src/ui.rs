@@ -0,0 +1,3 @@
+//! Ui: the terminal UI library, re-exported so the rest of sparktop (and its
+//! tests) name it in one place, and a change of library is a change here.
+pub use ratatui::{backend, buffer, layout, style, symbols, text, widgets, Frame, Terminal};
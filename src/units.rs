@@ -0,0 +1,41 @@
+//! Units: memory sizes from sysinfo as bytes, and bytes for display.
+//
+// sysinfo 0.20's memory readings are all documented as KB, but mean
+// different things by it: on linux a process's memory() is KiB (resident
+// pages times the page size / 1024) while the system's totals are
+// /proc/meminfo's KiB scaled to decimal kB; elsewhere both are bytes / 1000.
+// Everything is kept in bytes, converted once here on the way in. For
+// display, MB and GB are binary (MiB and GiB), as they always have been in
+// the tables.
+
+pub const MIB: f64 = 1024. * 1024.;
+const GIB: f64 = 1024. * MIB;
+
+/// A process's sysinfo memory() in bytes.
+pub fn process_mem_bytes(kb: u64) -> u64 {
+    if cfg!(any(target_os = "linux", target_os = "android")) {
+        kb * 1024
+    } else {
+        kb * 1000
+    }
+}
+
+/// The system's sysinfo total_memory(), used_memory() and the swap ones in
+/// bytes.
+pub fn system_mem_bytes(kb: u64) -> u64 {
+    kb * 1000
+}
+
+/// In MiB, e.g. for columns of numbers under a "mem" header.
+pub fn mib(bytes: f64) -> f64 {
+    bytes / MIB
+}
+
+/// e.g. "512 MB", "1.5 GB".
+pub fn render_bytes(bytes: f64) -> String {
+    if bytes >= GIB {
+        format!("{:.1} GB", bytes / GIB)
+    } else {
+        format!("{:.0} MB", bytes / MIB)
+    }
+}
@@ -1,76 +1,739 @@
 /// View: rendering the UI, interactions.
+use std::{collections::HashMap, time::Duration};
+
 use anyhow::Result;
 use ordered_float::OrderedFloat as OrdFloat;
 
-use crossterm::event::{KeyCode, KeyEvent};
-use tui::{
-    layout::{Constraint, Layout},
-    style::{Modifier, Style},
-    text::Span,
-    widgets::{Block, Borders, Paragraph, Row, Table},
-};
+use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 
+use crate::ui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Flex, Layout, Rect},
+    style::{Color, Modifier, Style},
+    symbols::Marker,
+    text::{Line, Span},
+    widgets::{Axis, Block, Borders, Cell, Chart, Dataset, GraphType, Paragraph, Row, Table},
+};
 use crate::{
+    action::{self, Action, Command},
+    alert::AlertLog,
+    cgroup_io::IoSummary,
+    column::{CellKind, Column, GAUGE_WIDTH},
+    config::{Breakpoint, ColorLevels},
+    coredump::DumpWith,
     event::Next,
-    sterm::STerm,
-    view_state::{Dir, Metric, ViewState},
-    {render, sproc::SProc},
+    group::{self, Grouping},
+    input::{InputEvent, LineInput},
+    kstack::StackSummary,
+    lifetime::{LifeEventKind, LifetimeLog},
+    platform::{self, IoPriority, Port, Pressure, Unavailable},
+    rename::Renames,
+    sprocs::SystemStats,
+    stats,
+    sterm::{CTBackend, STerm},
+    units::{self, render_bytes},
+    view_state::{
+        ConfirmCadence, ConfirmDump, DeadRows, Dir, IoChange, Jump, Metric, Move, Palette, RowCond,
+        RowRule, TableState, ViewState, ZoomTab,
+    },
+    vm,
+    {
+        render::{self, Bars},
+        sproc::{History, SProc},
+        theme::Theme,
+    },
 };
 
-#[derive(Default)]
-pub struct View {
-    terminal: STerm,
+pub struct View<B: Backend = CTBackend> {
+    terminal: STerm<B>,
     state: ViewState,
+    // where each table's headers and rows were last drawn, and which pane
+    // they're in, for the mouse.
+    targets: Vec<(Rect, usize, Target)>,
+}
+
+/// What's under a point on screen.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Target {
+    Header(Column),
+    Row(i32), // pid
+    Table,    // anywhere else in a table
+}
+
+// rows the wheel moves the selection per notch.
+const WHEEL_ROWS: usize = 3;
+
+impl Default for View {
+    fn default() -> Self {
+        Self::with_terminal(STerm::default())
+    }
 }
 
-impl View {
-    fn sort(&self, sprocs: &mut Vec<&SProc>) {
-        sprocs.sort_by_key(|&sp| {
-            let val = match self.state.sort_by {
-                Metric::Pid => sp.pid as f64,
-                Metric::Cpu => sp.cpu_ewma,
-                Metric::Mem => sp.mem_mb,
-                Metric::DiskRead => sp.disk_read_ewma,
-                Metric::DiskWrite => sp.disk_write_ewma,
-                Metric::DiskTotal => sp.disk_read_ewma + sp.disk_write_ewma,
+impl<B: Backend> View<B> {
+    /// A view drawing to `terminal`, e.g. one on a TestBackend.
+    pub fn with_terminal(terminal: STerm<B>) -> Self {
+        Self {
+            terminal,
+            state: ViewState::default(),
+            targets: vec![],
+        }
+    }
+
+    pub fn terminal(&self) -> &STerm<B> {
+        &self.terminal
+    }
+
+    pub fn terminal_mut(&mut self) -> &mut STerm<B> {
+        &mut self.terminal
+    }
+
+    pub fn set_unavailable(&mut self, unavailable: Vec<Unavailable>) {
+        self.state.set_unavailable(unavailable);
+    }
+
+    pub fn set_columns(&mut self, columns: Vec<Column>) {
+        for t in &mut self.state.panes {
+            t.columns = columns.clone();
+        }
+    }
+
+    pub fn set_sort(&mut self, by: Metric, dir: Dir) {
+        for t in &mut self.state.panes {
+            t.sort_by = by;
+            t.sort_dir = dir;
+        }
+    }
+
+    pub fn set_bookmarks(&mut self, bookmarks: Vec<String>) {
+        self.state.bookmarks = bookmarks;
+    }
+
+    pub fn bookmarks(&self) -> &[String] {
+        &self.state.bookmarks
+    }
+
+    /// Always show `pid` (sparktop's own) pinned to the top, so its overhead
+    /// can be seen; `in_totals` is whether it counts toward the summary,
+    /// group and filter totals.
+    pub fn show_own_row(&mut self, pid: i32, in_totals: bool) {
+        self.state.own_pid = Some(pid);
+        self.state.own_in_totals = in_totals;
+    }
+
+    pub fn set_muted(&mut self, muted: Vec<String>) {
+        self.state.muted = muted;
+    }
+
+    /// The mute list, if it's changed since the last call.
+    pub fn take_changed_muted(&mut self) -> Option<Vec<String>> {
+        std::mem::take(&mut self.state.muted_changed).then(|| self.state.muted.clone())
+    }
+
+    /// The io priority change asked for since the last call, and the process
+    /// it's for.
+    pub fn take_ionice(&mut self) -> Option<(i32, IoChange)> {
+        self.state.ionice.take()
+    }
+
+    /// The process (pid and name) to core dump, and how, if confirmed since
+    /// the last call.
+    pub fn take_dump(&mut self) -> Option<(i32, String, DumpWith)> {
+        self.state.dump.take()
+    }
+
+    /// The delay between samples in seconds and the ewma weight, if either
+    /// was changed since the last call.
+    pub fn take_tuning(&mut self) -> Option<(f64, f64)> {
+        let s = &mut self.state;
+        std::mem::take(&mut s.retune).then_some((s.sample_secs, s.ewma_weight))
+    }
+
+    /// Note that samples are being recorded to `path`, or no longer.
+    pub fn set_recording(&mut self, path: Option<String>) {
+        self.state.recording = path;
+    }
+
+    /// Note that `cmd` is being watched, and dies with sparktop.
+    pub fn set_watching(&mut self, cmd: Option<String>) {
+        self.state.watching = cmd;
+    }
+
+    /// Whether stopping the recording was asked for since the last call.
+    pub fn take_stop_recording(&mut self) -> bool {
+        std::mem::take(&mut self.state.stop_recording)
+    }
+
+    /// Whether a JSON export was asked for since the last call.
+    pub fn take_export(&mut self) -> bool {
+        std::mem::take(&mut self.state.export)
+    }
+
+    /// A heavy column (see Column::heavy) asked to be shown since the last
+    /// call: read it once to see what it costs, then pass that to
+    /// ask_cadence.
+    pub fn take_cost_probe(&mut self) -> Option<Column> {
+        self.state.cost_probe.take()
+    }
+
+    /// Say what reading `col` costs, and ask how often to read it; Enter
+    /// keeps `every`, how often it's read now.
+    pub fn ask_cadence(&mut self, col: Column, cost: Duration, every: u64) {
+        self.state.confirm_cadence = Some(ConfirmCadence {
+            column: col,
+            cost,
+            every,
+        });
+    }
+
+    /// The heavy column shown since the last call, and how many samples
+    /// apart to read it (see SProcs::set_every).
+    pub fn take_cadence(&mut self) -> Option<(Column, u64)> {
+        self.state.cadence.take()
+    }
+
+    /// Columns whose values weren't read on the latest sample, to draw dim
+    /// (see SProcs::stale).
+    pub fn set_stale(&mut self, stale: Vec<Column>) {
+        self.state.stale = stale;
+    }
+
+    /// The process to freeze or thaw, if asked since the last call.
+    pub fn take_freeze(&mut self) -> Option<i32> {
+        self.state.freeze.take()
+    }
+
+    /// The bookmarks, if they've changed since the last call.
+    pub fn take_changed_bookmarks(&mut self) -> Option<Vec<String>> {
+        std::mem::take(&mut self.state.bookmarks_changed).then(|| self.state.bookmarks.clone())
+    }
+
+    pub fn set_renames(&mut self, renames: Renames) {
+        self.state.renames = renames;
+    }
+
+    pub fn set_row_rules(&mut self, rules: Vec<RowRule>) {
+        self.state.row_rules = rules;
+    }
+
+    /// The --script's column names, shown in every table, and its
+    /// highlight styles, by index (see SProc::script_highlights).
+    pub fn set_script(&mut self, columns: Vec<String>, styles: Vec<Style>) {
+        if !columns.is_empty() {
+            for t in &mut self.state.panes {
+                if !t.columns.contains(&Column::Script) {
+                    t.columns.push(Column::Script);
+                    t.columns
+                        .sort_by_key(|c| Column::ALL.iter().position(|x| x == c));
+                }
+            }
+        }
+        self.state.script_columns = columns;
+        self.state.script_styles = styles;
+    }
+
+    /// Named filters, on keys 1-9 in order.
+    pub fn set_saved_filters(&mut self, filters: Vec<(String, RowCond)>) {
+        self.state.saved_filters = filters;
+    }
+
+    /// Screen-reader friendly: no sparklines, gauges or box drawing, and rows
+    /// only move when re-sorted.
+    pub fn set_accessible(&mut self, accessible: bool) {
+        self.state.accessible = accessible;
+        for t in &mut self.state.panes {
+            t.group_headers = !accessible;
+        }
+    }
+
+    pub fn set_breakpoints(&mut self, mut breakpoints: Vec<Breakpoint>) {
+        breakpoints.sort_by_key(|b| b.min_width);
+        self.state.breakpoints = breakpoints;
+        // now, so that later changes (e.g. startup commands) apply on top.
+        if let Err(e) = self.fit_width() {
+            log::debug!("terminal size: {}", e);
+        }
+    }
+
+    fn fit_width(&mut self) -> Result<()> {
+        let width = self.terminal.size()?.width;
+        let npanes = self.state.panes.len();
+        for i in 0..npanes {
+            self.state.fit_width(i, width / npanes as u16);
+        }
+        Ok(())
+    }
+
+    /// Whether any sort follows system pressure (see ViewState::set_pressure).
+    pub fn smart_sort(&self) -> bool {
+        self.state.panes.iter().any(|t| t.smart_sort.is_some())
+    }
+
+    pub fn set_pressure(&mut self, pressure: Pressure) {
+        self.state.set_pressure(pressure);
+    }
+
+    pub fn disable_smart_sort(&mut self, why: String) {
+        for t in &mut self.state.panes {
+            t.smart_sort = None;
+        }
+        self.state.alerts.push(why);
+    }
+
+    pub fn set_sample_interval(&mut self, secs: f64) {
+        self.state.sample_secs = secs;
+    }
+
+    /// Draw without color, for NO_COLOR and terminals without it: bold and
+    /// dim stand in for the colors that matter.
+    pub fn set_monochrome(&mut self, on: bool) {
+        self.state.monochrome = on;
+    }
+
+    /// The delay sampling has adapted to, if it's adaptive.
+    pub fn set_adaptive_delay(&mut self, secs: Option<f64>) {
+        self.state.adaptive_secs = secs;
+    }
+
+    /// Draw history sparklines two samples to a cell, in braille.
+    pub fn set_braille(&mut self, on: bool) {
+        self.state.braille = on;
+    }
+
+    /// Draw sparklines with `bars`, and the columns in `column_bars` with
+    /// theirs (see config::BarGlyphs).
+    pub fn set_bars(&mut self, bars: Bars, column_bars: HashMap<Column, Bars>) {
+        self.state.bars = bars;
+        self.state.column_bars = column_bars;
+    }
+
+    /// Where cpu and run queue wait colors change, if not the defaults.
+    pub fn set_color_levels(&mut self, levels: ColorLevels) {
+        self.state.colors.levels = levels;
+    }
+
+    /// The colors to draw in, if not the dark preset.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.state.colors.theme = theme;
+    }
+
+    /// Draw in plain ASCII only, for serial consoles and the like: charts in
+    /// dots rather than braille, and anything else redrawn with
+    /// render::to_ascii. Pair it with `Bars::ascii()`.
+    pub fn set_ascii(&mut self, on: bool) {
+        self.state.ascii = on;
+    }
+
+    pub fn set_ewma_weight(&mut self, weight: f64) {
+        self.state.ewma_weight = weight;
+    }
+
+    pub fn set_bandwidth(&mut self, groups: Vec<(String, Vec<f64>)>) {
+        self.state.bandwidth = Some(groups);
+    }
+
+    pub fn set_system(&mut self, system: SystemStats) {
+        self.state.system = Some(system);
+    }
+
+    pub fn set_history(&mut self, history: History) {
+        self.state.history = history;
+    }
+
+    /// The focused table's selected pid, if any.
+    pub fn selected(&self) -> Option<i32> {
+        self.state.table().selected
+    }
+
+    /// Whether the plain table is showing: no palette, search, help or zoom
+    /// over it, and no alert. Esc gets back here from anywhere.
+    pub fn at_top(&self) -> bool {
+        let s = &self.state;
+        s.palette.is_none()
+            && s.search.is_none()
+            && s.confirm_dump.is_none()
+            && s.confirm_cadence.is_none()
+            && !s.confirm_quit
+            && !s.help
+            && !s.zoom
+            && !s.cores
+            && s.alerts.current().is_none()
+    }
+
+    /// The pid being zoomed in on, if any.
+    pub fn zoomed(&self) -> Option<i32> {
+        self.state.table().selected.filter(|_| self.state.zoom)
+    }
+
+    pub fn set_cgroup_io(&mut self, io: Option<Result<IoSummary, String>>) {
+        self.state.cgroup_io = io;
+    }
+
+    /// Whether the view is paused: sampling carries on, but what's drawn
+    /// should stay as it was, so it can be looked over without rows moving.
+    pub fn paused(&self) -> bool {
+        self.state.paused
+    }
+
+    /// The zoom view's tab, if zoomed in.
+    pub fn zoom_tab(&self) -> Option<ZoomTab> {
+        Some(self.state.zoom_tab).filter(|_| self.state.zoom)
+    }
+
+    pub fn set_kernel_stacks(&mut self, stacks: Option<StackSummary>) {
+        self.state.kernel_stacks = stacks;
+    }
+
+    /// Whether any table is grouped `by`.
+    pub fn grouping(&self, by: Grouping) -> bool {
+        self.state.panes.iter().any(|t| t.group_by == Some(by))
+    }
+
+    /// Whether any table shows `col`.
+    pub fn showing(&self, col: Column) -> bool {
+        self.state.panes.iter().any(|t| t.columns.contains(&col))
+    }
+
+    /// Whether user/kernel cpu is on screen for every process, not just the
+    /// zoomed one (see SProcs::set_cpu_split).
+    pub fn splitting_cpu(&self) -> bool {
+        self.state.panes.iter().any(|t| {
+            t.columns.contains(&Column::Cpu) && t.cell_kind(Column::Cpu) == CellKind::Split
+        })
+    }
+
+    /// Whether cpu is on screen as a share of each process's quota (see
+    /// SProcs::set_cpu_quotas).
+    pub fn cpu_gauges(&self) -> bool {
+        self.state.panes.iter().any(|t| {
+            t.columns.contains(&Column::Cpu) && t.cell_kind(Column::Cpu) == CellKind::Gauge
+        })
+    }
+
+    /// Whether memory forecasts are on screen (see SProcs::set_mem_limits).
+    pub fn forecasting(&self) -> bool {
+        self.state.zoom || self.showing(Column::MemEta)
+    }
+
+    /// Whether exited children's cpu is being counted in their parents'.
+    pub fn cumulative(&self) -> bool {
+        self.state.panes.iter().any(|t| t.cumulative)
+    }
+
+    /// Show `alert` until dismissed, and keep it in the alert log.
+    pub fn set_alert(&mut self, alert: String) {
+        self.state.alerts.push(alert);
+    }
+
+    // a permanent note in the footer, e.g. about platform limitations.
+    pub fn add_note(&mut self, note: String) {
+        self.state.notes.push(note);
+    }
+
+    /// Run commands, as from the palette.
+    pub fn run(&mut self, cmds: Vec<Command>) -> Next {
+        for cmd in cmds {
+            if self.state.run(cmd) == Next::Quit {
+                return Next::Quit;
+            }
+        }
+        Next::Continue
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Next {
+        if std::mem::take(&mut self.state.confirm_quit) {
+            match key.code {
+                KeyCode::Char('y') => return Next::Quit,
+                KeyCode::Char('s') if self.state.recording.is_some() => {
+                    self.state.stop_recording = true
+                }
+                _ => (),
+            }
+            return Next::Continue;
+        }
+        if let Some(c) = self.state.confirm_dump.take() {
+            if key.code == KeyCode::Char('y') {
+                self.state.dump = Some((c.pid, c.name, c.with));
+            }
+            return Next::Continue;
+        }
+        if let Some(c) = self.state.confirm_cadence.take() {
+            let every = match key.code {
+                KeyCode::Char(d @ '1'..='9') => d.to_digit(10),
+                KeyCode::Enter => Some(c.every as u32),
+                _ => None,
             };
-            match self.state.sort_dir {
-                Dir::Asc => OrdFloat(val),
-                Dir::Desc => OrdFloat(-val),
+            if let Some(n) = every {
+                self.state.show_heavy(&c, n as u64);
+            }
+            return Next::Continue;
+        }
+        if self.state.palette.is_some() {
+            return self.handle_palette_key(key);
+        }
+        if self.state.search.is_some() {
+            return self.state.handle_search_key(key);
+        }
+        match action::for_key(key) {
+            Some(a) => {
+                self.state.alerts.dismiss();
+                let t = self.state.table();
+                let a = match a {
+                    // again to reverse, like clicking its header; but in
+                    // accessible mode, again re-sorts held rows.
+                    Action::SortBy(m)
+                        if m == t.sort_by && t.smart_sort.is_none() && !self.state.accessible =>
+                    {
+                        Action::FlipSort
+                    }
+                    a => a,
+                };
+                self.state.apply(a)
+            }
+            None => {
+                self.state.alerts.push(format!("unhandled key: {:?}", key));
+                Next::Continue
             }
+        }
+    }
+
+    /// Clicking a header sorts by it (again to reverse), clicking a row
+    /// selects it, and the wheel moves the selection. Only over the tables,
+    /// with nothing open over them.
+    pub fn handle_mouse(&mut self, m: MouseEvent) -> Next {
+        let s = &self.state;
+        if s.zoom || s.help || s.palette.is_some() || s.search.is_some() {
+            return Next::Continue;
+        }
+        if s.confirm_dump.is_some() || s.confirm_cadence.is_some() || s.confirm_quit {
+            return Next::Continue;
+        }
+        let hit = self.targets.iter().find(|(r, _, _)| {
+            (r.left()..r.right()).contains(&m.column) && (r.top()..r.bottom()).contains(&m.row)
         });
+        let (pane, target) = match hit {
+            Some(&(_, pane, target)) => (pane, target),
+            None => return Next::Continue,
+        };
+        self.state.focus = pane;
+        match (m.kind, target) {
+            (MouseEventKind::ScrollDown, _) => {
+                let moves = &mut self.state.table_mut().moves;
+                moves.extend(std::iter::repeat_n(Move::Down, WHEEL_ROWS));
+            }
+            (MouseEventKind::ScrollUp, _) => {
+                let moves = &mut self.state.table_mut().moves;
+                moves.extend(std::iter::repeat_n(Move::Up, WHEEL_ROWS));
+            }
+            (MouseEventKind::Down(MouseButton::Left), Target::Header(col)) => {
+                self.state.alerts.dismiss();
+                return match col.metric() {
+                    Some(m) if m == self.state.table().sort_by => {
+                        self.state.apply(Action::FlipSort)
+                    }
+                    Some(m) => self.state.apply(Action::SortBy(m)),
+                    None => Next::Continue,
+                };
+            }
+            (MouseEventKind::Down(MouseButton::Left), Target::Row(pid)) => {
+                self.state.table_mut().selected = Some(pid);
+            }
+            _ => (),
+        }
+        Next::Continue
     }
 
-    pub fn handle_key(&mut self, key: KeyEvent) -> Next {
-        let mut next = Next::Continue;
-        let mut unhandled = false;
-        match key.code {
-            KeyCode::Char('N') => self.state.sort_by = Metric::Pid,
-            KeyCode::Char('M') => self.state.sort_by = Metric::Mem,
-            KeyCode::Char('P') => self.state.sort_by = Metric::Cpu,
-            KeyCode::Char('R') => self.state.sort_by = Metric::DiskRead,
-            KeyCode::Char('W') => self.state.sort_by = Metric::DiskWrite,
-            KeyCode::Char('D') => self.state.sort_by = Metric::DiskTotal,
-            KeyCode::Char('I') => self.state.sort_dir.flip(),
-            KeyCode::Char('q') => next = Next::Quit,
-            KeyCode::Esc => (), // clear alert
-            _ => unhandled = true,
-        }
-
-        if unhandled {
-            self.state.alert = Some(format!("unhandled key: {:?}", key));
-        } else {
-            self.state.alert = None;
+    fn handle_palette_key(&mut self, key: KeyEvent) -> Next {
+        let palette = match &mut self.state.palette {
+            Some(p) => p,
+            None => return Next::Continue,
+        };
+        match palette.input.handle_key(key) {
+            InputEvent::Edited => palette.selected = 0,
+            InputEvent::Cancel => self.state.palette = None,
+            InputEvent::Submit => {
+                let query = palette.input.text.clone();
+                let chosen = action::complete(&query)
+                    .get(palette.selected)
+                    .map(|&(_, a)| a);
+                self.state.palette = None;
+                // a full command (maybe with arguments) as typed, or else the
+                // chosen completion.
+                match (action::parse_command(&query), chosen) {
+                    (Ok(cmds), _) => return self.run(cmds),
+                    (Err(_), Some(a)) => return self.state.apply(a),
+                    (Err(e), None) => self.state.alerts.push(e),
+                }
+            }
+            InputEvent::Ignored => match key.code {
+                KeyCode::Up | KeyCode::BackTab => {
+                    palette.selected = palette.selected.saturating_sub(1)
+                }
+                KeyCode::Down | KeyCode::Tab => palette.selected += 1,
+                _ => (),
+            },
+        }
+        if let Some(palette) = &mut self.state.palette {
+            let n = action::complete(&palette.input.text).len();
+            palette.selected = palette.selected.min(n.saturating_sub(1));
         }
+        Next::Continue
+    }
+
+    // bookmark the selected (or top) process by name, or drop the bookmarks
+    // matching it.
+    fn toggle_bookmark(&mut self, rows: &[&SProc]) {
+        let selected = self.state.table().selected;
+        let sp = match rows
+            .iter()
+            .find(|sp| Some(sp.pid) == selected)
+            .or(rows.first())
+        {
+            Some(sp) => sp,
+            None => return,
+        };
+        let before = self.state.bookmarks.len();
+        self.state.bookmarks.retain(|b| !sp.matches(b));
+        self.state
+            .alerts
+            .push(if self.state.bookmarks.len() < before {
+                format!("removed bookmarks matching {}", sp.name)
+            } else {
+                self.state.bookmarks.push(sp.name.clone());
+                format!("bookmarked {}: pinned to the top while running", sp.name)
+            });
+        self.state.bookmarks_changed = true;
+    }
 
-        next
+    // hide the selected process, and any others by the same name, from now
+    // on.
+    fn mute(&mut self, sprocs: &[&SProc]) {
+        let selected = self.state.table().selected;
+        let sp = match sprocs.iter().find(|sp| Some(sp.pid) == selected) {
+            Some(sp) => sp,
+            None => {
+                self.state.alerts.push("no process selected".into());
+                return;
+            }
+        };
+        self.state.muted.push(sp.name.clone());
+        self.state.muted_changed = true;
+        self.state.table_mut().selected = None;
+        self.state.alerts.push(format!(
+            "muted {}: hidden from now on (\"unmute all\" in the palette to undo)",
+            sp.name
+        ));
     }
 
-    pub fn draw(&mut self, sprocs: &mut Vec<&SProc>) -> Result<()> {
-        self.sort(sprocs);
+    pub fn draw(&mut self, sprocs: &[&SProc], events: &LifetimeLog) -> Result<()> {
+        self.fit_width()?;
+        if std::mem::take(&mut self.state.mute_pending) {
+            self.mute(sprocs);
+        }
+        let muted = &self.state.muted;
+        let sprocs: Vec<&SProc> = sprocs
+            .iter()
+            .copied()
+            .filter(|sp| is_own(sp, &self.state) || !muted.contains(&sp.name))
+            .collect();
+        let sprocs = &sprocs[..];
+        let npanes = self.state.panes.len();
+        // each table's rows, grouped, filtered and sorted its own way.
+        let totaled: Vec<&SProc> = sprocs
+            .iter()
+            .copied()
+            .filter(|sp| self.state.own_in_totals || !is_own(sp, &self.state))
+            .collect();
+        let folded: Vec<Vec<SProc>> = self
+            .state
+            .panes
+            .iter()
+            .map(|t| t.group_by.map_or(vec![], |g| group::fold(&totaled, g)))
+            .collect();
+        let state = &self.state;
+        let mut rows: Vec<Vec<&SProc>> = self
+            .state
+            .panes
+            .iter()
+            .zip(&folded)
+            .map(|(t, folded)| table_rows(t, &grouped(t, sprocs, folded, state), state))
+            .collect();
+        if std::mem::take(&mut self.state.bookmark_pending) {
+            let focused = std::mem::take(&mut rows[self.state.focus]);
+            self.toggle_bookmark(&focused);
+            let (t, folded) = (self.state.table(), &folded[self.state.focus]);
+            rows[self.state.focus] =
+                table_rows(t, &grouped(t, sprocs, folded, &self.state), &self.state);
+        }
+        if self.state.accessible {
+            for (t, rows) in self.state.panes.iter_mut().zip(&mut rows) {
+                keep_row_order(t, rows);
+            }
+        }
+        for (t, rows) in self.state.panes.iter_mut().zip(&rows) {
+            for m in std::mem::take(&mut t.moves) {
+                if rows.is_empty() {
+                    break;
+                }
+                let at = rows.iter().position(|sp| Some(sp.pid) == t.selected);
+                t.selected = Some(rows[m.from(at, rows.len(), t.page)].pid);
+            }
+            for j in std::mem::take(&mut t.jumps) {
+                match jump(j, rows, t.selected) {
+                    Ok(pid) => t.selected = Some(pid),
+                    Err(e) => self.state.alerts.push(e),
+                }
+            }
+        }
+        let focused = &rows[self.state.focus];
+        let selected = self.state.table().selected;
+        let selected = focused.iter().find(|sp| Some(sp.pid) == selected);
+        if let Some(change) = self.state.ionice_pending.take() {
+            match selected {
+                Some(sp) => self.state.ionice = Some((sp.pid, change)),
+                None => self.state.alerts.push("no process selected".into()),
+            }
+        }
+        if std::mem::take(&mut self.state.freeze_pending) {
+            match selected {
+                Some(sp) => self.state.freeze = Some(sp.pid),
+                None => self.state.alerts.push("no process selected".into()),
+            }
+        }
+        if let Some(with) = self.state.dump_pending.take() {
+            match selected {
+                Some(sp) => {
+                    self.state.confirm_dump = Some(ConfirmDump {
+                        pid: sp.pid,
+                        name: sp.name.clone(),
+                        with,
+                    })
+                }
+                None => self.state.alerts.push("no process selected".into()),
+            }
+        }
+        // zooming in with nothing selected picks the top row; likewise
+        // expanding a row.
+        if self.state.zoom && self.state.table().selected.is_none() {
+            self.state.table_mut().selected = focused.first().map(|sp| sp.pid);
+        }
+        for (t, rows) in self.state.panes.iter_mut().zip(&rows) {
+            if t.expanded && t.selected.is_none() {
+                t.selected = rows.first().map(|sp| sp.pid);
+            }
+        }
+        let mut notes = self.state.notes.clone();
+        if self.state.paused {
+            notes.insert(0, "PAUSED (space to resume)".into());
+        }
+        if !self.state.muted.is_empty() {
+            notes.push(format!("muted: {}", self.state.muted.join(", ")));
+        }
         // erhm, borrow checker workarounds...
-        let alert = self.state.alert.clone();
-        let sort_by = self.state.sort_by;
+        let alert = self.state.alerts.current().map(String::from);
+        let state = &self.state;
+        // each table's (v_scroll, page) as drawn, and where its parts are.
+        let mut scrolls = vec![None; npanes];
+        let mut targets = vec![vec![]; npanes];
         self.terminal.draw(|f| {
             let main_constraints = if alert.is_some() {
                 vec![Constraint::Min(1), Constraint::Min(3)]
@@ -79,95 +742,2191 @@ impl View {
             };
             let rects = Layout::default()
                 .constraints(main_constraints)
-                .split(f.size());
+                .split(f.area());
 
             // Draw main panel.
-            let main = rects[0];
-            let proc_table = ProcTable::new(sprocs, sort_by);
-            f.render_widget(proc_table.get_table(), main);
+            let mut main = rects[0];
+            if state.zoom {
+                let sp = focused
+                    .iter()
+                    .find(|sp| Some(sp.pid) == state.table().selected);
+                draw_zoom(f, sp.copied(), state, main);
+                if let Some(alert) = alert {
+                    let msg = Paragraph::new(alert).block(Block::default().borders(Borders::ALL));
+                    f.render_widget(msg, rects[1])
+                }
+                restyle(f, state);
+                return;
+            }
+            if state.summary {
+                let rects = Layout::default()
+                    .constraints(vec![Constraint::Length(SUMMARY_HEIGHT), Constraint::Min(3)])
+                    .split(main);
+                let system = match (&state.system, own_row(sprocs, state)) {
+                    (Some(s), Some(own)) if !state.own_in_totals => Some(s.without(own)),
+                    (s, _) => s.clone(),
+                };
+                draw_summary(f, system.as_ref(), state, rects[0]);
+                main = rects[1];
+            }
+            if state.cores {
+                draw_cores(f, state, main);
+            } else {
+                if state.event_log {
+                    let rects = Layout::default()
+                        .constraints(vec![Constraint::Min(3), Constraint::Percentage(35)])
+                        .split(main);
+                    draw_event_log(f, events, &state.colors.theme, rects[1]);
+                    main = rects[0];
+                }
+                if state.alert_pane {
+                    let rects = Layout::default()
+                        .constraints(vec![Constraint::Min(3), Constraint::Percentage(30)])
+                        .split(main);
+                    draw_alerts(f, &state.alerts, state.alert_scroll, rects[1]);
+                    main = rects[0];
+                }
+                if state.ranking {
+                    let rects = Layout::default()
+                        .constraints(vec![Constraint::Min(3), Constraint::Percentage(40)])
+                        .split(main);
+                    draw_ranking(f, focused, state, rects[1]);
+                    main = rects[0];
+                }
+                if state.bandwidth_pane {
+                    let height = state.bandwidth.as_ref().map_or(1, |b| b.len().max(1)) as u16 + 2;
+                    let rects = Layout::default()
+                        .constraints(vec![Constraint::Min(3), Constraint::Length(height)])
+                        .split(main);
+                    draw_bandwidth(f, state.bandwidth.as_deref(), &state.bars, rects[1]);
+                    main = rects[0];
+                }
+
+                if npanes == 1 {
+                    let (scroll, t) = draw_table(f, focused, &state.panes[0], state, &notes, main);
+                    scrolls[0] = Some(scroll);
+                    targets[0] = t;
+                } else {
+                    // notes go under both tables.
+                    if !notes.is_empty() {
+                        let rects = Layout::default()
+                            .constraints(vec![Constraint::Min(0), Constraint::Length(1)])
+                            .split(main);
+                        f.render_widget(
+                            Paragraph::new(notes.join(" | "))
+                                .style(Style::default().add_modifier(Modifier::DIM)),
+                            rects[1],
+                        );
+                        main = rects[0];
+                    }
+                    let panes = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(vec![Constraint::Ratio(1, npanes as u32); npanes])
+                        .split(main);
+                    for (i, (t, area)) in state.panes.iter().zip(panes.iter().copied()).enumerate()
+                    {
+                        let style = if i == state.focus {
+                            Style::default().add_modifier(Modifier::BOLD)
+                        } else {
+                            Style::default().add_modifier(Modifier::DIM)
+                        };
+                        let block = Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(style)
+                            .title(Span::styled(format!("by {}", t.sort_by.name()), style));
+                        let inner = block.inner(area);
+                        f.render_widget(block, area);
+                        let (scroll, t) = draw_table(f, &rows[i], t, state, &[], inner);
+                        scrolls[i] = Some(scroll);
+                        targets[i] = t;
+                    }
+                }
+            }
 
             // Draw alert.
             if let Some(alert) = alert {
                 let msg = Paragraph::new(alert).block(Block::default().borders(Borders::ALL));
                 f.render_widget(msg, rects[1])
             }
+
+            if state.help {
+                draw_help(f, state);
+            }
+
+            // Draw command palette over the bottom of the screen.
+            if let Some(palette) = &state.palette {
+                draw_palette(f, palette);
+            }
+            if let Some(search) = &state.search {
+                draw_search(f, &search.input);
+            }
+            if state.confirm_quit {
+                draw_confirm(f, &quit_question(state));
+            }
+            if let Some(c) = &state.confirm_dump {
+                draw_confirm(
+                    f,
+                    &format!(
+                        "core dump {} ({}) with {}? y/n",
+                        c.pid,
+                        c.name,
+                        c.with.name()
+                    ),
+                );
+            }
+            if let Some(c) = &state.confirm_cadence {
+                draw_confirm(f, &cadence_question(c, state.sample_secs));
+            }
+            restyle(f, state);
         })?;
+        for (t, scroll) in self.state.panes.iter_mut().zip(scrolls) {
+            if let Some((top, page)) = scroll {
+                t.v_scroll = top;
+                t.page = page;
+            }
+        }
+        self.targets = targets
+            .into_iter()
+            .enumerate()
+            .flat_map(|(pane, ts)| ts.into_iter().map(move |(r, t)| (r, pane, t)))
+            .collect();
         Ok(())
     }
 }
 
-struct ProcTable<'a> {
-    header: Vec<String>,
-    sprocs: &'a [&'a SProc],
+// The processes a table shows, in its order: bookmarked ones first.
+// with grouping on, the group rows stand in for their members.
+// sparktop's own row is shown on its own either way.
+fn grouped<'a>(
+    t: &TableState,
+    sprocs: &[&'a SProc],
+    folded: &'a [SProc],
+    state: &ViewState,
+) -> Vec<&'a SProc> {
+    match t.group_by {
+        Some(g) => folded
+            .iter()
+            .chain(
+                sprocs
+                    .iter()
+                    .copied()
+                    .filter(|sp| g.key(sp).is_none() || is_own(sp, state)),
+            )
+            .collect(),
+        None => sprocs.to_vec(),
+    }
 }
 
-impl<'a> ProcTable<'a> {
-    fn new(sprocs: &'a [&SProc], sort_by: Metric) -> Self {
-        use Metric::*;
-        let mut header = vec![String::from("pid"), String::from("process")];
-        header.extend(
-            [DiskRead, DiskWrite, Mem, Cpu]
-                .iter()
-                .map(|m| m.to_header_str(sort_by)),
-        );
-        header.push(String::from("cpu history"));
-        Self { header, sprocs }
-    }
-
-    fn get_table(&self) -> impl tui::widgets::Widget + '_ {
-        let rows = self.sprocs.iter().map(|sp| {
-            let mut liveness_style = Style::default();
-            if sp.is_dead() {
-                liveness_style = liveness_style.fg(tui::style::Color::Red);
-            }
-            Row::new(
-                vec![
-                    Span::styled(sp.pid.to_string(), liveness_style),
-                    Span::styled(sp.name.clone(), liveness_style),
-                    Span::from(render_metric(sp.disk_read_ewma)),
-                    Span::from(render_metric(sp.disk_write_ewma)),
-                    Span::from(render_metric(sp.mem_mb)),
-                    Span::from(render_metric(sp.cpu_ewma)),
-                    Span::from(render::render_vec(&sp.cpu_hist, 100.)),
-                ]
-                .into_iter(),
-            )
-        });
-        Table::new(rows)
-            .header(
-                // TODO: way to avoid making copy?
-                Row::new(self.header.to_vec())
-                    .style(Style::default().add_modifier(Modifier::UNDERLINED)),
-            )
-            .widths(&[
-                Constraint::Length(6),
-                Constraint::Length(24),
-                Constraint::Length(5),
-                Constraint::Length(5),
-                Constraint::Length(5),
-                Constraint::Length(4),
-                Constraint::Percentage(100),
-            ])
+// whether the table's name, saved and spike filters let `sp` through.
+fn passes_filters(t: &TableState, sp: &SProc, state: &ViewState) -> bool {
+    if matches!(&t.name_filter, Some(f) if !sp.matches_any_case(f)) {
+        return false;
+    }
+    let saved = t.saved_filter.and_then(|i| state.saved_filters.get(i));
+    if matches!(saved, Some((_, cond)) if !cond.matches(sp)) {
+        return false;
     }
+    !t.spike_filter
+        || stats::recent_spike(
+            &sp.cpu_history(t.cumulative),
+            CPU_MIN_SD,
+            RECENT_SPIKE_TICKS,
+        )
 }
 
-impl Metric {
-    fn to_header_str(self, sort_by: Metric) -> String {
-        use Metric::*;
-        let s = match self {
-            Pid => "pid",
-            Cpu => "cpu",
-            Mem => "mem",
-            DiskRead => "dr",
-            DiskWrite => "dw",
-            DiskTotal => "d+",
+fn table_rows<'a>(t: &TableState, sprocs: &[&'a SProc], state: &ViewState) -> Vec<&'a SProc> {
+    let mut rows = sprocs.to_vec();
+    rows.retain(|sp| is_own(sp, state) || passes_filters(t, sp, state));
+    sort_rows(&mut rows, t.sort_by, t.sort_dir, t.cumulative);
+    // stable, so each side keeps its sort order.
+    match t.dead_rows {
+        DeadRows::Interleaved => (),
+        DeadRows::Bottom => rows.sort_by_key(|sp| sp.is_dead()),
+        DeadRows::Top => rows.sort_by_key(|sp| !sp.is_dead()),
+    }
+    // sparktop's own row above the bookmarked ones.
+    let (mut pinned, rest): (Vec<_>, Vec<_>) =
+        rows.into_iter().partition(|sp| is_pinned(sp, state));
+    pinned.sort_by_key(|sp| !is_own(sp, state));
+    pinned.extend(rest);
+    pinned
+}
+
+/// Sort `rows` by `by`, breaking ties by pid (ascending, whichever way `dir`
+/// is). The order depends only on the rows' values, not the order they came
+/// in, so equal rows (e.g. all the idle ones at 0.0) don't trade places from
+/// tick to tick.
+pub fn sort_rows(rows: &mut [&SProc], by: Metric, dir: Dir, cumulative: bool) {
+    rows.sort_by_key(|&sp| {
+        let val = match by {
+            Metric::Pid => sp.pid as f64,
+            Metric::Cpu => sp.cpu(cumulative),
+            Metric::Mem => sp.mem_bytes as f64,
+            Metric::DiskRead => sp.disk_read_ewma,
+            Metric::DiskWrite => sp.disk_write_ewma,
+            Metric::DiskTotal => sp.disk_read_ewma + sp.disk_write_ewma,
+            Metric::Latency => sp.latency_ewma,
+            Metric::Wakeups => sp.wakeups_ewma,
+            // unreadable ones below those listening on nothing.
+            Metric::Ports => sp.ports.as_ref().map_or(-1., |p| p.len() as f64),
+            Metric::TreeCpu => sp.tree_cpu(cumulative),
         };
-        if sort_by == self || (sort_by == DiskTotal && (self == DiskRead || self == DiskWrite)) {
-            format!("*{}*", s)
-        } else {
-            String::from(s)
+        let val = match dir {
+            Dir::Asc => OrdFloat(val),
+            Dir::Desc => OrdFloat(-val),
+        };
+        (val, sp.pid)
+    });
+}
+
+// Rows seen last time keep their places, and new ones go after them (in
+// sorted order), until the sort changes.
+fn keep_row_order(t: &mut TableState, rows: &mut [&SProc]) {
+    let key = (t.sort_by, t.sort_dir);
+    if t.order_key == Some(key) {
+        let place: HashMap<i32, usize> = t
+            .row_order
+            .iter()
+            .enumerate()
+            .map(|(i, &pid)| (pid, i))
+            .collect();
+        rows.sort_by_key(|sp| place.get(&sp.pid).copied().unwrap_or(usize::MAX));
+    }
+    t.order_key = Some(key);
+    t.row_order = rows.iter().map(|sp| sp.pid).collect();
+}
+
+// bookmarked processes are pinned while they're running, and sparktop's own
+// process always.
+fn is_pinned(sp: &SProc, state: &ViewState) -> bool {
+    is_own(sp, state) || (!sp.is_dead() && state.bookmarks.iter().any(|b| sp.matches(b)))
+}
+
+fn is_own(sp: &SProc, state: &ViewState) -> bool {
+    Some(sp.pid) == state.own_pid
+}
+
+fn own_row<'a>(sprocs: &[&'a SProc], state: &ViewState) -> Option<&'a SProc> {
+    sprocs.iter().copied().find(|sp| is_own(sp, state))
+}
+
+// A process table with its group header and footer (status notes, hidden
+// columns, etc.), in `area`. Returns the rows scrolled off the top, and how
+// many rows fit.
+fn draw_table(
+    f: &mut crate::ui::Frame,
+    rows: &[&SProc],
+    t: &TableState,
+    state: &ViewState,
+    notes: &[String],
+    mut area: Rect,
+) -> ((usize, usize), Vec<(Rect, Target)>) {
+    let proc_table = ProcTable::new(t, state, area.width);
+    let mut footer = notes.to_vec();
+    if let Some(smart) = &t.smart_sort {
+        footer.push(match smart.pressure {
+            Some(p) => format!(
+                "sorting by pressure: {} (psi cpu {:.0}% mem {:.0}% io {:.0}%)",
+                t.sort_by.name(),
+                p.cpu,
+                p.memory,
+                p.io
+            ),
+            None => "sorting by pressure: waiting for psi".into(),
+        });
+    }
+    if t.spike_filter {
+        footer.push("showing only recent cpu spikes".into());
+    }
+    if let Some(f) = &t.name_filter {
+        footer.push(format!("filter: {}", f));
+    }
+    if let Some(i) = t.saved_filter {
+        if let Some((name, _)) = state.saved_filters.get(i) {
+            footer.push(format!("filter {} ({} to clear)", name, i + 1));
+        }
+    }
+    if t.cumulative {
+        footer.push("cumulative: cpu includes exited children".into());
+    }
+    if t.baseline {
+        footer.push("cpu history from each row's minimum".into());
+    }
+    if state.tuned {
+        footer.push(format!(
+            "sampling every {}s, ewma weight {}",
+            state.sample_secs, state.ewma_weight
+        ));
+    }
+    if let Some(secs) = state.adaptive_secs.filter(|&s| s != state.sample_secs) {
+        footer.push(format!("quiet, so sampling every {}s", secs));
+    }
+    for col in &t.columns {
+        if let Some(n) = state.cadences.get(col) {
+            footer.push(format!("{} read every {} samples", col.header(), n));
         }
     }
+    if let Some(g) = t.group_by {
+        footer.push(format!("grouped by {}", g.name()));
+    }
+    if state.accessible {
+        footer.push(format!(
+            "rows held in place, sort by {} again to re-sort",
+            t.sort_by.name()
+        ));
+    }
+    if !proc_table.hidden.is_empty() {
+        let names: Vec<&str> = proc_table.hidden.iter().map(|c| c.header()).collect();
+        footer.push(format!(
+            "hidden (terminal too narrow, ←/→ to scroll): {}",
+            names.join(" ")
+        ));
+    }
+    if !footer.is_empty() {
+        let rects = Layout::default()
+            .constraints(vec![Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+        f.render_widget(
+            Paragraph::new(footer.join(" | ")).style(Style::default().add_modifier(Modifier::DIM)),
+            rects[1],
+        );
+        area = rects[0];
+    }
+    if t.name_filter.is_some() || t.saved_filter.is_some() || t.spike_filter {
+        let rects = Layout::default()
+            .constraints(vec![Constraint::Length(1), Constraint::Min(0)])
+            .split(area);
+        // sparktop's own row is there whether it passed the filters or not.
+        let totaled: Vec<&SProc> = rows
+            .iter()
+            .copied()
+            .filter(|sp| {
+                !is_own(sp, state) || (state.own_in_totals && passes_filters(t, sp, state))
+            })
+            .collect();
+        f.render_widget(
+            Paragraph::new(filtered_total(&totaled, t.cumulative, state)),
+            rects[0],
+        );
+        area = rects[1];
+    }
+    if let Some(groups) = proc_table.group_header(area.width) {
+        let rects = Layout::default()
+            .constraints(vec![Constraint::Length(1), Constraint::Min(0)])
+            .split(area);
+        f.render_widget(Paragraph::new(groups), rects[0]);
+        area = rects[1];
+    }
+    let pinned = rows.iter().take_while(|sp| is_pinned(sp, state)).count();
+    // rule off the pinned rows, and the dead ones if they're kept apart.
+    let mut dividers = vec![pinned];
+    let dead = rows.iter().filter(|sp| sp.is_dead()).count();
+    match t.dead_rows {
+        DeadRows::Interleaved => (),
+        DeadRows::Bottom => dividers.push(rows.len() - dead),
+        DeadRows::Top => dividers.push(pinned + dead),
+    }
+    // scroll to keep the selection in view.
+    let selected = rows.iter().position(|sp| Some(sp.pid) == t.selected);
+    let mut lines = (area.height as usize).saturating_sub(1); // under the header
+    if t.expanded && selected.is_some() {
+        lines = lines.saturating_sub(EXPANDED_HEIGHT as usize);
+    }
+    let page = lines / proc_table.row_height as usize;
+    let top = scroll_rows(t.v_scroll, selected, page, rows.len());
+    let rows = &rows[top..];
+    let pinned = pinned.saturating_sub(top);
+    let dividers: Vec<usize> = dividers.iter().filter_map(|d| d.checked_sub(top)).collect();
+    // an expanded row's details go right under it, pushing the rest down.
+    let expanded = rows
+        .iter()
+        .position(|sp| t.expanded && Some(sp.pid) == t.selected);
+    let h = proc_table.row_height;
+    // the whole table last, so its rows and headers come first.
+    let mut targets = vec![];
+    match expanded {
+        Some(i) => {
+            let rects = Layout::default()
+                .constraints(vec![
+                    Constraint::Length((i as u16 + 1) * h + 1), // with the header
+                    Constraint::Length(EXPANDED_HEIGHT),
+                    Constraint::Min(0),
+                ])
+                .split(area);
+            let selected = Some(rows[i].pid);
+            let top = proc_table.get_table(&rows[..=i], true, selected, pinned, &dividers);
+            f.render_widget(top, rects[0]);
+            proc_table.draw_histories(f, &rows[..=i], true, rects[0]);
+            targets.extend(proc_table.targets(&rows[..=i], true, rects[0]));
+            draw_expanded(f, rows[i], t.cumulative, state, rects[1]);
+            let below: Vec<usize> = dividers
+                .iter()
+                .filter_map(|d| d.checked_sub(i + 1))
+                .collect();
+            let rest = proc_table.get_table(
+                &rows[i + 1..],
+                false,
+                None,
+                pinned.saturating_sub(i + 1),
+                &below,
+            );
+            f.render_widget(rest, rects[2]);
+            proc_table.draw_histories(f, &rows[i + 1..], false, rects[2]);
+            targets.extend(proc_table.targets(&rows[i + 1..], false, rects[2]));
+        }
+        None => {
+            f.render_widget(
+                proc_table.get_table(rows, true, t.selected, pinned, &dividers),
+                area,
+            );
+            proc_table.draw_histories(f, rows, true, area);
+            targets.extend(proc_table.targets(rows, true, area));
+        }
+    }
+    targets.push((area, Target::Table));
+    ((top, page), targets)
+}
+
+// The pid of the shown row related to the selected one by `j`; Err is a
+// message for the user.
+fn jump(j: Jump, rows: &[&SProc], selected: Option<i32>) -> Result<i32, String> {
+    let at = rows
+        .iter()
+        .position(|sp| Some(sp.pid) == selected)
+        .ok_or("select a process first")?;
+    let sp = rows[at];
+    let found = match j {
+        Jump::Parent => rows.iter().find(|p| Some(p.pid) == sp.ppid),
+        Jump::Child => rows.iter().find(|c| c.ppid == Some(sp.pid)),
+        Jump::Sibling => rows[at + 1..]
+            .iter()
+            .chain(&rows[..at])
+            .find(|s| s.name == sp.name),
+    };
+    found
+        .map(|r| r.pid)
+        .ok_or_else(|| format!("no {} of {} shown", j.name(), sp.name))
+}
+
+// The filtered rows all together: their cpu summed at each tick, as a
+// sparkline, and their memory.
+fn filtered_total(rows: &[&SProc], cumulative: bool, state: &ViewState) -> Line<'static> {
+    let mut cpu: Vec<f64> = vec![];
+    for sp in rows {
+        for (i, x) in sp.cpu_history(cumulative).into_iter().enumerate() {
+            match cpu.get_mut(i) {
+                Some(total) => *total += x,
+                None => cpu.push(x),
+            }
+        }
+    }
+    let mem: f64 = rows.iter().map(|sp| sp.mem_bytes as f64).sum();
+    let mut spans = vec![Span::styled(
+        format!(
+            "{} matching: cpu {:.1}% mem {}  ",
+            rows.len(),
+            cpu.first().copied().unwrap_or(0.),
+            render_bytes(mem)
+        ),
+        Style::default().add_modifier(Modifier::DIM),
+    )];
+    let max = cpu.iter().copied().fold(100., f64::max);
+    spans.extend(render::render_vec_colored(&cpu, max, &state.bars, |c| state.colors.cpu(c)).spans);
+    Line::from(spans)
+}
+
+// the first of `len` rows to show, `page` at a time: where it was (`top`),
+// unless that would leave `selected` out of view or space below the last row.
+fn scroll_rows(top: usize, selected: Option<usize>, page: usize, len: usize) -> usize {
+    let top = top.min(len.saturating_sub(page));
+    match selected {
+        Some(i) if i < top => i,
+        Some(i) if i >= top + page => (i + 1).saturating_sub(page.max(1)),
+        _ => top,
+    }
+}
+
+// lines of detail under an expanded row.
+const EXPANDED_HEIGHT: u16 = 5;
+
+// The command line, owner and age of a process, and a taller cpu history.
+fn draw_expanded(
+    f: &mut crate::ui::Frame,
+    sp: &SProc,
+    cumulative: bool,
+    state: &ViewState,
+    area: Rect,
+) {
+    let dim = Style::default().add_modifier(Modifier::DIM);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let user = sp
+        .uid
+        .map(|uid| platform::user_name(uid).unwrap_or_else(|| uid.to_string()));
+    let started = match sp.start_time {
+        0 => String::from("?"),
+        t => format!("{} ago", render_duration(now.saturating_sub(t) as f64)),
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!(
+                "  {}",
+                if sp.cmd.is_empty() {
+                    sp.name.clone()
+                } else {
+                    sp.cmd.join(" ")
+                }
+            ),
+            dim,
+        )),
+        Line::from(Span::styled(
+            format!(
+                "  user {}  started {}",
+                user.as_deref().unwrap_or("?"),
+                started
+            ),
+            dim,
+        )),
+    ];
+    let height = EXPANDED_HEIGHT as usize - lines.len();
+    for line in render::render_vec_tall(
+        &sp.cpu_history(cumulative),
+        100.,
+        height,
+        &state.bars,
+        |c| state.colors.cpu(c),
+    ) {
+        let mut spans = vec![Span::raw("  ")];
+        spans.extend(line.spans);
+        lines.push(Line::from(spans));
+    }
+    f.render_widget(Paragraph::new(lines), area);
+}
+
+struct ProcTable {
+    columns: Vec<(Column, CellKind)>,
+    widths: Vec<Constraint>,
+    hidden: Vec<Column>, // dropped to fit the terminal width
+    group_headers: bool,
+    row_height: u16, // 2 with a history line under each row
+    accessible: bool,
+    cumulative: bool,
+    baseline: bool,
+    braille: bool,
+    bars: Bars,
+    column_bars: HashMap<Column, Bars>,
+    ascii: bool,
+    colors: Colors,
+    history: History,
+    own_pid: Option<i32>,
+    sort_by: Metric,
+    renames: Renames,
+    stale: Vec<Column>,
+    row_rules: Vec<RowRule>,
+    script_columns: Vec<String>,
+    script_styles: Vec<Style>,
+}
+
+impl ProcTable {
+    fn new(t: &TableState, state: &ViewState, max_width: u16) -> Self {
+        let mut columns = t
+            .columns
+            .iter()
+            .map(|&c| match t.cell_kind(c) {
+                k if state.accessible => (c, k.plain()),
+                k => (c, k),
+            })
+            .collect::<Vec<_>>();
+        let mut hidden = if t.h_scroll == 0 {
+            drop_columns(&mut columns, max_width)
+        } else {
+            scroll_columns(&mut columns, t.h_scroll, max_width)
+        };
+        hidden.sort_by_key(|c| t.columns.iter().position(|x| x == c));
+        let widths = columns
+            .iter()
+            .map(|&(c, k)| match c {
+                Column::Script => {
+                    let w: usize = state
+                        .script_columns
+                        .iter()
+                        .map(|n| script_width(n) + 1)
+                        .sum();
+                    Constraint::Length(w.saturating_sub(1) as u16)
+                }
+                _ => c.width(k),
+            })
+            .collect();
+        Self {
+            columns,
+            widths,
+            hidden,
+            group_headers: t.group_headers,
+            row_height: if t.tall_rows && !state.accessible {
+                2
+            } else {
+                1
+            },
+            accessible: state.accessible,
+            cumulative: t.cumulative,
+            baseline: t.baseline,
+            braille: state.braille,
+            bars: state.bars.clone(),
+            column_bars: state.column_bars.clone(),
+            ascii: state.ascii,
+            colors: state.colors.clone(),
+            history: state.history.clone(),
+            own_pid: state.own_pid,
+            sort_by: t.sort_by,
+            renames: state.renames.clone(),
+            stale: state.stale.clone(),
+            row_rules: state.row_rules.clone(),
+            script_columns: state.script_columns.clone(),
+            script_styles: state.script_styles.clone(),
+        }
+    }
+
+    fn bars(&self, col: Column) -> &Bars {
+        self.column_bars.get(&col).unwrap_or(&self.bars)
+    }
+
+    fn header(&self, col: Column) -> String {
+        use Metric::*;
+        if col == Column::Script {
+            return self.script_cells(&self.script_columns);
+        }
+        let s = col.header();
+        let sorted = match col.metric() {
+            Some(m) => {
+                m == self.sort_by
+                    || (self.sort_by == DiskTotal && (m == DiskRead || m == DiskWrite))
+            }
+            None => false,
+        };
+        if sorted {
+            format!("*{}*", s)
+        } else {
+            String::from(s)
+        }
+    }
+
+    // the script's columns' `values`, each in its width, side by side.
+    fn script_cells(&self, values: &[String]) -> String {
+        let cells: Vec<String> = self
+            .script_columns
+            .iter()
+            .zip(values)
+            .map(|(name, v)| format!("{:<1$.1$}", v, script_width(name)))
+            .collect();
+        cells.join(" ")
+    }
+
+    // With tall rows, each row's cpu and memory history on its second line,
+    // across the table. The table drawn in `area` left those lines blank.
+    fn draw_histories(
+        &self,
+        f: &mut crate::ui::Frame,
+        sprocs: &[&SProc],
+        header: bool,
+        area: Rect,
+    ) {
+        if self.row_height < 2 {
+            return;
+        }
+        let first = area.y + header as u16 + 1;
+        for (i, sp) in sprocs.iter().enumerate() {
+            let y = first + i as u16 * self.row_height;
+            if y >= area.bottom() {
+                break;
+            }
+            // memory as a share of ram, like cpu, so neither drowns the
+            // other out.
+            let mem_max = match sp.mem_pct {
+                pct if pct > 0. => sp.mem_bytes as f64 * 100. / pct,
+                _ => sp.mem_hist.iter().copied().fold(1., f64::max),
+            };
+            let cpu = sp.cpu_history(self.cumulative);
+            let history = render::render_multi(&[
+                render::Series::new(&cpu, 100., |c| self.colors.cpu(c))
+                    .with_unborn(sp.unborn_samples(&self.history))
+                    .with_bars(&self.bars)
+                    .with_ascii(self.ascii),
+                render::Series::new(&sp.mem_hist, mem_max, |_| self.colors.theme.mem_chart),
+            ]);
+            let line = Rect {
+                x: area.x + 2,
+                y,
+                width: area.width.saturating_sub(2),
+                height: 1,
+            };
+            f.render_widget(Paragraph::new(history), line);
+        }
+    }
+
+    // Where the header's cells and each row are in a table of `sprocs`
+    // drawn in `area`.
+    fn targets(&self, sprocs: &[&SProc], header: bool, area: Rect) -> Vec<(Rect, Target)> {
+        let mut targets = vec![];
+        if header {
+            let mut x = area.x;
+            for (&(col, _), &w) in self
+                .columns
+                .iter()
+                .zip(column_widths(&self.widths, area.width).iter())
+            {
+                let width = w.min(area.right().saturating_sub(x));
+                targets.push((Rect::new(x, area.y, width, 1), Target::Header(col)));
+                x = (x + w + COLUMN_SPACING).min(area.right());
+            }
+        }
+        let first = area.y + header as u16;
+        for (i, sp) in sprocs.iter().enumerate() {
+            let y = first + i as u16 * self.row_height;
+            if y >= area.bottom() {
+                break;
+            }
+            let height = self.row_height.min(area.bottom() - y);
+            targets.push((
+                Rect::new(area.x, y, area.width, height),
+                Target::Row(sp.pid),
+            ));
+        }
+        targets
+    }
+
+    // A row above the header labeling runs of related columns, like
+    // `── disk ──`. None if disabled or no group has 2+ visible columns.
+    fn group_header(&self, max_width: u16) -> Option<Line<'static>> {
+        if !self.group_headers {
+            return None;
+        }
+        let col_widths = column_widths(&self.widths, max_width);
+        // (group, width) runs, including the spacing between grouped columns.
+        let mut runs: Vec<(Option<&str>, usize, usize)> = vec![]; // (group, width, ncols)
+        for (&(col, _), &w) in self.columns.iter().zip(col_widths.iter()) {
+            match runs.last_mut() {
+                Some((g, width, n)) if col.group().is_some() && *g == col.group() => {
+                    *width += COLUMN_SPACING as usize + w as usize;
+                    *n += 1;
+                }
+                _ => runs.push((col.group(), w as usize, 1)),
+            }
+        }
+        if !runs.iter().any(|&(g, _, n)| g.is_some() && n > 1) {
+            return None;
+        }
+        let style = Style::default().add_modifier(Modifier::DIM);
+        let mut spans = vec![];
+        for (i, (group, width, _)) in runs.into_iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::raw(" ".repeat(COLUMN_SPACING as usize)));
+            }
+            let label = match group {
+                Some(g) => {
+                    let mut l = format!("{:─^1$}", format!(" {} ", g), width);
+                    if l.chars().count() > width {
+                        l = "─".repeat(width);
+                    }
+                    l
+                }
+                None => " ".repeat(width),
+            };
+            spans.push(Span::styled(label, style));
+        }
+        Some(Line::from(spans))
+    }
+
+    fn cell(&self, sp: &SProc, col: Column, kind: CellKind) -> Cell<'static> {
+        let mut liveness_style = Style::default();
+        if sp.is_dead() {
+            liveness_style = liveness_style.fg(self.colors.theme.dead);
+        } else if sp.frozen {
+            liveness_style = liveness_style.fg(self.colors.theme.frozen);
+        }
+        match (col, kind) {
+            (Column::Pid, _) => Cell::from(Span::styled(sp.pid.to_string(), liveness_style)),
+            (Column::Name, _) => {
+                let mut name = display_name(sp, &self.renames);
+                if sp.frozen {
+                    let mark = if self.accessible { "frozen " } else { "❄ " };
+                    name.insert_str(0, mark);
+                }
+                if Some(sp.pid) == self.own_pid {
+                    name.push_str(" (this)");
+                }
+                Cell::from(Span::styled(name, liveness_style))
+            }
+            (Column::Pod, _) => Cell::from(sp.pod.clone().unwrap_or_else(|| String::from("_"))),
+            (Column::DiskRead, _) => Cell::from(render_metric(sp.disk_read_ewma)),
+            (Column::DiskWrite, _) => Cell::from(render_metric(sp.disk_write_ewma)),
+            (Column::Mem, _) => Cell::from(render_metric(units::mib(sp.mem_bytes as f64))),
+            (Column::MemPct, CellKind::Gauge) => {
+                Cell::from(render::render_gauge(sp.mem_pct, GAUGE_WIDTH))
+            }
+            (Column::MemPct, _) => Cell::from(render_metric(sp.mem_pct)),
+            (Column::MemEta, _) => Cell::from(
+                sp.mem_forecast(&self.history)
+                    .map_or(String::from("_"), render_duration),
+            ),
+            (Column::Fds, _) => Cell::from(sp.fds.map_or(String::from("?"), |n| n.to_string())),
+            (Column::Threads, _) => {
+                Cell::from(sp.threads.map_or(String::from("?"), |n| n.to_string()))
+            }
+            (Column::State, _) => Cell::from(match sp.state {
+                Some(s) => Span::styled(s.to_string(), Style::default().fg(self.colors.state(s))),
+                None => Span::raw("?"),
+            }),
+            (Column::IoPrio, _) => {
+                Cell::from(sp.io_priority.map_or(String::from("_"), IoPriority::name))
+            }
+            (Column::Ports, _) => Cell::from(match (&sp.ports, sp.net_ns) {
+                (None, _) => String::from("?"),
+                (Some(ports), None) => ports.len().to_string(),
+                (Some(ports), Some(ns)) => format!("{} ns {}", ports.len(), ns),
+            }),
+            (Column::Script, _) => Cell::from(self.script_cells(&sp.script_values)),
+            (Column::Cpu, CellKind::Split) => {
+                let style = match sp.kernel_heavy() {
+                    true => Style::default().fg(self.colors.theme.kernel_heavy),
+                    false => Style::default(),
+                };
+                Cell::from(Span::styled(
+                    format!(
+                        "{}/{}",
+                        render_metric(sp.user_cpu_ewma),
+                        render_metric(sp.sys_cpu_ewma)
+                    ),
+                    style,
+                ))
+            }
+            (Column::Cpu, CellKind::Gauge) => Cell::from(
+                sp.cpu_quota_pct(self.cumulative)
+                    .map_or(String::from("_"), |p| render::render_gauge(p, GAUGE_WIDTH)),
+            ),
+            (Column::Cpu, _) => Cell::from(render_metric(sp.cpu(self.cumulative))),
+            (Column::TreeCpu, _) => Cell::from(render_metric(sp.tree_cpu(self.cumulative))),
+            (Column::Latency, CellKind::Sparkline) => {
+                let series =
+                    render::Series::new(&sp.latency_hist, 100., |p| self.colors.latency(p))
+                        .with_bars(self.bars(col))
+                        .with_braille(self.braille)
+                        .with_ascii(self.ascii);
+                Cell::from(render::render_multi(&[series]))
+            }
+            (Column::Latency, CellKind::Trend) => Cell::from(render_trend(
+                col,
+                format!("{:.0}", sp.latency_ewma),
+                &sp.latency_hist,
+            )),
+            (Column::Latency, _) => Cell::from(Span::styled(
+                render_metric(sp.latency_ewma),
+                Style::default().fg(self.colors.latency(sp.latency_ewma)),
+            )),
+            (Column::Wakeups, CellKind::Sparkline) => {
+                let max = sp.wakeups_hist.iter().copied().fold(1., f64::max);
+                let series = render::Series::new(&sp.wakeups_hist, max, |_| Color::Reset)
+                    .with_bars(self.bars(col))
+                    .with_braille(self.braille)
+                    .with_ascii(self.ascii);
+                Cell::from(render::render_multi(&[series]))
+            }
+            (Column::Wakeups, CellKind::Trend) => Cell::from(render_trend(
+                col,
+                render_count(sp.wakeups_ewma),
+                &sp.wakeups_hist,
+            )),
+            (Column::Wakeups, _) => Cell::from(render_count(sp.wakeups_ewma)),
+            (Column::CpuHist, CellKind::Trend) => Cell::from(render_trend(
+                col,
+                format!("{:.0}", sp.cpu(self.cumulative)),
+                &sp.cpu_history(self.cumulative),
+            )),
+            (Column::CpuHist, _) => {
+                let hist = sp.cpu_history(self.cumulative);
+                let spikes = stats::spikes(&hist, CPU_MIN_SD);
+                let peaks: Vec<bool> = hist
+                    .iter()
+                    .zip(&sp.cpu_peaks)
+                    .map(|(mean, peak)| peak - mean >= PEAK_MIN_RISE)
+                    .collect();
+                let base = match self.baseline {
+                    true => baseline(&hist),
+                    false => 0.,
+                };
+                if base > 0. {
+                    // the baseline, colored as the cpu it stands for, then the
+                    // rest scaled to the variation above it.
+                    let above: Vec<f64> = hist.iter().map(|x| x - base).collect();
+                    let range = above.iter().copied().fold(BASELINE_MIN_RANGE, f64::max);
+                    let series = render::Series::new(&above, range, |_| Color::Reset)
+                        .with_marks(spikes, self.colors.theme.spike_marks)
+                        .with_marks(peaks, self.colors.theme.peak_marks)
+                        .with_unborn(sp.unborn_samples(&self.history))
+                        .with_bars(self.bars(col))
+                        .with_braille(self.braille)
+                        .with_ascii(self.ascii);
+                    let mut spans = vec![Span::styled(
+                        format!("{}+", render_metric(base)),
+                        Style::default().fg(self.colors.cpu(base)),
+                    )];
+                    spans.extend(render::render_multi(&[series]).spans);
+                    return Cell::from(Line::from(spans));
+                }
+                let series = render::Series::new(&hist, 100., |c| self.colors.cpu(c))
+                    .with_marks(spikes, self.colors.theme.spike_marks)
+                    .with_marks(peaks, self.colors.theme.peak_marks)
+                    .with_unborn(sp.unborn_samples(&self.history))
+                    .with_bars(self.bars(col))
+                    .with_braille(self.braille)
+                    .with_ascii(self.ascii);
+                Cell::from(render::render_multi(&[series]))
+            }
+        }
+    }
+
+    // `selected` is highlighted, the first `pinned` rows bold, and a rule
+    // drawn under the row before each of `dividers`.
+    fn get_table(
+        &self,
+        sprocs: &[&SProc],
+        header: bool,
+        selected: Option<i32>,
+        pinned: usize,
+        dividers: &[usize],
+    ) -> Table<'_> {
+        let rows = sprocs.iter().enumerate().map(|(i, sp)| {
+            let row = Row::new(
+                self.columns
+                    .iter()
+                    .map(|&(col, kind)| match self.cell(sp, col, kind) {
+                        c if self.stale.contains(&col) => {
+                            c.style(Style::default().add_modifier(Modifier::DIM))
+                        }
+                        c => c,
+                    })
+                    .collect::<Vec<_>>(),
+            );
+            let mut style = Style::default();
+            if i < pinned {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+            if Some(sp.pid) == self.own_pid {
+                style = style
+                    .fg(self.colors.theme.own_row)
+                    .add_modifier(Modifier::ITALIC);
+            }
+            if dividers.contains(&(i + 1)) && i + 1 < sprocs.len() {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
+            for rule in self.row_rules.iter().filter(|r| r.matches(sp)) {
+                style = style.patch(rule.style);
+            }
+            for &i in &sp.script_highlights {
+                if let Some(&s) = self.script_styles.get(i) {
+                    style = style.patch(s);
+                }
+            }
+            if Some(sp.pid) == selected {
+                style = match self.colors.theme.selected_row {
+                    Color::Reset => style.add_modifier(Modifier::REVERSED),
+                    c => style.bg(c),
+                };
+            }
+            row.style(style).height(self.row_height)
+        });
+        let table = Table::new(rows, &self.widths);
+        if !header {
+            return table;
+        }
+        let header: Vec<String> = self.columns.iter().map(|&(c, _)| self.header(c)).collect();
+        let style = Style::default()
+            .fg(self.colors.theme.header)
+            .add_modifier(Modifier::UNDERLINED);
+        table.header(Row::new(header).style(style))
+    }
+}
+
+// Drop columns, lowest priority (and then rightmost) first, until the rest
+// fit in `max_width`. Returns the dropped columns.
+fn drop_columns(columns: &mut Vec<(Column, CellKind)>, max_width: u16) -> Vec<Column> {
+    let mut hidden = vec![];
+    loop {
+        let needed: u16 = columns
+            .iter()
+            .map(|&(c, k)| c.min_width(k) + COLUMN_SPACING)
+            .sum::<u16>()
+            .saturating_sub(COLUMN_SPACING);
+        if needed <= max_width {
+            break;
+        }
+        let victim = columns
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &(c, _))| c.priority().map(|p| (p, std::cmp::Reverse(i))))
+            .min();
+        match victim {
+            Some((_, std::cmp::Reverse(i))) => hidden.push(columns.remove(i).0),
+            None => break,
+        }
+    }
+    hidden
+}
+
+// Freeze the name column and show the rest from the `offset`th on, as many as
+// fit in `max_width`. Returns the columns scrolled or cut off.
+fn scroll_columns(
+    columns: &mut Vec<(Column, CellKind)>,
+    offset: usize,
+    max_width: u16,
+) -> Vec<Column> {
+    let (mut shown, rest): (Vec<_>, Vec<_>) =
+        columns.iter().partition(|&&(c, _)| c == Column::Name);
+    let mut hidden = vec![];
+    let mut used: u16 = shown.iter().map(|&(c, k)| c.min_width(k)).sum();
+    for (i, (c, k)) in rest.into_iter().enumerate() {
+        let need = c.min_width(k) + if shown.is_empty() { 0 } else { COLUMN_SPACING };
+        if i < offset || used + need > max_width || hidden.len() > offset {
+            hidden.push(c);
+        } else {
+            used += need;
+            shown.push((c, k));
+        }
+    }
+    *columns = shown;
+    hidden
+}
+
+fn draw_help(f: &mut crate::ui::Frame, state: &ViewState) {
+    let mut lines: Vec<Line> = vec![Line::from("keys:")];
+    for &(key, action) in action::BINDINGS {
+        let key = match key {
+            KeyCode::Char(' ') => "space".into(),
+            KeyCode::Char(c) => c.to_string(),
+            k => format!("{:?}", k),
+        };
+        let name = action::name(action).unwrap_or_else(|| format!("{:?}", action));
+        lines.push(Line::from(format!("  {:<5} {}", key, name)));
+    }
+    lines.push(Line::from("sort keys: again to reverse"));
+    lines.push(Line::from(
+        "mouse: click a header to sort (again to reverse), a row to select",
+    ));
+    if !state.unavailable.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(
+            "unavailable here (confinement? try --privileged-helper):",
+        ));
+        for u in &state.unavailable {
+            let cols: Vec<&str> = u.columns.iter().map(|c| c.header()).collect();
+            lines.push(Line::from(format!("  {}: {}", cols.join(" "), u.reason)));
+        }
+    }
+    let area = f.area();
+    let width = area.width.min(70);
+    let height = area.height.min(lines.len() as u16 + 2);
+    let area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    f.render_widget(crate::ui::widgets::Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("help")),
+        area,
+    );
+}
+
+// Newest first, as much as fits.
+fn draw_event_log(f: &mut crate::ui::Frame, events: &LifetimeLog, theme: &Theme, area: Rect) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map_or(0., |d| d.as_secs_f64());
+    let lines: Vec<Line> = events
+        .iter()
+        .rev()
+        .take(area.height.saturating_sub(2) as usize)
+        .map(|e| {
+            let (kind, style) = match e.kind {
+                LifeEventKind::Start => ("start", Style::default().fg(theme.started)),
+                LifeEventKind::Exit => ("exit", Style::default().fg(theme.dead)),
+            };
+            Line::from(vec![
+                Span::raw(format!("{:>6.0}s ago ", (now - e.time).max(0.))),
+                Span::styled(format!("{:<5}", kind), style),
+                Span::raw(format!(" {:>7} {}", e.pid, e.name)),
+            ])
+        })
+        .collect();
+    let title = format!("process starts/exits ({})", events.len());
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+}
+
+// Newest first from `scroll` back, as much as fits.
+fn draw_alerts(f: &mut crate::ui::Frame, alerts: &AlertLog, scroll: usize, area: Rect) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map_or(0., |d| d.as_secs_f64());
+    let lines: Vec<Line> = alerts
+        .iter()
+        .rev()
+        .skip(scroll)
+        .take(area.height.saturating_sub(2) as usize)
+        .map(|(time, msg)| {
+            Line::from(vec![
+                Span::raw(format!("{:>6.0}s ago ", (now - time).max(0.))),
+                Span::raw(msg.clone()),
+            ])
+        })
+        .collect();
+    let title = match scroll {
+        0 => format!("alerts ({})", alerts.len()),
+        n => format!("alerts ({}, {} newer: <)", alerts.len(), n),
+    };
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+}
+
+// Everything about one process: full-width histories and the memory
+// forecast.
+fn draw_zoom(f: &mut crate::ui::Frame, sp: Option<&SProc>, state: &ViewState, area: Rect) {
+    let sp = match sp {
+        Some(sp) => sp,
+        None => {
+            f.render_widget(
+                Paragraph::new("process is gone (Esc to go back)")
+                    .block(Block::default().borders(Borders::ALL).title("zoom")),
+                area,
+            );
+            return;
+        }
+    };
+    if state.zoom_tab == ZoomTab::KernelStack {
+        draw_kernel_stacks(f, sp, state, area);
+        return;
+    }
+    let (dim, bars, colors) = (
+        Style::default().add_modifier(Modifier::DIM),
+        &state.bars,
+        &state.colors,
+    );
+    let disk_max = sp
+        .disk_read_hist
+        .iter()
+        .chain(&sp.disk_write_hist)
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let mem_max = sp.mem_hist.iter().copied().fold(1., f64::max);
+    let as_f64 =
+        |h: &std::collections::VecDeque<u64>| h.iter().map(|&x| x as f64).collect::<Vec<_>>();
+    let forecast = match (sp.mem_forecast(&state.history), sp.mem_limit_bytes) {
+        (Some(secs), Some(limit)) => format!(
+            "memory growing steadily: reaches {} in ~{}",
+            render_bytes(limit as f64),
+            render_duration(secs)
+        ),
+        (None, Some(_)) => "memory not growing steadily".into(),
+        (_, None) => "memory limit unknown".into(),
+    };
+    let mut lines = vec![
+        Line::from(format!(
+            "ppid {}  {}",
+            sp.ppid.map_or(String::from("?"), |p| p.to_string()),
+            if sp.is_dead() { "(dead)" } else { "" }
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("cpu {:.1}%", sp.cpu(state.table().cumulative)),
+            dim,
+        )),
+        render::render_multi(&[render::Series::new(
+            &sp.cpu_history(state.table().cumulative),
+            100.,
+            |c| colors.cpu(c),
+        )
+        .with_unborn(sp.unborn_samples(&state.history))
+        .with_bars(bars)
+        .with_ascii(state.ascii)]),
+    ];
+    if !sp.sys_cpu_hist.is_empty() {
+        let heavy = match sp.kernel_heavy() {
+            true => "  mostly in the kernel (syscall-heavy?)",
+            false => "",
+        };
+        lines.extend(vec![
+            Line::from(Span::styled(
+                format!(
+                    "user {:.1}%  kernel {:.1}%{}",
+                    sp.user_cpu_ewma, sp.sys_cpu_ewma, heavy
+                ),
+                dim,
+            )),
+            render::render_vec_colored(&sp.user_cpu_hist, 100., bars, |c| colors.cpu(c)),
+            render::render_vec_colored(&sp.sys_cpu_hist, 100., bars, |c| colors.kernel(c)),
+        ]);
+    }
+    lines.extend(vec![
+        Line::from(Span::styled(
+            format!(
+                "memory {} ({:.1}%)",
+                render_bytes(sp.mem_bytes as f64),
+                sp.mem_pct
+            ),
+            dim,
+        )),
+        Line::from(render::render_vec(&sp.mem_hist, mem_max, bars)),
+        Line::from(forecast),
+        Line::from(Span::styled(
+            format!(
+                "disk read {:.0} B/s, write {:.0} B/s",
+                sp.disk_read_ewma, sp.disk_write_ewma
+            ),
+            dim,
+        )),
+        Line::from(render::render_vec(
+            &as_f64(&sp.disk_read_hist),
+            disk_max,
+            bars,
+        )),
+        Line::from(render::render_vec(
+            &as_f64(&sp.disk_write_hist),
+            disk_max,
+            bars,
+        )),
+    ]);
+    if let Some(swap) = sp.swap_bytes {
+        // faults share a scale, as do swap in and out.
+        let faults_max = sp.faults_hist.iter().copied().fold(1., f64::max);
+        let swap_max = sp
+            .swap_in_hist
+            .iter()
+            .chain(&sp.swap_out_hist)
+            .copied()
+            .fold(1., f64::max);
+        let latest = |h: &std::collections::VecDeque<f64>| h.front().copied().unwrap_or(0.);
+        lines.extend(vec![
+            Line::from(Span::styled(
+                format!(
+                    "major faults {:.0}/s, swapped {}: in {}/s, out {}/s",
+                    latest(&sp.faults_hist),
+                    render_bytes(swap as f64),
+                    render_bytes(latest(&sp.swap_in_hist)),
+                    render_bytes(latest(&sp.swap_out_hist))
+                ),
+                dim,
+            )),
+            Line::from(render::render_vec(&sp.faults_hist, faults_max, bars)),
+            Line::from(render::render_vec(&sp.swap_in_hist, swap_max, bars)),
+            Line::from(render::render_vec(&sp.swap_out_hist, swap_max, bars)),
+        ]);
+    }
+    if let Some(fds) = sp.fds {
+        lines.push(Line::from(format!("open files {}", fds)));
+    }
+    if let Some(ports) = sp.ports.as_ref().filter(|p| !p.is_empty()) {
+        let ports: Vec<String> = ports.iter().map(Port::to_string).collect();
+        lines.push(Line::from(format!("listening on {}", ports.join(", "))));
+    }
+    if let Some(ns) = sp.net_ns {
+        lines.push(Line::from(format!("network namespace {}", ns)));
+    }
+    lines.push(Line::from(""));
+    match &state.cgroup_io {
+        Some(Ok(io)) => lines.extend(cgroup_io_lines(io, state, dim)),
+        Some(Err(e)) => lines.push(Line::from(Span::styled(format!("cgroup io: {}", e), dim))),
+        None => (),
+    }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(zoom_title(sp, state));
+    let mut inner = block.inner(area);
+    f.render_widget(block, area);
+    // charts on top when there's room for them and all the lines.
+    if !state.accessible && inner.height as usize >= lines.len() + CHART_HEIGHT as usize {
+        let rects = Layout::default()
+            .constraints(vec![Constraint::Length(CHART_HEIGHT), Constraint::Min(0)])
+            .split(inner);
+        draw_history_charts(f, sp, state, rects[0]);
+        inner = rects[1];
+    }
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+// the process and the zoom tabs, the current one in brackets.
+fn zoom_title(sp: &SProc, state: &ViewState) -> String {
+    let tabs: Vec<String> = ZoomTab::ALL
+        .iter()
+        .map(|&t| match t == state.zoom_tab {
+            true => format!("[{}]", t.name()),
+            false => t.name().to_string(),
+        })
+        .collect();
+    let who = match state.renames.apply(sp) {
+        Some(name) => format!("{} ({}, {})", name, sp.name, sp.pid),
+        None => format!("{} ({})", sp.name, sp.pid),
+    };
+    format!("{} {} - Tab for next, Esc to go back", who, tabs.join(" "))
+}
+
+// How often each kernel stack has been seen, most often first, to tell what
+// a process in D state is waiting on.
+fn draw_kernel_stacks(f: &mut crate::ui::Frame, sp: &SProc, state: &ViewState, area: Rect) {
+    let dim = Style::default().add_modifier(Modifier::DIM);
+    let mut lines = vec![];
+    match &state.kernel_stacks {
+        None => lines.push(Line::from(Span::styled("sampling...", dim))),
+        Some(stacks) => {
+            if let Some(e) = &stacks.error {
+                lines.push(Line::from(e.clone()));
+            }
+            lines.push(Line::from(Span::styled(
+                format!("{} samples of /proc/{}/stack", stacks.samples, sp.pid),
+                dim,
+            )));
+            for (count, frames) in &stacks.top {
+                let pct = 100. * *count as f64 / stacks.samples.max(1) as f64;
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(
+                    format!("{:.0}% ({})", pct, count),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )));
+                if frames.is_empty() {
+                    lines.push(Line::from("  (in user space)"));
+                }
+                lines.extend(frames.iter().map(|fr| Line::from(format!("  {}", fr))));
+            }
+        }
+    }
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(zoom_title(sp, state)),
+        ),
+        area,
+    );
+}
+
+const CHART_HEIGHT: u16 = 12;
+// behind each chart's line, the interquartile range of the BAND_WINDOW
+// samples up to each point: wide when it oscillates, thin when it's steady.
+const BAND_WINDOW: usize = 10;
+
+// cpu and memory history as line charts with labeled axes, now on the right.
+fn draw_history_charts(f: &mut crate::ui::Frame, sp: &SProc, state: &ViewState, area: Rect) {
+    // newest first; old samples are compacted, so further apart, and may
+    // not have been evenly spaced to begin with.
+    let points = |hist: &mut dyn Iterator<Item = &f64>| -> Vec<(f64, f64)> {
+        hist.zip(state.history.ages())
+            .map(|(&v, age)| (-age, v))
+            .collect()
+    };
+    let cpu = points(&mut sp.cpu_history(state.table().cumulative).iter());
+    let mem = points(&mut sp.mem_hist.iter());
+    let span = state.history.secs_ago(cpu.len().max(mem.len()).max(2) - 1);
+    let peak = |xs: &[(f64, f64)]| xs.iter().map(|&(_, v)| v).fold(0., f64::max);
+    // whole cores for cpu; a little headroom for memory.
+    let cpu_max = (peak(&cpu) / 100.).ceil().max(1.) * 100.;
+    let mem_max = (peak(&mem) * 1.2).max(1.);
+    let rects = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, 2); 2])
+        .split(area);
+    // a braille dot column is half a cell.
+    let dots = rects[0].width as usize * 2;
+    // where each older, compacted tier starts: where the one before ends.
+    let tiers = state.history.tiers();
+    let tier_starts: Vec<f64> = tiers
+        .iter()
+        .take(tiers.len().saturating_sub(1))
+        .scan(0., |end, t| {
+            *end += t.secs;
+            Some(*end)
+        })
+        .filter(|&start| start < span)
+        .collect();
+    let mut cpu = ChartLines::new(cpu, span, cpu_max, dots, &tier_starts);
+    let mut mem = ChartLines::new(mem, span, mem_max, dots, &tier_starts);
+    // without color, the band would run into the history.
+    if state.monochrome {
+        cpu.band.clear();
+        mem.band.clear();
+    }
+    let cpu_labels = [0., cpu_max / 2., cpu_max].map(|v| format!("{:.0}%", v));
+    let mem_labels = [0., mem_max / 2., mem_max].map(render_bytes);
+    let t = &state.colors.theme;
+    let style = |line, band| ChartStyle {
+        line,
+        band,
+        grid: t.grid,
+        ascii: state.ascii,
+    };
+    let cpu_chart = style(t.cpu_chart, t.cpu_band);
+    let mem_chart = style(t.mem_chart, t.mem_band);
+    f.render_widget(
+        history_chart("cpu", &cpu, span, cpu_max, cpu_labels, &cpu_chart),
+        rects[0],
+    );
+    f.render_widget(
+        history_chart("memory", &mem, span, mem_max, mem_labels, &mem_chart),
+        rects[1],
+    );
+}
+
+// how a history chart is drawn: its colors, and in ASCII or braille.
+struct ChartStyle {
+    line: Color,
+    band: Color,
+    grid: Color,
+    ascii: bool,
+}
+
+// what a history chart draws: the history, over grid lines, over its band.
+struct ChartLines {
+    data: Vec<(f64, f64)>,
+    grid: Vec<[(f64, f64); 2]>,
+    band: Vec<(f64, f64)>,
+}
+
+impl ChartLines {
+    // `dots` is how many braille dot columns wide the chart is, and
+    // `tier_starts` how many seconds back each compacted tier of history
+    // starts.
+    fn new(data: Vec<(f64, f64)>, span: f64, max: f64, dots: usize, tier_starts: &[f64]) -> Self {
+        Self {
+            grid: grid_lines(span, max, tier_starts),
+            band: band_lines(&data, dots),
+            data,
+        }
+    }
+}
+
+// dim horizontal lines at each quarter of `max`, across `span` seconds, and
+// vertical ones `tier_starts` seconds back.
+fn grid_lines(span: f64, max: f64, tier_starts: &[f64]) -> Vec<[(f64, f64); 2]> {
+    let mut lines: Vec<[(f64, f64); 2]> = (1..4)
+        .map(|q| {
+            let y = max * q as f64 / 4.;
+            [(-span, y), (0., y)]
+        })
+        .collect();
+    lines.extend(tier_starts.iter().map(|&t| [(-t, 0.), (-t, max)]));
+    lines
+}
+
+// a line zigzagging up and down the rolling band of `data` (newest first),
+// about `n` times across it, interpolated between samples: filled, in one
+// dataset, since each dataset's cells hide those of the ones before.
+fn band_lines(data: &[(f64, f64)], n: usize) -> Vec<(f64, f64)> {
+    if data.len() < 2 {
+        return vec![];
+    }
+    let values: Vec<f64> = data.iter().map(|&(_, v)| v).collect();
+    let band = stats::rolling_band(&values, BAND_WINDOW, 25., 75.);
+    let per_gap = (n / (data.len() - 1)).max(1);
+    let mut points = vec![];
+    for i in 0..data.len() - 1 {
+        let ((t0, _), (t1, _)) = (data[i], data[i + 1]);
+        let ((lo0, hi0), (lo1, hi1)) = (band[i], band[i + 1]);
+        for step in 0..per_gap {
+            let f = step as f64 / per_gap as f64;
+            let t = t0 + (t1 - t0) * f;
+            let (lo, hi) = (lo0 + (lo1 - lo0) * f, hi0 + (hi1 - hi0) * f);
+            match points.len() % 4 {
+                0 => points.extend([(t, lo), (t, hi)]),
+                _ => points.extend([(t, hi), (t, lo)]),
+            }
+        }
+    }
+    points
+}
+
+fn history_chart<'a>(
+    title: &'a str,
+    lines: &'a ChartLines,
+    span: f64,
+    max: f64,
+    y_labels: [String; 3],
+    style: &ChartStyle,
+) -> Chart<'a> {
+    let dim = Style::default().add_modifier(Modifier::DIM);
+    // unnamed, so there's no legend. Later ones are drawn over earlier.
+    let line = |points: &'a [(f64, f64)], color: Color| {
+        Dataset::default()
+            .marker(if style.ascii {
+                Marker::Dot
+            } else {
+                Marker::Braille
+            })
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(color))
+            .data(points)
+    };
+    // in ASCII a dot fills a whole cell, too coarse for the band and grid.
+    let mut datasets = vec![];
+    if !style.ascii {
+        datasets.push(line(&lines.band, style.band));
+        datasets.extend(lines.grid.iter().map(|l| line(l, style.grid)));
+    }
+    datasets.push(line(&lines.data, style.line));
+    let x_labels = vec![
+        Span::styled(format!("-{}", render_duration(span)), dim),
+        Span::styled(format!("-{}", render_duration(span / 2.)), dim),
+        Span::styled("now", dim),
+    ];
+    Chart::new(datasets)
+        .block(Block::default().title(Span::styled(title, dim)))
+        .x_axis(Axis::default().bounds([-span, 0.]).labels(x_labels))
+        .y_axis(
+            Axis::default().bounds([0., max]).labels(
+                y_labels
+                    .iter()
+                    .map(|l| Span::styled(l.clone(), dim))
+                    .collect::<Vec<_>>(),
+            ),
+        )
+}
+
+// Throughput and latency of the whole cgroup's block io: high throughput
+// with low latency is just writing a lot, high latency is a slow disk.
+fn cgroup_io_lines(io: &IoSummary, state: &ViewState, dim: Style) -> Vec<Line<'static>> {
+    let max = io
+        .read_bps
+        .iter()
+        .chain(&io.write_bps)
+        .copied()
+        .fold(1., f64::max);
+    let latency = match io.latency_us {
+        Some((p50, p95, p99)) => Span::styled(
+            format!(
+                "io latency{} p50 {} p95 {} p99 {}",
+                if io.measured {
+                    ""
+                } else {
+                    " (est. from stalls)"
+                },
+                render_us(p50),
+                render_us(p95),
+                render_us(p99)
+            ),
+            Style::default().fg(state.colors.io_latency(p95)),
+        ),
+        None => Span::styled("io latency: no ios yet", dim),
+    };
+    vec![
+        Line::from(Span::styled(
+            format!(
+                "cgroup {} io read {}/s, write {}/s",
+                io.cgroup,
+                render_bytes(io.read_bps.first().copied().unwrap_or(0.)),
+                render_bytes(io.write_bps.first().copied().unwrap_or(0.))
+            ),
+            dim,
+        )),
+        render::render_multi(&[
+            render::Series::new(&io.read_bps, max, |_| state.colors.theme.read)
+                .with_bars(&state.bars),
+            render::Series::new(&io.write_bps, max, |_| state.colors.theme.write),
+        ]),
+        Line::from(latency),
+    ]
+}
+
+fn render_us(us: f64) -> String {
+    if us >= 1000. {
+        format!("{:.1}ms", us / 1000.)
+    } else {
+        format!("{:.0}µs", us)
+    }
+}
+
+// The biggest users over the retained history, by disk bytes when sorting by
+// disk and otherwise by cpu time. Catches things that spiked and went idle
+// (or died) since.
+fn draw_ranking(f: &mut crate::ui::Frame, sprocs: &[&SProc], state: &ViewState, area: Rect) {
+    let by_disk = matches!(
+        state.table().sort_by,
+        Metric::DiskRead | Metric::DiskWrite | Metric::DiskTotal
+    );
+    let total = |sp: &SProc| -> f64 {
+        if by_disk {
+            let bytes = |h: &std::collections::VecDeque<u64>| -> Vec<f64> {
+                h.iter().map(|&b| b as f64).collect()
+            };
+            state.history.total(&bytes(&sp.disk_read_hist))
+                + state.history.total(&bytes(&sp.disk_write_hist))
+        } else {
+            state.history.integral(&sp.cpu_hist) / 100.
+        }
+    };
+    let mut ranked: Vec<(f64, &SProc)> = sprocs
+        .iter()
+        .map(|&sp| (total(sp), sp))
+        .filter(|&(t, _)| t > 0.)
+        .collect();
+    ranked.sort_by_key(|&(t, sp)| (OrdFloat(-t), sp.pid));
+    let lines: Vec<Line> = ranked
+        .iter()
+        .take(area.height.saturating_sub(2) as usize)
+        .enumerate()
+        .map(|(i, &(t, sp))| {
+            let amount = if by_disk {
+                format!("{:>10.1} MB", units::mib(t))
+            } else {
+                format!("{:>10.1} cpu·s", t)
+            };
+            let style = if sp.is_dead() {
+                Style::default().fg(state.colors.theme.dead)
+            } else {
+                Style::default()
+            };
+            Line::from(Span::styled(
+                format!("{:>3}. {:>7} {:<24} {}", i + 1, sp.pid, sp.name, amount),
+                style,
+            ))
+        })
+        .collect();
+    let title = format!(
+        "most {} over the last {:.0}s",
+        if by_disk { "disk io" } else { "cpu" },
+        state
+            .history
+            .secs_ago(sprocs.iter().map(|sp| sp.cpu_hist.len()).max().unwrap_or(0))
+    );
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+}
+
+fn draw_bandwidth(
+    f: &mut crate::ui::Frame,
+    groups: Option<&[(String, Vec<f64>)]>,
+    bars: &Bars,
+    area: Rect,
+) {
+    let lines: Vec<Line> = match groups {
+        None => vec![Line::from(
+            "not monitoring (needs --resctrl, and a build with the resctrl feature)",
+        )],
+        Some([]) => vec![Line::from("no resctrl monitoring groups")],
+        Some(groups) => {
+            let max = groups
+                .iter()
+                .flat_map(|(_, h)| h.iter().copied())
+                .fold(1., f64::max);
+            groups
+                .iter()
+                .map(|(name, hist)| {
+                    Line::from(format!(
+                        "{:<24} {:>8.1} MB/s {}",
+                        name,
+                        hist.first().copied().unwrap_or(0.),
+                        render::render_vec(hist.iter(), max, bars)
+                    ))
+                })
+                .collect()
+        }
+    };
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("memory bandwidth"),
+        ),
+        area,
+    );
+}
+
+const SUMMARY_HEIGHT: u16 = 4;
+
+// whole-system cpu (and its recent history), load, uptime, memory and swap,
+// and interrupts while sampling wakeups.
+fn draw_summary(
+    f: &mut crate::ui::Frame,
+    system: Option<&SystemStats>,
+    state: &ViewState,
+    area: Rect,
+) {
+    let (accessible, braille) = (state.accessible, state.braille);
+    let s = match system {
+        Some(s) => s,
+        None => {
+            f.render_widget(Paragraph::new("waiting for the first sample"), area);
+            return;
+        }
+    };
+    let [one, five, fifteen] = s.load;
+    let mut cpu = vec![Span::raw(format!("cpu  {:>5.1}% ", s.cpu_pct))];
+    if !accessible {
+        let series = render::Series::new(&s.cpu_hist, 100., |c| state.colors.cpu(c))
+            .with_bars(&state.bars)
+            .with_braille(braille)
+            .with_ascii(state.ascii);
+        cpu.extend(render::render_multi(&[series]).spans);
+    }
+    let used = |used: u64, total: u64| {
+        let pct = if total > 0 {
+            100. * used as f64 / total as f64
+        } else {
+            0.
+        };
+        format!(
+            "{} {} / {}",
+            render::render_gauge(pct, GAUGE_WIDTH),
+            render_bytes(used as f64),
+            render_bytes(total as f64)
+        )
+    };
+    let (mem, swap) = (
+        used(s.mem_used_bytes, s.mem_total_bytes),
+        used(s.swap_used_bytes, s.swap_total_bytes),
+    );
+    let w = mem.chars().count().max(swap.chars().count());
+    let lines = vec![
+        Line::from(cpu),
+        Line::from(format!(
+            "mem  {:<w$}   load {:.2} {:.2} {:.2}",
+            mem,
+            one,
+            five,
+            fifteen,
+            w = w
+        )),
+        Line::from(format!(
+            "swap {:<w$}   up {}{}",
+            swap,
+            render_duration(s.uptime_secs as f64),
+            match s.interrupts_per_sec {
+                Some(n) => format!("   irq/s {}", render_count(n)),
+                None => String::new(),
+            },
+            w = w
+        )),
+        Line::from(churn_spans(s, state)),
+    ];
+    f.render_widget(Paragraph::new(lines), area);
+}
+
+// Process count, with starts and exits per tick: green where starts
+// outnumber exits, red where exits do.
+fn churn_spans(s: &SystemStats, state: &ViewState) -> Vec<Span<'static>> {
+    let (starts, exits) = (
+        s.starts_hist.front().copied().unwrap_or(0.),
+        s.exits_hist.front().copied().unwrap_or(0.),
+    );
+    if state.accessible {
+        return vec![Span::raw(format!(
+            "procs {}, {} started and {} exited last tick",
+            s.procs, starts, exits
+        ))];
+    }
+    let max = s
+        .starts_hist
+        .iter()
+        .chain(&s.exits_hist)
+        .fold(1., |m: f64, &x| m.max(x));
+    let mut spans = vec![Span::raw(format!(
+        "proc {:>5}  +{:<3} -{:<3} ",
+        s.procs, starts, exits
+    ))];
+    spans.extend(
+        render::render_multi(&[
+            render::Series::new(&s.starts_hist, max, |_| state.colors.theme.started)
+                .with_bars(&state.bars),
+            render::Series::new(&s.exits_hist, max, |_| state.colors.theme.dead),
+        ])
+        .spans,
+    );
+    spans
+}
+
+const PALETTE_COMPLETIONS: usize = 6;
+
+fn draw_palette(f: &mut crate::ui::Frame, palette: &Palette) {
+    let area = f.area();
+    let completions = action::complete(&palette.input.text);
+    let shown = completions.len().min(PALETTE_COMPLETIONS);
+    let height = (shown as u16 + 1).min(area.height);
+    let area = Rect {
+        y: area.y + area.height - height,
+        height,
+        ..area
+    };
+    // scroll so the selected completion stays visible.
+    let skip = (palette.selected + 1).saturating_sub(shown);
+    let mut lines: Vec<Line> = completions
+        .iter()
+        .enumerate()
+        .skip(skip)
+        .take(shown)
+        .map(|(i, (name, _))| {
+            let style = if i == palette.selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            Line::from(Span::styled(format!("  {}", name), style))
+        })
+        .collect();
+    lines.push(Line::from(format!(":{}", palette.input.text)));
+    f.render_widget(crate::ui::widgets::Clear, area);
+    f.render_widget(Paragraph::new(lines), area);
+    f.set_cursor_position((
+        area.x + 1 + palette.input.cursor() as u16,
+        area.y + area.height - 1,
+    ));
+}
+
+// The search being typed, on the bottom line.
+fn draw_search(f: &mut crate::ui::Frame, input: &LineInput) {
+    let area = f.area();
+    let area = Rect {
+        y: area.y + area.height.saturating_sub(1),
+        height: area.height.min(1),
+        ..area
+    };
+    f.render_widget(crate::ui::widgets::Clear, area);
+    f.render_widget(Paragraph::new(format!("/{}", input.text)), area);
+    f.set_cursor_position((area.x + 1 + input.cursor() as u16, area.y));
+}
+
+// one line per core: its cpu % and a sparkline of its history, in as many
+// columns as it takes to fit.
+fn draw_cores(f: &mut crate::ui::Frame, state: &ViewState, area: Rect) {
+    let (system, accessible) = (state.system.as_ref(), state.accessible);
+    let hists = system.map_or(&[][..], |s| &s.core_hists[..]);
+    let now: Vec<f64> = hists
+        .iter()
+        .map(|h| h.front().copied().unwrap_or(0.))
+        .collect();
+    let mut title = String::from("cpu cores");
+    if let Some((busiest, &max)) = now.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)) {
+        let avg = now.iter().sum::<f64>() / now.len() as f64;
+        title = format!(
+            "{}: avg {:.0}%, busiest cpu{} {:.0}%",
+            title, avg, busiest, max
+        );
+    }
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if hists.is_empty() {
+        f.render_widget(Paragraph::new("waiting for the first sample"), inner);
+        return;
+    }
+    let rows = inner.height.max(1) as usize;
+    let ncols = hists.len().div_ceil(rows);
+    let cols = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, ncols as u32); ncols])
+        .split(inner);
+    let label_width = format!("cpu{}", hists.len() - 1).len();
+    for (col, &area) in cols.iter().enumerate() {
+        let lines: Vec<Line> = hists
+            .iter()
+            .zip(&now)
+            .enumerate()
+            .skip(col * rows)
+            .take(rows)
+            .map(|(i, (hist, &pct))| {
+                let label = format!("{:<w$} ", format!("cpu{}", i), w = label_width);
+                let mut spans = vec![Span::raw(label)];
+                if accessible {
+                    spans.push(Span::raw(render::render_gauge(pct, GAUGE_WIDTH)));
+                } else {
+                    spans.push(Span::styled(
+                        format!("{:>5.1}% ", pct),
+                        Style::default().fg(state.colors.cpu(pct)),
+                    ));
+                    let series = render::Series::new(hist, 100., |c| state.colors.cpu(c))
+                        .with_bars(&state.bars)
+                        .with_braille(state.braille)
+                        .with_ascii(state.ascii);
+                    spans.extend(render::render_multi(&[series]).spans);
+                }
+                Line::from(spans)
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines), area);
+    }
+}
+
+// a question over the bottom line, for the next key to answer.
+// e.g. "quit, stopping the recording to x.jsonl and killing make? y/n, or
+// s to just stop recording"
+fn quit_question(state: &ViewState) -> String {
+    let mut cut_short = vec![];
+    if let Some(path) = &state.recording {
+        cut_short.push(format!("stopping the recording to {}", path));
+    }
+    if let Some(cmd) = &state.watching {
+        cut_short.push(format!("killing {}", cmd));
+    }
+    let mut question = format!("quit, {}? y/n", cut_short.join(" and "));
+    if state.recording.is_some() {
+        question.push_str(", or s to just stop recording");
+    }
+    question
+}
+
+// e.g. "fds takes 12.5ms a sample (0.6% of a cpu): read every 1-9 samples?
+// enter for every 10, n to leave it off".
+fn cadence_question(c: &ConfirmCadence, sample_secs: f64) -> String {
+    let secs = c.cost.as_secs_f64();
+    format!(
+        "{} takes {:.1}ms a sample ({:.1}% of a cpu): read every 1-9 samples? enter for every \
+         {}, n to leave it off",
+        c.column.header(),
+        secs * 1000.,
+        100. * secs / sample_secs.max(0.001),
+        c.every
+    )
+}
+
+fn draw_confirm(f: &mut crate::ui::Frame, question: &str) {
+    let area = f.area();
+    let area = Rect {
+        y: area.y + area.height.saturating_sub(1),
+        height: area.height.min(1),
+        ..area
+    };
+    f.render_widget(crate::ui::widgets::Clear, area);
+    f.render_widget(
+        Paragraph::new(question).style(Style::default().add_modifier(Modifier::BOLD)),
+        area,
+    );
+}
+
+// the finishing touches to a frame, over everything drawn: no color, or
+// only ASCII, if asked.
+fn restyle(f: &mut crate::ui::Frame, state: &ViewState) {
+    if state.monochrome {
+        f.render_widget(Monochrome(&state.colors.theme), f.area());
+    }
+    if state.ascii {
+        f.render_widget(Ascii, f.area());
+    }
+}
+
+// Redraws everything under it without color: what the colors flagged as
+// hot, dead or alarming goes bold, and what they played down goes dim.
+struct Monochrome<'a>(&'a Theme);
+
+impl crate::ui::widgets::Widget for Monochrome<'_> {
+    fn render(self, area: Rect, buf: &mut crate::ui::buffer::Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = &mut buf[(x, y)];
+                if self.0.alarming(cell.fg) {
+                    cell.modifier.insert(Modifier::BOLD);
+                } else if self.0.muted(cell.fg) {
+                    cell.modifier.insert(Modifier::DIM);
+                }
+                // e.g. a themed selected row.
+                if cell.bg != Color::Reset {
+                    cell.modifier.insert(Modifier::REVERSED);
+                }
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
+            }
+        }
+    }
+}
+
+// Redraws everything under it in plain ASCII (see View::set_ascii), box
+// drawing and all, so no widget is missed.
+struct Ascii;
+
+impl crate::ui::widgets::Widget for Ascii {
+    fn render(self, area: Rect, buf: &mut crate::ui::buffer::Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = &mut buf[(x, y)];
+                match cell.symbol().chars().next() {
+                    Some(c) if !cell.symbol().is_ascii() => {
+                        cell.set_char(render::to_ascii(c));
+                    }
+                    // the rest of a wide glyph, now one cell.
+                    None => {
+                        cell.set_char(' ');
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+// the narrowest each of the script's columns is, however short its name.
+const SCRIPT_MIN_WIDTH: usize = 8;
+
+fn script_width(name: &str) -> usize {
+    name.chars().count().max(SCRIPT_MIN_WIDTH)
+}
+
+// ratatui's default spacing between table columns.
+const COLUMN_SPACING: u16 = 1;
+
+// The widths ui::widgets::Table will resolve `widths` to, so that other rows
+// can line up with the table's columns.
+fn column_widths(widths: &[Constraint], max_width: u16) -> Vec<u16> {
+    Layout::horizontal(widths.iter().copied())
+        .flex(Flex::Start)
+        .spacing(COLUMN_SPACING)
+        .split(Rect::new(0, 0, max_width, 1))
+        .iter()
+        .map(|c| c.width)
+        .collect()
+}
+
+// spikes in cpu history, flagged by stats::spikes: cpu jitter below this
+// many points isn't interesting.
+const CPU_MIN_SD: f64 = 2.;
+// compacted cpu history whose highest sample was at least this many points
+// above the mean it's drawn at (see sproc::CompactBy::MeanAndPeak).
+const PEAK_MIN_RISE: f64 = 20.;
+// with a baseline, variation smaller than this many points isn't blown up to
+// fill the cell.
+const BASELINE_MIN_RANGE: f64 = 10.;
+// below this, a baseline isn't worth the room it takes.
+const BASELINE_MIN: f64 = 1.;
+
+// what a history's minimum is, if it's worth drawing from.
+fn baseline(hist: &[f64]) -> f64 {
+    match hist.iter().copied().reduce(f64::min) {
+        Some(min) if min >= BASELINE_MIN => min,
+        _ => 0.,
+    }
+}
+
+// how far back the spike filter looks, in samples.
+const RECENT_SPIKE_TICKS: usize = 10;
+
+/// Whether the NO_COLOR convention (no-color.org) asks for no color: set,
+/// and not empty.
+pub fn no_color_env() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
+
+/// What the view draws in: the theme, and where the cpu and run queue wait
+/// colors change.
+#[derive(Clone, Debug, Default)]
+pub struct Colors {
+    pub theme: Theme,
+    pub levels: ColorLevels,
+}
+
+impl Colors {
+    // cpu above 100% means multiple cores are busy; shade by how many.
+    fn cpu(&self, cpu: f64) -> Color {
+        let [warm, hot, very_hot] = self.levels.cpu;
+        if cpu >= very_hot {
+            self.theme.cpu_very_hot
+        } else if cpu >= hot {
+            self.theme.cpu_hot
+        } else if cpu > warm {
+            self.theme.cpu_warm
+        } else {
+            Color::Reset
+        }
+    }
+
+    // kernel time on its own is worth flagging sooner than total cpu.
+    fn kernel(&self, cpu: f64) -> Color {
+        if cpu >= 50. {
+            self.theme.kernel_heavy
+        } else {
+            Color::Reset
+        }
+    }
+
+    // zombies aren't being reaped by their parent; D is uninterruptible
+    // sleep, usually stuck on io.
+    fn state(&self, state: char) -> Color {
+        match state {
+            'D' => self.theme.alarm,
+            'Z' => self.theme.warn,
+            _ => Color::Reset,
+        }
+    }
+
+    // runnable but starved: waiting for a cpu a good part of the time.
+    fn latency(&self, pct: f64) -> Color {
+        let [warm, hot] = self.levels.latency;
+        if pct >= hot {
+            self.theme.alarm
+        } else if pct >= warm {
+            self.theme.warn
+        } else {
+            Color::Reset
+        }
+    }
+
+    // ssd reads are ~100µs; tens of ms means a struggling or spinning disk.
+    fn io_latency(&self, us: f64) -> Color {
+        if us >= 50_000. {
+            self.theme.alarm
+        } else if us >= 10_000. {
+            self.theme.warn
+        } else {
+            Color::Reset
+        }
+    }
+}
+
+// e.g. "45s", "12m", "3.5h", "2.0d".
+pub(crate) fn render_duration(secs: f64) -> String {
+    if secs < 60. {
+        format!("{:.0}s", secs)
+    } else if secs < 3600. {
+        format!("{:.0}m", secs / 60.)
+    } else if secs < 86400. {
+        format!("{:.1}h", secs / 3600.)
+    } else {
+        format!("{:.1}d", secs / 86400.)
+    }
+}
+
+// A rename rule's name, or for VMs their guest's, e.g. "web01
+// (qemu-system-x86)".
+fn display_name(sp: &SProc, renames: &Renames) -> String {
+    if let Some(name) = renames.apply(sp) {
+        return name;
+    }
+    match &sp.vm {
+        Some(vm) if vm::is_hypervisor(&sp.name) => format!("{} ({})", vm, sp.name),
+        _ => sp.name.clone(),
+    }
+}
+
+// e.g. "85% cpu, rising", in the column's unit.
+fn render_trend<'a, II>(col: Column, now: String, hist: II) -> String
+where
+    II: IntoIterator<Item = &'a f64>,
+{
+    format!("{}{}, {}", now, col.unit(), stats::direction(hist).name())
+}
+
+// a rate that can run to thousands, in a few chars: e.g. "_", "850", "12k".
+fn render_count(n: f64) -> String {
+    if n < 0.5 {
+        String::from("_")
+    } else if n < 1000. {
+        format!("{:.0}", n)
+    } else if n < 10000. {
+        format!("{:.1}k", n / 1000.)
+    } else {
+        format!("{:.0}k", n / 1000.)
+    }
 }
 
 // hide low values
@@ -1,23 +1,771 @@
 /// ViewState: view model and interactions.
 // rendering is done in view.rs
+use std::{cmp::Ordering, collections::HashMap, env, fmt, str::FromStr, time::Duration};
+
+use anyhow::{anyhow, bail};
+use crossterm::event::KeyEvent;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::ui::style::{Modifier, Style};
+use crate::{
+    action::{Action, Command},
+    alert::AlertLog,
+    cgroup_io::IoSummary,
+    column::{CellKind, Column},
+    config::Breakpoint,
+    coredump::DumpWith,
+    event::Next,
+    group::Grouping,
+    input::{InputEvent, LineInput},
+    kstack::StackSummary,
+    platform::{self, IoPriority, Pressure, Unavailable},
+    rename::Renames,
+    render::Bars,
+    sproc::{History, SProc},
+    sprocs::SystemStats,
+    theme::parse_color,
+    units,
+    view::Colors,
+};
 
 pub struct ViewState {
+    // one table, or two side by side in split mode.
+    pub panes: Vec<TableState>,
+    pub focus: usize, // index into panes; keys go to this one
+    // everything alerted; the latest is shown until dismissed.
+    pub alerts: AlertLog,
+    pub paused: bool, // drawing what was sampled when paused
+    // a full-screen view of the focused pane's selected process.
+    pub zoom: bool,
+    pub zoom_tab: ZoomTab,
+    // the zoomed process's most frequent kernel stacks, on that tab.
+    pub kernel_stacks: Option<StackSummary>,
+    // a page of per-core cpu instead of the tables.
+    pub cores: bool,
+    // io of the zoomed process's cgroup, or why it can't be read.
+    pub cgroup_io: Option<Result<IoSummary, String>>,
+    // name/cmdline patterns of processes pinned to the top of every table.
+    pub bookmarks: Vec<String>,
+    // bookmark (or unbookmark) the selected process on the next draw, when
+    // the rows are known.
+    pub bookmark_pending: bool,
+    pub bookmarks_changed: bool, // since last saved
+    // sparktop's own process, always shown pinned to the top of every table
+    // whatever the filters, and whether it counts toward totals (the system
+    // summary, groups and filter totals).
+    pub own_pid: Option<i32>,
+    pub own_in_totals: bool,
+    // names of processes left out of every table, and the group and filter
+    // totals.
+    pub muted: Vec<String>,
+    pub mute_pending: bool, // likewise resolved on the next draw
+    pub muted_changed: bool,
+    // an io priority change for the selected process, resolved to a pid on
+    // the next draw and then left for the caller (see View::take_ionice).
+    pub ionice_pending: Option<IoChange>,
+    pub ionice: Option<(i32, IoChange)>,
+    // likewise freezing or thawing it (see View::take_freeze).
+    pub freeze_pending: bool,
+    pub freeze: Option<i32>,
+    // a core dump of the selected process: asked for, then waiting on 'y',
+    // then confirmed (see View::take_dump).
+    pub dump_pending: Option<DumpWith>,
+    pub confirm_dump: Option<ConfirmDump>,
+    pub dump: Option<(i32, String, DumpWith)>,
+    pub export: bool, // left for the caller (see View::take_export)
+    // what quitting would cut short: the file being recorded to, and the
+    // command being watched (it gets killed). With either, 'q' asks first,
+    // and can stop the recording instead (see View::take_stop_recording).
+    pub recording: Option<String>,
+    pub watching: Option<String>,
+    pub confirm_quit: bool,
+    pub stop_recording: bool,
+    // turning on a heavy column (see Column::heavy): first measured by the
+    // caller (see View::take_cost_probe), then the next key picks how many
+    // samples apart it's read (see View::take_cadence).
+    pub cost_probe: Option<Column>,
+    pub confirm_cadence: Option<ConfirmCadence>,
+    pub cadence: Option<(Column, u64)>,
+    pub cadences: HashMap<Column, u64>, // as picked, if changed
+    // columns showing values from an earlier sample (see SProcs::stale),
+    // drawn dim.
+    pub stale: Vec<Column>,
+    // column sets by terminal width, widest last.
+    pub breakpoints: Vec<Breakpoint>,
+    pub palette: Option<Palette>,
+    // '/': filtering the focused table as the query is typed.
+    pub search: Option<Search>,
+    pub help: bool,
+    pub event_log: bool, // the process start/exit pane
+    // the alert log pane, and how many of its newest are scrolled past.
+    pub alert_pane: bool,
+    pub alert_scroll: usize,
+    // ranking by usage over the whole history, rather than the ewma.
+    pub ranking: bool,
+    pub sample_secs: f64,           // seconds between samples, for totals
+    pub adaptive_secs: Option<f64>, // what it's stretched to, see adaptive.rs
+    pub monochrome: bool,           // no color, see View::set_monochrome
+    pub ewma_weight: f64,
+    // either changed with a key, so shown in the footer; and whether the
+    // change is left for the caller (see View::take_tuning).
+    pub tuned: bool,
+    pub retune: bool,
+    // memory bandwidth pane: MB/s history per resctrl group, newest first.
+    pub bandwidth_pane: bool,
+    pub bandwidth: Option<Vec<(String, Vec<f64>)>>, // None if not monitored
+    // cpu, memory, load and uptime above the tables.
+    pub summary: bool,
+    // history sparklines two samples to a cell, in braille.
+    pub braille: bool,
+    // the glyphs sparklines are drawn with, and for the columns that read
+    // better with fewer levels, their own.
+    pub bars: Bars,
+    pub column_bars: HashMap<Column, Bars>,
+    // nothing but ASCII on screen (see View::set_ascii).
+    pub ascii: bool,
+    pub colors: Colors,
+    pub system: Option<SystemStats>, // None until the first sample
+    pub history: History,            // how the histories drawn are kept
+    // metric sources that can't be read here; see platform::probe.
+    pub unavailable: Vec<Unavailable>,
+    pub notes: Vec<String>, // shown in the footer
+    // for screen readers: words instead of glyphs, and rows that stay put.
+    pub accessible: bool,
+    pub renames: Renames,
+    pub row_rules: Vec<RowRule>, // from the config, in order
+    // the --script's column names and highlight styles (see script.rs).
+    pub script_columns: Vec<String>,
+    pub script_styles: Vec<Style>,
+    // named filters from the config, on keys 1-9 in order.
+    pub saved_filters: Vec<(String, RowCond)>,
+}
+
+// A process table's own sorting, filtering and columns.
+#[derive(Clone)]
+pub struct TableState {
     pub sort_by: Metric,
     pub sort_dir: Dir,
-    pub alert: Option<String>,
+    // sort by whichever resource is under pressure; None if off.
+    pub smart_sort: Option<SmartSort>,
+    // the selected process, by pid rather than row, so it stays with the
+    // process through ticks and re-sorts. A dead row stays selected while
+    // it's shown; if the row goes (filtered out, or reaped), the selection is
+    // kept and comes back with it.
+    pub selected: Option<i32>,
+    // selection moves and jumps waiting for the next draw, when the rows are
+    // known.
+    pub moves: Vec<Move>,
+    pub jumps: Vec<Jump>,
+    // show the selected row's details under it.
+    pub expanded: bool,
+    pub columns: Vec<Column>,
+    // columns scrolled off to the left; the name column stays put. Like
+    // `expanded`, kept across ticks, sorts and filters.
+    pub h_scroll: usize,
+    // rows scrolled off the top, and how many fit below them, as of the
+    // last draw. Scrolling follows the selection.
+    pub v_scroll: usize,
+    pub page: usize,
+    breakpoint: Option<usize>, // index into ViewState::breakpoints
+    // renderer overrides; columns not in here use their default kind.
+    pub cell_kinds: HashMap<Column, CellKind>,
+    pub group_headers: bool,
+    // each row two lines tall, the second a full-width history.
+    pub tall_rows: bool,
+    // only show processes whose cpu spiked recently.
+    pub spike_filter: bool,
+    // cpu histories drawn from each row's minimum, so variation on top of a
+    // constant load shows.
+    pub baseline: bool,
+    // only show processes whose name or command line contains this, in any
+    // case.
+    pub name_filter: Option<String>,
+    // and only those matching this saved filter (an index into
+    // ViewState::saved_filters).
+    pub saved_filter: Option<usize>,
+    // count exited children's cpu in their parents', like `top -S`.
+    pub cumulative: bool,
+    // one row per group (e.g. pod) instead of per process.
+    pub group_by: Option<Grouping>,
+    pub dead_rows: DeadRows,
+    // in accessible mode, the row order (pids) to keep, and the sort it was
+    // made with.
+    pub row_order: Vec<i32>,
+    pub order_key: Option<(Metric, Dir)>,
+    undo: Vec<Snapshot>,
+}
+
+// the parts of TableState that 'u' can revert.
+#[derive(Clone, PartialEq)]
+struct Snapshot {
+    sort_by: Metric,
+    sort_dir: Dir,
+    smart_sort: bool,
+    columns: Vec<Column>,
+    cell_kinds: HashMap<Column, CellKind>,
+    group_headers: bool,
+    tall_rows: bool,
+    spike_filter: bool,
+    baseline: bool,
+    name_filter: Option<String>,
+    saved_filter: Option<usize>,
+    cumulative: bool,
+    group_by: Option<Grouping>,
+    dead_rows: DeadRows,
+}
+
+const UNDO_LIMIT: usize = 50;
+
+// smart sort switches resource when another's pressure is this many points
+// higher, and it's been at least SMART_HOLD_TICKS since the last switch.
+const SMART_MARGIN: f64 = 5.;
+const SMART_HOLD_TICKS: usize = 5;
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SmartSort {
+    pub pressure: Option<Pressure>, // None until read
+    held: usize,                    // ticks since the last switch
+}
+
+// the ':' command palette.
+#[derive(Default)]
+pub struct Palette {
+    pub input: LineInput,
+    pub selected: usize, // index into the current completions
+}
+
+// the next key answers: 'y' goes ahead, anything else doesn't.
+pub struct ConfirmDump {
+    pub pid: i32,
+    pub name: String,
+    pub with: DumpWith,
+}
+
+// the next key answers: a digit is how many samples apart to read the
+// column, Enter as often as it's read now, anything else leaves it off.
+pub struct ConfirmCadence {
+    pub column: Column,
+    pub cost: Duration, // reading it for every process, once
+    pub every: u64,     // samples apart it's read now
+}
+
+pub struct Search {
+    pub input: LineInput,
+    // the table before the search, for undo.
+    before: Snapshot,
 }
 
 impl Default for ViewState {
     fn default() -> Self {
+        Self {
+            panes: vec![TableState::default()],
+            focus: 0,
+            alerts: AlertLog::default(),
+            paused: false,
+            zoom: false,
+            zoom_tab: ZoomTab::Overview,
+            kernel_stacks: None,
+            cores: false,
+            cgroup_io: None,
+            bookmarks: vec![],
+            bookmark_pending: false,
+            ionice_pending: None,
+            ionice: None,
+            freeze_pending: false,
+            freeze: None,
+            dump_pending: None,
+            confirm_dump: None,
+            dump: None,
+            export: false,
+            recording: None,
+            watching: None,
+            confirm_quit: false,
+            stop_recording: false,
+            cost_probe: None,
+            confirm_cadence: None,
+            cadence: None,
+            cadences: HashMap::default(),
+            stale: vec![],
+            bookmarks_changed: false,
+            own_pid: None,
+            own_in_totals: true,
+            muted: vec![],
+            mute_pending: false,
+            muted_changed: false,
+            breakpoints: vec![],
+            palette: None,
+            search: None,
+            help: false,
+            event_log: false,
+            alert_pane: false,
+            alert_scroll: 0,
+            ranking: false,
+            sample_secs: 1.,
+            adaptive_secs: None,
+            monochrome: false,
+            ewma_weight: 0.5,
+            tuned: false,
+            retune: false,
+            bandwidth_pane: false,
+            bandwidth: None,
+            summary: false,
+            braille: false,
+            bars: Bars::default(),
+            column_bars: HashMap::default(),
+            ascii: false,
+            colors: Colors::default(),
+            system: None,
+            history: History::default(),
+            unavailable: vec![],
+            notes: vec![],
+            accessible: false,
+            renames: Renames::default(),
+            row_rules: vec![],
+            script_columns: vec![],
+            script_styles: vec![],
+            saved_filters: vec![],
+        }
+    }
+}
+
+impl Default for TableState {
+    fn default() -> Self {
+        use Column::*;
         Self {
             sort_by: Metric::Cpu,
             sort_dir: Dir::Desc,
-            alert: None,
+            smart_sort: None,
+            selected: None,
+            moves: vec![],
+            jumps: vec![],
+            expanded: false,
+            columns: vec![Pid, Name, DiskRead, DiskWrite, Mem, MemPct, Cpu, CpuHist],
+            h_scroll: 0,
+            v_scroll: 0,
+            page: 0,
+            breakpoint: None,
+            cell_kinds: HashMap::default(),
+            group_headers: true,
+            tall_rows: false,
+            spike_filter: false,
+            baseline: false,
+            name_filter: None,
+            saved_filter: None,
+            cumulative: false,
+            group_by: None,
+            dead_rows: DeadRows::Interleaved,
+            row_order: vec![],
+            order_key: None,
+            undo: vec![],
         }
     }
 }
 
-#[derive(Copy, Clone, PartialEq)]
+impl ViewState {
+    pub fn apply(&mut self, action: Action) -> Next {
+        use Action::*;
+        match action {
+            ToggleEventLog => self.event_log = !self.event_log,
+            ToggleAlerts => {
+                self.alert_pane = !self.alert_pane;
+                self.alert_scroll = 0;
+            }
+            // down to older alerts, up back to the newest.
+            ScrollAlerts(step) => {
+                self.alert_pane = true;
+                self.alert_scroll = match step {
+                    Step::Down => (self.alert_scroll + 1).min(self.alerts.len().saturating_sub(1)),
+                    Step::Up => self.alert_scroll.saturating_sub(1),
+                };
+            }
+            ToggleBandwidth => self.bandwidth_pane = !self.bandwidth_pane,
+            ToggleSummary => self.summary = !self.summary,
+            ToggleBraille => self.braille = !self.braille,
+            TogglePause => self.paused = !self.paused,
+            Delay(step) => {
+                self.sample_secs = step.delay(self.sample_secs);
+                self.tuned = true;
+                self.retune = true;
+            }
+            EwmaWeight(step) => {
+                self.ewma_weight = step.ewma_weight(self.ewma_weight);
+                self.tuned = true;
+                self.retune = true;
+            }
+            ToggleRanking => self.ranking = !self.ranking,
+            ToggleSplit => self.toggle_split(),
+            FocusNext if self.zoom => self.zoom_tab = self.zoom_tab.next(),
+            FocusNext => self.focus = (self.focus + 1) % self.panes.len(),
+            OpenPalette => self.palette = Some(Palette::default()),
+            OpenSearch => {
+                let t = self.table();
+                let mut input = LineInput::default();
+                input.set(t.name_filter.as_deref().unwrap_or(""));
+                self.search = Some(Search {
+                    input,
+                    before: t.snapshot(),
+                });
+            }
+            ClearAlert => {
+                self.alerts.dismiss();
+                self.help = false;
+                self.zoom = false;
+                self.zoom_tab = ZoomTab::Overview;
+                self.cores = false;
+            }
+            ToggleBookmark => self.bookmark_pending = true,
+            Mute => self.mute_pending = true,
+            UnmuteAll => {
+                self.alerts
+                    .push(format!("unmuted {} names", self.muted.len()));
+                self.muted_changed |= !self.muted.is_empty();
+                self.muted.clear();
+            }
+            Ionice(change) => self.ionice_pending = Some(change),
+            ToggleFreeze => self.freeze_pending = true,
+            CoreDump(with) => self.dump_pending = Some(with),
+            Export => self.export = true,
+            Zoom => self.zoom = !self.zoom,
+            ZoomTo(tab) => {
+                self.zoom = true;
+                self.zoom_tab = tab;
+            }
+            ToggleCores => self.cores = !self.cores,
+            SavedFilter(i) if i >= self.saved_filters.len() => self.alerts.push(format!(
+                "no saved filter {} (add some to the config's filters)",
+                i + 1
+            )),
+            ToggleHelp => self.help = !self.help,
+            Quit if self.recording.is_some() || self.watching.is_some() => self.confirm_quit = true,
+            Quit => return Next::Quit,
+            ToggleColumn(Column::Script) | SetColumn(Column::Script, true)
+                if self.script_columns.is_empty()
+                    && !self.table().columns.contains(&Column::Script) =>
+            {
+                self.alerts
+                    .push("script unavailable: no --script with columns".into())
+            }
+            ToggleColumn(c)
+                if c.heavy()
+                    && !self.table().columns.contains(&c)
+                    && !self.unavailable.iter().any(|u| u.columns.contains(&c)) =>
+            {
+                self.cost_probe = Some(c)
+            }
+            _ => {
+                let unavailable = &self.unavailable;
+                if let Err(e) = self.panes[self.focus].apply(action, unavailable) {
+                    self.alerts.push(e);
+                }
+            }
+        }
+        Next::Continue
+    }
+
+    // the answer to a ConfirmCadence: show the column, read every `n`
+    // samples.
+    pub fn show_heavy(&mut self, c: &ConfirmCadence, n: u64) {
+        let col = c.column;
+        let unavailable = &self.unavailable;
+        if let Err(e) = self.panes[self.focus].apply(Action::SetColumn(col, true), unavailable) {
+            self.alerts.push(e);
+            return;
+        }
+        self.cadence = Some((col, n));
+        if n != c.every {
+            self.cadences.insert(col, n);
+        }
+    }
+
+    pub fn run(&mut self, cmd: Command) -> Next {
+        match cmd {
+            Command::Run(a) => return self.apply(a),
+            Command::Filter(f) => {
+                let t = self.table_mut();
+                let before = t.snapshot();
+                t.name_filter = f;
+                t.push_undo(before);
+            }
+        }
+        Next::Continue
+    }
+
+    // typing filters the focused table as it goes; Enter keeps the filter,
+    // Esc clears it.
+    pub fn handle_search_key(&mut self, key: KeyEvent) -> Next {
+        let search = match &mut self.search {
+            Some(s) => s,
+            None => return Next::Continue,
+        };
+        let event = search.input.handle_key(key);
+        let query = Some(search.input.text.clone()).filter(|q| !q.is_empty());
+        let t = &mut self.panes[self.focus];
+        match event {
+            InputEvent::Edited => t.name_filter = query,
+            InputEvent::Submit | InputEvent::Cancel => {
+                if event == InputEvent::Cancel {
+                    t.name_filter = None;
+                }
+                if let Some(search) = self.search.take() {
+                    t.push_undo(search.before);
+                }
+            }
+            InputEvent::Ignored => (),
+        }
+        Next::Continue
+    }
+
+    /// The glyphs for column `col`'s sparklines: its own if it has any,
+    /// else the default set.
+    pub fn bars(&self, col: Column) -> &Bars {
+        self.column_bars.get(&col).unwrap_or(&self.bars)
+    }
+
+    /// The focused table.
+    pub fn table(&self) -> &TableState {
+        &self.panes[self.focus]
+    }
+
+    pub fn table_mut(&mut self) -> &mut TableState {
+        &mut self.panes[self.focus]
+    }
+
+    // a second table next to the first, sorted by memory (or cpu, if the
+    // first already is), or back to just the focused one.
+    fn toggle_split(&mut self) {
+        if self.panes.len() > 1 {
+            let keep = self.panes.swap_remove(self.focus);
+            self.panes = vec![keep];
+            self.focus = 0;
+            return;
+        }
+        let mut other = self.panes[0].clone();
+        other.sort_by = match other.sort_by {
+            Metric::Mem => Metric::Cpu,
+            _ => Metric::Mem,
+        };
+        other.sort_dir = Dir::Desc;
+        other.smart_sort = None;
+        other.undo.clear();
+        self.panes.push(other);
+        self.focus = 1;
+    }
+
+    // with smart sort on, follow the resource under the most pressure.
+    pub fn set_pressure(&mut self, pressure: Pressure) {
+        for t in &mut self.panes {
+            t.set_pressure(pressure);
+        }
+    }
+
+    // hide columns whose data can't be read, and refuse to show them.
+    pub fn set_unavailable(&mut self, unavailable: Vec<Unavailable>) {
+        for t in &mut self.panes {
+            for u in &unavailable {
+                t.columns.retain(|c| !u.columns.contains(c));
+            }
+        }
+        self.unavailable = unavailable;
+    }
+
+    // switch each table to its breakpoint's column set when its width
+    // crosses into it. Columns toggled by hand stay until the next crossing.
+    pub fn fit_width(&mut self, pane: usize, width: u16) {
+        let i = self.breakpoints.iter().rposition(|b| width >= b.min_width);
+        let t = &mut self.panes[pane];
+        if i == t.breakpoint {
+            return;
+        }
+        t.breakpoint = i;
+        if let Some(i) = i {
+            let unavailable = &self.unavailable;
+            t.columns = self.breakpoints[i]
+                .columns
+                .iter()
+                .copied()
+                .filter(|c| !unavailable.iter().any(|u| u.columns.contains(c)))
+                .collect();
+        }
+    }
+}
+
+impl TableState {
+    // Err is a message for the user.
+    fn apply(&mut self, action: Action, unavailable: &[Unavailable]) -> Result<(), String> {
+        use Action::*;
+        let before = self.snapshot();
+        match action {
+            SortBy(m) => {
+                self.sort_by = m;
+                self.smart_sort = None;
+                self.order_key = None; // re-sort, even if by the same thing
+            }
+            ToggleSmartSort => {
+                self.smart_sort = match self.smart_sort {
+                    Some(_) => None,
+                    None => Some(SmartSort::default()),
+                }
+            }
+            FlipSort => {
+                self.sort_dir.flip();
+                self.order_key = None;
+            }
+            ToggleColumn(c) => self.toggle_column(c, unavailable)?,
+            SetColumn(c, show) if self.columns.contains(&c) != show => {
+                self.toggle_column(c, unavailable)?
+            }
+            CycleCellKind(c) => self.cycle_cell_kind(c),
+            ToggleGroupHeaders => self.group_headers = !self.group_headers,
+            ToggleTallRows => self.tall_rows = !self.tall_rows,
+            ToggleCumulative => self.cumulative = !self.cumulative,
+            ToggleSpikeFilter => self.spike_filter = !self.spike_filter,
+            // again to turn it off.
+            SavedFilter(i) => self.saved_filter = (self.saved_filter != Some(i)).then_some(i),
+            ToggleBaseline => self.baseline = !self.baseline,
+            CycleDeadRows => self.dead_rows = self.dead_rows.next(),
+            PlaceDeadRows(d) => self.dead_rows = d,
+            GroupBy(g) => {
+                self.group_by = match self.group_by {
+                    Some(cur) if cur == g => None,
+                    _ => Some(g),
+                }
+            }
+            ToggleExpand => self.expanded = !self.expanded,
+            Select(m) => self.moves.push(m),
+            JumpTo(j) => self.jumps.push(j),
+            ScrollLeft => self.h_scroll = self.h_scroll.saturating_sub(1),
+            ScrollRight => {
+                let scrollable = self.columns.iter().filter(|&&c| c != Column::Name).count();
+                self.h_scroll = (self.h_scroll + 1).min(scrollable.saturating_sub(1));
+            }
+            Undo => return self.undo(),
+            _ => (),
+        }
+        self.push_undo(before);
+        Ok(())
+    }
+
+    fn push_undo(&mut self, before: Snapshot) {
+        if self.snapshot() != before {
+            self.undo.push(before);
+            if self.undo.len() > UNDO_LIMIT {
+                self.undo.remove(0);
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            sort_by: self.sort_by,
+            sort_dir: self.sort_dir,
+            smart_sort: self.smart_sort.is_some(),
+            columns: self.columns.clone(),
+            cell_kinds: self.cell_kinds.clone(),
+            group_headers: self.group_headers,
+            tall_rows: self.tall_rows,
+            spike_filter: self.spike_filter,
+            baseline: self.baseline,
+            name_filter: self.name_filter.clone(),
+            saved_filter: self.saved_filter,
+            cumulative: self.cumulative,
+            group_by: self.group_by,
+            dead_rows: self.dead_rows,
+        }
+    }
+
+    fn undo(&mut self) -> Result<(), String> {
+        let s = self.undo.pop().ok_or("nothing to undo")?;
+        self.sort_by = s.sort_by;
+        self.sort_dir = s.sort_dir;
+        if s.smart_sort != self.smart_sort.is_some() {
+            self.smart_sort = s.smart_sort.then(SmartSort::default);
+        }
+        self.columns = s.columns;
+        self.cell_kinds = s.cell_kinds;
+        self.group_headers = s.group_headers;
+        self.tall_rows = s.tall_rows;
+        self.spike_filter = s.spike_filter;
+        self.baseline = s.baseline;
+        self.name_filter = s.name_filter;
+        self.saved_filter = s.saved_filter;
+        self.cumulative = s.cumulative;
+        self.group_by = s.group_by;
+        self.dead_rows = s.dead_rows;
+        Ok(())
+    }
+
+    fn set_pressure(&mut self, pressure: Pressure) {
+        let smart = match &mut self.smart_sort {
+            Some(s) => s,
+            None => return,
+        };
+        smart.pressure = Some(pressure);
+        smart.held += 1;
+        let of = |m: Metric| match m {
+            Metric::Cpu => pressure.cpu,
+            Metric::Mem => pressure.memory,
+            _ => pressure.io,
+        };
+        let (best, best_pct) = [Metric::Cpu, Metric::Mem, Metric::DiskTotal]
+            .iter()
+            .map(|&m| (m, of(m)))
+            .fold(
+                (Metric::Cpu, f64::MIN),
+                |a, b| if b.1 > a.1 { b } else { a },
+            );
+        let current = match self.sort_by {
+            m @ (Metric::Cpu | Metric::Mem | Metric::DiskTotal) => Some(m),
+            _ => None,
+        };
+        let switch = match current {
+            Some(m) if m == best => false,
+            Some(m) => best_pct >= of(m) + SMART_MARGIN && smart.held >= SMART_HOLD_TICKS,
+            None => true,
+        };
+        if switch {
+            smart.held = 0;
+            self.sort_by = best;
+            self.sort_dir = Dir::Desc;
+        }
+    }
+
+    // show or hide a column, keeping columns in their canonical order.
+    pub fn toggle_column(
+        &mut self,
+        col: Column,
+        unavailable: &[Unavailable],
+    ) -> Result<(), String> {
+        if let Some(i) = self.columns.iter().position(|&c| c == col) {
+            self.columns.remove(i);
+        } else if let Some(u) = unavailable.iter().find(|u| u.columns.contains(&col)) {
+            return Err(format!("{} unavailable: {}", col.header(), u.reason));
+        } else {
+            self.columns.push(col);
+            self.columns
+                .sort_by_key(|c| Column::ALL.iter().position(|x| x == c));
+        }
+        Ok(())
+    }
+
+    pub fn cell_kind(&self, col: Column) -> CellKind {
+        self.cell_kinds
+            .get(&col)
+            .copied()
+            .unwrap_or_else(|| col.kinds()[0])
+    }
+
+    // switch a column to its next supported renderer.
+    pub fn cycle_cell_kind(&mut self, col: Column) {
+        let kinds = col.kinds();
+        let cur = kinds.iter().position(|&k| k == self.cell_kind(col));
+        let next = kinds[cur.map_or(0, |i| (i + 1) % kinds.len())];
+        self.cell_kinds.insert(col, next);
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Metric {
     Pid, // not really a "metric"... rename this?
     Cpu,
@@ -25,8 +773,216 @@ pub enum Metric {
     DiskRead,
     DiskWrite,
     DiskTotal,
+    Latency,
+    Wakeups,
+    Ports,
+    TreeCpu,
 }
 
+impl Metric {
+    pub const ALL: [Metric; 10] = [
+        Metric::Pid,
+        Metric::Cpu,
+        Metric::Mem,
+        Metric::DiskRead,
+        Metric::DiskWrite,
+        Metric::DiskTotal,
+        Metric::Latency,
+        Metric::Wakeups,
+        Metric::Ports,
+        Metric::TreeCpu,
+    ];
+
+    pub fn name(self) -> &'static str {
+        use Metric::*;
+        match self {
+            Pid => "pid",
+            Cpu => "cpu",
+            Mem => "mem",
+            DiskRead => "disk read",
+            DiskWrite => "disk write",
+            DiskTotal => "disk total",
+            Latency => "run queue wait",
+            Wakeups => "wakeups",
+            Ports => "listening ports",
+            TreeCpu => "subtree cpu",
+        }
+    }
+}
+
+// where exited processes go in a table.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DeadRows {
+    Interleaved, // sorted among the living by their decaying values
+    Bottom,
+    Top,
+}
+
+impl DeadRows {
+    pub const ALL: [DeadRows; 3] = [DeadRows::Interleaved, DeadRows::Bottom, DeadRows::Top];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DeadRows::Interleaved => "interleaved",
+            DeadRows::Bottom => "at bottom",
+            DeadRows::Top => "at top",
+        }
+    }
+
+    fn next(self) -> DeadRows {
+        let i = DeadRows::ALL.iter().position(|&d| d == self).unwrap_or(0);
+        DeadRows::ALL[(i + 1) % DeadRows::ALL.len()]
+    }
+}
+
+// what the zoom view shows; Tab goes to the next.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ZoomTab {
+    Overview,
+    KernelStack, // sampled /proc/<pid>/stack
+}
+
+impl ZoomTab {
+    pub const ALL: [ZoomTab; 2] = [ZoomTab::Overview, ZoomTab::KernelStack];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ZoomTab::Overview => "overview",
+            ZoomTab::KernelStack => "kernel stack",
+        }
+    }
+
+    fn next(self) -> ZoomTab {
+        let i = ZoomTab::ALL.iter().position(|&t| t == self).unwrap_or(0);
+        ZoomTab::ALL[(i + 1) % ZoomTab::ALL.len()]
+    }
+}
+
+// the delays '+' and '-' step through, in seconds.
+const DELAYS: [f64; 9] = [0.1, 0.2, 0.5, 1., 2., 3., 5., 10., 30.];
+
+// a step up or down in the delay between samples, or the ewma weight.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Step {
+    Up,
+    Down,
+}
+
+impl Step {
+    pub const ALL: [Step; 2] = [Step::Up, Step::Down];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Step::Up => "up",
+            Step::Down => "down",
+        }
+    }
+
+    // the next of DELAYS from `secs`, which needn't be one of them.
+    pub fn delay(self, secs: f64) -> f64 {
+        let next = match self {
+            Step::Up => DELAYS.iter().find(|&&d| d > secs * 1.001),
+            Step::Down => DELAYS.iter().rev().find(|&&d| d < secs * 0.999),
+        };
+        next.copied().unwrap_or(secs)
+    }
+
+    // a tenth up or down, staying above 0 and at most 1.
+    pub fn ewma_weight(self, weight: f64) -> f64 {
+        let tenths = match self {
+            Step::Up => weight * 10. + 1.,
+            Step::Down => weight * 10. - 1.,
+        };
+        tenths.round().clamp(1., 10.) / 10.
+    }
+}
+
+// a move of the selection through a table's rows.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Move {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+}
+
+impl Move {
+    pub const ALL: [Move; 6] = [
+        Move::Up,
+        Move::Down,
+        Move::PageUp,
+        Move::PageDown,
+        Move::Home,
+        Move::End,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Move::Up => "up",
+            Move::Down => "down",
+            Move::PageUp => "page up",
+            Move::PageDown => "page down",
+            Move::Home => "first",
+            Move::End => "last",
+        }
+    }
+
+    // the row index to move to from `from` (None if no row is selected), in
+    // `len` rows with `page` shown at once. Only called with len > 0.
+    pub fn from(self, from: Option<usize>, len: usize, page: usize) -> usize {
+        let last = len - 1;
+        let page = page.max(1);
+        match (self, from) {
+            (Move::End, _) => last,
+            (Move::Home, _) | (_, None) => 0,
+            (Move::Up, Some(i)) => i.saturating_sub(1),
+            (Move::Down, Some(i)) => (i + 1).min(last),
+            (Move::PageUp, Some(i)) => i.saturating_sub(page),
+            (Move::PageDown, Some(i)) => (i + page).min(last),
+        }
+    }
+}
+
+// a jump of the selection to a related process.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Jump {
+    Parent,
+    Child,   // the first shown
+    Sibling, // the next with the same name, around and back
+}
+
+impl Jump {
+    pub const ALL: [Jump; 3] = [Jump::Parent, Jump::Child, Jump::Sibling];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Jump::Parent => "parent",
+            Jump::Child => "child",
+            Jump::Sibling => "sibling",
+        }
+    }
+}
+
+// a change to a process's io priority (its ionice).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IoChange {
+    Lower, // one class down, see IoPriority::lower
+    Set(IoPriority),
+}
+
+impl IoChange {
+    pub fn apply(self, current: IoPriority) -> IoPriority {
+        match self {
+            IoChange::Lower => current.lower(),
+            IoChange::Set(p) => p,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Dir {
     Asc,
     Desc,
@@ -41,3 +997,305 @@ impl Dir {
         }
     }
 }
+
+/// A row highlighting rule, e.g. `cpu_ewma > 200 -> bg:red` or
+/// `name =~ "chrome" -> fg:yellow, bold`: rows it matches are drawn in its
+/// style, over their usual one. Later rules win where they overlap.
+//
+// Syntax: `<condition> -> <style>`, the condition as in RowCond. The style is
+// any of fg:<color>, bg:<color> (colors as in theme.rs), bold, dim, italic,
+// underlined and reversed, separated by commas or spaces.
+#[derive(Clone, Debug)]
+pub struct RowRule {
+    src: String,
+    cond: RowCond,
+    pub style: Style,
+}
+
+/// A test of a process's fields, e.g. `cpu_ewma > 200`, `name in {postgres,
+/// redis}` or `user == $USER`, for highlighting rules and saved filters.
+//
+// Syntax: `<field> <op> <value>`. Fields are SProc's: pid, cpu (the latest
+// sample), cpu_ewma, mem_mb, mem_pct, disk_read_ewma, disk_write_ewma, fds,
+// threads, latency_ewma and wakeups_ewma compare as numbers with > >= < <=
+// == !=; name, cmd, state and pod as text with == != or =~ for a regex; and
+// user (a name or uid) with == !=. Text fields and user can also be `in` a
+// braced, comma separated list. Text values may be quoted, and a value like
+// $USER (other than a regex) is that environment variable's. Rows without
+// the field (e.g. fds unreadable, or not being read) never match.
+#[derive(Clone, Debug)]
+pub struct RowCond {
+    src: String,
+    field: RuleField,
+    test: RuleTest,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum RuleField {
+    Pid,
+    Cpu,
+    CpuEwma,
+    MemMb,
+    MemPct,
+    DiskReadEwma,
+    DiskWriteEwma,
+    Fds,
+    Threads,
+    LatencyEwma,
+    WakeupsEwma,
+    Name,
+    Cmd,
+    State,
+    Pod,
+    User,
+}
+
+#[derive(Clone, Debug)]
+enum RuleTest {
+    Compare(Ordering, bool, f64), // the ordering wanted, or not
+    Is(String, bool),             // equal, or not
+    In(Vec<String>),
+    Matches(Regex),
+}
+
+impl RuleField {
+    const ALL: [(&'static str, RuleField); 16] = [
+        ("pid", RuleField::Pid),
+        ("cpu", RuleField::Cpu),
+        ("cpu_ewma", RuleField::CpuEwma),
+        ("mem_mb", RuleField::MemMb),
+        ("mem_pct", RuleField::MemPct),
+        ("disk_read_ewma", RuleField::DiskReadEwma),
+        ("disk_write_ewma", RuleField::DiskWriteEwma),
+        ("fds", RuleField::Fds),
+        ("threads", RuleField::Threads),
+        ("latency_ewma", RuleField::LatencyEwma),
+        ("wakeups_ewma", RuleField::WakeupsEwma),
+        ("name", RuleField::Name),
+        ("cmd", RuleField::Cmd),
+        ("state", RuleField::State),
+        ("pod", RuleField::Pod),
+        ("user", RuleField::User),
+    ];
+
+    fn text(self) -> bool {
+        use RuleField::*;
+        matches!(self, Name | Cmd | State | Pod | User)
+    }
+
+    fn number(self, sp: &SProc) -> Option<f64> {
+        use RuleField::*;
+        match self {
+            Pid => Some(sp.pid as f64),
+            Cpu => sp.cpu_hist.front().copied(),
+            CpuEwma => Some(sp.cpu_ewma),
+            MemMb => Some(units::mib(sp.mem_bytes as f64)),
+            MemPct => Some(sp.mem_pct),
+            DiskReadEwma => Some(sp.disk_read_ewma),
+            DiskWriteEwma => Some(sp.disk_write_ewma),
+            Fds => sp.fds.map(|n| n as f64),
+            Threads => sp.threads.map(|n| n as f64),
+            LatencyEwma => Some(sp.latency_ewma),
+            WakeupsEwma => Some(sp.wakeups_ewma),
+            Name | Cmd | State | Pod | User => None,
+        }
+    }
+
+    // users by uid, so rows needn't look up their names.
+    fn string(self, sp: &SProc) -> Option<String> {
+        use RuleField::*;
+        match self {
+            Name => Some(sp.name.clone()),
+            Cmd => Some(sp.cmd.join(" ")),
+            State => sp.state.map(String::from),
+            Pod => sp.pod.clone(),
+            User => sp.uid.map(|u| u.to_string()),
+            _ => None,
+        }
+    }
+
+    // a value as given, as string() would have it.
+    fn value(self, v: &str) -> anyhow::Result<String> {
+        let v = match v.strip_prefix('$') {
+            Some(var) => env::var(var).map_err(|_| anyhow!("${} isn't set", var))?,
+            None => v.to_string(),
+        };
+        match self {
+            RuleField::User if v.parse::<u32>().is_err() => platform::user_uid(&v)
+                .map(|u| u.to_string())
+                .ok_or_else(|| anyhow!("no such user: {}", v)),
+            _ => Ok(v),
+        }
+    }
+}
+
+// unquoted.
+fn unquote(v: &str) -> &str {
+    v.strip_prefix('"')
+        .and_then(|r| r.strip_suffix('"'))
+        .unwrap_or(v)
+}
+
+impl FromStr for RowCond {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        // the first operator in it, longest first where they overlap.
+        let ops = [" in ", "=~", ">=", "<=", "==", "!=", ">", "<"];
+        let (at, op) = ops
+            .iter()
+            .filter_map(|&op| s.find(op).map(|at| (at, op)))
+            .min_by_key(|&(at, op)| (at, usize::MAX - op.len()))
+            .ok_or_else(|| anyhow!("no comparison in {:?}", s))?;
+        let (lhs, rhs) = (s[..at].trim(), s[at + op.len()..].trim());
+        let op = op.trim();
+        let field = RuleField::ALL
+            .iter()
+            .find(|(name, _)| *name == lhs)
+            .map(|&(_, f)| f)
+            .ok_or_else(|| {
+                let names: Vec<&str> = RuleField::ALL.iter().map(|(n, _)| *n).collect();
+                anyhow!("unknown field {:?} (want one of {})", lhs, names.join(", "))
+            })?;
+        let rhs = unquote(rhs);
+        let test = match (op, field.text()) {
+            ("in", true) => {
+                let list = rhs
+                    .strip_prefix('{')
+                    .and_then(|r| r.strip_suffix('}'))
+                    .ok_or_else(|| anyhow!("in wants a list like {{a, b}}, not {:?}", rhs))?;
+                let values = list
+                    .split(',')
+                    .map(|v| unquote(v.trim()))
+                    .filter(|v| !v.is_empty())
+                    .map(|v| field.value(v))
+                    .collect::<anyhow::Result<_>>()?;
+                RuleTest::In(values)
+            }
+            ("=~", true) if field == RuleField::User => bail!("user is compared with ==, != or in"),
+            ("=~", true) => RuleTest::Matches(Regex::new(rhs)?),
+            ("==", true) | ("!=", true) => RuleTest::Is(field.value(rhs)?, op == "=="),
+            (_, true) => bail!("{} is text: compare it with ==, !=, =~ or in", lhs),
+            ("=~", false) | ("in", false) => bail!("{} is a number: {} is for text", lhs, op),
+            (_, false) => {
+                let n = field
+                    .value(rhs)?
+                    .parse()
+                    .map_err(|_| anyhow!("{:?} isn't a number (for {})", rhs, lhs))?;
+                match op {
+                    ">" => RuleTest::Compare(Ordering::Greater, true, n),
+                    "<" => RuleTest::Compare(Ordering::Less, true, n),
+                    ">=" => RuleTest::Compare(Ordering::Less, false, n),
+                    "<=" => RuleTest::Compare(Ordering::Greater, false, n),
+                    "==" => RuleTest::Compare(Ordering::Equal, true, n),
+                    _ => RuleTest::Compare(Ordering::Equal, false, n),
+                }
+            }
+        };
+        Ok(Self {
+            src: s.trim().into(),
+            field,
+            test,
+        })
+    }
+}
+
+impl fmt::Display for RowCond {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.src)
+    }
+}
+
+impl RowCond {
+    pub fn matches(&self, sp: &SProc) -> bool {
+        match &self.test {
+            RuleTest::Compare(ord, want, n) => self
+                .field
+                .number(sp)
+                .and_then(|v| v.partial_cmp(n))
+                .is_some_and(|o| (o == *ord) == *want),
+            RuleTest::Is(s, want) => self.field.string(sp).is_some_and(|v| (v == *s) == *want),
+            RuleTest::In(list) => self.field.string(sp).is_some_and(|v| list.contains(&v)),
+            RuleTest::Matches(re) => self.field.string(sp).is_some_and(|v| re.is_match(&v)),
+        }
+    }
+}
+
+impl FromStr for RowRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (cond, style) = s
+            .split_once("->")
+            .ok_or_else(|| anyhow!("no -> style in {:?}", s))?;
+        Ok(Self {
+            src: s.trim().into(),
+            cond: cond.parse()?,
+            style: parse_style(style)?,
+        })
+    }
+}
+
+impl fmt::Display for RowRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.src)
+    }
+}
+
+impl RowRule {
+    pub fn matches(&self, sp: &SProc) -> bool {
+        self.cond.matches(sp)
+    }
+}
+
+#[cfg(feature = "scripting")]
+pub(crate) enum FieldValue {
+    Count(i64), // pid, fds and threads
+    Number(f64),
+    Text(String),
+}
+
+// a process's fields under the names conditions know them by, leaving out
+// those it hasn't got (e.g. fds unreadable), for scripts (see script.rs).
+#[cfg(feature = "scripting")]
+pub(crate) fn row_fields(sp: &SProc) -> Vec<(&'static str, FieldValue)> {
+    use RuleField::*;
+    RuleField::ALL
+        .iter()
+        .filter_map(|&(name, field)| {
+            let v = match field {
+                _ if field.text() => FieldValue::Text(field.string(sp)?),
+                Pid | Fds | Threads => FieldValue::Count(field.number(sp)? as i64),
+                _ => FieldValue::Number(field.number(sp)?),
+            };
+            Some((name, v))
+        })
+        .collect()
+}
+
+// e.g. "fg:yellow, bold".
+pub(crate) fn parse_style(s: &str) -> anyhow::Result<Style> {
+    let mut style = Style::default();
+    for part in s.split([',', ' ']).filter(|p| !p.is_empty()) {
+        let color = |c: &str| parse_color(c).ok_or_else(|| anyhow!("no color {:?}", c));
+        style = match part.split_once(':') {
+            Some(("fg", c)) => style.fg(color(c)?),
+            Some(("bg", c)) => style.bg(color(c)?),
+            _ => style.add_modifier(match part {
+                "bold" => Modifier::BOLD,
+                "dim" => Modifier::DIM,
+                "italic" => Modifier::ITALIC,
+                "underlined" => Modifier::UNDERLINED,
+                "reversed" => Modifier::REVERSED,
+                _ => bail!(
+                    "unknown style {:?} (want fg:<color>, bg:<color>, bold, ...)",
+                    part
+                ),
+            }),
+        };
+    }
+    if style == Style::default() {
+        bail!("no style in {:?}", s);
+    }
+    Ok(style)
+}
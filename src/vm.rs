@@ -0,0 +1,75 @@
+//! VMs: which virtual machine a hypervisor process (qemu/kvm, firecracker,
+//! cloud-hypervisor) runs, and the helpers working for it.
+//
+// A VM's cpu is split over its hypervisor process, the kernel's worker
+// threads for it ("vhost-<hypervisor pid>", "kvm-pit/<pid>") and whatever it
+// spawned (e.g. helpers
+// like qemu-pr-helper). Each gets the VM's name, from the command line:
+//   qemu:         -name guest=web01,debug-threads=on   (or -name web01)
+//   firecracker:  --id web01
+//   lkvm:         --name web01
+// and falls back to "vm <pid>".
+use std::collections::HashMap;
+
+use crate::sproc::SProc;
+
+// process names (comm, so at most 15 chars) start with one of these; plain
+// "qemu" would catch qemu-ga, qemu-pr-helper etc.
+const HYPERVISORS: &[&str] = &[
+    "qemu-system",
+    "qemu-kvm",
+    "firecracker",
+    "cloud-hyperviso",
+    "lkvm",
+];
+// kernel threads named for the hypervisor pid they work for.
+const WORKERS: &[&str] = &["vhost-", "kvm-pit/"];
+
+pub fn is_hypervisor(name: &str) -> bool {
+    name == "kvm" || HYPERVISORS.iter().any(|h| name.starts_with(h))
+}
+
+/// The guest name given on a hypervisor's command line.
+pub fn guest_name(cmd: &[String]) -> Option<String> {
+    let mut args = cmd.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((f, v)) if f.starts_with("--") => (f, Some(v.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        if !matches!(flag, "-name" | "--name" | "--id") {
+            continue;
+        }
+        let value = value.or_else(|| args.next().cloned())?;
+        // qemu's is a list of options, the name first.
+        let first = value.split(',').next().unwrap_or_default();
+        let name = first.strip_prefix("guest=").unwrap_or(first);
+        if !name.is_empty() {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+/// Set each process's vm: hypervisors by their command line, then vhost
+/// workers and children by the hypervisor they serve.
+pub fn annotate(sprocs: &mut HashMap<i32, SProc>) {
+    let guests: HashMap<i32, String> = sprocs
+        .values()
+        .filter(|sp| is_hypervisor(&sp.name))
+        .map(|sp| {
+            let name = guest_name(&sp.cmd).unwrap_or_else(|| format!("vm {}", sp.pid));
+            (sp.pid, name)
+        })
+        .collect();
+    for sp in sprocs.values_mut() {
+        let served = WORKERS
+            .iter()
+            .find_map(|w| sp.name.strip_prefix(w)?.parse().ok())
+            .or(sp.ppid);
+        sp.vm = guests
+            .get(&sp.pid)
+            .or_else(|| guests.get(&served?))
+            .cloned();
+    }
+}
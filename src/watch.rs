@@ -0,0 +1,167 @@
+//! Watch: run a command and track the resource usage of its process tree.
+use std::{
+    collections::HashMap,
+    path::Path,
+    process::{Child, Command, ExitStatus, Stdio},
+    time::Instant,
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{sprocs::SProcs, threshold::Thresholds, units};
+
+pub struct Watch {
+    child: Child,
+    start: Instant,
+    status: Option<ExitStatus>,
+    wall_secs: Option<f64>, // from start to when it was seen to exit
+    ticks: usize,
+    cpu_sum: f64,
+    cpu_peak: f64,
+    rss_peak_mb: f64,
+    disk_read: u64,
+    disk_write: u64,
+    cpu_secs: HashMap<i32, f64>, // latest total, by pid
+}
+
+/// What `--summary-json` writes: totals over the watched tree's lifetime.
+#[derive(Serialize)]
+pub struct Summary {
+    pub command: Vec<String>,
+    pub exit_code: Option<i32>,
+    pub wall_secs: f64,
+    pub cpu_avg: f64, // percent, summed over the tree
+    pub cpu_peak: f64,
+    pub rss_peak_mb: f64,
+    pub disk_read_bytes: u64,
+    pub disk_write_bytes: u64,
+    // cpu seconds used by every process seen in the tree (as of its last
+    // sample), where readable.
+    pub cpu_secs: f64,
+}
+
+impl Watch {
+    /// Start `cmd`. With `quiet`, its stdio goes to /dev/null so it doesn't
+    /// scribble over the TUI.
+    pub fn spawn(cmd: &[String], quiet: bool) -> Result<Self> {
+        let (prog, args) = cmd.split_first().context("no command to watch")?;
+        let mut command = Command::new(prog);
+        command.args(args);
+        if quiet {
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+        }
+        let child = command
+            .spawn()
+            .with_context(|| format!("starting {}", prog))?;
+        Ok(Self {
+            child,
+            start: Instant::now(),
+            status: None,
+            wall_secs: None,
+            ticks: 0,
+            cpu_sum: 0.,
+            cpu_peak: 0.,
+            rss_peak_mb: 0.,
+            disk_read: 0,
+            disk_write: 0,
+            cpu_secs: HashMap::new(),
+        })
+    }
+
+    pub fn pid(&self) -> i32 {
+        self.child.id() as i32
+    }
+
+    /// Fold in the latest sample of the watched tree, checking it against
+    /// `thresholds`. Returns messages about newly violated thresholds.
+    pub fn sample(&mut self, sprocs: &SProcs, thresholds: &mut Thresholds) -> Vec<String> {
+        let tree = sprocs.descendants(self.pid());
+        let msgs = thresholds.check(&tree);
+        let cpu: f64 = tree
+            .iter()
+            .map(|sp| sp.cpu_hist.front().unwrap_or(&0.))
+            .sum();
+        let rss: f64 = tree.iter().map(|sp| units::mib(sp.mem_bytes as f64)).sum();
+        self.ticks += 1;
+        self.cpu_sum += cpu;
+        self.cpu_peak = self.cpu_peak.max(cpu);
+        self.rss_peak_mb = self.rss_peak_mb.max(rss);
+        for sp in tree {
+            self.disk_read += sp.disk_read_hist.front().unwrap_or(&0);
+            self.disk_write += sp.disk_write_hist.front().unwrap_or(&0);
+            if let Some(secs) = sp.cpu_secs {
+                self.cpu_secs.insert(sp.pid, secs);
+            }
+        }
+        msgs
+    }
+
+    /// The command's exit status, once it has exited.
+    pub fn exited(&mut self) -> Result<Option<ExitStatus>> {
+        if self.status.is_none() {
+            self.status = self.child.try_wait()?;
+            if self.status.is_some() {
+                self.wall_secs = Some(self.start.elapsed().as_secs_f64());
+            }
+        }
+        Ok(self.status)
+    }
+
+    /// The code to exit with for the command, once it has exited: its own,
+    /// or 128 + the signal that killed it, as shells do.
+    pub fn exit_code(&mut self) -> Result<Option<i32>> {
+        Ok(self.exited()?.and_then(exit_code))
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.exited(), Ok(None))
+    }
+
+    pub fn summary(&self, command: &[String]) -> Summary {
+        Summary {
+            command: command.to_vec(),
+            exit_code: self.status.and_then(exit_code),
+            wall_secs: self
+                .wall_secs
+                .unwrap_or_else(|| self.start.elapsed().as_secs_f64()),
+            cpu_avg: self.cpu_sum / self.ticks.max(1) as f64,
+            cpu_peak: self.cpu_peak,
+            rss_peak_mb: self.rss_peak_mb,
+            disk_read_bytes: self.disk_read,
+            disk_write_bytes: self.disk_write,
+            cpu_secs: self.cpu_secs.values().sum(),
+        }
+    }
+
+    pub fn write_summary(&self, command: &[String], path: &Path) -> Result<()> {
+        let f = std::fs::File::create(path)
+            .with_context(|| format!("writing summary {}", path.display()))?;
+        serde_json::to_writer_pretty(f, &self.summary(command))?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn exit_code(status: ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.code().or_else(|| Some(128 + status.signal()?))
+}
+
+#[cfg(not(unix))]
+fn exit_code(status: ExitStatus) -> Option<i32> {
+    status.code()
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        // quitting early: don't leave the command running unwatched.
+        if self.is_running() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
@@ -0,0 +1,23 @@
+// In accessible mode the history columns read as a value in the column's
+// own unit and which way it's going.
+use sparktop::{action::parse_command, sproc::SProc};
+
+mod common;
+use common::{draw, view};
+
+#[test]
+fn trends_say_their_unit() {
+    let mut v = view(160, 10);
+    v.set_accessible(true);
+    v.run(parse_command("columns +wait% +wake/s").unwrap());
+    v.run(parse_command("cycle renderer wait%").unwrap());
+    v.run(parse_command("cycle renderer wake/s").unwrap());
+    let mut sp = SProc::synthetic(10, "alpha");
+    sp.latency_ewma = 12.;
+    sp.wakeups_ewma = 300.;
+    let screen = draw(&mut v, &[sp]);
+    let row = screen.iter().find(|l| l.contains("alpha")).unwrap();
+    assert!(row.contains("12% waiting, "), "{}", row);
+    assert!(row.contains("300 wakeups/s, "), "{}", row);
+    assert!(row.contains("0% cpu, "), "{}", row);
+}
@@ -0,0 +1,43 @@
+// The adaptive delay: stretched while quiet, back to the base when busy.
+use sparktop::{
+    adaptive::{Adaptive, AdaptiveTick},
+    sprocs::SystemStats,
+};
+
+fn system(cpu_pct: f64, starts: f64) -> SystemStats {
+    SystemStats {
+        cpu_pct,
+        starts_hist: vec![starts].into(),
+        exits_hist: vec![0.].into(),
+        ..SystemStats::default()
+    }
+}
+
+#[test]
+fn slows_while_quiet_up_to_the_max() {
+    let mut a = Adaptive::new(AdaptiveTick::default(), 1.);
+    let quiet = system(2., 0.);
+    let delays: Vec<Option<f64>> = (0..15).map(|_| a.update(&quiet)).collect();
+    let changes: Vec<f64> = delays.into_iter().flatten().collect();
+    assert_eq!(changes, [2., 4., 8.]);
+    assert_eq!(a.delay(), 8.);
+}
+
+#[test]
+fn busy_cpu_or_churn_snaps_back() {
+    let mut a = Adaptive::new(AdaptiveTick::default(), 1.);
+    for _ in 0..6 {
+        a.update(&system(2., 0.));
+    }
+    assert_eq!(a.delay(), 4.);
+    assert_eq!(a.update(&system(80., 0.)), Some(1.));
+    for _ in 0..3 {
+        a.update(&system(2., 0.));
+    }
+    assert_eq!(a.delay(), 2.);
+    assert_eq!(a.update(&system(2., 9.)), Some(1.));
+    // in between neither speeds up nor slows down.
+    for _ in 0..6 {
+        assert_eq!(a.update(&system(20., 0.)), None);
+    }
+}
@@ -0,0 +1,157 @@
+// Alert rules fire once their condition has held long enough, once per
+// spell, run their command no more than once a minute, and everything
+// alerted stays in the log after it's dismissed.
+use std::{fs, thread, time::Duration};
+
+use crossterm::event::KeyCode;
+use sparktop::ui::backend::TestBackend;
+use sparktop::{
+    alert::{AlertRule, Alerts},
+    sproc::{History, SProc},
+    sprocs::SystemStats,
+    view::View,
+};
+
+mod common;
+use common::{press, view};
+
+fn busy(pid: i32, cpu: f64) -> SProc {
+    let mut sp = SProc::synthetic(pid, "spin");
+    sp.add_synthetic_sample(&History::default(), cpu, 0, 0, 0, 0.5);
+    sp
+}
+
+fn rules(rules: &[&str]) -> Alerts {
+    Alerts::new(rules.iter().map(|r| r.parse().unwrap()).collect())
+}
+
+#[test]
+fn parses() {
+    for ok in [
+        "cpu > 90 for 30s",
+        "mem >= 2G",
+        "write > 10M for 1m",
+        "total mem_pct > 95",
+        "total load >= 8 for 5m",
+        "cpu > 90 -> kill -QUIT $SPARKTOP_PID",
+    ] {
+        assert!(ok.parse::<AlertRule>().is_ok(), "{}", ok);
+    }
+    for bad in [
+        "cpu 90",
+        "disk > 1",
+        "total mem > 1G",
+        "cpu > lots",
+        "cpu > 90 for ever",
+        "cpu > 90 ->",
+    ] {
+        assert!(bad.parse::<AlertRule>().is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn fires_after_holding() {
+    let mut alerts = rules(&["cpu > 90 for 2s"]);
+    let system = SystemStats::default();
+    let (hot, cool) = (busy(1, 95.), busy(2, 10.));
+    let procs = [&hot, &cool];
+    assert!(alerts.check(&procs, &system, 1.).is_empty());
+    assert!(alerts.check(&procs, &system, 1.).is_empty());
+    let fired = alerts.check(&procs, &system, 1.);
+    assert_eq!(fired.len(), 1);
+    assert!(fired[0].contains("spin (1)"), "{}", fired[0]);
+    // not again while it lasts...
+    assert!(alerts.check(&procs, &system, 1.).is_empty());
+    // ...but once it's stopped and held again.
+    let calm = busy(1, 50.);
+    assert!(alerts.check(&[&calm], &system, 1.).is_empty());
+    for _ in 0..2 {
+        assert!(alerts.check(&procs, &system, 1.).is_empty());
+    }
+    assert_eq!(alerts.check(&procs, &system, 1.).len(), 1);
+}
+
+#[test]
+fn totals() {
+    let mut alerts = rules(&["total mem_pct > 95", "total load > 4"]);
+    let mut system = SystemStats {
+        mem_used_bytes: 90,
+        mem_total_bytes: 100,
+        ..SystemStats::default()
+    };
+    assert!(alerts.check(&[], &system, 1.).is_empty());
+    system.mem_used_bytes = 97;
+    system.load = [6., 2., 1.];
+    let fired = alerts.check(&[], &system, 1.);
+    assert_eq!(fired.len(), 2);
+    assert!(fired[0].contains("system 97.0%"), "{}", fired[0]);
+}
+
+#[test]
+fn commands_rate_limited() {
+    let out = std::env::temp_dir().join(format!("sparktop-alert-{}", std::process::id()));
+    let _ = fs::remove_file(&out);
+    let rule = format!(
+        "cpu > 90 -> echo $SPARKTOP_NAME $SPARKTOP_PID $SPARKTOP_CPU >> {}",
+        out.display()
+    );
+    let mut alerts = rules(&[&rule]);
+    let system = SystemStats::default();
+    let (hot, calm) = (busy(1, 95.), busy(1, 10.));
+    let fired = alerts.check(&[&hot], &system, 1.);
+    assert!(fired[0].contains("; ran echo"), "{}", fired[0]);
+    // it fires again after flapping, but the command waits out its gap.
+    alerts.check(&[&calm], &system, 1.);
+    let fired = alerts.check(&[&hot], &system, 1.);
+    assert!(fired[0].contains("not running"), "{}", fired[0]);
+    alerts.check(&[&calm], &system, 60.);
+    let fired = alerts.check(&[&hot], &system, 1.);
+    assert!(fired[0].contains("; ran echo"), "{}", fired[0]);
+
+    let mut lines = vec![];
+    for _ in 0..50 {
+        lines = fs::read_to_string(&out)
+            .unwrap_or_default()
+            .lines()
+            .map(String::from)
+            .collect();
+        if lines.len() == 2 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    let _ = fs::remove_file(&out);
+    assert_eq!(lines, ["spin 1 95.0", "spin 1 95.0"]);
+}
+
+fn draw(view: &mut View<TestBackend>) -> String {
+    common::draw(view, &[]).join("\n")
+}
+
+#[test]
+fn log_outlives_dismissal() {
+    let mut v = view(80, 20);
+    v.set_alert("first thing".into());
+    v.set_alert("second thing".into());
+    let screen = draw(&mut v);
+    assert!(screen.contains("second thing"));
+    assert!(!screen.contains("first thing"));
+    press(&mut v, KeyCode::Esc);
+    assert!(v.at_top());
+    assert!(!draw(&mut v).contains("second thing"));
+
+    press(&mut v, KeyCode::Char('a'));
+    let screen = draw(&mut v);
+    assert!(screen.contains("alerts (2)"));
+    let (first, second) = (screen.find("first thing"), screen.find("second thing"));
+    assert!(
+        second.is_some() && second < first,
+        "newest first:\n{}",
+        screen
+    );
+    // scrolled past the newest.
+    press(&mut v, KeyCode::Char('>'));
+    let screen = draw(&mut v);
+    assert!(!screen.contains("second thing"));
+    assert!(screen.contains("first thing"));
+}
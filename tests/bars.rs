@@ -0,0 +1,56 @@
+// Each view draws its sparklines with its own glyphs, a column given fewer
+// levels with those, and keeps to ASCII only if it's asked to.
+use std::collections::HashMap;
+
+use sparktop::{
+    column::Column,
+    render::Bars,
+    sproc::{History, SProc},
+};
+
+mod common;
+use common::{draw, view};
+
+fn busy() -> SProc {
+    let (mut sp, history) = (SProc::synthetic(10, "alpha"), History::default());
+    for cpu in [10., 40., 70., 100.] {
+        sp.add_synthetic_sample(&history, cpu, 0, 0, 0, 0.5);
+    }
+    sp
+}
+
+#[test]
+fn per_view_glyphs() {
+    let (mut ascii, mut blocks) = (view(100, 8), view(100, 8));
+    ascii.set_bars(Bars::ascii(), HashMap::new());
+    let row = |screen: Vec<String>| screen.into_iter().find(|l| l.contains("alpha")).unwrap();
+    let a = row(draw(&mut ascii, &[busy()]));
+    let b = row(draw(&mut blocks, &[busy()]));
+    assert!(a.contains("@=:"), "{}", a);
+    assert!(b.contains("█▆▄"), "{}", b);
+}
+
+#[test]
+fn column_levels() {
+    let mut v = view(100, 8);
+    let bars = Bars::parse("abcd").unwrap();
+    let levels = vec![(Column::CpuHist, bars.with_levels(2))];
+    v.set_bars(bars, levels.into_iter().collect());
+    let screen = draw(&mut v, &[busy()]);
+    let row = screen.iter().find(|l| l.contains("alpha")).unwrap();
+    assert!(row.contains("ddbb"), "{}", row);
+}
+
+#[test]
+fn ascii_per_view() {
+    let (mut ascii, mut plain) = (view(100, 8), view(100, 8));
+    ascii.set_bars(Bars::ascii(), HashMap::new());
+    ascii.set_ascii(true);
+    for v in [&mut ascii, &mut plain] {
+        v.set_braille(true);
+    }
+    let screen = draw(&mut ascii, &[busy()]);
+    assert!(screen.iter().all(|l| l.is_ascii()), "{:?}", screen);
+    let screen = draw(&mut plain, &[busy()]);
+    assert!(!screen.iter().all(|l| l.is_ascii()), "{:?}", screen);
+}
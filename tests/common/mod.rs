@@ -0,0 +1,65 @@
+// Shared by the tests that draw the view: a view on a TestBackend, made-up
+// processes to draw in it, keys to press and the screen read back as lines.
+#![allow(dead_code)] // each test binary uses its own share of these
+
+use crossterm::event::{KeyCode, KeyEvent};
+use sparktop::ui::{backend::TestBackend, buffer::Cell};
+use sparktop::{
+    event::Next, lifetime::LifetimeLog, sproc::SProc, sterm::STerm, units::MIB, view::View,
+};
+
+pub fn view(width: u16, height: u16) -> View<TestBackend> {
+    View::with_terminal(STerm::with_backend(TestBackend::new(width, height)).unwrap())
+}
+
+pub fn proc(pid: i32, name: &str, cpu: f64, mem_mb: f64) -> SProc {
+    let mut sp = SProc::synthetic(pid, name);
+    sp.cpu_ewma = cpu;
+    sp.mem_bytes = (mem_mb * MIB) as u64;
+    sp
+}
+
+// draws `procs` as the table's rows and returns the screen.
+pub fn draw(view: &mut View<TestBackend>, procs: &[SProc]) -> Vec<String> {
+    let rows: Vec<&SProc> = procs.iter().collect();
+    draw_rows(view, &rows)
+}
+
+pub fn draw_rows(view: &mut View<TestBackend>, rows: &[&SProc]) -> Vec<String> {
+    view.draw(rows, &LifetimeLog::default()).unwrap();
+    screen(view)
+}
+
+// the last drawn screen, a line per row.
+pub fn screen(view: &View<TestBackend>) -> Vec<String> {
+    let buf = view.terminal().backend().buffer();
+    (0..buf.area.height)
+        .map(|y| (0..buf.area.width).map(|x| buf[(x, y)].symbol()).collect())
+        .collect()
+}
+
+pub fn cell(view: &View<TestBackend>, x: u16, y: u16) -> &Cell {
+    &view.terminal().backend().buffer()[(x, y)]
+}
+
+// where `text` starts on the screen, as (x, y).
+pub fn find(screen: &[String], text: &str) -> Option<(u16, u16)> {
+    screen.iter().enumerate().find_map(|(y, l)| {
+        let i = l.find(text)?;
+        Some((l[..i].chars().count() as u16, y as u16))
+    })
+}
+
+pub fn line_of(screen: &[String], text: &str) -> Option<usize> {
+    screen.iter().position(|l| l.contains(text))
+}
+
+pub fn press(view: &mut View<TestBackend>, code: KeyCode) -> Next {
+    view.handle_key(KeyEvent::from(code))
+}
+
+pub fn type_keys(view: &mut View<TestBackend>, keys: &str) {
+    for c in keys.chars() {
+        view.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+}
@@ -0,0 +1,102 @@
+// The config file: TOML, with every setting optional, and what it writes
+// back readable again.
+use std::{fs, path::PathBuf};
+
+use sparktop::config::Config;
+
+// a config file with `text` in a fresh directory, removed on drop.
+struct TempConfig(PathBuf);
+
+impl TempConfig {
+    fn new(name: &str, text: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("sparktop-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, text).unwrap();
+        Self(path)
+    }
+}
+
+impl Drop for TempConfig {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(self.0.parent().unwrap());
+    }
+}
+
+#[test]
+fn template_loads() {
+    let file = TempConfig::new("template", &Config::template().unwrap());
+    let config = Config::load(Some(&file.0)).unwrap();
+    assert_eq!(config.delay, Some(1.));
+    assert!(config.colors.is_some());
+}
+
+#[test]
+fn reads_toml() {
+    let file = TempConfig::new(
+        "toml",
+        r#"
+delay = 2
+muted = ["tracker-miner"]
+highlight = ['name =~ "chrome" -> fg:yellow']
+refresh = { fds = 30 }
+bars = { glyphs = "dots", levels = { wakeups = 2 } }
+filters = [{ name = "db", filter = "name in {postgres, redis}" }]
+renames = [{ pattern = '^python3\S* /opt/app/(\w+)\.py', name = "app-$1" }]
+"#,
+    );
+    let config = Config::load(Some(&file.0)).unwrap();
+    assert_eq!(config.delay, Some(2.));
+    assert_eq!(config.muted, vec!["tracker-miner"]);
+    assert_eq!(config.renames[0].pattern, r"^python3\S* /opt/app/(\w+)\.py");
+    assert_eq!(config.saved_filters().unwrap()[0].0, "db");
+}
+
+#[test]
+fn rejects_unknown_and_json() {
+    let unknown = TempConfig::new("unknown", "dealy = 2\n");
+    assert!(Config::load(Some(&unknown.0)).is_err());
+    let json = TempConfig::new("json", r#"{"delay": 2}"#);
+    assert!(Config::load(Some(&json.0)).is_err());
+}
+
+#[test]
+fn saves_what_it_loads() {
+    let file = TempConfig::new("save", "delay = 2\n");
+    let mut config = Config::load(Some(&file.0)).unwrap();
+    config.muted.push("tracker-miner".into());
+    config.save().unwrap();
+    let again = Config::load(Some(&file.0)).unwrap();
+    assert_eq!(again.delay, Some(2.));
+    assert_eq!(again.muted, vec!["tracker-miner"]);
+}
+
+#[test]
+fn saving_keeps_comments() {
+    let text = "\
+# sampled every two seconds
+delay = 2 # not too busy
+
+# things to keep an eye on
+bookmarks = [\"postgres\"] # mine
+sort = \"mem\"
+";
+    let file = TempConfig::new("comments", text);
+    let mut config = Config::load(Some(&file.0)).unwrap();
+    config.bookmarks.push("nginx".into());
+    config.muted.push("tracker-miner".into());
+    config.save().unwrap();
+    let saved = fs::read_to_string(&file.0).unwrap();
+    assert_eq!(
+        saved,
+        text.replace("[\"postgres\"]", "[\"postgres\", \"nginx\"]")
+            + "muted = [\"tracker-miner\"]\n",
+    );
+    // and nothing left beside it.
+    let dir = fs::read_dir(file.0.parent().unwrap()).unwrap();
+    assert_eq!(dir.count(), 1);
+    // taking one away takes its line.
+    config.muted.clear();
+    config.save().unwrap();
+    assert!(!fs::read_to_string(&file.0).unwrap().contains("muted"));
+}
@@ -0,0 +1,33 @@
+// The per-core cpu page fits however many cores there are, in columns.
+use crossterm::event::KeyCode;
+use sparktop::sprocs::SystemStats;
+
+mod common;
+use common::{draw, press, view};
+
+#[test]
+fn cores_fill_columns() {
+    let mut v = view(100, 8);
+    v.set_system(SystemStats {
+        core_hists: (0..12).map(|i| vec![i as f64 * 5.].into()).collect(),
+        ..SystemStats::default()
+    });
+    press(&mut v, KeyCode::F(2));
+    let screen = draw(&mut v, &[]);
+    assert!(screen[0].contains("busiest cpu11 55%"));
+    // 6 rows inside the border, so two columns (under the title).
+    let row = |s: &str| {
+        screen[1..]
+            .iter()
+            .position(|l| l.contains(s))
+            .map(|i| i + 1)
+    };
+    assert_eq!(row("cpu0 "), row("cpu6 "));
+    assert_eq!(row("cpu5 "), row("cpu11 "));
+    assert_eq!(row("cpu0 "), Some(1));
+    assert!(screen[1].find("cpu6") > Some(40));
+
+    press(&mut v, KeyCode::Esc);
+    let screen = draw(&mut v, &[]);
+    assert!(!screen.iter().any(|l| l.contains("cpu cores")));
+}
@@ -0,0 +1,105 @@
+// Rates from cumulative counters start over when sampling resumes, instead
+// of counting the whole gap as one sample.
+use sparktop::{
+    sproc::{History, SProc},
+    sprocs::SProcs,
+};
+
+#[test]
+fn child_cpu_starts_over() {
+    let (mut sp, history) = (SProc::synthetic(1, "p"), History::default());
+    sp.add_child_cpu_sample(&history, 10., 1., 1.);
+    sp.add_child_cpu_sample(&history, 10.5, 1., 1.);
+    assert_eq!(sp.child_cpu_hist.front(), Some(&50.));
+    sp.forget_child_cpu();
+    sp.add_child_cpu_sample(&history, 70., 1., 1.);
+    assert_eq!(sp.child_cpu_hist.front(), Some(&0.));
+}
+
+#[test]
+fn child_cpu_lines_up_after_a_toggle() {
+    let (mut sp, history) = (SProc::synthetic(1, "p"), History::default());
+    sp.add_cpu_sample(&history, 10., 1.);
+    sp.add_child_cpu_sample(&history, 0., 1., 1.);
+    sp.add_cpu_sample(&history, 10., 1.);
+    sp.add_child_cpu_sample(&history, 0.5, 1., 1.);
+    // off for two samples, then on again.
+    sp.forget_child_cpu();
+    sp.add_cpu_sample(&history, 20., 1.);
+    sp.add_cpu_sample(&history, 30., 1.);
+    sp.add_child_cpu_sample(&history, 5., 1., 1.);
+    sp.add_cpu_sample(&history, 40., 1.);
+    sp.add_child_cpu_sample(&history, 5.25, 1., 1.);
+    // newest first: only the samples since, none of the 50% from before.
+    assert_eq!(sp.cpu_history(true), [65., 30., 20., 10., 10.]);
+    assert_eq!(sp.cpu(true), 40. + sp.child_cpu_ewma);
+}
+
+#[test]
+fn latency_starts_over() {
+    let (mut sp, history) = (SProc::synthetic(1, "p"), History::default());
+    sp.add_latency_sample(&history, 0, 1., 1.);
+    sp.add_latency_sample(&history, 250_000_000, 1., 1.);
+    assert_eq!(sp.latency_hist.front(), Some(&25.));
+    sp.forget_latency();
+    sp.add_latency_sample(&history, 9_000_000_000, 1., 1.);
+    assert_eq!(sp.latency_hist, [0.]);
+    assert_eq!(sp.latency_ewma, 0.);
+}
+
+#[test]
+fn cpu_split_starts_over() {
+    let (mut sp, history) = (SProc::synthetic(1, "p"), History::default());
+    sp.add_cpu_split_sample(&history, 1., 1., 1., 1.);
+    sp.add_cpu_split_sample(&history, 1.5, 1.25, 1., 1.);
+    assert_eq!(sp.user_cpu_hist.front(), Some(&50.));
+    assert_eq!(sp.sys_cpu_hist.front(), Some(&25.));
+    sp.forget_cpu_split();
+    sp.add_cpu_split_sample(&history, 60., 60., 1., 1.);
+    assert_eq!(sp.user_cpu_hist.front(), Some(&0.));
+    // the samples from before are gone too.
+    assert_eq!(sp.user_cpu_hist, [0.]);
+    assert_eq!(sp.sys_cpu_hist, [0.]);
+    assert_eq!(sp.sys_cpu_ewma, 0.);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn zoom_splits_only_its_process() {
+    let me = std::process::id() as i32;
+    let mut sprocs = SProcs::default();
+    sprocs.set_zoomed(Some(me));
+    sprocs.update(1.);
+    sprocs.update(1.);
+    assert!(!sprocs.by_pid(me).unwrap().user_cpu_hist.is_empty());
+    assert!(sprocs
+        .get()
+        .filter(|sp| sp.pid != me)
+        .all(|sp| sp.user_cpu_hist.is_empty()));
+}
+
+#[test]
+fn wakeups_start_over() {
+    let (mut sp, history) = (SProc::synthetic(1, "p"), History::default());
+    sp.add_wakeups_sample(&history, 100, 1., 1.);
+    sp.add_wakeups_sample(&history, 150, 1., 1.);
+    assert_eq!(sp.wakeups_hist.front(), Some(&50.));
+    sp.forget_wakeups();
+    sp.add_wakeups_sample(&history, 9000, 1., 1.);
+    assert_eq!(sp.wakeups_hist, [0.]);
+    assert_eq!(sp.wakeups_ewma, 0.);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn interrupts_only_while_sampling_wakeups() {
+    let mut sprocs = SProcs::default();
+    sprocs.set_wakeups(true);
+    sprocs.update(1.);
+    assert_eq!(sprocs.system().interrupts_per_sec, None);
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    sprocs.update(1.);
+    assert!(sprocs.system().interrupts_per_sec.is_some());
+    sprocs.set_wakeups(false);
+    assert_eq!(sprocs.system().interrupts_per_sec, None);
+}
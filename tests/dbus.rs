@@ -0,0 +1,145 @@
+// The session bus export: Top and Processes answer with the latest publish,
+// and alerts reach a notification daemon, on a private bus (skipped where
+// there's no dbus-daemon to run one).
+#![cfg(all(feature = "dbus", target_os = "linux"))]
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::{Mutex, MutexGuard},
+};
+
+use sparktop::{
+    dbus::{DbusExport, Entry, BUS_NAME, PATH},
+    sproc::SProc,
+    units::MIB,
+};
+use zbus::blocking::Connection;
+
+// a private session bus, stopped on drop. One at a time, since it's found
+// through the environment.
+struct Bus {
+    daemon: Child,
+    _one: MutexGuard<'static, ()>,
+}
+
+static ONE_BUS: Mutex<()> = Mutex::new(());
+
+impl Bus {
+    fn start() -> Option<Self> {
+        let guard = ONE_BUS.lock().unwrap_or_else(|e| e.into_inner());
+        let mut child = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let mut addr = String::new();
+        BufReader::new(child.stdout.take()?)
+            .read_line(&mut addr)
+            .ok()?;
+        std::env::set_var("DBUS_SESSION_BUS_ADDRESS", addr.trim());
+        Some(Self {
+            daemon: child,
+            _one: guard,
+        })
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+fn proc(pid: i32, name: &str, cpu: f64) -> SProc {
+    let mut sp = SProc::synthetic(pid, name);
+    sp.cpu_ewma = cpu;
+    sp.mem_bytes = (2. * MIB) as u64;
+    sp
+}
+
+fn call<T: serde::de::DeserializeOwned + zbus::zvariant::Type>(
+    conn: &Connection,
+    method: &str,
+    args: &(impl serde::Serialize + zbus::zvariant::DynamicType),
+) -> zbus::Result<T> {
+    conn.call_method(Some(BUS_NAME), PATH, Some(BUS_NAME), method, args)?
+        .body()
+        .deserialize()
+}
+
+#[test]
+fn answers_with_the_latest_snapshot() {
+    let _bus = match Bus::start() {
+        Some(b) => b,
+        None => return eprintln!("no dbus-daemon, skipping"),
+    };
+    let export = DbusExport::spawn().unwrap();
+    let conn = Connection::session().unwrap();
+    assert!(call::<Entry>(&conn, "Top", &()).is_err(), "no samples yet");
+
+    let procs = [
+        proc(1, "idle", 0.5),
+        proc(2, "busy", 80.),
+        proc(3, "some", 10.),
+    ];
+    export.publish(&procs.iter().collect::<Vec<_>>(), false);
+    let top: Entry = call(&conn, "Top", &()).unwrap();
+    assert_eq!(top, (2, "busy".into(), 80., 2.));
+    let two: Vec<Entry> = call(&conn, "Processes", &(2u32,)).unwrap();
+    let pids: Vec<i32> = two.iter().map(|e| e.0).collect();
+    assert_eq!(pids, [2, 3]);
+
+    // only one sparktop gets the name.
+    assert!(DbusExport::spawn().is_err());
+}
+
+#[cfg(feature = "desktop-notify")]
+#[test]
+fn alerts_reach_the_notification_daemon() {
+    use std::{collections::HashMap, sync::mpsc, time::Duration};
+    use zbus::zvariant::OwnedValue;
+
+    struct Daemon(mpsc::Sender<(String, String)>);
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl Daemon {
+        fn get_server_information(&self) -> (String, String, String, String) {
+            ("test".into(), "sparktop".into(), "0".into(), "1.2".into())
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            _app: String,
+            _replaces: u32,
+            _icon: String,
+            summary: String,
+            body: String,
+            _actions: Vec<String>,
+            _hints: HashMap<String, OwnedValue>,
+            _timeout: i32,
+        ) -> u32 {
+            let _ = self.0.send((summary, body));
+            1
+        }
+    }
+
+    let _bus = match Bus::start() {
+        Some(b) => b,
+        None => return eprintln!("no dbus-daemon, skipping"),
+    };
+    // nothing to show them yet.
+    assert!(sparktop::notify::DesktopNotifier::spawn().is_err());
+
+    let (tx, rx) = mpsc::channel();
+    let _daemon = zbus::blocking::connection::Builder::session()
+        .and_then(|b| b.name("org.freedesktop.Notifications"))
+        .and_then(|b| b.serve_at("/org/freedesktop/Notifications", Daemon(tx)))
+        .and_then(|b| b.build())
+        .unwrap();
+    let notifier = sparktop::notify::DesktopNotifier::spawn().unwrap();
+    notifier.notify("sparktop", "cpu(make) > 90");
+    let shown = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(shown, ("sparktop".into(), "cpu(make) > 90".into()));
+}
@@ -0,0 +1,68 @@
+// Which processes --pid, --name and --user let through to be sampled.
+#![cfg(target_os = "linux")]
+use sparktop::{filter::ProcFilter, platform};
+use sysinfo::{RefreshKind, System, SystemExt};
+
+// this test process, as sysinfo reads it.
+fn own() -> (i32, System) {
+    let pid = std::process::id() as i32;
+    let mut sys = System::new_with_specifics(RefreshKind::new());
+    assert!(sys.refresh_process(pid));
+    (pid, sys)
+}
+
+fn filter(pids: Vec<i32>, name: Option<&str>, user: Option<&str>) -> ProcFilter {
+    ProcFilter::new(pids, name, user).unwrap().unwrap()
+}
+
+#[test]
+fn nothing_to_filter_on() {
+    assert!(ProcFilter::new(vec![], None, None).unwrap().is_none());
+}
+
+#[test]
+fn all_criteria_must_hold() {
+    let (pid, sys) = own();
+    let p = sys.process(pid).unwrap();
+    let me = unsafe { libc::getuid() }.to_string();
+    assert!(filter(vec![pid], None, None).matches(pid, p));
+    assert!(!filter(vec![pid + 1], None, None).matches(pid, p));
+    // the test binary is named after this file.
+    assert!(filter(vec![], Some("^filter"), Some(&me)).matches(pid, p));
+    assert!(!filter(vec![pid], Some("^nope$"), None).matches(pid, p));
+    let other = (unsafe { libc::getuid() } + 1).to_string();
+    assert!(!filter(vec![pid], None, Some(&other)).matches(pid, p));
+}
+
+#[test]
+fn users_by_name_or_uid() {
+    assert_eq!(platform::user_uid("root"), Some(0));
+    assert_eq!(platform::user_uid("no-such-user-here"), None);
+    assert!(ProcFilter::new(vec![], None, Some("root"))
+        .unwrap()
+        .is_some());
+    assert!(ProcFilter::new(vec![], None, Some("12345"))
+        .unwrap()
+        .is_some());
+    let err = ProcFilter::new(vec![], None, Some("no-such-user-here")).err();
+    assert_eq!(
+        err.map(|e| e.to_string()),
+        Some("no such user: no-such-user-here".into())
+    );
+    assert!(ProcFilter::new(vec![], Some("("), None).is_err());
+}
+
+#[test]
+fn leaving_the_filter_is_leaving() {
+    use sparktop::sprocs::SProcs;
+
+    let (me, _) = own();
+    let mut sprocs = SProcs::default();
+    sprocs.set_filter(filter(vec![me], None, None));
+    sprocs.update(1.);
+    assert!(!sprocs.by_pid(me).unwrap().is_dead());
+    // still running, but no longer let through: gone as far as we know.
+    sprocs.set_filter(filter(vec![1], None, None));
+    sprocs.update(1.);
+    assert!(sprocs.by_pid(me).is_none_or(|sp| sp.is_dead()));
+}
@@ -0,0 +1,38 @@
+// The cpu column as a gauge: each process's cpu against what its cgroup
+// lets it use.
+use sparktop::action::parse_command;
+
+mod common;
+use common::{draw, proc, view};
+
+#[test]
+fn cpu_against_its_quota() {
+    let mut v = view(120, 10);
+    v.run(parse_command("cycle renderer cpu").unwrap());
+    v.run(parse_command("cycle renderer cpu").unwrap());
+    assert!(v.cpu_gauges());
+    let mut capped = proc(10, "capped", 75., 1.);
+    capped.cpu_quota = Some(1.5);
+    let unread = proc(20, "unread", 75., 1.);
+    let screen = draw(&mut v, &[capped, unread]);
+    let row = |name| screen.iter().find(|l| l.contains(name)).unwrap();
+    assert!(row("capped").contains("[###---]  50%"), "{:#?}", screen);
+    assert!(!row("unread").contains('['), "{:#?}", screen);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn quotas_read_while_shown() {
+    use sparktop::sprocs::SProcs;
+    use sysinfo::{System, SystemExt};
+
+    let me = std::process::id() as i32;
+    let mut sprocs = SProcs::default();
+    sprocs.update(1.);
+    assert_eq!(sprocs.by_pid(me).unwrap().cpu_quota, None);
+    sprocs.set_cpu_quotas(true);
+    sprocs.update(1.);
+    let quota = sprocs.by_pid(me).unwrap().cpu_quota.unwrap();
+    let cpus = System::new_all().processors().len() as f64;
+    assert!(quota > 0. && quota <= cpus, "{} of {}", quota, cpus);
+}
@@ -0,0 +1,48 @@
+// Headless reports are plain text for logs and pipes: ASCII throughout,
+// sparklines included. Batch JSON carries the lifetime counters too.
+use std::collections::HashMap;
+
+use sparktop::headless::{top_report, Tally};
+
+#[test]
+fn top_report_is_ascii() {
+    let tally = Tally {
+        name: "spin".into(),
+        cpu_sum: 150.,
+        cpu_peak: 100.,
+        samples: vec![10., 40., 100.],
+        cpu_secs: None,
+    };
+    let tallies: HashMap<i32, Tally> = vec![(7, tally)].into_iter().collect();
+    let report = top_report(&tallies, 5);
+    assert!(report.is_ascii(), "{}", report);
+    assert!(report.contains("@"), "{}", report);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn batch_json_has_counters() {
+    use std::time::Duration;
+
+    use sparktop::{
+        export::Snapshot,
+        headless::{batch, Batch, Output},
+        sprocs::SProcs,
+        threshold::Thresholds,
+        view_state::{Dir, Metric},
+    };
+
+    let b = Batch {
+        delay: Duration::from_millis(10),
+        iterations: Some(1),
+        output: Output::Json,
+        sort: (Metric::Cpu, Dir::Desc),
+    };
+    let mut out = vec![];
+    let mut thresholds = Thresholds::new(vec![]);
+    batch(&mut SProcs::default(), 0.5, &b, &mut thresholds, &mut out).unwrap();
+    let snapshot: Snapshot = serde_json::from_slice(&out).unwrap();
+    let me = std::process::id() as i32;
+    let own = snapshot.procs.iter().find(|p| p.pid == me).unwrap();
+    assert!(own.cpu_secs.is_some());
+}
@@ -0,0 +1,36 @@
+// The privileged helper, run as `sparktop helper --allow-ionice`: it won't
+// put someone else's process's io in the realtime class.
+#![cfg(target_os = "linux")]
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Command, Stdio},
+};
+
+// the helper's answers to `requests`, started as if by sudo for `sudo_uid`.
+fn ask(sudo_uid: u32, requests: &[&str]) -> Vec<String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sparktop"))
+        .args(["helper", "--allow-ionice"])
+        .env("SUDO_UID", sudo_uid.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for r in requests {
+        writeln!(stdin, "{}", r).unwrap();
+    }
+    drop(stdin);
+    let out = BufReader::new(child.stdout.take().unwrap());
+    let answers = out.lines().map(Result::unwrap).collect();
+    child.wait().unwrap();
+    answers
+}
+
+#[test]
+fn realtime_only_for_own_processes() {
+    // pid 1 is root's; whether we're root (and taken to be sudo's caller,
+    // someone else) or not, it isn't ours.
+    let answers = ask(12345, &["ionice 1 rt/4"]);
+    assert_eq!(answers.len(), 1);
+    assert!(answers[0].starts_with("err realtime io"), "{:?}", answers);
+}
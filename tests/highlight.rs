@@ -0,0 +1,55 @@
+// Row highlighting rules from the config: a comparison on a process's
+// fields, and the style to draw matching rows in.
+use sparktop::ui::style::{Color, Modifier, Style};
+use sparktop::{sproc::SProc, view_state::RowRule};
+
+fn rule(s: &str) -> RowRule {
+    s.parse().unwrap()
+}
+
+#[test]
+fn rules_match_on_fields() {
+    let mut chrome = SProc::synthetic(10, "chrome");
+    chrome.cpu_ewma = 250.;
+    let mut bash = SProc::synthetic(20, "bash");
+    bash.cpu_ewma = 200.;
+
+    let hot = rule("cpu_ewma > 200 -> bg:red");
+    assert!(hot.matches(&chrome));
+    assert!(!hot.matches(&bash));
+    assert_eq!(hot.style, Style::default().bg(Color::Red));
+    assert!(rule("cpu_ewma >= 200 -> bold").matches(&bash));
+
+    let browser = rule(r#"name =~ "chro(me|mium)" -> fg:yellow, bold"#);
+    assert!(browser.matches(&chrome));
+    assert!(!browser.matches(&bash));
+    assert_eq!(
+        browser.style,
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD)
+    );
+    assert!(rule("name != bash -> dim").matches(&chrome));
+
+    // rows without the field don't match either way.
+    assert!(!rule("fds > 0 -> dim").matches(&chrome));
+    assert!(!rule("fds <= 0 -> dim").matches(&chrome));
+}
+
+#[test]
+fn bad_rules_are_errors() {
+    for bad in [
+        "cpu_ewma > 200",           // no style
+        "cpu_ewma -> bold",         // no comparison
+        "heat > 200 -> bold",       // no such field
+        "name > 3 -> bold",         // text compared as a number
+        "cpu_ewma =~ 2.. -> bold",  // a number as text
+        "cpu_ewma > lots -> bold",  // not a number
+        "name =~ ( -> bold",        // bad regex
+        "cpu_ewma > 1 -> bg:blood", // no such color
+        "cpu_ewma > 1 -> sparkly",  // no such modifier
+        "cpu_ewma > 1 -> ",         // empty style
+    ] {
+        assert!(bad.parse::<RowRule>().is_err(), "{:?} parsed", bad);
+    }
+}
@@ -0,0 +1,144 @@
+// How histories are kept: when each entry was taken, going by the actual
+// time between samples.
+use sparktop::sproc::{CompactBy, History, SProc};
+
+#[test]
+fn ages_follow_sample_times() {
+    let mut h = History::default();
+    h.set_interval(2.);
+    for secs in [2., 4., 1.] {
+        h.next_sample(secs);
+    }
+    let ages: Vec<f64> = h.ages().take(5).collect();
+    // newest first; past the samples taken, the delay stands in.
+    assert_eq!(ages, vec![0., 1., 5., 7., 9.]);
+    assert_eq!(h.secs_ago(3), 7.);
+    assert_eq!(h.integral(&[100., 50., 10.]), 100. + 200. + 20.);
+}
+
+// `n` samples of 10% cpu into `sp`, at the history's delay.
+fn sample(h: &mut History, sp: &mut SProc, n: usize) {
+    for _ in 0..n {
+        h.next_sample(h.interval());
+        sp.add_cpu_sample(h, 10., 1.);
+    }
+}
+
+fn tiers(h: &History) -> Vec<(usize, f64)> {
+    h.tiers().iter().map(|t| (t.samples_each, t.secs)).collect()
+}
+
+#[test]
+fn compacts_past_full_resolution() {
+    let (mut h, mut sp) = (History::default(), SProc::synthetic(1, "p"));
+    h.set_len(100);
+    // 4 samples at full resolution.
+    h.set_interval(150.);
+    sample(&mut h, &mut sp, 12);
+    assert_eq!(tiers(&h), vec![(1, 600.), (4, 1200.)]);
+    assert_eq!(sp.cpu_hist.len(), 6);
+    assert_eq!(h.total(&sp.cpu_hist), 120.);
+}
+
+#[test]
+fn delay_changes_leave_entries_alone() {
+    let (mut h, mut sp) = (History::default(), SProc::synthetic(1, "p"));
+    h.set_len(100);
+    h.set_interval(150.);
+    sample(&mut h, &mut sp, 12);
+    // more at full resolution: what's compacted stays so.
+    h.set_interval(50.);
+    sample(&mut h, &mut sp, 1);
+    assert_eq!(tiers(&h), vec![(1, 650.), (4, 1200.)]);
+    assert_eq!(h.sample_weight(5), 4);
+    // less: only what ages past it now is compacted, once.
+    h.set_interval(600.);
+    sample(&mut h, &mut sp, 1);
+    assert_eq!(tiers(&h), vec![(1, 600.), (4, 500.), (1, 150.), (4, 1200.)]);
+    assert_eq!(sp.cpu_hist.len(), 5);
+    assert_eq!(h.total(&sp.cpu_hist), 140.);
+}
+
+#[test]
+fn keeps_len_samples() {
+    let (mut h, mut sp) = (History::default(), SProc::synthetic(1, "p"));
+    h.set_len(10);
+    h.set_interval(150.);
+    sample(&mut h, &mut sp, 40);
+    let kept: usize = (0..sp.cpu_hist.len()).map(|i| h.sample_weight(i)).sum();
+    assert!((10..14).contains(&kept), "{}", kept);
+}
+
+#[test]
+fn history_secs_follow_the_delay() {
+    let (mut h, mut sp) = (History::default(), SProc::synthetic(1, "p"));
+    h.set_secs(60.);
+    h.set_interval(2.);
+    assert_eq!(h.samples(), 30);
+    sample(&mut h, &mut sp, 40);
+    assert_eq!(sp.cpu_hist.len(), 30);
+    // a longer delay keeps fewer samples for the same time.
+    h.set_interval(5.);
+    assert_eq!(h.samples(), 12);
+    sample(&mut h, &mut sp, 4);
+    assert_eq!(h.ages().nth(sp.cpu_hist.len() - 1), Some(58.));
+    // and set_len goes back to a count.
+    h.set_len(100);
+    assert_eq!(h.samples(), 100);
+}
+
+#[test]
+fn late_histories_weigh_what_they_average() {
+    let mut h = History::default();
+    h.set_len(100);
+    h.set_interval(150.);
+    let mut early = SProc::synthetic(1, "p");
+    let mut late = SProc::synthetic(2, "q");
+    for i in 0..8 {
+        h.next_sample(h.interval());
+        early.add_cpu_sample(&h, 10., 1.);
+        // born 2 samples into what becomes the first compacted entry.
+        if i >= 2 {
+            late.add_cpu_sample(&h, 10., 1.);
+        }
+    }
+    // ...which stands for 4 samples, 2 of them before it was born.
+    assert_eq!(h.sample_weight(4), 4);
+    assert_eq!(h.total(&early.cpu_hist), 80.);
+    assert_eq!(late.cpu_hist.len(), 5);
+    assert_eq!(h.total(&late.cpu_hist), 60.);
+}
+
+#[test]
+fn compact_by_is_per_history() {
+    let cpu = [0., 40., 0., 0.];
+    let mut kept = vec![];
+    for by in [CompactBy::Mean, CompactBy::Max, CompactBy::MeanAndPeak] {
+        let (mut h, mut sp) = (History::default(), SProc::synthetic(1, "p"));
+        h.set_compact_by(by);
+        h.set_interval(600.);
+        for &x in cpu.iter().chain(&[0.]) {
+            h.next_sample(h.interval());
+            sp.add_cpu_sample(&h, x, 1.);
+        }
+        kept.push((sp.cpu_hist[1], sp.cpu_peaks.get(1).copied()));
+    }
+    assert_eq!(kept, vec![(10., None), (40., None), (10., Some(40.))],);
+}
+
+#[test]
+fn forecasts_go_by_sample_times() {
+    let (mut h, mut sp) = (History::default(), SProc::synthetic(1, "p"));
+    h.set_len(100);
+    // 1 MB a second, sampled 1s and then 3s apart.
+    let mut t = 0.;
+    for i in 0..20 {
+        let gap = if i % 2 == 0 { 1. } else { 3. };
+        t += gap;
+        h.next_sample(gap);
+        sp.add_synthetic_sample(&h, 0., (t * 1e6) as u64, 0, 0, 1.);
+    }
+    sp.mem_limit_bytes = Some((t * 1e6 + 100e6) as u64);
+    let secs = sp.mem_forecast(&h).unwrap();
+    assert!((secs - 100.).abs() < 0.01, "{}", secs);
+}
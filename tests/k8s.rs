@@ -0,0 +1,31 @@
+// Pod names come from the kubelet's pod list, by uid.
+use sparktop::k8s::{pod_names, Pods};
+
+#[test]
+fn names_by_uid() {
+    let json = r#"{"kind":"PodList","apiVersion":"v1","metadata":{},"items":[
+        {"metadata":{"name":"web-7d4b9","namespace":"shop","uid":"0f6a2b4e-1c3d-4e5f-8a9b-0c1d2e3f4a5b"},
+         "spec":{},"status":{}},
+        {"metadata":{"name":"coredns","namespace":"kube-system","uid":"11111111-2222-3333-4444-555555555555"}}
+    ]}"#;
+    let names = pod_names(json).unwrap();
+    assert_eq!(names.len(), 2);
+    assert_eq!(
+        names
+            .get("0f6a2b4e-1c3d-4e5f-8a9b-0c1d2e3f4a5b")
+            .map(String::as_str),
+        Some("shop/web-7d4b9")
+    );
+    assert!(pod_names(r#"{"items":[{"metadata":{"name":"x"}}]}"#).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn pruned_processes_are_read_again() {
+    let mut pods = Pods::default();
+    let me = std::process::id() as i32;
+    // None unless these tests run in a pod.
+    let pod = pods.lookup(me);
+    pods.prune(|pid| pid != me);
+    assert_eq!(pods.lookup(me), pod);
+}
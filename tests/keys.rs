@@ -0,0 +1,211 @@
+// Random key sequences against the view: nothing panics, drawing included,
+// and from wherever they end up, Esc gets back to the plain table and q quits.
+// Seeded, so a failure names the seed that reproduces it.
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::time::Duration;
+
+use sparktop::ui::backend::TestBackend;
+use sparktop::{
+    action::{Action, Command, BINDINGS},
+    column::Column,
+    event::Next,
+    sproc::{History, SProc},
+    view::View,
+};
+
+mod common;
+use common::{draw, line_of, press, proc, view};
+
+const SEEDS: u64 = 40;
+const KEYS_PER_RUN: usize = 150;
+
+// xorshift64; no need for anything better here.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+// every bound key, plus what the palette's line editor and the keys with no
+// binding see.
+fn keys() -> Vec<KeyCode> {
+    let mut keys: Vec<KeyCode> = BINDINGS.iter().map(|&(k, _)| k).collect();
+    keys.extend("abcxyz019 ;=+-".chars().map(KeyCode::Char));
+    keys.extend([
+        KeyCode::Up,
+        KeyCode::Down,
+        KeyCode::Home,
+        KeyCode::End,
+        KeyCode::PageUp,
+        KeyCode::PageDown,
+        KeyCode::BackTab,
+        KeyCode::Backspace,
+        KeyCode::Delete,
+        KeyCode::F(1),
+    ]);
+    keys
+}
+
+fn procs() -> Vec<SProc> {
+    let mut procs: Vec<SProc> = (1..12)
+        .map(|pid| {
+            let (cpu, mem_mb) = (pid * 7 % 10, pid * 13 % 10);
+            proc(pid, &format!("proc{}", pid), cpu as f64, mem_mb as f64)
+        })
+        .collect();
+    procs[3].add_dead_sample(&History::default(), 0.5);
+    procs
+}
+
+#[test]
+fn random_keys() {
+    let keys = keys();
+    for seed in 1..=SEEDS {
+        let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        // narrow and short terminals too, where columns and panes get squeezed.
+        let (w, h) = [(120, 40), (60, 15), (20, 6)][rng.below(3)];
+        let mut view = view(w, h);
+        // a process dies partway through.
+        let mut procs = procs();
+        for i in 0..KEYS_PER_RUN {
+            let mut key = KeyEvent::from(keys[rng.below(keys.len())]);
+            if rng.below(10) == 0 {
+                key.modifiers = KeyModifiers::CONTROL;
+            }
+            view.handle_key(key);
+            if i == KEYS_PER_RUN / 2 {
+                procs.remove(0);
+            }
+            draw(&mut view, &procs);
+        }
+
+        // Esc backs out of each layer in turn.
+        for _ in 0..3 {
+            if view.at_top() {
+                break;
+            }
+            press(&mut view, KeyCode::Esc);
+            draw(&mut view, &procs);
+        }
+        assert!(
+            view.at_top(),
+            "seed {}: Esc didn't get back to the table",
+            seed
+        );
+        assert!(
+            press(&mut view, KeyCode::Char('q')) == Next::Quit,
+            "seed {}: q didn't quit",
+            seed
+        );
+    }
+}
+
+#[test]
+fn delay_and_ewma_keys() {
+    let mut view = view(80, 20);
+    view.set_sample_interval(1.);
+    view.set_ewma_weight(0.35);
+    assert_eq!(view.take_tuning(), None);
+
+    press(&mut view, KeyCode::Char('+'));
+    press(&mut view, KeyCode::Char('+'));
+    press(&mut view, KeyCode::Char('['));
+    assert_eq!(view.take_tuning(), Some((3., 0.3)));
+    assert_eq!(view.take_tuning(), None, "only until taken");
+
+    // the ends of each range hold.
+    for _ in 0..20 {
+        press(&mut view, KeyCode::Char('-'));
+        press(&mut view, KeyCode::Char(']'));
+    }
+    assert_eq!(view.take_tuning(), Some((0.1, 1.)));
+}
+
+#[test]
+fn sort_keys_flip_on_repeat() {
+    let mut view = view(80, 20);
+    let procs = procs();
+    // the row of the process with the most memory, then the least.
+    let order = |view: &mut View<TestBackend>| {
+        let lines = draw(view, &procs);
+        let row = |name: &str| line_of(&lines, name).unwrap();
+        (row("proc3 "), row("proc10 "))
+    };
+
+    press(&mut view, KeyCode::Char('M'));
+    let (most, least) = order(&mut view);
+    assert!(most < least);
+    press(&mut view, KeyCode::Char('M'));
+    let (most, least) = order(&mut view);
+    assert!(most > least, "reversed");
+    // switching keys keeps the direction.
+    press(&mut view, KeyCode::Char('P'));
+    press(&mut view, KeyCode::Char('M'));
+    let (most, least) = order(&mut view);
+    assert!(most > least, "keeps the direction");
+}
+
+#[test]
+fn quit_asks_while_recording() {
+    let mut view = view(80, 20);
+    view.set_recording(Some("samples.jsonl".into()));
+    let procs = procs();
+    assert!(press(&mut view, KeyCode::Char('q')) == Next::Continue);
+    draw(&mut view, &procs);
+    assert!(!view.at_top(), "should be asking");
+
+    // 's' stops the recording and carries on.
+    assert!(press(&mut view, KeyCode::Char('s')) == Next::Continue);
+    assert!(view.take_stop_recording());
+    view.set_recording(None);
+    assert!(press(&mut view, KeyCode::Char('q')) == Next::Quit);
+
+    // anything but 'y' stays.
+    view.set_watching(Some("make".into()));
+    press(&mut view, KeyCode::Char('q'));
+    assert!(press(&mut view, KeyCode::Char('s')) == Next::Continue);
+    assert!(!view.take_stop_recording(), "nothing to stop");
+    press(&mut view, KeyCode::Char('q'));
+    assert!(press(&mut view, KeyCode::Char('y')) == Next::Quit);
+}
+
+#[test]
+fn heavy_columns_ask_how_often() {
+    let mut view = view(80, 20);
+    let procs = procs();
+    let toggle = || vec![Command::Run(Action::ToggleColumn(Column::Fds))];
+
+    // measured first, then shown once a cadence is picked.
+    view.run(toggle());
+    assert!(!view.showing(Column::Fds));
+    assert_eq!(view.take_cost_probe(), Some(Column::Fds));
+    view.ask_cadence(Column::Fds, Duration::from_millis(5), 10);
+    draw(&mut view, &procs);
+    assert!(!view.at_top(), "should be asking");
+    press(&mut view, KeyCode::Char('4'));
+    assert!(view.showing(Column::Fds));
+    assert_eq!(view.take_cadence(), Some((Column::Fds, 4)));
+
+    // turning it off doesn't ask.
+    view.run(toggle());
+    assert!(!view.showing(Column::Fds));
+    assert_eq!(view.take_cost_probe(), None);
+
+    // anything but a digit or Enter leaves it off.
+    view.run(toggle());
+    assert_eq!(view.take_cost_probe(), Some(Column::Fds));
+    view.ask_cadence(Column::Fds, Duration::from_millis(5), 10);
+    press(&mut view, KeyCode::Char('n'));
+    assert!(!view.showing(Column::Fds));
+    assert_eq!(view.take_cadence(), None);
+    assert!(view.at_top());
+}
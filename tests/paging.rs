@@ -0,0 +1,57 @@
+// Page faults and swapping as rates between samples, from cumulative
+// counters and how much is swapped out.
+use sparktop::{
+    sproc::{History, SProc},
+    sprocs::SProcs,
+};
+
+#[test]
+fn rates_between_samples() {
+    let (mut sp, history) = (SProc::synthetic(1, "p"), History::default());
+    sp.add_paging_sample(&history, 100, 4 << 20, 2.);
+    // nothing to compare the first against.
+    assert_eq!(sp.faults_hist.front(), Some(&0.));
+    sp.add_paging_sample(&history, 300, 8 << 20, 2.);
+    assert_eq!(sp.faults_hist.front(), Some(&100.));
+    assert_eq!(sp.swap_out_hist.front().copied(), Some((2 << 20) as f64));
+    assert_eq!(sp.swap_in_hist.front(), Some(&0.));
+    sp.add_paging_sample(&history, 300, 6 << 20, 1.);
+    assert_eq!(sp.swap_in_hist.front().copied(), Some((2 << 20) as f64));
+    assert_eq!(sp.swap_out_hist.front(), Some(&0.));
+    assert_eq!(sp.swap_bytes, Some(6 << 20));
+}
+
+#[test]
+fn starts_over() {
+    let (mut sp, history) = (SProc::synthetic(1, "p"), History::default());
+    sp.add_paging_sample(&history, 100, 4 << 20, 1.);
+    sp.forget_paging();
+    assert_eq!(sp.swap_bytes, None);
+    sp.add_paging_sample(&history, 900, 8 << 20, 1.);
+    assert_eq!(sp.faults_hist, [0.]);
+    assert_eq!(sp.swap_in_hist, [0.]);
+    assert_eq!(sp.swap_out_hist, [0.]);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn only_the_zoomed() {
+    let me = std::process::id() as i32;
+    let mut sprocs = SProcs::default();
+    sprocs.set_zoomed(Some(me));
+    sprocs.update(1.);
+    sprocs.update(1.);
+    assert!(sprocs.by_pid(me).unwrap().swap_bytes.is_some());
+    assert!(sprocs
+        .get()
+        .filter(|sp| sp.pid != me)
+        .all(|sp| sp.faults_hist.is_empty()));
+    sprocs.set_zoomed(None);
+    assert_eq!(sprocs.by_pid(me).unwrap().swap_bytes, None);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn reads_own() {
+    assert!(sparktop::platform::paging(std::process::id() as i32).is_ok());
+}
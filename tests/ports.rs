@@ -0,0 +1,32 @@
+// Listening ports are read from the process's network namespace: TCP
+// listeners, and UDP sockets bound but not connected.
+#![cfg(target_os = "linux")]
+use std::net::{TcpListener, UdpSocket};
+
+use sparktop::platform::{self, Port};
+
+#[test]
+fn reads_tcp_and_udp() {
+    let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+    let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+    // connected, so not listening.
+    let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+    client.connect(udp.local_addr().unwrap()).unwrap();
+
+    let me = std::process::id() as i32;
+    let ports: Vec<Port> = platform::listening_sockets(me)
+        .unwrap()
+        .into_values()
+        .collect();
+    let port = |addr: std::net::SocketAddr, udp| Port {
+        number: addr.port(),
+        udp,
+    };
+    assert!(ports.contains(&port(tcp.local_addr().unwrap(), false)));
+    assert!(ports.contains(&port(udp.local_addr().unwrap(), true)));
+    assert!(!ports.contains(&port(client.local_addr().unwrap(), true)));
+    assert_eq!(
+        port(udp.local_addr().unwrap(), true).to_string(),
+        format!("{}/udp", udp.local_addr().unwrap().port())
+    );
+}
@@ -0,0 +1,187 @@
+// The real binary in a pseudo-terminal: keys in, frames out, resizes, and
+// the terminal put back as it was on quitting. Linux only, for openpty.
+#![cfg(target_os = "linux")]
+use std::{
+    fs::File,
+    io::{Read, Write},
+    os::unix::{
+        io::{AsRawFd, FromRawFd},
+        process::CommandExt,
+    },
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Pty {
+    master: File,
+    slave: File, // kept, to check the terminal modes after
+    child: Child,
+    output: Arc<Mutex<Vec<u8>>>,
+    seen: usize, // output already matched by wait_for
+}
+
+impl Pty {
+    fn spawn(args: &[&str], cols: u16, rows: u16) -> Self {
+        let (mut master, mut slave) = (0, 0);
+        let ws = winsize(cols, rows);
+        let rc = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                &ws,
+            )
+        };
+        assert_eq!(rc, 0, "openpty: {}", std::io::Error::last_os_error());
+        let (master, slave) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+        let stdio = || Stdio::from(slave.try_clone().unwrap());
+        // a fresh config dir, so the user's settings don't leak in.
+        let config = std::env::temp_dir().join(format!("sparktop-pty-{}", std::process::id()));
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_sparktop"));
+        cmd.args(args)
+            .env("XDG_CONFIG_HOME", &config)
+            .stdin(stdio())
+            .stdout(stdio())
+            .stderr(stdio());
+        // its own session, with the pty as its controlling terminal, so
+        // resizes reach it as SIGWINCH.
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::setsid() < 0 || libc::ioctl(0, libc::TIOCSCTTY, 0) < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = cmd.spawn().expect("starting sparktop");
+        let output = Arc::new(Mutex::new(vec![]));
+        let mut reader = master.try_clone().unwrap();
+        let sink = Arc::clone(&output);
+        thread::spawn(move || {
+            let mut buf = [0; 4096];
+            // EIO once the last of the slave side closes.
+            while let Ok(n @ 1..) = reader.read(&mut buf) {
+                sink.lock().unwrap().extend_from_slice(&buf[..n]);
+            }
+        });
+        Self {
+            master,
+            slave,
+            child,
+            output,
+            seen: 0,
+        }
+    }
+
+    fn send(&mut self, keys: &str) {
+        self.master.write_all(keys.as_bytes()).unwrap();
+    }
+
+    fn resize(&self, cols: u16, rows: u16) {
+        let ws = winsize(cols, rows);
+        let rc = unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &ws) };
+        assert_eq!(rc, 0, "resizing: {}", std::io::Error::last_os_error());
+    }
+
+    // wait for `text` in the output since the last match, as written (so
+    // within one run of same-styled cells).
+    fn wait_for(&mut self, text: &str) {
+        let start = Instant::now();
+        loop {
+            {
+                let out = self.output.lock().unwrap();
+                let new = String::from_utf8_lossy(&out[self.seen..]);
+                if let Some(i) = new.find(text) {
+                    self.seen += new[..i + text.len()].len();
+                    return;
+                }
+            }
+            assert!(
+                start.elapsed() < TIMEOUT,
+                "never saw {:?}; last output: {:?}",
+                text,
+                String::from_utf8_lossy(&self.output.lock().unwrap()[self.seen..])
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    fn wait_exit(&mut self) -> std::process::ExitStatus {
+        let start = Instant::now();
+        loop {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                return status;
+            }
+            assert!(start.elapsed() < TIMEOUT, "didn't exit");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    // whether the terminal is back in cooked mode, echoing what's typed.
+    fn cooked(&self) -> bool {
+        let mut t: libc::termios = unsafe { std::mem::zeroed() };
+        let rc = unsafe { libc::tcgetattr(self.slave.as_raw_fd(), &mut t) };
+        assert_eq!(rc, 0, "tcgetattr: {}", std::io::Error::last_os_error());
+        t.c_lflag & libc::ICANON != 0 && t.c_lflag & libc::ECHO != 0
+    }
+}
+
+impl Drop for Pty {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn winsize(cols: u16, rows: u16) -> libc::winsize {
+    libc::winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+#[test]
+fn draws_takes_keys_and_restores_the_terminal() {
+    let mut pty = Pty::spawn(&["-d", "0.2"], 120, 30);
+    // the alternate screen, then the table.
+    pty.wait_for("\x1b[?1049h");
+    pty.wait_for("cpu history");
+    assert!(!pty.cooked(), "should be in raw mode");
+
+    // 'O' shows the system summary above the table.
+    pty.send("O");
+    pty.wait_for("load");
+
+    pty.send("q");
+    assert!(pty.wait_exit().success());
+    pty.wait_for("\x1b[?1049l");
+    assert!(pty.cooked(), "raw mode left on after quitting");
+}
+
+#[test]
+fn resizing_refits_the_columns() {
+    let mut pty = Pty::spawn(&["-d", "0.2"], 120, 30);
+    pty.wait_for("cpu history");
+    pty.resize(50, 20);
+    pty.wait_for("hidden (terminal too narrow");
+    pty.resize(120, 30);
+    pty.send("q");
+    assert!(pty.wait_exit().success());
+}
+
+#[test]
+fn paused_still_notices_the_watched_command_exit() {
+    let mut pty = Pty::spawn(&["-d", "0.1", "--", "sleep", "1"], 120, 30);
+    pty.wait_for("cpu history");
+    pty.send(" ");
+    pty.wait_for("PAUSED");
+    // sampling carries on behind the frozen screen, so this exits with it.
+    assert!(pty.wait_exit().success());
+}
@@ -0,0 +1,98 @@
+// Recording to disk: resuming keeps every whole tick, whether or not the
+// index got to it, and drops only a tick torn by a crash.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use sparktop::{record::Recorder, sproc::SProc};
+
+// a fresh directory for a recording, removed on drop.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("sparktop-record-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn record(path: &Path, ticks: usize) {
+    let procs = [SProc::synthetic(1, "init"), SProc::synthetic(2, "ünïcode")];
+    let mut recorder = Recorder::open(path).unwrap();
+    for _ in 0..ticks {
+        recorder.record(&procs).unwrap();
+    }
+}
+
+fn lines(path: &Path) -> usize {
+    fs::read_to_string(path).unwrap().lines().count()
+}
+
+fn index(path: &Path) -> String {
+    fs::read_to_string(format!("{}.idx", path.display())).unwrap()
+}
+
+#[test]
+fn resumes_after_what_it_has() {
+    let dir = TempDir::new("resume");
+    let path = dir.0.join("rec.jsonl");
+    record(&path, 3);
+    record(&path, 2);
+    assert_eq!(lines(&path), 5);
+    let len = fs::metadata(&path).unwrap().len();
+    assert!(index(&path).ends_with(&format!("{} 2\n", len)));
+}
+
+#[test]
+fn drops_only_a_torn_tick() {
+    let dir = TempDir::new("torn");
+    let path = dir.0.join("rec.jsonl");
+    record(&path, 2);
+    let whole = fs::read(&path).unwrap();
+    // a third tick cut off partway through a character.
+    let mut torn = whole.clone();
+    torn.extend_from_slice(&whole[..whole.len() / 4]);
+    torn.extend_from_slice(&"ü".as_bytes()[..1]);
+    fs::write(&path, &torn).unwrap();
+    record(&path, 0);
+    assert_eq!(fs::read(&path).unwrap(), whole);
+}
+
+#[test]
+fn keeps_the_data_without_an_index() {
+    let dir = TempDir::new("no-index");
+    let path = dir.0.join("rec.jsonl");
+    record(&path, 4);
+    let data = fs::read(&path).unwrap();
+    for idx in ["missing", "empty"] {
+        let idx_path = format!("{}.idx", path.display());
+        match idx {
+            "missing" => fs::remove_file(&idx_path).unwrap(),
+            _ => fs::write(&idx_path, "").unwrap(),
+        }
+        record(&path, 1);
+        assert_eq!(
+            fs::read(&path).unwrap()[..data.len()],
+            data[..],
+            "{} index",
+            idx
+        );
+        fs::write(&path, &data).unwrap();
+        // the ticks found are indexed again.
+        assert!(
+            index(&path).starts_with(&format!("{} 4\n", data.len())),
+            "{} index",
+            idx
+        );
+    }
+}
@@ -0,0 +1,73 @@
+// render_multi drawn on a TestBackend: the taller of two series wins a cell,
+// and where both are high they share it as a half block.
+use sparktop::render::{render_multi, Series};
+use sparktop::ui::{
+    backend::TestBackend,
+    buffer::Buffer,
+    style::Color,
+    text::{Line, Text},
+    widgets::Paragraph,
+    Terminal,
+};
+
+fn draw(line: Line<'static>) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(8, 1)).unwrap();
+    terminal
+        .draw(|f| f.render_widget(Paragraph::new(Text::from(line)), f.area()))
+        .unwrap();
+    terminal.backend().buffer().clone()
+}
+
+fn two(a: &[f64], b: &[f64]) -> Buffer {
+    draw(render_multi(&[
+        Series::new(a, 100., |_| Color::Green),
+        Series::new(b, 100., |_| Color::Red),
+    ]))
+}
+
+#[test]
+fn tallest_wins() {
+    let buf = two(&[80., 10., 0.], &[10., 60., 0.]);
+    let cells: Vec<_> = (0..3).map(|x| &buf[(x, 0)]).collect();
+    assert_eq!(cells[0].symbol(), "▇");
+    assert_eq!(cells[0].fg, Color::Green);
+    assert_eq!(cells[1].symbol(), "▅");
+    assert_eq!(cells[1].fg, Color::Red);
+    assert_eq!(cells[2].symbol(), " ");
+    for c in cells {
+        assert_eq!(c.bg, Color::Reset);
+    }
+}
+
+#[test]
+fn overlaps_share_a_half_block() {
+    // the shorter series below, the taller above.
+    let buf = two(&[90., 40., 100.], &[50., 95., 100.]);
+    let cell = |x| {
+        let c = &buf[(x, 0)];
+        (c.symbol(), c.fg, c.bg)
+    };
+    assert_eq!(cell(0), ("▄", Color::Red, Color::Green));
+    assert_eq!(cell(1), ("▄", Color::Green, Color::Red));
+    // a tie goes to the first series, on top.
+    assert_eq!(cell(2), ("▄", Color::Red, Color::Green));
+}
+
+#[test]
+fn low_overlaps_keep_the_bar() {
+    // the shorter too low to share the cell, or the taller not high enough.
+    let buf = two(&[90., 60.], &[10., 40.]);
+    assert_eq!(buf[(0, 0)].symbol(), "█");
+    assert_eq!(buf[(1, 0)].symbol(), "▅");
+    assert_eq!(buf[(1, 0)].bg, Color::Reset);
+}
+
+#[test]
+fn no_half_blocks_in_ascii() {
+    let buf = draw(render_multi(&[
+        Series::new(&[90.], 100., |_| Color::Green).with_ascii(true),
+        Series::new(&[80.], 100., |_| Color::Red),
+    ]));
+    assert_ne!(buf[(0, 0)].symbol(), "▄");
+    assert_eq!(buf[(0, 0)].bg, Color::Reset);
+}
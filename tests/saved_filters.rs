@@ -0,0 +1,78 @@
+// Named filters from the config, switched to with 1-9: the focused table
+// shows only what matches, and says which filter it's showing.
+use sparktop::ui::backend::TestBackend;
+use sparktop::{sproc::SProc, view::View, view_state::RowCond};
+
+mod common;
+use common::{type_keys, view};
+
+fn cond(s: &str) -> RowCond {
+    s.parse().unwrap()
+}
+
+fn draw(view: &mut View<TestBackend>, procs: &[SProc]) -> String {
+    common::draw(view, procs).join("\n")
+}
+
+#[test]
+fn conditions() {
+    let mut redis = SProc::synthetic(10, "redis");
+    redis.uid = Some(0);
+    let mut bash = SProc::synthetic(20, "bash");
+    bash.uid = Some(1000);
+
+    let db = cond("name in {postgres, \"redis\"}");
+    assert!(db.matches(&redis));
+    assert!(!db.matches(&bash));
+    let root = cond("user == 0");
+    assert!(root.matches(&redis));
+    assert!(!root.matches(&bash));
+    assert!(cond("user in {0, 1000}").matches(&bash));
+
+    std::env::set_var("SPARKTOP_TEST_NAME", "bash");
+    assert!(cond("name == $SPARKTOP_TEST_NAME").matches(&bash));
+
+    for bad in [
+        "cpu in {1, 2}",
+        "name in postgres",
+        "user =~ ro+t",
+        "name == $SPARKTOP_TEST_UNSET",
+    ] {
+        assert!(bad.parse::<RowCond>().is_err(), "{:?} parsed", bad);
+    }
+}
+
+#[test]
+fn number_keys_switch() {
+    let mut v = view(100, 20);
+    v.set_saved_filters(vec![
+        ("db".into(), cond("name in {postgres, redis}")),
+        ("shells".into(), cond("name =~ sh$")),
+    ]);
+    let procs = [
+        SProc::synthetic(10, "postgres"),
+        SProc::synthetic(20, "bash"),
+        SProc::synthetic(30, "redis"),
+    ];
+
+    type_keys(&mut v, "1");
+    let screen = draw(&mut v, &procs);
+    assert!(screen.contains("postgres") && screen.contains("redis"));
+    assert!(!screen.contains("bash"));
+    assert!(screen.contains("filter db (1 to clear)"));
+
+    type_keys(&mut v, "2");
+    let screen = draw(&mut v, &procs);
+    assert!(screen.contains("bash"));
+    assert!(!screen.contains("postgres"));
+    assert!(screen.contains("filter shells"));
+
+    // again for everything.
+    type_keys(&mut v, "2");
+    let screen = draw(&mut v, &procs);
+    assert!(screen.contains("postgres") && screen.contains("bash"));
+    assert!(!screen.contains("filter shells"));
+
+    type_keys(&mut v, "7");
+    assert!(draw(&mut v, &procs).contains("no saved filter 7"));
+}
@@ -0,0 +1,122 @@
+// A rhai script's columns, highlights and alerts, run against each sample,
+// and its columns and highlights drawn in the table.
+#![cfg(feature = "scripting")]
+use sparktop::ui::style::{Color, Modifier, Style};
+use sparktop::{script::Script, sproc::SProc, sprocs::SystemStats};
+
+mod common;
+use common::{cell, draw, find, proc, view};
+
+fn script(src: &str) -> Script {
+    Script::compile(src).unwrap()
+}
+
+fn system(cpu: f64) -> SystemStats {
+    SystemStats {
+        cpu_pct: cpu,
+        mem_used_bytes: 3,
+        mem_total_bytes: 4,
+        load: [1.5, 1., 0.5],
+        ..SystemStats::default()
+    }
+}
+
+#[test]
+fn columns_and_highlights() {
+    let mut s = script(
+        r#"
+        column("mb/thr", |p| p.mem_mb / (p.threads ?? 1));
+        column("who", |p| `${p.name}:${p.pid}`);
+        highlight(|p| p.cpu_ewma > 100., "fg:red, bold");
+        highlight(|p| p.name == "java", "italic");
+        "#,
+    );
+    assert_eq!(s.columns(), ["mb/thr", "who"]);
+    assert_eq!(
+        s.highlights(),
+        [
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            Style::default().add_modifier(Modifier::ITALIC),
+        ]
+    );
+    let mut procs = [proc(10, "java", 150., 64.), proc(20, "bash", 1., 3.)];
+    procs[0].threads = Some(4);
+    assert!(s.tick(&mut procs, &system(10.)).is_empty());
+    assert_eq!(procs[0].script_values, ["16.0", "java:10"]);
+    assert_eq!(procs[0].script_highlights, [0, 1]);
+    assert_eq!(procs[1].script_values, ["3.0", "bash:20"]);
+    assert!(procs[1].script_highlights.is_empty());
+}
+
+#[test]
+fn alerts_fire_once_per_spell() {
+    let mut s = script(
+        r#"
+        alert("busy", |s| s.cpu > 90.);
+        alert("javas", |s| {
+            let n = s.procs.filter(|p| p.name == "java").len();
+            if n > 1 { `${n} running` } else { false }
+        });
+        alert("loaded", |s| s.load[0] > 1. && s.mem_pct == 75.);
+        "#,
+    );
+    let mut procs = [proc(10, "java", 0., 1.), proc(11, "java", 0., 1.)];
+    assert_eq!(
+        s.tick(&mut procs, &system(95.)),
+        ["alert: busy", "alert: javas: 2 running", "alert: loaded"]
+    );
+    assert!(s.tick(&mut procs, &system(95.)).is_empty());
+    // re-armed once it stops holding.
+    assert!(s.tick(&mut procs, &system(50.)).is_empty());
+    assert_eq!(s.tick(&mut procs, &system(95.)), ["alert: busy"]);
+}
+
+#[test]
+fn failures_are_said_once() {
+    let mut s = script(
+        r#"
+        column("bad", |p| p.name / 2);
+        column("ok", |p| p.pid);
+        alert("spin", |s| { loop {} });
+        "#,
+    );
+    let mut procs = [proc(10, "a", 0., 1.), proc(11, "b", 0., 1.)];
+    let msgs = s.tick(&mut procs, &system(0.));
+    assert_eq!(msgs.len(), 2, "{:?}", msgs);
+    assert!(msgs[0].starts_with("script: column bad failed"));
+    assert!(msgs[1].starts_with("script: alert spin failed"));
+    assert_eq!(procs[1].script_values, ["", "11"]);
+    assert!(s.tick(&mut procs, &system(0.)).is_empty());
+}
+
+#[test]
+fn bad_scripts_are_errors() {
+    for bad in [
+        "column(\"x\", |p| p.cpu",          // doesn't parse
+        "highlight(|p| true, \"sparkly\")", // no such style
+        "no_such_function()",
+    ] {
+        assert!(Script::compile(bad).is_err(), "{:?} compiled", bad);
+    }
+}
+
+#[test]
+fn drawn_in_the_table() {
+    let mut s = script(
+        r#"
+        column("doubled", |p| p.cpu_ewma * 2.);
+        highlight(|p| p.cpu_ewma > 100., "bg:blue");
+        "#,
+    );
+    let mut procs: Vec<SProc> = vec![proc(10, "hot", 150., 1.), proc(20, "cool", 1., 1.)];
+    s.tick(&mut procs, &system(0.));
+    let mut v = view(120, 10);
+    v.set_script(s.columns(), s.highlights());
+    let screen = draw(&mut v, &procs);
+    assert!(find(&screen, "doubled").is_some(), "{:#?}", screen);
+    assert!(find(&screen, "300.0").is_some());
+    let (x, y) = find(&screen, "hot").unwrap();
+    assert_eq!(cell(&v, x, y).bg, Color::Blue);
+    let (x, y) = find(&screen, "cool").unwrap();
+    assert_ne!(cell(&v, x, y).bg, Color::Blue);
+}
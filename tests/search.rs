@@ -0,0 +1,45 @@
+// '/' filters the table as the query is typed, in any case; Enter keeps the
+// filter and Esc clears it.
+use crossterm::event::KeyCode;
+use sparktop::ui::backend::TestBackend;
+use sparktop::{sproc::SProc, view::View};
+
+mod common;
+use common::{press, type_keys, view};
+
+fn draw(view: &mut View<TestBackend>, procs: &[SProc]) -> String {
+    common::draw(view, procs).join("\n")
+}
+
+#[test]
+fn search_filters_as_typed() {
+    let mut v = view(100, 20);
+    let mut alpha = SProc::synthetic(10, "alpha");
+    alpha.cmd = vec!["/usr/bin/Alpha".into(), "--serve".into()];
+    let procs = [alpha, SProc::synthetic(20, "bravo")];
+
+    type_keys(&mut v, "/SERV");
+    let screen = draw(&mut v, &procs);
+    assert!(screen.contains("alpha"));
+    assert!(!screen.contains("bravo"));
+    assert!(screen.contains("/SERV"));
+
+    press(&mut v, KeyCode::Enter);
+    let screen = draw(&mut v, &procs);
+    assert!(v.at_top());
+    assert!(!screen.contains("bravo"));
+    assert!(screen.contains("filter: SERV"));
+
+    // Esc clears it, even after editing it again.
+    type_keys(&mut v, "/x");
+    press(&mut v, KeyCode::Esc);
+    let screen = draw(&mut v, &procs);
+    assert!(v.at_top());
+    assert!(screen.contains("alpha") && screen.contains("bravo"));
+    assert!(!screen.contains("filter:"));
+
+    // and undo brings it back.
+    type_keys(&mut v, "u");
+    let screen = draw(&mut v, &procs);
+    assert!(!screen.contains("bravo"));
+}
@@ -0,0 +1,360 @@
+// Selection, scroll and expanded rows hold still across ticks, re-sorts,
+// filters and process death. Drawn to a TestBackend from made-up processes.
+use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use sparktop::ui::backend::TestBackend;
+use sparktop::{
+    action::parse_command,
+    coredump::DumpWith,
+    sproc::{History, SProc},
+    view::View,
+};
+
+mod common;
+use common::{draw, line_of, proc};
+
+fn view(width: u16) -> View<TestBackend> {
+    common::view(width, 30)
+}
+
+fn key(view: &mut View<TestBackend>, code: KeyCode) {
+    common::press(view, code);
+}
+
+fn run(view: &mut View<TestBackend>, cmd: &str) {
+    view.run(parse_command(cmd).unwrap());
+}
+
+fn mouse(view: &mut View<TestBackend>, kind: MouseEventKind, column: u16, row: u16) {
+    view.handle_mouse(MouseEvent {
+        kind,
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    });
+}
+
+// the expanded details (the command line, here just the name) go right under
+// the selected row.
+fn expanded_under(screen: &[String], name: &str) -> bool {
+    match line_of(screen, name) {
+        Some(i) => screen[i + 1].trim() == name,
+        None => false,
+    }
+}
+
+fn procs() -> Vec<SProc> {
+    vec![
+        proc(10, "alpha", 50., 100.),
+        proc(20, "bravo", 30., 300.),
+        proc(30, "charlie", 10., 200.),
+    ]
+}
+
+#[test]
+fn resort_keeps_selection() {
+    let mut v = view(100);
+    let procs = procs();
+    draw(&mut v, &procs);
+    key(&mut v, KeyCode::Char('e'));
+    let screen = draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+    assert!(expanded_under(&screen, "alpha"));
+
+    key(&mut v, KeyCode::Char('M'));
+    let screen = draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+    assert!(expanded_under(&screen, "alpha"));
+    assert!(line_of(&screen, "bravo") < line_of(&screen, "alpha"));
+}
+
+#[test]
+fn ticks_keep_selection() {
+    let mut v = view(100);
+    let mut procs = procs();
+    key(&mut v, KeyCode::Char('e'));
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+
+    // alpha drops to the bottom, and a new process arrives on top.
+    procs[0].cpu_ewma = 1.;
+    procs.push(proc(40, "delta", 90., 50.));
+    let screen = draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+    assert!(expanded_under(&screen, "alpha"));
+    assert!(line_of(&screen, "delta") < line_of(&screen, "alpha"));
+}
+
+#[test]
+fn filter_keeps_selection() {
+    let mut v = view(100);
+    let procs = procs();
+    key(&mut v, KeyCode::Char('e'));
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+
+    run(&mut v, "filter bravo");
+    let screen = draw(&mut v, &procs);
+    assert_eq!(line_of(&screen, "alpha"), None);
+    assert_eq!(v.selected(), Some(10));
+
+    run(&mut v, "filter off");
+    let screen = draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+    assert!(expanded_under(&screen, "alpha"));
+}
+
+#[test]
+fn death_keeps_selection() {
+    let mut v = view(100);
+    let mut procs = procs();
+    key(&mut v, KeyCode::Char('e'));
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+
+    // dead rows stay selected while they're shown.
+    procs[0].add_dead_sample(&History::default(), 0.5);
+    let screen = draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+    assert!(expanded_under(&screen, "alpha"));
+
+    // once reaped, nothing else is picked in its place.
+    procs.remove(0);
+    let screen = draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+    assert!(!expanded_under(&screen, "bravo"));
+
+    key(&mut v, KeyCode::Enter);
+    let screen = draw(&mut v, &procs);
+    assert!(line_of(&screen, "process is gone").is_some());
+}
+
+#[test]
+fn scroll_survives_ticks_and_sorts() {
+    let mut v = view(60);
+    let mut procs = procs();
+    let unscrolled = draw(&mut v, &procs);
+    key(&mut v, KeyCode::Right);
+    let scrolled = draw(&mut v, &procs);
+    // the sort column is starred; only which columns are shown matters.
+    let columns = |screen: &[String]| {
+        let header = screen[line_of(screen, "process").unwrap()].replace('*', " ");
+        header
+            .split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+    assert_ne!(columns(&scrolled), columns(&unscrolled));
+
+    procs[2].cpu_ewma = 99.;
+    key(&mut v, KeyCode::Char('M'));
+    let screen = draw(&mut v, &procs);
+    assert_eq!(columns(&screen), columns(&scrolled));
+    run(&mut v, "filter a");
+    let screen = draw(&mut v, &procs);
+    assert_eq!(columns(&screen), columns(&scrolled));
+}
+
+#[test]
+fn keys_move_selection() {
+    let mut v = view(100);
+    let procs = procs();
+    // by cpu: alpha, bravo, charlie.
+    key(&mut v, KeyCode::Down);
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+    key(&mut v, KeyCode::Char('j'));
+    key(&mut v, KeyCode::Down);
+    key(&mut v, KeyCode::Down);
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(30));
+    key(&mut v, KeyCode::Char('k'));
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(20));
+
+    // the selection stays on bravo, now at the top.
+    key(&mut v, KeyCode::Char('M'));
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(20));
+    key(&mut v, KeyCode::Down);
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(30));
+    key(&mut v, KeyCode::Home);
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(20));
+    key(&mut v, KeyCode::End);
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+}
+
+#[test]
+fn scrolling_follows_selection() {
+    let mut v = common::view(100, 8);
+    let procs: Vec<SProc> = (1..=50)
+        .map(|pid| proc(pid, &format!("p{:02}", pid), 100. - pid as f64, 1.))
+        .collect();
+    draw(&mut v, &procs);
+    key(&mut v, KeyCode::End);
+    let screen = draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(50));
+    assert!(line_of(&screen, "p50").is_some());
+    assert_eq!(line_of(&screen, "p01"), None);
+
+    key(&mut v, KeyCode::PageUp);
+    let screen = draw(&mut v, &procs);
+    let selected = v.selected().unwrap();
+    assert!(selected < 50);
+    assert!(line_of(&screen, &format!("p{:02}", selected)).is_some());
+
+    key(&mut v, KeyCode::Home);
+    let screen = draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(1));
+    assert!(line_of(&screen, "p01").is_some());
+}
+
+#[test]
+fn tall_rows_scroll_by_row() {
+    let mut v = common::view(100, 10);
+    let procs: Vec<SProc> = (1..=20)
+        .map(|pid| {
+            let mut sp = proc(pid, &format!("p{:02}", pid), 100. - pid as f64, 1.);
+            sp.cpu_hist = vec![50.; 10].into();
+            sp
+        })
+        .collect();
+    key(&mut v, KeyCode::Char('T'));
+    key(&mut v, KeyCode::End);
+    let screen = draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(20));
+    let last = line_of(&screen, "p20").unwrap();
+    assert_eq!(screen[last + 1].trim(), "▄".repeat(10));
+    assert!(line_of(&screen, "p16").is_none());
+}
+
+#[test]
+fn jumps_follow_the_family() {
+    let mut v = view(100);
+    let mut procs = vec![
+        proc(1, "init", 1., 1.),
+        proc(10, "nginx", 9., 1.),
+        proc(11, "nginx", 5., 1.),
+        proc(12, "nginx", 3., 1.),
+    ];
+    procs[1].ppid = Some(1);
+    procs[2].ppid = Some(10);
+    procs[3].ppid = Some(10);
+    key(&mut v, KeyCode::Home);
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+    key(&mut v, KeyCode::Char('c'));
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(11));
+    key(&mut v, KeyCode::Char('n'));
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(12));
+    key(&mut v, KeyCode::Char('n'));
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+    key(&mut v, KeyCode::Char('p'));
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(1));
+
+    // init's parent isn't shown; the selection stays put.
+    key(&mut v, KeyCode::Char('p'));
+    let screen = draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(1));
+    assert!(line_of(&screen, "no parent of init shown").is_some());
+}
+
+#[test]
+fn core_dump_waits_for_yes() {
+    let mut v = view(100);
+    let procs = procs();
+    key(&mut v, KeyCode::Home);
+    draw(&mut v, &procs);
+    key(&mut v, KeyCode::Char('C'));
+    let screen = draw(&mut v, &procs);
+    assert!(line_of(&screen, "core dump 10 (alpha) with gcore? y/n").is_some());
+    key(&mut v, KeyCode::Char('n'));
+    let screen = draw(&mut v, &procs);
+    assert_eq!(line_of(&screen, "core dump"), None);
+    assert_eq!(v.take_dump(), None);
+
+    key(&mut v, KeyCode::Char('C'));
+    draw(&mut v, &procs);
+    key(&mut v, KeyCode::Char('y'));
+    assert_eq!(v.take_dump(), Some((10, "alpha".into(), DumpWith::Gcore)));
+    assert_eq!(v.take_dump(), None);
+}
+
+#[test]
+fn mute_hides_by_name() {
+    let mut v = view(100);
+    let mut procs = procs();
+    procs.push(proc(25, "bravo", 1., 10.));
+    // the same name in its command line, or a longer name, isn't muted.
+    let mut cousin = proc(26, "bravo-agent", 1., 10.);
+    cousin.cmd = vec!["bravo".into()];
+    procs.push(cousin);
+    let row_of = |screen: &[String], pid: &str| screen.iter().position(|l| l.starts_with(pid));
+    key(&mut v, KeyCode::Home);
+    key(&mut v, KeyCode::Down);
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(20));
+    key(&mut v, KeyCode::Char('x'));
+    draw(&mut v, &procs);
+    key(&mut v, KeyCode::Esc); // the alert saying so
+    let screen = draw(&mut v, &procs);
+    assert_eq!(row_of(&screen, "20 "), None);
+    assert_eq!(row_of(&screen, "25 "), None);
+    assert!(row_of(&screen, "10 ").is_some());
+    assert!(row_of(&screen, "26 ").is_some());
+    assert_eq!(v.take_changed_muted(), Some(vec!["bravo".into()]));
+
+    run(&mut v, "unmute all");
+    key(&mut v, KeyCode::Esc);
+    let screen = draw(&mut v, &procs);
+    assert!(row_of(&screen, "25 ").is_some());
+    assert_eq!(v.take_changed_muted(), Some(vec![]));
+}
+
+#[test]
+fn mouse_sorts_and_selects() {
+    let mut v = view(100);
+    let procs = procs();
+    let screen = draw(&mut v, &procs);
+    let click = MouseEventKind::Down(MouseButton::Left);
+
+    // the mem header, then it again to reverse.
+    let header = line_of(&screen, "process").unwrap();
+    let x = screen[header].find("mem ").unwrap() as u16;
+    mouse(&mut v, click, x, header as u16);
+    let screen = draw(&mut v, &procs);
+    assert!(line_of(&screen, "bravo") < line_of(&screen, "charlie"));
+    assert!(line_of(&screen, "charlie") < line_of(&screen, "alpha"));
+    mouse(&mut v, click, x, header as u16);
+    let screen = draw(&mut v, &procs);
+    assert!(line_of(&screen, "alpha") < line_of(&screen, "bravo"));
+
+    let row = line_of(&screen, "charlie").unwrap();
+    mouse(&mut v, click, 3, row as u16);
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(30));
+
+    mouse(&mut v, MouseEventKind::ScrollUp, 3, row as u16);
+    draw(&mut v, &procs);
+    assert_eq!(v.selected(), Some(10));
+}
+
+#[test]
+fn own_row_pinned_through_filters() {
+    let mut v = view(100);
+    let procs = procs();
+    v.show_own_row(30, true);
+    let screen = draw(&mut v, &procs);
+    assert!(line_of(&screen, "charlie (this)") < line_of(&screen, "alpha"));
+
+    run(&mut v, "filter alpha");
+    let screen = draw(&mut v, &procs);
+    assert!(line_of(&screen, "charlie (this)") < line_of(&screen, "alpha"));
+    assert_eq!(line_of(&screen, "bravo"), None);
+}
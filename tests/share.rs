@@ -0,0 +1,77 @@
+// Sharing a session: frames survive the trip to attached clients, and binding
+// only ever replaces a socket left behind.
+#![cfg(unix)]
+use std::{
+    fs,
+    os::unix::{fs::PermissionsExt, net::UnixListener},
+    path::PathBuf,
+};
+
+use sparktop::share::{Frame, Share};
+use sparktop::ui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+};
+
+// a fresh path to bind at, removed again on drop.
+struct TempSock(PathBuf);
+
+impl TempSock {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("sparktop-share-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let _ = fs::remove_file(&path);
+        Self(path)
+    }
+}
+
+impl Drop for TempSock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn frames_round_trip() {
+    let mut buf = Buffer::empty(Rect::new(0, 0, 6, 2));
+    let bold_red = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+    buf.set_string(0, 0, "ab", bold_red);
+    buf.set_string(2, 0, "cd", Style::default().bg(Color::Indexed(42)));
+    buf.set_string(0, 1, "界x", Style::default());
+
+    let line = serde_json::to_string(&Frame::from_buffer(&buf)).unwrap();
+    let frame: Frame = serde_json::from_str(&line).unwrap();
+    let lines = frame.to_spans();
+    let texts: Vec<Vec<&str>> = lines
+        .iter()
+        .map(|l| l.spans.iter().map(|s| s.content.as_ref()).collect())
+        .collect();
+    // runs of one style; the wide character's hidden cell isn't repeated.
+    assert_eq!(texts, vec![vec!["ab", "cd", "  "], vec!["界x   "]]);
+    assert_eq!(lines[0].spans[0].style, bold_red.bg(Color::Reset));
+    assert_eq!(lines[0].spans[1].style.bg, Some(Color::Indexed(42)));
+}
+
+#[test]
+fn replaces_only_stale_sockets() {
+    let sock = TempSock::new("stale.sock");
+    // a socket nobody listens on any more.
+    drop(UnixListener::bind(&sock.0).unwrap());
+    let share = Share::bind(&sock.0).unwrap();
+    let mode = fs::metadata(&sock.0).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    // one still in use.
+    assert!(Share::bind(&sock.0).is_err());
+    drop(share);
+    assert!(!sock.0.exists());
+}
+
+#[test]
+fn leaves_other_files_alone() {
+    let file = TempSock::new("not-a.sock");
+    fs::write(&file.0, "keep me").unwrap();
+    assert!(Share::bind(&file.0).is_err());
+    assert_eq!(fs::read_to_string(&file.0).unwrap(), "keep me");
+}
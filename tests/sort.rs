@@ -0,0 +1,75 @@
+// The table's row order is a function of the rows' values alone: ties go to
+// the lower pid, whatever order the processes arrive in.
+use sparktop::{
+    platform::Port,
+    sproc::SProc,
+    view::sort_rows,
+    view_state::{Dir, Metric},
+};
+
+mod common;
+
+fn proc(pid: i32, cpu: f64, mem_mb: f64) -> SProc {
+    common::proc(pid, "p", cpu, mem_mb)
+}
+
+fn pids(rows: &[&SProc]) -> Vec<i32> {
+    rows.iter().map(|sp| sp.pid).collect()
+}
+
+#[test]
+fn ties_break_by_pid() {
+    let procs = [
+        proc(30, 0., 1.),
+        proc(10, 5., 1.),
+        proc(20, 0., 1.),
+        proc(40, 5., 1.),
+    ];
+    let mut rows: Vec<&SProc> = procs.iter().collect();
+    sort_rows(&mut rows, Metric::Cpu, Dir::Desc, false);
+    assert_eq!(pids(&rows), [10, 40, 20, 30]);
+    sort_rows(&mut rows, Metric::Cpu, Dir::Asc, false);
+    assert_eq!(pids(&rows), [20, 30, 10, 40]);
+}
+
+#[test]
+fn order_doesnt_depend_on_input_order() {
+    let procs: Vec<SProc> = (1..=50)
+        .map(|pid| proc(pid, (pid % 3) as f64, (pid % 5) as f64))
+        .collect();
+    for &by in &[Metric::Cpu, Metric::Mem, Metric::Pid] {
+        for &dir in &[Dir::Asc, Dir::Desc] {
+            let mut forward: Vec<&SProc> = procs.iter().collect();
+            let mut backward: Vec<&SProc> = procs.iter().rev().collect();
+            // and one shuffled by a fixed stride.
+            let mut strided: Vec<&SProc> = (0..procs.len())
+                .map(|i| &procs[i * 7 % procs.len()])
+                .collect();
+            sort_rows(&mut forward, by, dir, false);
+            sort_rows(&mut backward, by, dir, false);
+            sort_rows(&mut strided, by, dir, false);
+            assert_eq!(pids(&forward), pids(&backward));
+            assert_eq!(pids(&forward), pids(&strided));
+        }
+    }
+}
+
+#[test]
+fn negative_zero_ties_with_zero() {
+    let procs = [proc(2, -0., 0.), proc(1, 0., 0.)];
+    let mut rows: Vec<&SProc> = procs.iter().collect();
+    sort_rows(&mut rows, Metric::Cpu, Dir::Desc, false);
+    assert_eq!(pids(&rows), [1, 2]);
+}
+
+#[test]
+fn unreadable_ports_sort_below_none() {
+    let mut procs = [proc(1, 0., 0.), proc(2, 0., 0.), proc(3, 0., 0.)];
+    procs[0].ports = None; // couldn't read its fds
+    procs[1].ports = Some(vec![]);
+    let tcp = |number| Port { number, udp: false };
+    procs[2].ports = Some(vec![tcp(22), tcp(80)]);
+    let mut rows: Vec<&SProc> = procs.iter().collect();
+    sort_rows(&mut rows, Metric::Ports, Dir::Desc, false);
+    assert_eq!(pids(&rows), [3, 2, 1]);
+}
@@ -0,0 +1,32 @@
+// Columns read less often than every sample are drawn dim while what they
+// show is from an earlier one.
+use sparktop::ui::{backend::TestBackend, style::Modifier};
+use sparktop::{
+    action::{Action, Command},
+    column::Column,
+    sproc::SProc,
+    view::View,
+};
+
+mod common;
+use common::{cell, draw, find, view};
+
+// whether the cell where `text` starts is drawn dim.
+fn dim_at(view: &mut View<TestBackend>, procs: &[SProc], text: &str) -> bool {
+    let (x, y) = find(&draw(view, procs), text).expect("on screen");
+    cell(view, x, y).modifier.contains(Modifier::DIM)
+}
+
+#[test]
+fn stale_columns_are_dim() {
+    let mut v = view(100, 10);
+    v.run(vec![Command::Run(Action::SetColumn(Column::Fds, true))]);
+    let mut sp = SProc::synthetic(10, "alpha");
+    sp.fds = Some(4242);
+    let procs = [sp];
+
+    assert!(!dim_at(&mut v, &procs, "4242"));
+    v.set_stale(vec![Column::Fds]);
+    assert!(dim_at(&mut v, &procs, "4242"));
+    assert!(!dim_at(&mut v, &procs, "alpha"), "only the stale column");
+}
@@ -0,0 +1,78 @@
+// The tree column: each process's cpu plus everything below it, so a shell
+// running a build shows what the build costs.
+use std::collections::HashMap;
+
+use sparktop::{
+    action::parse_command,
+    sproc::SProc,
+    sprocs::sum_subtrees,
+    view::sort_rows,
+    view_state::{Dir, Metric},
+};
+
+mod common;
+use common::{draw_rows, view};
+
+fn proc(pid: i32, ppid: Option<i32>, name: &str, cpu: f64) -> SProc {
+    let mut sp = common::proc(pid, name, cpu, 0.);
+    sp.ppid = ppid;
+    sp
+}
+
+// bash (10) runs make (20), which runs two compilers; idle (40) is alone.
+fn tree() -> HashMap<i32, SProc> {
+    let mut procs: HashMap<i32, SProc> = vec![
+        proc(10, Some(1), "bash", 1.),
+        proc(20, Some(10), "make", 2.),
+        proc(30, Some(20), "cc1", 90.),
+        proc(31, Some(20), "cc1plus", 80.),
+        proc(40, Some(1), "idle", 0.5),
+    ]
+    .into_iter()
+    .map(|sp| (sp.pid, sp))
+    .collect();
+    sum_subtrees(&mut procs);
+    procs
+}
+
+#[test]
+fn sums_descendants() {
+    let procs = tree();
+    let tree_cpu = |pid| procs[&pid].tree_cpu(false);
+    assert_eq!(tree_cpu(10), 173.);
+    assert_eq!(tree_cpu(20), 172.);
+    assert_eq!(tree_cpu(30), 90.);
+    assert_eq!(tree_cpu(40), 0.5);
+
+    // summing again doesn't pile up.
+    let mut procs = procs;
+    sum_subtrees(&mut procs);
+    assert_eq!(procs[&10].tree_cpu(false), 173.);
+
+    let mut rows: Vec<&SProc> = procs.values().collect();
+    sort_rows(&mut rows, Metric::TreeCpu, Dir::Desc, false);
+    let pids: Vec<i32> = rows.iter().map(|sp| sp.pid).collect();
+    assert_eq!(pids, [10, 20, 30, 31, 40]);
+}
+
+#[test]
+fn cycles_end() {
+    let mut procs: HashMap<i32, SProc> = vec![proc(5, Some(6), "a", 1.), proc(6, Some(5), "b", 2.)]
+        .into_iter()
+        .map(|sp| (sp.pid, sp))
+        .collect();
+    sum_subtrees(&mut procs);
+    assert!(procs[&5].tree_cpu(false) >= 3.);
+}
+
+#[test]
+fn column_shows_totals() {
+    let mut v = view(100, 12);
+    v.run(parse_command("columns +tree_cpu").unwrap());
+    let procs = tree();
+    let rows: Vec<&SProc> = procs.values().collect();
+    let screen = draw_rows(&mut v, &rows);
+    assert!(screen.iter().any(|l| l.contains("tree")));
+    let bash = screen.iter().find(|l| l.contains("bash")).unwrap();
+    assert!(bash.contains("173"), "{}", bash);
+}
@@ -0,0 +1,75 @@
+// Themes from the config file: a preset, with slots overridden by name, and
+// each view drawing in its own.
+use sparktop::ui::style::Color;
+use sparktop::{
+    config::{ColorLevels, ThemeConfig},
+    theme::{color_name, parse_color, Theme},
+};
+
+mod common;
+use common::{cell, draw, find, proc, view};
+
+#[test]
+fn overrides_apply_over_the_preset() {
+    let config: ThemeConfig = toml::from_str(
+        r##"preset = "light"
+colors = { dead = "#aa0000" }"##,
+    )
+    .unwrap();
+    let theme = config.theme().unwrap();
+    assert_eq!(theme.dead, Color::Rgb(0xaa, 0, 0));
+    assert_eq!(theme.frozen, Theme::preset("light").unwrap().frozen);
+}
+
+#[test]
+fn bad_names_are_errors() {
+    let bad_preset: ThemeConfig = toml::from_str(r#"preset = "neon""#).unwrap();
+    assert!(bad_preset.theme().is_err());
+    let bad_color: ThemeConfig = toml::from_str(r#"colors = { dead = "blood" }"#).unwrap();
+    assert!(bad_color.theme().is_err());
+    assert!(toml::from_str::<ThemeConfig>(r#"colors = { zombie = "red" }"#).is_err());
+}
+
+#[test]
+fn color_names_round_trip() {
+    for c in [
+        Color::LightBlue,
+        Color::Indexed(208),
+        Color::Rgb(1, 2, 3),
+        Color::Reset,
+    ] {
+        assert_eq!(parse_color(&color_name(c)), Some(c));
+    }
+}
+
+#[test]
+fn per_view_themes() {
+    let (mut themed, mut plain) = (view(100, 8), view(100, 8));
+    themed.set_theme(Theme {
+        header: Color::Magenta,
+        ..Theme::default()
+    });
+    let header_fg = |v: &mut sparktop::view::View<sparktop::ui::backend::TestBackend>| {
+        let (x, y) = find(&draw(v, &[]), "pid").unwrap();
+        cell(v, x, y).fg
+    };
+    assert_eq!(header_fg(&mut themed), Color::Magenta);
+    assert_eq!(header_fg(&mut plain), Color::Reset);
+}
+
+#[test]
+fn per_view_color_levels() {
+    let (mut calm, mut plain) = (view(100, 8), view(100, 8));
+    calm.set_color_levels(ColorLevels {
+        cpu: [200., 300., 400.],
+        ..ColorLevels::default()
+    });
+    let warm = |v: &mut sparktop::view::View<sparktop::ui::backend::TestBackend>| {
+        let mut sp = proc(1, "busy", 150., 10.);
+        sp.cpu_hist.push_back(150.);
+        draw(v, &[sp]);
+        (0..100).any(|x| (0..8).any(|y| cell(v, x, y).fg == Theme::default().cpu_warm))
+    };
+    assert!(warm(&mut plain));
+    assert!(!warm(&mut calm));
+}
@@ -0,0 +1,52 @@
+// Memory is kept in bytes and only turned into MB or GB (binary) for display,
+// so the mem column shows what the process actually has resident.
+use sparktop::{
+    sproc::{History, SProc},
+    units::{self, MIB},
+};
+
+mod common;
+use common::{draw_rows, view};
+
+#[test]
+fn sysinfo_kb_to_bytes() {
+    // a process's memory() is KiB on linux, kB elsewhere.
+    let expected = if cfg!(any(target_os = "linux", target_os = "android")) {
+        1 << 20
+    } else {
+        1_024_000
+    };
+    assert_eq!(units::process_mem_bytes(1024), expected);
+    // the system's totals are always kB.
+    assert_eq!(units::system_mem_bytes(16_000_000), 16_000_000_000);
+}
+
+#[test]
+fn render_is_binary() {
+    assert_eq!(units::mib(256. * MIB), 256.);
+    assert_eq!(units::render_bytes(0.), "0 MB");
+    assert_eq!(units::render_bytes(512. * MIB), "512 MB");
+    assert_eq!(units::render_bytes(1023. * MIB), "1023 MB");
+    assert_eq!(units::render_bytes(1536. * MIB), "1.5 GB");
+    // 16 decimal GB is a little under 15 GiB.
+    assert_eq!(units::render_bytes(16e9), "14.9 GB");
+}
+
+#[test]
+fn samples_keep_bytes() {
+    let (mut sp, history) = (SProc::synthetic(1, "p"), History::default());
+    sp.add_synthetic_sample(&history, 0., 300 << 20, 0, 0, 0.5);
+    sp.add_synthetic_sample(&history, 0., 1 << 30, 0, 0, 0.5);
+    assert_eq!(sp.mem_bytes, 1 << 30);
+    assert_eq!(sp.mem_hist.front().copied(), Some((1u64 << 30) as f64));
+}
+
+#[test]
+fn mem_column_in_mib() {
+    let mut v = view(120, 10);
+    let mut sp = SProc::synthetic(7, "hog");
+    sp.add_synthetic_sample(&History::default(), 0., 512 << 20, 0, 0, 0.5);
+    let screen = draw_rows(&mut v, &[&sp]);
+    let row = screen.iter().find(|l| l.contains("hog")).unwrap();
+    assert!(row.contains("512.0"), "{}", row);
+}
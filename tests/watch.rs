@@ -0,0 +1,35 @@
+// Watch: how a watched command's exit is reported.
+use std::{thread, time::Duration};
+
+use sparktop::watch::Watch;
+
+fn wait(w: &mut Watch) {
+    while w.is_running() {
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn sh(script: &str) -> Watch {
+    let cmd = ["sh".to_string(), "-c".into(), script.into()];
+    Watch::spawn(&cmd, true).unwrap()
+}
+
+#[test]
+fn exit_codes_follow_the_shell() {
+    let mut w = sh("exit 3");
+    wait(&mut w);
+    assert_eq!(w.exit_code().unwrap(), Some(3));
+    // killed by SIGKILL (9).
+    let mut w = sh("kill -9 $$");
+    wait(&mut w);
+    assert_eq!(w.exit_code().unwrap(), Some(137));
+    assert_eq!(w.summary(&[]).exit_code, Some(137));
+}
+
+#[test]
+fn wall_secs_stop_at_exit() {
+    let mut w = sh("true");
+    wait(&mut w);
+    thread::sleep(Duration::from_millis(300));
+    assert!(w.summary(&[]).wall_secs < 0.3);
+}
@@ -0,0 +1,15 @@
+// What's read for the process zoomed in on alone, when no column asks for it
+// for every process.
+#[cfg(target_os = "linux")]
+#[test]
+fn fds_only_for_the_zoomed() {
+    let me = std::process::id() as i32;
+    let mut sprocs = sparktop::sprocs::SProcs::default();
+    sprocs.set_zoomed(Some(me));
+    sprocs.update(1.);
+    assert!(sprocs.by_pid(me).unwrap().fds.is_some());
+    assert!(sprocs
+        .get()
+        .filter(|sp| sp.pid != me)
+        .all(|sp| sp.fds.is_none()));
+}